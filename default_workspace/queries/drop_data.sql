@@ -1,8 +1,25 @@
+-- Vectors tables are named by the size of their vectors, run the statements
+-- printed by this query before the rest of the script:
+-- SELECT 'DROP TABLE ' || name || ';' FROM sqlite_master
+-- WHERE sql LIKE 'CREATE VIRTUAL TABLE%'
+-- AND (name GLOB 'vectors_[0-9]*' OR name GLOB 'knowledge_vectors_[0-9]*');
 DROP TABLE IF EXISTS vectors_files;
+DROP TABLE IF EXISTS vectors_fts;
+DROP TABLE IF EXISTS vectors_watched_paths;
 DROP TABLE IF EXISTS vectors;
+DROP TABLE IF EXISTS knowledge_bases;
+DROP TABLE IF EXISTS knowledge_base_links;
+DROP TABLE IF EXISTS knowledge_base_files;
+DROP TABLE IF EXISTS knowledge_base_watched_paths;
+DROP TABLE IF EXISTS knowledge_vectors_fts;
 DROP TABLE IF EXISTS conversations;
 DROP TABLE IF EXISTS messages;
+DROP TABLE IF EXISTS messages_fts;
+DROP TABLE IF EXISTS attachments;
+DROP TABLE IF EXISTS completion_usage;
 DROP TABLE IF EXISTS vectors_embeddings_chunks;
 DROP TABLE IF EXISTS vectors_embeddings_rowids;
 DROP TABLE IF EXISTS vectors_embeddings_info;
-DROP TABLE IF EXISTS vectors_embeddings_vector_chunks00;
\ No newline at end of file
+DROP TABLE IF EXISTS vectors_embeddings_vector_chunks00;
+-- Lets the migrations create the dropped tables again
+PRAGMA user_version = 0;
@@ -4,6 +4,7 @@ CREATE TABLE IF NOT EXISTS "providers" (
 	"name"	TEXT NOT NULL UNIQUE,
 	"url"	TEXT NOT NULL,
 	"api_key"	TEXT NOT NULL,
+	"default_model"	TEXT,
 	PRIMARY KEY("id")
 );
 CREATE TABLE IF NOT EXISTS "storage" (
@@ -12,6 +13,6 @@ CREATE TABLE IF NOT EXISTS "storage" (
 	"value"	TEXT NOT NULL,
 	PRIMARY KEY("id")
 );
-INSERT INTO "providers" VALUES (1,'Ollama Native','http://127.0.0.1:11434/v1','0');
+INSERT INTO "providers" VALUES (1,'Ollama Native','http://127.0.0.1:11434/v1','0',NULL);
 INSERT INTO "storage" VALUES (1,'current_provider_id','1');
 COMMIT;
@@ -1,7 +1,8 @@
-use api::open_ai_api::OpenAiApi;
+use api::lm_backend::LmApi;
 use database::create_database_connection;
 use services::{
-    AppSettings, ConversationsService, MessagingService, PresetsService, VectorService,
+    AppSettings, ConversationsService, MessagingService, PresetsService, ToolRegistry,
+    UsageService, VectorService,
 };
 use utils::{event_system::EventSystem, focus_manager::FocusManager};
 
@@ -13,15 +14,18 @@ pub struct Context {
     pub presets_service: PresetsService,
     pub vector_service: VectorService,
     pub messaging_service: MessagingService,
+    pub usage_service: UsageService,
 }
 
 impl Context {
-    #[must_use] pub fn new() -> Self {
+    /// # Errors
+    /// When the database can't be opened or migrated.
+    pub fn new() -> Result<Self, String> {
         let folder = Self::get_application_folder();
-        let connection = create_database_connection(format!("{folder}/database.db").as_str());
+        let connection = create_database_connection(format!("{folder}/database.db").as_str())?;
 
         let event_system = EventSystem::new();
-        let lm_api = OpenAiApi::new(connection.clone());
+        let lm_api = LmApi::new(connection.clone());
 
         let vector_service = VectorService::new(
             64,
@@ -46,6 +50,12 @@ impl Context {
             event_system.clone(),
         );
         let focus_manager = FocusManager::new();
+        let usage_service = UsageService::new(connection.clone());
+
+        let tools_folder = format!("{folder}/tools");
+        if let Err(e) = std::fs::create_dir_all(&tools_folder) {
+            log::error!("Failed to create tools folder: {e}");
+        }
 
         let messaging_service = MessagingService::new(
             conversations_service.clone(),
@@ -53,9 +63,10 @@ impl Context {
             vector_service.clone(),
             connection,
             event_system.clone(),
+            ToolRegistry::with_builtins(tools_folder.into()),
         );
 
-        Self {
+        Ok(Self {
             app_settings,
             event_system,
             focus_manager,
@@ -63,7 +74,8 @@ impl Context {
             presets_service,
             vector_service,
             messaging_service,
-        }
+            usage_service,
+        })
     }
 
     /// # Panics
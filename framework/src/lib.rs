@@ -6,5 +6,6 @@ mod context;
 pub use context::Context;
 pub use database::create_database_connection;
 
+pub use api::CancellationToken;
 pub use api::ComplitationStatus;
 pub use api::open_ai_api::ChatCompletionMessage;
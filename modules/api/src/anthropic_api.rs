@@ -0,0 +1,609 @@
+use base64::{Engine, prelude::BASE64_STANDARD};
+use reqwest_eventsource as SSE;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use types::{
+    dto::{LmModel, MessageDTO, PresetDTO, ProviderDTO, RoleType, ToolCallDTO, ToolDefinitionDTO},
+    error::{AppError, AppResult},
+};
+
+use crate::{
+    CancellationToken, EmbeddingResponse, RerankResult,
+    lm_backend::{
+        BackendFuture, CompletionStream, LmBackend, build_client, event_source_stream, send_json,
+        with_preset_prompt,
+    },
+};
+
+use super::{CompletionInfo, ComplitationStatus, MessageChunk};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const MAX_TEMPERATURE: f32 = 1.0;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart { message: MessageStartBody },
+    ContentBlockStart { content_block: ContentBlock },
+    ContentBlockDelta { delta: ContentDelta },
+    ContentBlockStop {},
+    MessageDelta {
+        delta: MessageDeltaBody,
+        #[serde(default)]
+        usage: Option<Usage>,
+    },
+    MessageStop {},
+    Ping {},
+    Error { error: StreamError },
+    /// Event types added to the API later, see
+    /// <https://docs.anthropic.com/en/docs/build-with-claude/streaming#other-events>.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    ToolUse {
+        id: String,
+        name: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentDelta {
+    TextDelta {
+        text: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageDeltaBody {
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageStartBody {
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+/// `input_tokens` comes with the start of the message, `output_tokens` with its end.
+#[derive(Debug, Deserialize)]
+struct Usage {
+    input_tokens: Option<usize>,
+    output_tokens: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct StreamState {
+    tool_calls: Vec<ToolCallDTO>,
+    info: CompletionInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamError {
+    #[serde(rename = "type")]
+    kind: String,
+    message: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GetModelsModel {
+    id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GetModelsResponse {
+    data: Vec<GetModelsModel>,
+}
+
+/// Native backend for the Anthropic Messages API.
+#[derive(Debug, Clone)]
+pub struct AnthropicApi;
+
+impl AnthropicApi {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Splits history into the top-level system prompt and the conversation turns,
+    /// merging consecutive turns of the same role as the API requires alternation.
+    fn build_messages(messages: Vec<MessageDTO>) -> (String, Vec<serde_json::Value>) {
+        let mut system = Vec::new();
+        let mut turns: Vec<(&str, Vec<serde_json::Value>)> = Vec::new();
+
+        for message in messages {
+            let (role, blocks) = match message.role {
+                RoleType::System => {
+                    system.push(message.content);
+                    continue;
+                }
+                RoleType::Tool => (
+                    "user",
+                    vec![json!({
+                        "type": "tool_result",
+                        "tool_use_id": message.tool_call_id,
+                        "content": message.content,
+                    })],
+                ),
+                RoleType::User => {
+                    let mut blocks = Self::text_blocks(&message.content);
+                    blocks.extend(
+                        message
+                            .attachments
+                            .iter()
+                            .filter(|attachment| attachment.is_image())
+                            .map(|attachment| {
+                                json!({
+                                    "type": "image",
+                                    "source": {
+                                        "type": "base64",
+                                        "media_type": attachment.mime_type,
+                                        "data": BASE64_STANDARD.encode(&attachment.data),
+                                    }
+                                })
+                            }),
+                    );
+                    ("user", blocks)
+                }
+                RoleType::Assistant => {
+                    let mut blocks = Self::text_blocks(&message.content);
+                    blocks.extend(message.tool_calls.into_iter().map(|tool_call| {
+                        json!({
+                            "type": "tool_use",
+                            "id": tool_call.id,
+                            "name": tool_call.name,
+                            "input": serde_json::from_str::<serde_json::Value>(&tool_call.arguments)
+                                .unwrap_or_else(|_| json!({})),
+                        })
+                    }));
+                    ("assistant", blocks)
+                }
+            };
+
+            match turns.last_mut() {
+                Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+                _ => turns.push((role, blocks)),
+            }
+        }
+
+        let turns = turns
+            .into_iter()
+            .filter(|(_, blocks)| !blocks.is_empty())
+            .map(|(role, content)| {
+                json!({
+                    "role": role,
+                    "content": content,
+                })
+            })
+            .collect();
+
+        (system.join("\n\n"), turns)
+    }
+
+    fn text_blocks(text: &str) -> Vec<serde_json::Value> {
+        if text.trim().is_empty() {
+            return vec![];
+        }
+
+        vec![json!({ "type": "text", "text": text })]
+    }
+}
+
+impl LmBackend for AnthropicApi {
+    fn chat_completions(
+        &self,
+        lm_model: LmModel,
+        messages: Vec<MessageDTO>,
+        preset: Option<PresetDTO>,
+        tools: Vec<ToolDefinitionDTO>,
+        cancel: CancellationToken,
+    ) -> AppResult<CompletionStream> {
+        let Some(provider) = lm_model.provider else {
+            log::error!("Provider is None");
+            return Err(AppError::not_found("Provider"));
+        };
+
+        let client = build_client().inspect_err(|e| log::error!("Failed to build client: {e}"))?;
+
+        let preset = preset.unwrap_or_default();
+
+        let (system, messages) = Self::build_messages(with_preset_prompt(messages, &preset));
+
+        let model = if lm_model.model_name.is_empty() {
+            log::error!("Model is empty");
+            provider.default_model
+        } else {
+            lm_model.model_name
+        };
+
+        let mut body = json!({
+            "model": model,
+            "messages": messages,
+            "temperature": preset.temperature.min(MAX_TEMPERATURE),
+            "max_tokens": preset.max_tokens,
+            "stream": true
+        });
+
+        if !system.is_empty() {
+            body["system"] = json!(system);
+        }
+
+        if !tools.is_empty() {
+            body["tools"] = tools
+                .into_iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters,
+                    })
+                })
+                .collect();
+        }
+
+        log::debug!(
+            "Anthropic messages request body: {}",
+            serde_json::to_string_pretty(&body).unwrap_or_default()
+        );
+
+        let request = client
+            .post(format!("{}/messages", provider.url))
+            .header("Accept", "text/event-stream")
+            .header("Content-Type", "application/json")
+            .header("x-api-key", provider.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body);
+
+        let es = SSE::EventSource::new(request).map_err(AppError::network)?;
+
+        Ok(event_source_stream(
+            es,
+            cancel,
+            StreamState::default(),
+            handle_sse_event,
+        ))
+    }
+
+    fn embeddings(
+        &self,
+        _embedding_lm_model: LmModel,
+        _inputs: Vec<String>,
+    ) -> BackendFuture<'_, EmbeddingResponse> {
+        Box::pin(async {
+            Err(AppError::validation(
+                "Anthropic does not provide an embeddings API",
+            ))
+        })
+    }
+
+    fn rerank(
+        &self,
+        _rerank_lm_model: LmModel,
+        _query: String,
+        _documents: Vec<String>,
+    ) -> BackendFuture<'_, Vec<RerankResult>> {
+        Box::pin(async {
+            Err(AppError::validation("Anthropic does not provide a rerank API"))
+        })
+    }
+
+    fn get_models<'a>(&'a self, provider: &'a ProviderDTO) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let request = build_client()?
+                .get(format!("{}/models", provider.url))
+                .header("x-api-key", &provider.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION);
+
+            let json: GetModelsResponse = send_json(request).await?;
+
+            Ok(json.data.into_iter().map(|model| model.id).collect())
+        })
+    }
+}
+
+fn handle_sse_event(
+    event: SSE::Event,
+    state: &mut StreamState,
+) -> AppResult<Option<ComplitationStatus>> {
+    match event {
+        SSE::Event::Open => Ok(None),
+        SSE::Event::Message(message_raw_event) => {
+            let event = serde_json::from_str::<StreamEvent>(&message_raw_event.data)?;
+
+            match event {
+                StreamEvent::ContentBlockStart { content_block } => {
+                    if let ContentBlock::ToolUse { id, name } = content_block {
+                        state.tool_calls.push(ToolCallDTO {
+                            id,
+                            name,
+                            arguments: String::new(),
+                        });
+                    }
+                    Ok(None)
+                }
+                StreamEvent::ContentBlockDelta { delta } => match delta {
+                    ContentDelta::TextDelta { text } => {
+                        Ok(Some(ComplitationStatus::Message(MessageChunk {
+                            role: RoleType::Assistant.to_string(),
+                            content: text,
+                            reasoning_content: String::new(),
+                        })))
+                    }
+                    ContentDelta::ThinkingDelta { thinking } => {
+                        Ok(Some(ComplitationStatus::Message(MessageChunk {
+                            role: RoleType::Assistant.to_string(),
+                            content: String::new(),
+                            reasoning_content: thinking,
+                        })))
+                    }
+                    ContentDelta::InputJsonDelta { partial_json } => {
+                        if let Some(tool_call) = state.tool_calls.last_mut() {
+                            tool_call.arguments.push_str(&partial_json);
+                        }
+                        Ok(None)
+                    }
+                    ContentDelta::Other => Ok(None),
+                },
+                StreamEvent::MessageStart { message } => {
+                    if let Some(usage) = message.usage {
+                        state.info.prompt_tokens = usage.input_tokens;
+                    }
+                    Ok(None)
+                }
+                StreamEvent::MessageDelta { delta, usage } => {
+                    if let Some(output_tokens) = usage.and_then(|usage| usage.output_tokens) {
+                        state.info.completion_tokens = Some(output_tokens);
+                    }
+                    state.info.finish_reason.clone_from(&delta.stop_reason);
+
+                    if delta.stop_reason.as_deref() == Some("tool_use")
+                        && !state.tool_calls.is_empty()
+                    {
+                        return Ok(Some(ComplitationStatus::ToolCalls(std::mem::take(
+                            &mut state.tool_calls,
+                        ))));
+                    }
+                    Ok(None)
+                }
+                StreamEvent::MessageStop {} => Ok(Some(ComplitationStatus::End(std::mem::take(
+                    &mut state.info,
+                )))),
+                StreamEvent::Error { error } => Ok(Some(ComplitationStatus::Error(
+                    stream_error(&error),
+                ))),
+                StreamEvent::ContentBlockStop {}
+                | StreamEvent::Ping {}
+                | StreamEvent::Unknown => Ok(None),
+            }
+        }
+    }
+}
+
+/// Errors sent inside an already open stream, see
+/// <https://docs.anthropic.com/en/api/errors>.
+fn stream_error(error: &StreamError) -> AppError {
+    let status = match error.kind.as_str() {
+        "invalid_request_error" => 400,
+        "authentication_error" => 401,
+        "permission_error" => 403,
+        "not_found_error" => 404,
+        "rate_limit_error" => 429,
+        "overloaded_error" => 529,
+        _ => 500,
+    };
+
+    AppError::Http(status, format!("{}: {}", error.kind, error.message))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use futures_util::StreamExt;
+    use serde_json::json;
+    use types::{
+        dto::{LmModel, ProviderDTO},
+        error::AppError,
+    };
+
+    use super::AnthropicApi;
+    use crate::{CancellationToken, ComplitationStatus, lm_backend::LmBackend, test_server};
+
+    /// Streams `events` as the Messages API sends them and collects what the
+    /// backend makes of them.
+    async fn complete(events: &[serde_json::Value]) -> Vec<ComplitationStatus> {
+        let mut body = String::new();
+        for event in events {
+            let _ = write!(body, "event: {}\ndata: {event}\n\n", event["type"].as_str().unwrap_or_default());
+        }
+        let url = test_server::serve_once(200, "text/event-stream", body).await;
+
+        let lm_model = LmModel {
+            model_name: "model".to_string(),
+            provider: Some(ProviderDTO {
+                url,
+                ..Default::default()
+            }),
+        };
+
+        AnthropicApi::new()
+            .chat_completions(lm_model, vec![], None, vec![], CancellationToken::new())
+            .unwrap()
+            .collect()
+            .await
+    }
+
+    fn message_start(input_tokens: usize) -> serde_json::Value {
+        json!({
+            "type": "message_start",
+            "message": {
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "content": [],
+                "usage": { "input_tokens": input_tokens, "output_tokens": 1 },
+            },
+        })
+    }
+
+    fn content_block_start(index: usize, content_block: &serde_json::Value) -> serde_json::Value {
+        json!({ "type": "content_block_start", "index": index, "content_block": content_block })
+    }
+
+    fn content_block_delta(index: usize, delta: &serde_json::Value) -> serde_json::Value {
+        json!({ "type": "content_block_delta", "index": index, "delta": delta })
+    }
+
+    fn message_delta(stop_reason: &str, output_tokens: usize) -> serde_json::Value {
+        json!({
+            "type": "message_delta",
+            "delta": { "stop_reason": stop_reason, "stop_sequence": null },
+            "usage": { "output_tokens": output_tokens },
+        })
+    }
+
+    fn text(statuses: &[ComplitationStatus]) -> (String, String) {
+        statuses
+            .iter()
+            .filter_map(|status| match status {
+                ComplitationStatus::Message(chunk) => Some(chunk),
+                _ => None,
+            })
+            .fold((String::new(), String::new()), |(content, reasoning), chunk| {
+                (content + &chunk.content, reasoning + &chunk.reasoning_content)
+            })
+    }
+
+    #[tokio::test]
+    async fn streams_text_and_thinking_with_usage() {
+        let statuses = complete(&[
+            message_start(12),
+            content_block_start(0, &json!({ "type": "thinking", "thinking": "" })),
+            content_block_delta(0, &json!({ "type": "thinking_delta", "thinking": "One plus " })),
+            content_block_delta(0, &json!({ "type": "thinking_delta", "thinking": "one." })),
+            content_block_delta(0, &json!({ "type": "signature_delta", "signature": "abc" })),
+            json!({ "type": "content_block_stop", "index": 0 }),
+            content_block_start(1, &json!({ "type": "text", "text": "" })),
+            json!({ "type": "ping" }),
+            content_block_delta(1, &json!({ "type": "text_delta", "text": "It is " })),
+            content_block_delta(1, &json!({ "type": "text_delta", "text": "two." })),
+            json!({ "type": "content_block_stop", "index": 1 }),
+            message_delta("end_turn", 9),
+            json!({ "type": "message_stop" }),
+        ])
+        .await;
+
+        assert_eq!(text(&statuses), ("It is two.".to_string(), "One plus one.".to_string()));
+
+        match statuses.last() {
+            Some(ComplitationStatus::End(info)) => {
+                assert_eq!(info.prompt_tokens, Some(12));
+                assert_eq!(info.completion_tokens, Some(9));
+                assert_eq!(info.finish_reason.as_deref(), Some("end_turn"));
+            }
+            other => panic!("expected the end of the answer, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn assembles_tool_input_from_json_deltas() {
+        let statuses = complete(&[
+            message_start(20),
+            content_block_start(0, &json!({ "type": "text", "text": "" })),
+            content_block_delta(0, &json!({ "type": "text_delta", "text": "Let me count." })),
+            json!({ "type": "content_block_stop", "index": 0 }),
+            content_block_start(
+                1,
+                &json!({ "type": "tool_use", "id": "toolu_1", "name": "calculator", "input": {} }),
+            ),
+            content_block_delta(1, &json!({ "type": "input_json_delta", "partial_json": "" })),
+            content_block_delta(1, &json!({ "type": "input_json_delta", "partial_json": "{\"expression\": " })),
+            content_block_delta(1, &json!({ "type": "input_json_delta", "partial_json": "\"1 + 1\"}" })),
+            json!({ "type": "content_block_stop", "index": 1 }),
+            message_delta("tool_use", 30),
+            json!({ "type": "message_stop" }),
+        ])
+        .await;
+
+        assert_eq!(text(&statuses).0, "Let me count.");
+
+        let tool_calls = statuses
+            .iter()
+            .find_map(|status| match status {
+                ComplitationStatus::ToolCalls(tool_calls) => Some(tool_calls),
+                _ => None,
+            })
+            .expect("No tool calls");
+        assert_eq!(tool_calls.len(), 1);
+        assert_eq!(tool_calls[0].id, "toolu_1");
+        assert_eq!(tool_calls[0].name, "calculator");
+        assert_eq!(tool_calls[0].arguments, "{\"expression\": \"1 + 1\"}");
+
+        match statuses.last() {
+            Some(ComplitationStatus::End(info)) => {
+                assert_eq!(info.prompt_tokens, Some(20));
+                assert_eq!(info.completion_tokens, Some(30));
+                assert_eq!(info.finish_reason.as_deref(), Some("tool_use"));
+            }
+            other => panic!("expected the end of the answer, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn error_events_end_the_stream() {
+        let statuses = complete(&[
+            message_start(5),
+            content_block_start(0, &json!({ "type": "text", "text": "" })),
+            content_block_delta(0, &json!({ "type": "text_delta", "text": "Partial" })),
+            json!({
+                "type": "error",
+                "error": { "type": "overloaded_error", "message": "Overloaded" },
+            }),
+            content_block_delta(0, &json!({ "type": "text_delta", "text": " never read" })),
+        ])
+        .await;
+
+        assert_eq!(text(&statuses).0, "Partial");
+
+        match statuses.last() {
+            Some(ComplitationStatus::Error(AppError::Http(status, message))) => {
+                assert_eq!(*status, 529);
+                assert_eq!(message, "overloaded_error: Overloaded");
+            }
+            other => panic!("expected an HTTP error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_events_it_does_not_know() {
+        let statuses = complete(&[
+            message_start(5),
+            json!({ "type": "message_annotation", "annotation": { "kind": "new" } }),
+            content_block_start(0, &json!({ "type": "text", "text": "" })),
+            content_block_delta(0, &json!({ "type": "text_delta", "text": "Still here" })),
+            json!({ "type": "content_block_stop", "index": 0 }),
+            message_delta("end_turn", 2),
+            json!({ "type": "message_stop" }),
+        ])
+        .await;
+
+        assert_eq!(text(&statuses).0, "Still here");
+        assert!(!statuses.iter().any(|status| matches!(status, ComplitationStatus::Error(_))));
+        assert!(matches!(statuses.last(), Some(ComplitationStatus::End(_))));
+    }
+}
@@ -1,8 +1,16 @@
 use std::fmt::Debug;
 
 use serde::{Deserialize, Serialize};
+use types::{dto::ToolCallDTO, error::AppError};
 
+pub mod anthropic_api;
+pub mod lm_backend;
 pub mod open_ai_api;
+pub mod reasoning_parser;
+#[cfg(test)]
+mod test_server;
+
+pub use tokio_util::sync::CancellationToken;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct MessageChunk
@@ -25,15 +33,27 @@ impl MessageChunk {
     }
 }
 
+/// What the provider tells about a finished answer.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CompletionInfo {
+    /// `None` when the provider does not report usage.
+    pub prompt_tokens: Option<usize>,
+    pub completion_tokens: Option<usize>,
+    pub finish_reason: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum ComplitationStatus
 where
     Self: Sized + std::marker::Send + Sync + 'static,
 {
     Start,
-    End,
+    End(CompletionInfo),
     Message(MessageChunk),
-    Error(String),
+    Error(AppError),
+    Cancelled,
+    /// The model stopped to let the client run these tools.
+    ToolCalls(Vec<ToolCallDTO>),
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -65,3 +85,15 @@ Self: Sized + std::marker::Send + Sync + 'static
     pub prompt_tokens: u32,
     pub total_tokens: u32,
 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RerankResponse {
+    pub results: Vec<RerankResult>,
+}
+
+/// Relevance of the document at `index` of the request, higher is better.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RerankResult {
+    pub index: usize,
+    pub relevance_score: f32,
+}
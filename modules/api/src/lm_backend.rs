@@ -0,0 +1,269 @@
+use std::{future::Future, pin::Pin};
+
+use futures_util::{Stream, StreamExt};
+use reqwest_eventsource as SSE;
+
+use database::{DatabaseConnection, databases::ProvidersDatabase};
+use types::{
+    dto::{
+        LmModel, MessageDTO, PresetDTO, ProviderDTO, ProviderID, ProviderKind, RoleType,
+        ToolDefinitionDTO,
+    },
+    error::{AppError, AppResult},
+};
+
+use crate::{
+    CancellationToken, ComplitationStatus, EmbeddingResponse, RerankResult,
+    anthropic_api::AnthropicApi, open_ai_api::OpenAiApi,
+};
+
+pub type CompletionStream = Pin<Box<dyn Stream<Item = ComplitationStatus> + Send>>;
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = AppResult<T>> + Send + 'a>>;
+
+/// Wire protocol of a language model provider.
+///
+/// `messages` of a chat completion are the whole ordered history,
+/// the prompt of the preset goes right before the last user message.
+pub trait LmBackend: Send + Sync {
+    /// Cancelling `cancel` closes the connection and ends the stream with
+    /// [`ComplitationStatus::Cancelled`].
+    ///
+    /// # Errors
+    fn chat_completions(
+        &self,
+        lm_model: LmModel,
+        messages: Vec<MessageDTO>,
+        preset: Option<PresetDTO>,
+        tools: Vec<ToolDefinitionDTO>,
+        cancel: CancellationToken,
+    ) -> AppResult<CompletionStream>;
+
+    fn embeddings(
+        &self,
+        embedding_lm_model: LmModel,
+        inputs: Vec<String>,
+    ) -> BackendFuture<'_, EmbeddingResponse>;
+
+    /// Scores every document against the query, the results may come in any order.
+    fn rerank(
+        &self,
+        rerank_lm_model: LmModel,
+        query: String,
+        documents: Vec<String>,
+    ) -> BackendFuture<'_, Vec<RerankResult>>;
+
+    fn get_models<'a>(&'a self, provider: &'a ProviderDTO) -> BackendFuture<'a, Vec<String>>;
+}
+
+/// Picks the backend matching the kind of the provider.
+#[derive(Debug, Clone)]
+pub struct LmApi {
+    providers_db: ProvidersDatabase,
+    open_ai: OpenAiApi,
+    anthropic: AnthropicApi,
+}
+
+impl LmApi {
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            providers_db: ProvidersDatabase::new(connection),
+            open_ai: OpenAiApi::new(),
+            anthropic: AnthropicApi::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn backend(&self, kind: ProviderKind) -> &dyn LmBackend {
+        match kind {
+            ProviderKind::OpenAi => &self.open_ai,
+            ProviderKind::Anthropic => &self.anthropic,
+        }
+    }
+
+    /// # Errors
+    pub fn chat_completions(
+        &self,
+        lm_model: LmModel,
+        messages: Vec<MessageDTO>,
+        preset: Option<PresetDTO>,
+        tools: Vec<ToolDefinitionDTO>,
+        cancel: CancellationToken,
+    ) -> AppResult<CompletionStream> {
+        let kind = Self::provider_kind(&lm_model)?;
+
+        self.backend(kind)
+            .chat_completions(lm_model, messages, preset, tools, cancel)
+    }
+
+    /// # Errors
+    pub async fn embeddings(
+        &self,
+        embedding_lm_model: LmModel,
+        inputs: Vec<String>,
+    ) -> AppResult<EmbeddingResponse> {
+        let kind = Self::provider_kind(&embedding_lm_model)?;
+
+        self.backend(kind)
+            .embeddings(embedding_lm_model, inputs)
+            .await
+    }
+
+    /// # Errors
+    pub async fn rerank(
+        &self,
+        rerank_lm_model: LmModel,
+        query: String,
+        documents: Vec<String>,
+    ) -> AppResult<Vec<RerankResult>> {
+        let kind = Self::provider_kind(&rerank_lm_model)?;
+
+        self.backend(kind)
+            .rerank(rerank_lm_model, query, documents)
+            .await
+    }
+
+    /// # Errors
+    pub async fn get_models(&self, provider_id: ProviderID) -> AppResult<Vec<String>> {
+        let provider = self
+            .providers_db
+            .get_provider(provider_id)
+            .ok_or_else(|| AppError::not_found("Provider"))?;
+
+        self.backend(provider.kind).get_models(&provider).await
+    }
+
+    fn provider_kind(lm_model: &LmModel) -> AppResult<ProviderKind> {
+        lm_model
+            .provider
+            .as_ref()
+            .map(|provider| provider.kind)
+            .ok_or_else(|| AppError::not_found("Provider"))
+    }
+}
+
+pub(crate) fn build_client() -> AppResult<reqwest::Client> {
+    reqwest::Client::builder()
+        .build()
+        .map_err(AppError::network)
+}
+
+/// Passes successful responses through and turns the others into [`AppError::Http`].
+pub(crate) async fn check_status(response: reqwest::Response) -> AppResult<reqwest::Response> {
+    let status = response.status();
+
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+
+    Err(AppError::Http(status.as_u16(), body))
+}
+
+/// Sends the request and parses the JSON of a successful response.
+pub(crate) async fn send_json<T: serde::de::DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+) -> AppResult<T> {
+    let response = request.send().await.map_err(AppError::network)?;
+
+    let text = check_status(response)
+        .await?
+        .text()
+        .await
+        .map_err(AppError::network)?;
+
+    Ok(serde_json::from_str(&text)?)
+}
+
+pub(crate) fn with_preset_prompt(
+    mut messages: Vec<MessageDTO>,
+    preset: &PresetDTO,
+) -> Vec<MessageDTO> {
+    if preset.prompt.is_empty() {
+        return messages;
+    }
+
+    let position = messages
+        .iter()
+        .rposition(|message| message.role == RoleType::User)
+        .unwrap_or(messages.len());
+
+    messages.insert(
+        position,
+        MessageDTO {
+            content: preset.prompt.clone(),
+            role: RoleType::System,
+            ..Default::default()
+        },
+    );
+
+    messages
+}
+
+/// Turns server-sent events into completion statuses until the stream ends,
+/// fails or `cancel` fires. An event may yield any number of statuses.
+pub(crate) fn event_source_stream<S, F, R>(
+    mut es: SSE::EventSource,
+    cancel: CancellationToken,
+    mut state: S,
+    mut handle_event: F,
+) -> CompletionStream
+where
+    S: Send + 'static,
+    F: FnMut(SSE::Event, &mut S) -> AppResult<R> + Send + 'static,
+    R: IntoIterator<Item = ComplitationStatus> + Send,
+    R::IntoIter: Send,
+{
+    Box::pin(async_stream::stream! {
+        yield ComplitationStatus::Start;
+
+        loop {
+            let event_result = tokio::select! {
+                biased;
+                () = cancel.cancelled() => None,
+                event_result = es.next() => Some(event_result),
+            };
+
+            let Some(event_result) = event_result else {
+                es.close();
+                yield ComplitationStatus::Cancelled;
+                break;
+            };
+
+            let Some(event_result) = event_result else {
+                break;
+            };
+
+            let event_result = match event_result {
+                Ok(event) => handle_event(event, &mut state),
+                Err(SSE::Error::InvalidStatusCode(status, response)) => {
+                    let body = response.text().await.unwrap_or_default();
+                    Err(AppError::Http(status.as_u16(), body))
+                }
+                Err(SSE::Error::Transport(e)) => Err(AppError::network(e)),
+                Err(e) => Err(AppError::parse(e)),
+            };
+
+            match event_result {
+                Ok(events) => {
+                    let mut should_close = false;
+                    for event in events {
+                        should_close |= matches!(event, ComplitationStatus::Error(_) | ComplitationStatus::End(_));
+                        yield event;
+                    }
+
+                    if should_close {
+                        es.close();
+                        break;
+                    }
+                },
+                Err(e) => {
+                    yield ComplitationStatus::Error(e);
+                    es.close();
+                    break;
+                }
+            }
+        }
+    })
+}
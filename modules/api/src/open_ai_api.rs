@@ -1,19 +1,25 @@
-use std::pin::Pin;
-
-use futures_util::{Stream, StreamExt};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use futures_util::StreamExt;
 use reqwest_eventsource as SSE;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use database::{databases::{ProvidersDatabase, VectorDatabase}, DatabaseConnection};
-use types::dto::{LmModel, MessageDTO, PresetDTO, ProviderID};
+use types::{
+    dto::{LmModel, MessageDTO, PresetDTO, ProviderDTO, RoleType, ToolCallDTO, ToolDefinitionDTO},
+    error::{AppError, AppResult},
+};
 
-use crate::EmbeddingResponse;
+use crate::{
+    CancellationToken, EmbeddingResponse, RerankResponse, RerankResult,
+    lm_backend::{
+        BackendFuture, CompletionStream, LmBackend, build_client, event_source_stream, send_json,
+        with_preset_prompt,
+    },
+    reasoning_parser::ReasoningParser,
+};
 
-use super::{ComplitationStatus, MessageChunk};
+use super::{CompletionInfo, ComplitationStatus, MessageChunk};
 
-const REASONING_OPEN_TAGS: [&str; 4] = ["<reasoning>", "<think>", "<thinking>", "<reason>"];
-const REASONING_CLOSE_TAGS: [&str; 4] = ["</reasoning>", "</think>", "</thinking>", "</reason>"];
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatCompletionChunk {
@@ -25,6 +31,15 @@ pub struct ChatCompletionChunk {
     #[serde(rename = "system_fingerprint", default)]
     pub system_fingerprint: String,
     pub choices: Vec<ChatCompletionChoice>,
+    /// Sent in a last chunk without choices when `stream_options.include_usage` is set.
+    #[serde(default)]
+    pub usage: Option<ChatCompletionUsage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatCompletionUsage {
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -44,6 +59,22 @@ pub struct ChatCompletionMessage {
     pub content: Option<String>,
     #[serde(default)]
     pub reasoning_content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCallDelta {
+    #[serde(default)]
+    pub index: usize,
+    pub id: Option<String>,
+    pub function: Option<ToolCallFunctionDelta>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCallFunctionDelta {
+    pub name: Option<String>,
+    pub arguments: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -61,69 +92,35 @@ pub struct GetModelsResponse {
 }
 
 #[derive(Debug, Clone)]
-pub struct OpenAiApi {
-    providers_db: ProvidersDatabase,
-    vector_db: VectorDatabase,
-}
+pub struct OpenAiApi;
 
 impl OpenAiApi {
-    pub fn new(connection: DatabaseConnection) -> Self {
-        Self {
-            providers_db: ProvidersDatabase::new(connection.clone()),
-            vector_db: VectorDatabase::new(connection),
-        }
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
     }
+}
 
-    /// # Errors
-    /// # Panics
-    pub fn chat_completions(
+impl LmBackend for OpenAiApi {
+    fn chat_completions(
         &self,
         lm_model: LmModel,
-        messages_history: Vec<MessageDTO>,
+        messages: Vec<MessageDTO>,
         preset: Option<PresetDTO>,
-        user_message: MessageDTO,
-    ) -> Result<Pin<Box<dyn Stream<Item = ComplitationStatus> + Send>>, String> {
-        if lm_model.provider.is_none() {
+        tools: Vec<ToolDefinitionDTO>,
+        cancel: CancellationToken,
+    ) -> AppResult<CompletionStream> {
+        let Some(provider) = lm_model.provider else {
             log::error!("Provider is None");
-            return Err("Provider not found".into());
-        }
-
-        let provider = lm_model.provider.unwrap();
-
-        let client = match Self::build_client() {
-            Ok(client) => client,
-            Err(e) => {
-                log::error!("Failed to build client: {e}");
-                return Err(format!("Failed to build client: {e}"));
-            }
+            return Err(AppError::not_found("Provider"));
         };
 
-        let preset = preset.unwrap_or_default();
+        let reasoning_tags = provider.reasoning_tag_pairs();
 
-        let prompt_message = if preset.prompt.is_empty() {
-            None
-        } else {
-            Some(MessageDTO {
-                content: preset.prompt.clone(),
-                conversation_id: 0,
-                id: 0,
-                reasoning: None,
-                role: types::dto::RoleType::System,
-                timestamp: String::new(),
-                summary: None,
-                chunks: Vec::new(),
-            })
-        };
+        let client = build_client().inspect_err(|e| log::error!("Failed to build client: {e}"))?;
 
-        let mut messages = if let Some(prompt) = prompt_message {
-            let mut messages = messages_history;
-            messages.push(prompt);
-            messages
-        } else {
-            messages_history
-        };
-
-        messages.push(user_message);
+        let preset = preset.unwrap_or_default();
+        let messages = with_preset_prompt(messages, &preset);
 
         let model = lm_model.model_name;
         let model = if model.is_empty() {
@@ -133,247 +130,551 @@ impl OpenAiApi {
             model
         };
 
-        let body = json!({
+        let mut body = json!({
           "model": model,
-          "messages": messages.iter().map(|message| json!({
-            "content": message.content,
-            "reasoning_content": message.reasoning,
-            "role": message.role.to_string(),
-          })).collect::<Vec<_>>(),
+          "messages": messages.iter().map(message_to_json).collect::<Vec<_>>(),
           "temperature": preset.temperature,
           "max_tokens": preset.max_tokens,
           "stream": true
         });
 
-        log::debug!("Chat completion request body: {}", serde_json::to_string_pretty(&body).unwrap());
-
-        let request = client
-            .post(format!("{}/chat/completions", provider.url))
-            .header("Accept", "text/event-stream")
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", provider.api_key))
-            .json(&body);
+        if !tools.is_empty() {
+            body["tools"] = tools
+                .into_iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters,
+                        }
+                    })
+                })
+                .collect();
+        }
 
-        let mut es = match SSE::EventSource::new(request) {
-            Ok(source) => source,
-            Err(e) => {
-                return Err(format!("Failed to create event source: {e}"));
-            }
+        log::debug!(
+            "Chat completion request body: {}",
+            serde_json::to_string_pretty(&body).unwrap_or_default()
+        );
+
+        let send = move |body: &serde_json::Value| -> AppResult<CompletionStream> {
+            let request = client
+                .post(format!("{}/chat/completions", provider.url))
+                .header("Accept", "text/event-stream")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", provider.api_key))
+                .json(body);
+
+            let es = SSE::EventSource::new(request).map_err(AppError::network)?;
+
+            Ok(event_source_stream(
+                es,
+                cancel.clone(),
+                SseEventSharedState {
+                    reasoning_parser: ReasoningParser::new(reasoning_tags.clone()),
+                    tool_calls: Vec::new(),
+                    info: CompletionInfo::default(),
+                },
+                handle_sse_event,
+            ))
         };
 
-        let stream = Box::pin(async_stream::stream! {
-            yield ComplitationStatus::Start;
+        let mut body_with_usage = body.clone();
+        body_with_usage["stream_options"] = json!({ "include_usage": true });
 
-            let mut state = SseEventSharedState::default();
+        Ok(retry_without_usage(send(&body_with_usage)?, move || send(&body)))
+    }
 
-            while let Some(event_result) = es.next().await {
-                match handle_sse_event(event_result, &mut state) {
-                    Ok(Some(event)) => {
-                        let should_close = matches!(event, ComplitationStatus::Error(_) | ComplitationStatus::End);
-                        yield event;
+    fn embeddings(
+        &self,
+        embedding_lm_model: LmModel,
+        inputs: Vec<String>,
+    ) -> BackendFuture<'_, EmbeddingResponse> {
+        Box::pin(async move {
+            let provider = embedding_lm_model
+                .provider
+                .ok_or_else(|| AppError::not_found("Embedding provider"))?;
+
+            let body = json!({
+                "model": embedding_lm_model.model_name,
+                "input": inputs,
+                "encoding_format": "float",
+            });
 
-                        if should_close {
-                            es.close();
-                            break;
-                        }
-                    },
-                    Ok(None) => continue,
-                    Err(e) => {
-                        yield ComplitationStatus::Error(e);
-                        es.close();
-                        break;
-                    }
-                }
+            let request = build_client()?
+                .post(format!("{}/embeddings", provider.url))
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", provider.api_key))
+                .json(&body);
+
+            let json: EmbeddingResponse = send_json(request).await?;
+
+            if json.data.is_empty() {
+                return Err(AppError::parse("Empty data in response"));
+            }
+            if json.data.len() != inputs.len() {
+                return Err(AppError::parse("Data length does not match input length"));
             }
-        });
 
-        Ok(stream)
+            Ok(json)
+        })
     }
 
-    /// # Errors
-    /// # Panics
-    pub async fn embeddings(
+    fn rerank(
         &self,
-        embedding_lm_model: LmModel,
-        inputs: Vec<String>,
-    ) -> Result<EmbeddingResponse, String> {
-        let client = Self::build_client().unwrap();
+        rerank_lm_model: LmModel,
+        query: String,
+        documents: Vec<String>,
+    ) -> BackendFuture<'_, Vec<RerankResult>> {
+        Box::pin(async move {
+            let provider = rerank_lm_model
+                .provider
+                .ok_or_else(|| AppError::not_found("Rerank provider"))?;
+
+            let body = json!({
+                "model": rerank_lm_model.model_name,
+                "query": query,
+                "documents": documents,
+                "top_n": documents.len(),
+            });
 
-        let body = json!({
-            "model": embedding_lm_model.model_name,
-            "input": inputs,
-            "encoding_format": "float",
-        });
+            let request = build_client()?
+                .post(format!("{}/rerank", provider.url))
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", provider.api_key))
+                .json(&body);
 
-        let request = client
-            .post(format!(
-                "{}/embeddings",
-                embedding_lm_model.clone().provider.unwrap().url
-            ))
-            .header("Content-Type", "application/json")
-            .header(
-                "Authorization",
-                format!("Bearer {}", embedding_lm_model.provider.unwrap().api_key),
-            )
-            .json(&body);
+            let json: RerankResponse = send_json(request).await?;
 
-        let response = request.send().await.expect("Failed to send request");
+            if json.results.iter().any(|result| result.index >= documents.len()) {
+                return Err(AppError::parse("Rerank result index is out of range"));
+            }
 
-        if !response.status().is_success() {
-            return Err(format!(
-                "HTTP error: {} - {}",
-                response.status(),
-                response.text().await.unwrap_or_default()
-            ));
-        }
+            Ok(json.results)
+        })
+    }
 
-        let text = response
-            .text()
-            .await
-            .map_err(|e| format!("Failed to read response body: {e}"))?;
+    fn get_models<'a>(&'a self, provider: &'a ProviderDTO) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let request = build_client()?
+                .get(format!("{}/models", provider.url))
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", provider.api_key));
 
-        let json = serde_json::from_str::<EmbeddingResponse>(&text)
-            .map_err(|e| format!("Failed to parse JSON: {e}"))?;
+            let json: GetModelsResponse = send_json(request).await?;
 
-        if json.data.is_empty() {
-            return Err("Empty data in response".to_string());
-        }
-        if json.data.len() != inputs.len() {
-            return Err("Data length does not match input length".to_string());
-        }
+            let models = json.data.into_iter().map(|model| model.id).collect();
 
-        Ok(json)
+            Ok(models)
+        })
     }
+}
 
-    /// # Errors
-    /// # Panics
-    #[allow(dead_code)]
-    pub async fn get_models(&self, provider_id: ProviderID) -> Result<Vec<String>, String> {
-        let client = Self::build_client()?;
+#[derive(Debug)]
+struct SseEventSharedState {
+    reasoning_parser: ReasoningParser,
+    /// Tool calls are streamed in pieces, indexed by their position.
+    tool_calls: Vec<ToolCallDTO>,
+    info: CompletionInfo,
+}
 
-        let provider = self.providers_db.get_provider(provider_id);
+impl SseEventSharedState {
+    fn push_tool_call_deltas(&mut self, deltas: Vec<ToolCallDelta>) {
+        for delta in deltas {
+            if self.tool_calls.len() <= delta.index {
+                self.tool_calls
+                    .resize_with(delta.index + 1, ToolCallDTO::default);
+            }
 
-        if provider.is_none() {
-            return Err("Provider not found".into());
-        }
+            let tool_call = &mut self.tool_calls[delta.index];
 
-        let provider = provider.unwrap();
-
-        let response = client
-            .get(format!("{}/models", provider.url))
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", provider.api_key))
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {e}"))?;
-
-        if !response.status().is_success() {
-            return Err(match response.status().as_u16() {
-                401 => "Invalid API key".into(),
-                429 => "Rate limit exceeded".into(),
-                _ => format!(
-                    "HTTP error: {} - {}",
-                    response.status(),
-                    response.text().await.unwrap_or_default()
-                ),
-            });
+            if let Some(id) = delta.id {
+                tool_call.id = id;
+            }
+
+            if let Some(function) = delta.function {
+                tool_call.name.push_str(&function.name.unwrap_or_default());
+                tool_call
+                    .arguments
+                    .push_str(&function.arguments.unwrap_or_default());
+            }
         }
+    }
+}
 
-        let text = response
-            .text()
-            .await
-            .map_err(|e| format!("Failed to read response body: {e}"))?;
+/// Usage is only streamed when asked for with `stream_options`, servers that
+/// reject unknown fields refuse the request then. It is sent again without the
+/// option and the answer has no usage.
+fn retry_without_usage<F>(stream: CompletionStream, retry: F) -> CompletionStream
+where
+    F: FnOnce() -> AppResult<CompletionStream> + Send + 'static,
+{
+    Box::pin(async_stream::stream! {
+        let mut stream = stream;
+        let mut retry = Some(retry);
+        let mut started = false;
+
+        while let Some(status) = stream.next().await {
+            let rejected = matches!(
+                &status,
+                ComplitationStatus::Error(AppError::Http(400 | 422, body)) if body.contains("stream_options")
+            );
+
+            let retry_now = if rejected { retry.take() } else { None };
+
+            if let Some(retry) = retry_now {
+                log::warn!("The server rejected stream_options, sending the request without it");
+
+                match retry() {
+                    Ok(retried) => {
+                        stream = retried;
+                        continue;
+                    }
+                    Err(e) => {
+                        yield ComplitationStatus::Error(e);
+                        break;
+                    }
+                }
+            }
 
-        let json = serde_json::from_str::<GetModelsResponse>(&text)
-            .map_err(|e| format!("Failed to parse JSON: {e}"))?;
+            match status {
+                ComplitationStatus::Start if started => continue,
+                ComplitationStatus::Start => started = true,
+                // Only the refusal of the request itself is retried
+                _ => retry = None,
+            }
 
-        let models = json.data.into_iter().map(|model| model.id).collect();
+            yield status;
+        }
+    })
+}
 
-        Ok(models)
+fn message_to_json(message: &MessageDTO) -> serde_json::Value {
+    let content = if message.attachments.is_empty() {
+        json!(message.content)
+    } else {
+        let mut parts = vec![json!({ "type": "text", "text": message.content })];
+        parts.extend(
+            message
+                .attachments
+                .iter()
+                .filter(|attachment| attachment.is_image())
+                .map(|attachment| {
+                    json!({
+                        "type": "image_url",
+                        "image_url": {
+                            "url": format!(
+                                "data:{};base64,{}",
+                                attachment.mime_type,
+                                BASE64_STANDARD.encode(&attachment.data)
+                            ),
+                        }
+                    })
+                }),
+        );
+        json!(parts)
+    };
+
+    let mut json = json!({
+        "content": content,
+        "reasoning_content": message.reasoning,
+        "role": message.role.to_string(),
+    });
+
+    if !message.tool_calls.is_empty() {
+        json["tool_calls"] = message
+            .tool_calls
+            .iter()
+            .map(|tool_call| {
+                json!({
+                    "id": tool_call.id,
+                    "type": "function",
+                    "function": {
+                        "name": tool_call.name,
+                        "arguments": tool_call.arguments,
+                    }
+                })
+            })
+            .collect();
     }
 
-    /// # Errors
-    /// # Panics
-    fn build_client() -> Result<reqwest::Client, String> {
-        reqwest::Client::builder()
-            .build()
-            .map_err(|err| err.to_string())
+    if message.role == RoleType::Tool {
+        json["tool_call_id"] = json!(message.tool_call_id);
     }
-}
 
-#[derive(Debug, Default)]
-struct SseEventSharedState {
-    is_reasoning: bool,
+    json
 }
 
 fn handle_sse_event(
-    event_result: Result<reqwest_eventsource::Event, reqwest_eventsource::Error>,
+    event: SSE::Event,
     state: &mut SseEventSharedState,
-) -> Result<Option<ComplitationStatus>, String> {
-    match event_result {
-        Ok(SSE::Event::Open) => Ok(None),
-        Ok(SSE::Event::Message(message_raw_event)) => {
+) -> AppResult<Vec<ComplitationStatus>> {
+    match event {
+        SSE::Event::Open => Ok(vec![]),
+        SSE::Event::Message(message_raw_event) => {
             let message_raw = message_raw_event.data;
 
             if message_raw == "[DONE]" {
-                return Ok(Some(ComplitationStatus::End));
+                // Text held back for a possible tag is kept when no finish reason came
+                let rest = state.reasoning_parser.finish();
+
+                let mut statuses = vec![];
+                if !rest.is_empty() {
+                    statuses.push(ComplitationStatus::Message(MessageChunk {
+                        role: RoleType::Assistant.to_string(),
+                        content: rest.content,
+                        reasoning_content: rest.reasoning,
+                    }));
+                }
+                statuses.push(ComplitationStatus::End(std::mem::take(&mut state.info)));
+
+                return Ok(statuses);
             }
 
             match serde_json::from_str::<ChatCompletionChunk>(&message_raw) {
                 Ok(message) => {
+                    if let Some(usage) = &message.usage {
+                        state.info.prompt_tokens = Some(usage.prompt_tokens);
+                        state.info.completion_tokens = Some(usage.completion_tokens);
+                    }
+
                     if message.choices.is_empty() {
-                        return Err("Empty choices in response".to_string());
+                        if message.usage.is_some() {
+                            return Ok(vec![]);
+                        }
+                        return Err(AppError::parse("Empty choices in response"));
                     }
 
                     let choice = &message.choices[0];
                     let delta = match &choice.delta {
                         Some(delta) => delta.clone(),
-                        None => return Err("Delta is None".to_string()),
+                        None => return Err(AppError::parse("Delta is None")),
                     };
 
-                    let content_str = delta.content.clone().unwrap_or_default();
-                    let content = content_str.as_str();
+                    if let Some(tool_calls) = delta.tool_calls {
+                        state.push_tool_call_deltas(tool_calls);
+                    }
 
-                    if REASONING_OPEN_TAGS.contains(&content) {
-                        if state.is_reasoning {
-                            return Err("Nested reasoning tags".to_string());
-                        }
+                    let mut parsed = state
+                        .reasoning_parser
+                        .push(&delta.content.unwrap_or_default());
 
-                        state.is_reasoning = true;
-                        return Ok(None);
+                    if choice.finish_reason.is_some() {
+                        state.info.finish_reason.clone_from(&choice.finish_reason);
+
+                        let rest = state.reasoning_parser.finish();
+                        parsed.content.push_str(&rest.content);
+                        parsed.reasoning.push_str(&rest.reasoning);
                     }
 
-                    if REASONING_CLOSE_TAGS.contains(&content) {
-                        if !state.is_reasoning {
-                            return Err("Unmatched reasoning close tag".to_string());
-                        }
+                    let mut reasoning_content = delta.reasoning_content.unwrap_or_default();
+                    reasoning_content.push_str(&parsed.reasoning);
 
-                        state.is_reasoning = false;
-                        return Ok(None);
-                    }
+                    let mut statuses = vec![];
 
-                    if state.is_reasoning {
-                        let mut reasoning_content =
-                            delta.reasoning_content.clone().unwrap_or_default();
-                        reasoning_content.push_str(&content_str);
-                        return Ok(Some(ComplitationStatus::Message(MessageChunk {
+                    if !parsed.content.is_empty() || !reasoning_content.is_empty() {
+                        statuses.push(ComplitationStatus::Message(MessageChunk {
                             role: delta.role,
-                            content: String::new(),
+                            content: parsed.content,
                             reasoning_content,
-                        })));
+                        }));
                     }
 
-                    let message_chunk = MessageChunk {
-                        role: delta.role.clone(),
-                        content: content_str,
-                        reasoning_content: delta.reasoning_content.unwrap_or_default(),
-                    };
+                    // The text before the calls is part of the same answer, it goes first
+                    if choice.finish_reason.is_some() && !state.tool_calls.is_empty() {
+                        statuses.push(ComplitationStatus::ToolCalls(std::mem::take(
+                            &mut state.tool_calls,
+                        )));
+                    }
 
-                    Ok(Some(ComplitationStatus::Message(message_chunk)))
+                    Ok(statuses)
                 }
-                Err(error) => Err(format!("Failed to parse message: {error}")),
+                Err(error) => Err(AppError::parse(format!("Failed to parse message: {error}"))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use serde_json::json;
+    use types::{
+        dto::{LmModel, ProviderDTO},
+        error::AppError,
+    };
+
+    use super::OpenAiApi;
+    use crate::{CancellationToken, ComplitationStatus, lm_backend::LmBackend, test_server};
+
+    fn lm_model(url: String) -> LmModel {
+        LmModel {
+            model_name: "model".to_string(),
+            provider: Some(ProviderDTO {
+                url,
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn chunk(delta: &serde_json::Value, finish_reason: Option<&str>) -> String {
+        let chunk = json!({
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": "model",
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+        });
+
+        format!("data: {chunk}\n\n")
+    }
+
+    #[tokio::test]
+    async fn text_held_back_at_tool_calls_is_streamed_before_them() {
+        let tool_call = json!({
+            "index": 0,
+            "id": "call_1",
+            "function": { "name": "calculator", "arguments": "{\"expression\": \"1 + 1\"}" },
+        });
+        let body = [
+            chunk(&json!({ "role": "assistant", "content": "Let me count <" }), None),
+            chunk(&json!({ "tool_calls": [tool_call] }), Some("tool_calls")),
+            "data: [DONE]\n\n".to_string(),
+        ]
+        .concat();
+        let url = test_server::serve_once(200, "text/event-stream", body).await;
+
+        let statuses: Vec<ComplitationStatus> = OpenAiApi::new()
+            .chat_completions(lm_model(url), vec![], None, vec![], CancellationToken::new())
+            .unwrap()
+            .collect()
+            .await;
+
+        assert_eq!(content(&statuses), "Let me count <");
+
+        let tool_calls = statuses
+            .iter()
+            .position(|status| matches!(status, ComplitationStatus::ToolCalls(calls) if calls.len() == 1))
+            .expect("No tool calls");
+        let last_message = statuses
+            .iter()
+            .rposition(|status| matches!(status, ComplitationStatus::Message(_)))
+            .expect("No message");
+        assert!(last_message < tool_calls);
+        assert!(matches!(statuses.last(), Some(ComplitationStatus::End(_))));
+    }
+
+    fn content(statuses: &[ComplitationStatus]) -> String {
+        statuses
+            .iter()
+            .filter_map(|status| match status {
+                ComplitationStatus::Message(chunk) => Some(chunk.content.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn text_held_back_without_finish_reason_is_kept_at_done() {
+        let body = [
+            chunk(&json!({ "role": "assistant", "content": "Almost <" }), None),
+            "data: [DONE]\n\n".to_string(),
+        ]
+        .concat();
+        let url = test_server::serve_once(200, "text/event-stream", body).await;
+
+        let statuses: Vec<ComplitationStatus> = OpenAiApi::new()
+            .chat_completions(lm_model(url), vec![], None, vec![], CancellationToken::new())
+            .unwrap()
+            .collect()
+            .await;
+
+        assert_eq!(content(&statuses), "Almost <");
+        assert!(matches!(statuses.last(), Some(ComplitationStatus::End(_))));
+    }
+
+    #[tokio::test]
+    async fn retries_without_stream_options_when_the_server_rejects_them() {
+        let rejection = json!({ "error": { "message": "Unrecognized request argument supplied: stream_options" } });
+        let body = [
+            chunk(&json!({ "role": "assistant", "content": "Hello" }), Some("stop")),
+            "data: [DONE]\n\n".to_string(),
+        ]
+        .concat();
+        let (url, requests) = test_server::serve(vec![
+            (400, "application/json", rejection.to_string()),
+            (200, "text/event-stream", body),
+        ])
+        .await;
+
+        let statuses: Vec<ComplitationStatus> = OpenAiApi::new()
+            .chat_completions(lm_model(url), vec![], None, vec![], CancellationToken::new())
+            .unwrap()
+            .collect()
+            .await;
+
+        assert_eq!(content(&statuses), "Hello");
+        assert!(matches!(statuses.first(), Some(ComplitationStatus::Start)));
+        assert_eq!(
+            statuses.iter().filter(|status| matches!(status, ComplitationStatus::Start)).count(),
+            1
+        );
+        assert!(!statuses.iter().any(|status| matches!(status, ComplitationStatus::Error(_))));
+        assert!(matches!(statuses.last(), Some(ComplitationStatus::End(_))));
+
+        let requests = requests.await.unwrap();
+        assert!(requests[0].contains("stream_options"));
+        assert!(!requests[1].contains("stream_options"));
+    }
+
+    async fn rerank(status: u16, body: &serde_json::Value) -> Result<Vec<(usize, f32)>, AppError> {
+        let url = test_server::serve_once(status, "application/json", body.to_string()).await;
+
+        let results = OpenAiApi::new()
+            .rerank(
+                lm_model(url),
+                "apples".to_string(),
+                vec!["pears".to_string(), "apples".to_string(), "plums".to_string()],
+            )
+            .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| (result.index, result.relevance_score))
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn rerank_reads_the_results() {
+        let body = json!({
+            "id": "rerank",
+            "results": [
+                { "index": 1, "relevance_score": 0.9 },
+                { "index": 2, "relevance_score": 0.25, "document": { "text": "plums" } },
+            ],
+        });
+
+        assert_eq!(rerank(200, &body).await.unwrap(), vec![(1, 0.9), (2, 0.25)]);
+    }
+
+    #[tokio::test]
+    async fn rerank_rejects_results_out_of_range() {
+        let body = json!({ "results": [{ "index": 3, "relevance_score": 0.9 }] });
+
+        assert!(matches!(rerank(200, &body).await, Err(AppError::Parse(_))));
+    }
+
+    #[tokio::test]
+    async fn rerank_maps_failed_responses_to_http_errors() {
+        let body = json!({ "error": "model not found" });
+
+        match rerank(404, &body).await {
+            Err(AppError::Http(status, body)) => {
+                assert_eq!(status, 404);
+                assert!(body.contains("model not found"));
             }
+            other => panic!("expected an HTTP error, got {other:?}"),
         }
-        Err(err) => Err(err.to_string()),
     }
 }
@@ -0,0 +1,229 @@
+/// Incrementally separates reasoning wrapped in tags (`<think>...</think>`)
+/// from the visible content of a streamed answer.
+///
+/// Tags may be split between deltas or share a delta with text, an unfinished
+/// prefix of a tag is kept until the next delta decides what it is. Tags of the
+/// same pair may be nested, only the outermost pair switches the output.
+#[derive(Debug, Default)]
+pub struct ReasoningParser {
+    tags: Vec<(String, String)>,
+    /// Index of the tag pair that opened the current reasoning block.
+    active: Option<usize>,
+    depth: usize,
+    pending: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ParsedDelta {
+    pub content: String,
+    pub reasoning: String,
+}
+
+impl ParsedDelta {
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.content.is_empty() && self.reasoning.is_empty()
+    }
+}
+
+enum TagMatch {
+    Open(usize),
+    Close,
+    Partial,
+    None,
+}
+
+impl ReasoningParser {
+    #[must_use]
+    pub fn new(tags: Vec<(String, String)>) -> Self {
+        Self {
+            tags: tags
+                .into_iter()
+                .filter(|(open, close)| !open.is_empty() && !close.is_empty())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[must_use]
+    pub const fn is_reasoning(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn push(&mut self, delta: &str) -> ParsedDelta {
+        let mut buffer = std::mem::take(&mut self.pending);
+        buffer.push_str(delta);
+
+        let mut parsed = ParsedDelta::default();
+        let mut position = 0;
+
+        while position < buffer.len() {
+            let rest = &buffer[position..];
+
+            match self.match_tag(rest) {
+                TagMatch::Open(index) => {
+                    let tag_len = self.tags[index].0.len();
+                    if self.active.is_none() {
+                        self.active = Some(index);
+                    }
+                    self.depth += 1;
+                    position += tag_len;
+                }
+                TagMatch::Close => {
+                    let tag_len = self.active.map_or(0, |index| self.tags[index].1.len());
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        self.active = None;
+                    }
+                    position += tag_len;
+                }
+                TagMatch::Partial => {
+                    self.pending = rest.to_string();
+                    break;
+                }
+                TagMatch::None => {
+                    let ch = rest.chars().next().unwrap_or_default();
+                    self.output(&mut parsed).push(ch);
+                    position += ch.len_utf8();
+                }
+            }
+        }
+
+        parsed
+    }
+
+    /// Flushes a held back tag prefix once the stream is over.
+    pub fn finish(&mut self) -> ParsedDelta {
+        let mut parsed = ParsedDelta::default();
+        let pending = std::mem::take(&mut self.pending);
+        self.output(&mut parsed).push_str(&pending);
+        parsed
+    }
+
+    const fn output<'a>(&self, parsed: &'a mut ParsedDelta) -> &'a mut String {
+        if self.active.is_some() {
+            &mut parsed.reasoning
+        } else {
+            &mut parsed.content
+        }
+    }
+
+    fn match_tag(&self, rest: &str) -> TagMatch {
+        if let Some(index) = self.active {
+            let (open, close) = &self.tags[index];
+            return Self::match_candidates(
+                rest,
+                [(index, close.as_str(), false), (index, open.as_str(), true)].into_iter(),
+            );
+        }
+
+        Self::match_candidates(
+            rest,
+            self.tags
+                .iter()
+                .enumerate()
+                .map(|(index, (open, _))| (index, open.as_str(), true)),
+        )
+    }
+
+    fn match_candidates<'a>(
+        rest: &str,
+        candidates: impl Iterator<Item = (usize, &'a str, bool)>,
+    ) -> TagMatch {
+        let mut partial = false;
+        for (index, tag, is_open) in candidates {
+            if rest.starts_with(tag) {
+                return if is_open {
+                    TagMatch::Open(index)
+                } else {
+                    TagMatch::Close
+                };
+            }
+
+            if tag.starts_with(rest) {
+                partial = true;
+            }
+        }
+
+        if partial {
+            TagMatch::Partial
+        } else {
+            TagMatch::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParsedDelta, ReasoningParser};
+
+    fn parser() -> ReasoningParser {
+        ReasoningParser::new(vec![("<think>".to_string(), "</think>".to_string())])
+    }
+
+    fn push_all(parser: &mut ReasoningParser, deltas: &[&str]) -> ParsedDelta {
+        let mut parsed = ParsedDelta::default();
+        for delta in deltas {
+            let delta = parser.push(delta);
+            parsed.content.push_str(&delta.content);
+            parsed.reasoning.push_str(&delta.reasoning);
+        }
+        parsed
+    }
+
+    #[test]
+    fn open_tag_split_between_deltas() {
+        let mut parser = parser();
+
+        assert!(parser.push("<th").is_empty());
+        let parsed = parser.push("ink>plan");
+
+        assert_eq!(parsed.reasoning, "plan");
+        assert!(parsed.content.is_empty());
+        assert!(parser.is_reasoning());
+    }
+
+    #[test]
+    fn tags_share_a_delta_with_text() {
+        let mut parser = parser();
+
+        let parsed = parser.push("before<think>plan</think>after");
+
+        assert_eq!(parsed.content, "beforeafter");
+        assert_eq!(parsed.reasoning, "plan");
+        assert!(!parser.is_reasoning());
+    }
+
+    #[test]
+    fn nested_tags_close_on_the_outermost() {
+        let mut parser = parser();
+
+        let parsed = push_all(&mut parser, &["<think>a<think>b</think>", "c</think>d"]);
+
+        assert_eq!(parsed.reasoning, "abc");
+        assert_eq!(parsed.content, "d");
+        assert!(!parser.is_reasoning());
+    }
+
+    #[test]
+    fn close_tag_split_between_three_deltas() {
+        let mut parser = parser();
+
+        let parsed = push_all(&mut parser, &["<think>plan</", "thi", "nk>answer"]);
+
+        assert_eq!(parsed.reasoning, "plan");
+        assert_eq!(parsed.content, "answer");
+    }
+
+    #[test]
+    fn finish_flushes_a_partial_tag_as_content() {
+        let mut parser = parser();
+
+        let parsed = parser.push("a < b, c <th");
+        assert_eq!(parsed.content, "a < b, c ");
+
+        let parsed = parser.finish();
+        assert_eq!(parsed.content, "<th");
+        assert!(parsed.reasoning.is_empty());
+    }
+}
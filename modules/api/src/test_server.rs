@@ -0,0 +1,78 @@
+//! HTTP server answering a few requests, the backends are tested against it.
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    task::JoinHandle,
+};
+
+/// Answers the first request with `status` and `body`, returns the base URL
+/// to give the provider.
+pub async fn serve_once(status: u16, content_type: &'static str, body: String) -> String {
+    serve(vec![(status, content_type, body)]).await.0
+}
+
+/// Answers one request with each of `responses` in turn, returns the base URL
+/// to give the provider and the requests it read.
+pub async fn serve(responses: Vec<(u16, &'static str, String)>) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind test server");
+    let url = format!(
+        "http://{}",
+        listener.local_addr().expect("Failed to get test server address")
+    );
+
+    let requests = tokio::spawn(async move {
+        let mut requests = vec![];
+
+        for (status, content_type, body) in responses {
+            let (mut socket, _) = listener.accept().await.expect("Failed to accept request");
+
+            let mut request = vec![];
+            let mut buffer = [0; 4096];
+            while !is_complete(&request) {
+                let read = socket.read(&mut buffer).await.expect("Failed to read request");
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            let response = format!(
+                "HTTP/1.1 {status} Test\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket
+                .write_all(response.as_bytes())
+                .await
+                .expect("Failed to write response");
+            let _ = socket.shutdown().await;
+
+            requests.push(String::from_utf8_lossy(&request).into_owned());
+        }
+
+        requests
+    });
+
+    (url, requests)
+}
+
+/// Headers and as much body as they announce were read.
+fn is_complete(request: &[u8]) -> bool {
+    let request = String::from_utf8_lossy(request);
+
+    let Some((headers, body)) = request.split_once("\r\n\r\n") else {
+        return false;
+    };
+
+    let length = headers
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    body.len() >= length
+}
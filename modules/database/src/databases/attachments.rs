@@ -0,0 +1,86 @@
+use types::dto::{AttachmentDTO, ConversationNodeID, MessageID};
+
+use crate::utils::DatabaseConnection;
+
+#[derive(Debug, Clone)]
+pub struct AttachmentsDatabase {
+    connection: DatabaseConnection,
+}
+
+impl AttachmentsDatabase {
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn insert_attachments(
+        &self,
+        message_id: MessageID,
+        attachments: &[AttachmentDTO],
+    ) -> Result<(), rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        Self::insert(&connection, message_id, attachments)
+    }
+
+    pub(crate) fn insert(
+        connection: &rusqlite::Connection,
+        message_id: MessageID,
+        attachments: &[AttachmentDTO],
+    ) -> Result<(), rusqlite::Error> {
+        for attachment in attachments {
+            connection.execute(
+                "INSERT INTO attachments (message_id, mime_type, data) VALUES (?, ?, ?)",
+                rusqlite::params![message_id, attachment.mime_type, attachment.data],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn get_attachments(
+        &self,
+        message_id: MessageID,
+    ) -> Result<Vec<AttachmentDTO>, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        let mut stmt = connection.prepare(
+            "SELECT id, message_id, mime_type, data
+             FROM attachments
+             WHERE message_id = ?
+             ORDER BY id",
+        )?;
+
+        let rows = stmt.query_map(rusqlite::params![message_id], |row| {
+            Ok(AttachmentDTO {
+                id: row.get(0)?,
+                message_id: row.get(1)?,
+                mime_type: row.get(2)?,
+                data: row.get(3)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn delete_conversation_attachments(
+        &self,
+        conversation_id: ConversationNodeID,
+    ) -> Result<(), rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        connection.execute(
+            "DELETE FROM attachments
+             WHERE message_id IN (SELECT id FROM messages WHERE conversation_id = ?)",
+            rusqlite::params![conversation_id],
+        )?;
+
+        Ok(())
+    }
+}
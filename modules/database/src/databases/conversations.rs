@@ -1,44 +1,52 @@
-use types::dto::{ConversationNodeDTO, ConversationNodeID, ConversationType, PresetId};
+use types::dto::{
+    ConversationNodeDTO, ConversationNodeID, ConversationType, DEFAULT_RAG_DISTANCE_CUTOFF, PresetId,
+};
 
 use crate::utils::DatabaseConnection;
 
 pub type ConversationTypeRaw = i32;
 
+const CONVERSATION_COLUMNS: &str = "
+    id,
+    name,
+    parent_id,
+    type,
+    ordr,
+    preset_id,
+    max_messages,
+    embedding_provider,
+    embedding_model,
+    rag_chunk_size,
+    rag_chunks_count,
+    summary_enabled,
+    summary_model,
+    summary_provider,
+    provider,
+    model,
+    prompt,
+    tools_enabled,
+    context_size,
+    rag_vector_weight,
+    rag_keyword_weight,
+    rag_distance_cutoff,
+    rag_rerank_candidates,
+    rerank_provider,
+    rerank_model,
+    rag_query_rewrite,
+    rag_sub_queries,
+    rag_hyde,
+    rewrite_provider,
+    rewrite_model
+";
+
 #[derive(Debug, Clone)]
 pub struct ConversationDatabase {
     connection: DatabaseConnection,
 }
 
 impl ConversationDatabase {
-    /// # Panics
-    pub fn new(connection: DatabaseConnection) -> Self {
-        connection
-            .lock()
-            .unwrap()
-            .execute(
-                "CREATE TABLE IF NOT EXISTS conversations (
-                    id INTEGER PRIMARY KEY,
-                    name TEXT NOT NULL,
-                    parent_id INTEGER,
-                    type INTEGER NOT NULL,
-                    ordr INTEGER DEFAULT 0,
-                    preset_id INTEGER,
-                    max_messages INTEGER NOT NULL,
-                    embedding_provider INTEGER,
-                    embedding_model TEXT,
-                    rag_chunk_size INTEGER NOT NULL,
-                    rag_chunks_count INTEGER NOT NULL,
-                    summary_enabled INTEGER DEFAULT 0,
-                    summary_model TEXT,
-                    summary_provider INTEGER,
-                    provider INTEGER,
-                    model TEXT,
-                    prompt TEXT
-            )",
-                [],
-            )
-            .expect("Failed to create conversations table");
-
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
         Self { connection }
     }
 
@@ -50,49 +58,11 @@ impl ConversationDatabase {
     ) -> Result<ConversationNodeDTO, rusqlite::Error> {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
-        let mut stmt = connection.prepare(
-            "SELECT 
-                id,
-                name,
-                parent_id,
-                type,
-                ordr,
-                preset_id,
-                max_messages,
-                embedding_provider,
-                embedding_model,
-                rag_chunk_size,
-                rag_chunks_count,
-                summary_enabled,
-                summary_model,
-                summary_provider,
-                provider,
-                model,
-                prompt
-            FROM conversations WHERE id = ?",
-        )?;
+        let mut stmt = connection.prepare(&format!(
+            "SELECT {CONVERSATION_COLUMNS} FROM conversations WHERE id = ?"
+        ))?;
 
-        let mut conversation_iter = stmt.query_map(rusqlite::params![id], |row| {
-            Ok(ConversationNodeDTO {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                parent_id: row.get(2)?,
-                tp: Self::conversation_type_from_int(row.get(3)?),
-                order: row.get(4)?,
-                preset_id: row.get(5)?,
-                max_messages: row.get(6)?,
-                embedding_provider: row.get(7)?,
-                embedding_model: row.get(8)?,
-                rag_chunk_size: row.get(9).unwrap_or(512),
-                rag_chunks_count: row.get(10).unwrap_or(2),
-                summary_enabled: row.get(11).unwrap_or_default(),
-                summary_model: row.get(12).unwrap_or_default(),
-                summary_provider: row.get(13).unwrap_or_default(),
-                provider: row.get(14).unwrap_or_default(),
-                model: row.get(15).unwrap_or_default(),
-                prompt: row.get(16).unwrap_or_default(),
-            })
-        })?;
+        let mut conversation_iter = stmt.query_map(rusqlite::params![id], Self::row_to_dto)?;
 
         let conversation = conversation_iter
             .next()
@@ -141,48 +111,11 @@ impl ConversationDatabase {
     ) -> Result<Vec<ConversationNodeDTO>, rusqlite::Error> {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
-        let mut stmt = connection
-            .prepare("SELECT
-                id,
-                name,
-                parent_id,
-                type,
-                ordr,
-                preset_id,
-                max_messages,
-                embedding_provider,
-                embedding_model,
-                rag_chunk_size,
-                rag_chunks_count,
-                summary_enabled,
-                summary_model,
-                summary_provider,
-                provider,
-                model,
-                prompt
-            FROM conversations WHERE parent_id = ? ORDER BY ordr")?;
-
-        let conversation_iter = stmt.query_map(rusqlite::params![parent_id], |row| {
-            Ok(ConversationNodeDTO {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                parent_id: row.get(2)?,
-                tp: Self::conversation_type_from_int(row.get(3)?),
-                order: row.get(4)?,
-                preset_id: row.get(5)?,
-                max_messages: row.get(6)?,
-                embedding_provider: row.get(7)?,
-                embedding_model: row.get(8)?,
-                rag_chunk_size: row.get(9)?,
-                rag_chunks_count: row.get(10)?,
-                summary_enabled: row.get(11).unwrap_or_default(),
-                summary_model: row.get(12).unwrap_or_default(),
-                summary_provider: row.get(13).unwrap_or_default(),
-                provider: row.get(14).unwrap_or_default(),
-                model: row.get(15).unwrap_or_default(),
-                prompt: row.get(16).unwrap_or_default(),
-            })
-        })?;
+        let mut stmt = connection.prepare(&format!(
+            "SELECT {CONVERSATION_COLUMNS} FROM conversations WHERE parent_id = ? ORDER BY ordr"
+        ))?;
+
+        let conversation_iter = stmt.query_map(rusqlite::params![parent_id], Self::row_to_dto)?;
 
         let conversations: Vec<ConversationNodeDTO> =
             conversation_iter.collect::<Result<_, _>>()?;
@@ -234,8 +167,7 @@ impl ConversationDatabase {
             .execute(
                 "UPDATE conversations SET preset_id = ? WHERE id = ?",
                 rusqlite::params![preset_id, id],
-            )
-            .expect("Failed to set preset");
+            )?;
 
         Ok(())
     }
@@ -264,7 +196,20 @@ impl ConversationDatabase {
                     summary_provider = ?,
                     provider = ?,
                     model = ?,
-                    prompt = ?
+                    prompt = ?,
+                    tools_enabled = ?,
+                    context_size = ?,
+                    rag_vector_weight = ?,
+                    rag_keyword_weight = ?,
+                    rag_distance_cutoff = ?,
+                    rag_rerank_candidates = ?,
+                    rerank_provider = ?,
+                    rerank_model = ?,
+                    rag_query_rewrite = ?,
+                    rag_sub_queries = ?,
+                    rag_hyde = ?,
+                    rewrite_provider = ?,
+                    rewrite_model = ?
                 WHERE id = ?",
                 rusqlite::params![
                     new_dto.name,
@@ -281,11 +226,23 @@ impl ConversationDatabase {
                     new_dto.provider,
                     new_dto.model,
                     new_dto.prompt,
+                    new_dto.tools_enabled,
+                    new_dto.context_size,
+                    new_dto.rag_vector_weight,
+                    new_dto.rag_keyword_weight,
+                    new_dto.rag_distance_cutoff,
+                    new_dto.rag_rerank_candidates,
+                    new_dto.rerank_provider,
+                    new_dto.rerank_model,
+                    new_dto.rag_query_rewrite,
+                    new_dto.rag_sub_queries,
+                    new_dto.rag_hyde,
+                    new_dto.rewrite_provider,
+                    new_dto.rewrite_model,
 
                     id
                 ],
-            )
-            .expect("Failed to rename conversation");
+            )?;
 
         Ok(())
     }
@@ -298,12 +255,131 @@ impl ConversationDatabase {
             .execute(
                 "DELETE FROM conversations WHERE id = ?",
                 rusqlite::params![id],
-            )
-            .expect("Failed to delete conversation");
+            )?;
 
         Ok(())
     }
 
+    /// Adds the node with all its settings at the end of `parent_id`.
+    /// Providers and presets that do not exist here are dropped.
+    pub(crate) fn insert_imported(
+        connection: &rusqlite::Connection,
+        parent_id: ConversationNodeID,
+        dto: &ConversationNodeDTO,
+        import_source: Option<&str>,
+    ) -> Result<ConversationNodeID, rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO conversations (
+                name,
+                parent_id,
+                type,
+                ordr,
+                preset_id,
+                max_messages,
+                embedding_provider,
+                embedding_model,
+                rag_chunk_size,
+                rag_chunks_count,
+                summary_enabled,
+                summary_model,
+                summary_provider,
+                provider,
+                model,
+                prompt,
+                tools_enabled,
+                context_size,
+                import_source,
+                rag_vector_weight,
+                rag_keyword_weight,
+                rag_distance_cutoff,
+                rag_rerank_candidates,
+                rerank_provider,
+                rerank_model,
+                rag_query_rewrite,
+                rag_sub_queries,
+                rag_hyde,
+                rewrite_provider,
+                rewrite_model
+            ) VALUES (
+                ?1,
+                ?2,
+                ?3,
+                (SELECT COALESCE(MAX(ordr) + 1, 0) FROM conversations WHERE parent_id = ?2),
+                (SELECT id FROM presets WHERE id = ?4),
+                ?5,
+                (SELECT id FROM providers WHERE id = ?6),
+                ?7,
+                ?8,
+                ?9,
+                ?10,
+                ?11,
+                (SELECT id FROM providers WHERE id = ?12),
+                (SELECT id FROM providers WHERE id = ?13),
+                ?14,
+                ?15,
+                ?16,
+                ?17,
+                ?18,
+                ?19,
+                ?20,
+                ?21,
+                ?22,
+                (SELECT id FROM providers WHERE id = ?23),
+                ?24,
+                ?25,
+                ?26,
+                ?27,
+                (SELECT id FROM providers WHERE id = ?28),
+                ?29
+            )",
+            rusqlite::params![
+                dto.name,
+                parent_id,
+                Self::conversation_type_to_int(&dto.tp),
+                dto.preset_id,
+                dto.max_messages,
+                dto.embedding_provider,
+                dto.embedding_model,
+                dto.rag_chunk_size,
+                dto.rag_chunks_count,
+                dto.summary_enabled,
+                dto.summary_model,
+                dto.summary_provider,
+                dto.provider,
+                dto.model,
+                dto.prompt,
+                dto.tools_enabled,
+                dto.context_size,
+                import_source,
+                dto.rag_vector_weight,
+                dto.rag_keyword_weight,
+                dto.rag_distance_cutoff,
+                dto.rag_rerank_candidates,
+                dto.rerank_provider,
+                dto.rerank_model,
+                dto.rag_query_rewrite,
+                dto.rag_sub_queries,
+                dto.rag_hyde,
+                dto.rewrite_provider,
+                dto.rewrite_model,
+            ],
+        )?;
+
+        Ok(connection.last_insert_rowid())
+    }
+
+    /// Whether a chat imported from `import_source` still exists.
+    pub(crate) fn is_imported(
+        connection: &rusqlite::Connection,
+        import_source: &str,
+    ) -> Result<bool, rusqlite::Error> {
+        connection.query_row(
+            "SELECT COUNT(*) > 0 FROM conversations WHERE import_source = ?",
+            rusqlite::params![import_source],
+            |row| row.get(0),
+        )
+    }
+
     fn recusive_move_conversation(
         &self,
         moving: ConversationNodeID,
@@ -312,8 +388,7 @@ impl ConversationDatabase {
     ) -> Result<(), rusqlite::Error> {
         let mut connection = self.connection.lock().expect("Failed to lock connection");
         let transaction = connection
-            .transaction()
-            .expect("Failed to create transaction");
+            .transaction()?;
 
         transaction
             .prepare("UPDATE conversations SET ordr = ordr + 1 WHERE parent_id = ? AND ordr >= ?")?
@@ -327,7 +402,7 @@ impl ConversationDatabase {
             .prepare("UPDATE conversations SET parent_id = ?, ordr = ? WHERE id = ?")?
             .execute(rusqlite::params![new_parent, new_index, moving])?;
 
-        transaction.commit().expect("Failed to commit transaction");
+        transaction.commit()?;
 
         Ok(())
     }
@@ -337,8 +412,7 @@ impl ConversationDatabase {
 
         let mut connection = self.connection.lock().expect("Failed to lock connection");
         let transaction = connection
-            .transaction()
-            .expect("Failed to create transaction");
+            .transaction()?;
 
         for (index, conversation) in conversations.iter().enumerate() {
             transaction
@@ -346,11 +420,46 @@ impl ConversationDatabase {
                 .execute(rusqlite::params![index, conversation.id])?;
         }
 
-        transaction.commit().expect("Failed to commit transaction");
+        transaction.commit()?;
 
         Ok(())
     }
 
+    fn row_to_dto(row: &rusqlite::Row) -> Result<ConversationNodeDTO, rusqlite::Error> {
+        Ok(ConversationNodeDTO {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            parent_id: row.get(2)?,
+            tp: Self::conversation_type_from_int(row.get(3)?)?,
+            order: row.get(4)?,
+            preset_id: row.get(5)?,
+            max_messages: row.get(6)?,
+            embedding_provider: row.get(7)?,
+            embedding_model: row.get(8)?,
+            rag_chunk_size: row.get(9).unwrap_or(512),
+            rag_chunks_count: row.get(10).unwrap_or(2),
+            summary_enabled: row.get(11).unwrap_or_default(),
+            summary_model: row.get(12).unwrap_or_default(),
+            summary_provider: row.get(13).unwrap_or_default(),
+            provider: row.get(14).unwrap_or_default(),
+            model: row.get(15).unwrap_or_default(),
+            prompt: row.get(16).unwrap_or_default(),
+            tools_enabled: row.get(17).unwrap_or_default(),
+            context_size: row.get(18).unwrap_or_default(),
+            rag_vector_weight: row.get(19).unwrap_or(1.0),
+            rag_keyword_weight: row.get(20).unwrap_or(1.0),
+            rag_distance_cutoff: row.get(21).unwrap_or(DEFAULT_RAG_DISTANCE_CUTOFF),
+            rag_rerank_candidates: row.get(22).unwrap_or_default(),
+            rerank_provider: row.get(23).unwrap_or_default(),
+            rerank_model: row.get(24).unwrap_or_default(),
+            rag_query_rewrite: row.get(25).unwrap_or_default(),
+            rag_sub_queries: row.get(26).unwrap_or_default(),
+            rag_hyde: row.get(27).unwrap_or_default(),
+            rewrite_provider: row.get(28).unwrap_or_default(),
+            rewrite_model: row.get(29).unwrap_or_default(),
+        })
+    }
+
     const fn conversation_type_to_int(tp: &ConversationType) -> ConversationTypeRaw {
         match tp {
             ConversationType::Chat => 0,
@@ -358,11 +467,13 @@ impl ConversationDatabase {
         }
     }
 
-    fn conversation_type_from_int(tp: ConversationTypeRaw) -> ConversationType {
+    fn conversation_type_from_int(
+        tp: ConversationTypeRaw,
+    ) -> Result<ConversationType, rusqlite::Error> {
         match tp {
-            0 => ConversationType::Chat,
-            1 => ConversationType::Folder,
-            _ => panic!("Invalid conversation type"),
+            0 => Ok(ConversationType::Chat),
+            1 => Ok(ConversationType::Folder),
+            _ => Err(rusqlite::Error::IntegralValueOutOfRange(3, tp.into())),
         }
     }
 
@@ -377,8 +488,7 @@ impl ConversationDatabase {
 
         let mut connection = self.connection.lock().expect("Failed to lock connection");
         let transaction = connection
-            .transaction()
-            .expect("Failed to create transaction");
+            .transaction()?;
 
         transaction
             .prepare("UPDATE conversations SET ordr = ordr + 1 WHERE parent_id = ?")?
@@ -402,7 +512,7 @@ impl ConversationDatabase {
                 2
             ])?;
 
-        transaction.commit().expect("Failed to commit transaction");
+        transaction.commit()?;
 
         Ok(connection.last_insert_rowid() as ConversationNodeID)
     }
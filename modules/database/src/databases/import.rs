@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use rusqlite::params;
+use types::dto::{
+    ConversationNodeDTO, ConversationNodeID, ConversationType, ImportNodeDTO, ImportReportDTO,
+    MessageID,
+};
+
+use crate::DatabaseConnection;
+
+use super::{ConversationDatabase, MessagesDatabase};
+
+#[derive(Debug, Clone)]
+pub struct ImportDatabase {
+    connection: DatabaseConnection,
+}
+
+impl ImportDatabase {
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// Writes the nodes into the `parent_id` folder in one transaction,
+    /// nothing is imported if any of them fails. Chats imported before are
+    /// skipped and reported as duplicates.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn import(
+        &self,
+        parent_id: ConversationNodeID,
+        nodes: &[ImportNodeDTO],
+    ) -> Result<ImportReportDTO, rusqlite::Error> {
+        let mut connection = self.connection.lock().expect("Failed to lock connection");
+        let transaction = connection.transaction()?;
+
+        let mut report = ImportReportDTO::default();
+
+        for node in nodes {
+            Self::import_node(&transaction, parent_id, node, &mut report)?;
+        }
+
+        transaction.commit()?;
+
+        Ok(report)
+    }
+
+    fn import_node(
+        connection: &rusqlite::Connection,
+        parent_id: ConversationNodeID,
+        node: &ImportNodeDTO,
+        report: &mut ImportReportDTO,
+    ) -> Result<(), rusqlite::Error> {
+        match node {
+            ImportNodeDTO::Folder { name, children } => {
+                let folder = ConversationNodeDTO {
+                    name: name.clone(),
+                    tp: ConversationType::Folder,
+                    ..ConversationNodeDTO::empty_with_id(0)
+                };
+
+                let folder_id =
+                    ConversationDatabase::insert_imported(connection, parent_id, &folder, None)?;
+                let chats = report.chats;
+
+                for child in children {
+                    Self::import_node(connection, folder_id, child, report)?;
+                }
+
+                // Everything in it was imported before
+                if report.chats == chats {
+                    connection.execute("DELETE FROM conversations WHERE id = ?", params![folder_id])?;
+                } else {
+                    report.folders += 1;
+                }
+            }
+            ImportNodeDTO::Chat { source, chat } => {
+                if ConversationDatabase::is_imported(connection, source)? {
+                    report.duplicates.push(chat.conversation.name.clone());
+                    return Ok(());
+                }
+
+                let conversation = ConversationNodeDTO {
+                    tp: ConversationType::Chat,
+                    ..chat.conversation.clone()
+                };
+
+                let conversation_id = ConversationDatabase::insert_imported(
+                    connection,
+                    parent_id,
+                    &conversation,
+                    Some(source),
+                )?;
+
+                // Ids of the document only link its messages
+                let mut ids: HashMap<MessageID, MessageID> = HashMap::new();
+                let mut last_id = None;
+
+                for message in &chat.messages {
+                    let parent_id = message.parent_id.and_then(|id| ids.get(&id).copied());
+                    let id = MessagesDatabase::insert_imported(
+                        connection,
+                        conversation_id,
+                        parent_id,
+                        message,
+                    )?;
+
+                    ids.insert(message.id, id);
+                    last_id = Some(id);
+                }
+
+                let active_id = chat
+                    .active_message_id
+                    .and_then(|id| ids.get(&id).copied())
+                    .or(last_id);
+                MessagesDatabase::set_active(connection, conversation_id, active_id)?;
+
+                report.chats += 1;
+                report.messages += chat.messages.len();
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use rusqlite::{OptionalExtension, params};
+use types::dto::{ConversationNodeID, KnowledgeBaseDTO, KnowledgeBaseID};
+
+use crate::DatabaseConnection;
+
+const KNOWLEDGE_BASE_COLUMNS: &str = "id, name, embedding_provider, embedding_model, chunk_size";
+
+/// Knowledge bases and the chats and folders they are attached to. Their files
+/// are kept by [`super::VectorDatabase`].
+#[derive(Debug, Clone)]
+pub struct KnowledgeBasesDatabase {
+    connection: DatabaseConnection,
+}
+
+impl KnowledgeBasesDatabase {
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn create(&self, name: &str) -> Result<KnowledgeBaseDTO, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        connection.execute(
+            "INSERT INTO knowledge_bases (name) VALUES (?)",
+            params![name],
+        )?;
+
+        connection.query_row(
+            &format!("SELECT {KNOWLEDGE_BASE_COLUMNS} FROM knowledge_bases WHERE id = ?"),
+            params![connection.last_insert_rowid()],
+            Self::row_to_dto,
+        )
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn update(&self, dto: &KnowledgeBaseDTO) -> Result<(), rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        connection.execute(
+            "UPDATE knowledge_bases
+             SET name = ?, embedding_provider = ?, embedding_model = ?, chunk_size = ?
+             WHERE id = ?",
+            params![
+                dto.name,
+                dto.embedding_provider,
+                dto.embedding_model,
+                dto.chunk_size,
+                dto.id,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Files of the knowledge base have to be deleted before.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn delete(&self, id: KnowledgeBaseID) -> Result<(), rusqlite::Error> {
+        let mut connection = self.connection.lock().expect("Failed to lock connection");
+        let transaction = connection.transaction()?;
+
+        transaction.execute(
+            "DELETE FROM knowledge_base_links WHERE knowledge_base_id = ?",
+            params![id],
+        )?;
+        transaction.execute("DELETE FROM knowledge_bases WHERE id = ?", params![id])?;
+
+        transaction.commit()
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn get(&self, id: KnowledgeBaseID) -> Result<Option<KnowledgeBaseDTO>, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        connection
+            .query_row(
+                &format!("SELECT {KNOWLEDGE_BASE_COLUMNS} FROM knowledge_bases WHERE id = ?"),
+                params![id],
+                Self::row_to_dto,
+            )
+            .optional()
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn get_all(&self) -> Result<Vec<KnowledgeBaseDTO>, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        let mut stmt = connection.prepare(&format!(
+            "SELECT {KNOWLEDGE_BASE_COLUMNS} FROM knowledge_bases ORDER BY name COLLATE NOCASE"
+        ))?;
+
+        stmt.query_map([], Self::row_to_dto)?.collect()
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn attach(
+        &self,
+        id: KnowledgeBaseID,
+        node_id: ConversationNodeID,
+    ) -> Result<(), rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        connection.execute(
+            "INSERT OR IGNORE INTO knowledge_base_links (knowledge_base_id, node_id) VALUES (?, ?)",
+            params![id, node_id],
+        )?;
+
+        Ok(())
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn detach(
+        &self,
+        id: KnowledgeBaseID,
+        node_id: ConversationNodeID,
+    ) -> Result<(), rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        connection.execute(
+            "DELETE FROM knowledge_base_links WHERE knowledge_base_id = ? AND node_id = ?",
+            params![id, node_id],
+        )?;
+
+        Ok(())
+    }
+
+    /// For a deleted chat or folder.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn detach_node(&self, node_id: ConversationNodeID) -> Result<(), rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        connection.execute(
+            "DELETE FROM knowledge_base_links WHERE node_id = ?",
+            params![node_id],
+        )?;
+
+        Ok(())
+    }
+
+    /// Chats and folders the knowledge base is attached to.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn get_nodes(&self, id: KnowledgeBaseID) -> Result<Vec<ConversationNodeID>, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        let mut stmt = connection.prepare(
+            "SELECT node_id FROM knowledge_base_links WHERE knowledge_base_id = ? ORDER BY node_id",
+        )?;
+
+        stmt.query_map(params![id], |row| row.get(0))?.collect()
+    }
+
+    /// Knowledge bases attached to the node itself, not to its folders.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn get_linked(&self, node_id: ConversationNodeID) -> Result<Vec<KnowledgeBaseID>, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        let mut stmt = connection.prepare(
+            "SELECT knowledge_base_id FROM knowledge_base_links WHERE node_id = ?",
+        )?;
+
+        stmt.query_map(params![node_id], |row| row.get(0))?.collect()
+    }
+
+    /// Knowledge bases a chat searches, attached to it or to any folder above it.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn get_attached(
+        &self,
+        conversation_id: ConversationNodeID,
+    ) -> Result<Vec<KnowledgeBaseDTO>, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        let mut stmt = connection.prepare(&format!(
+            "WITH RECURSIVE ancestors(id) AS (
+                SELECT ?
+                UNION
+                SELECT conversations.parent_id FROM conversations
+                JOIN ancestors ON conversations.id = ancestors.id
+            )
+            SELECT {KNOWLEDGE_BASE_COLUMNS} FROM knowledge_bases
+            WHERE id IN (
+                SELECT knowledge_base_id FROM knowledge_base_links
+                WHERE node_id IN (SELECT id FROM ancestors)
+            )
+            ORDER BY name COLLATE NOCASE"
+        ))?;
+
+        stmt.query_map(params![conversation_id], Self::row_to_dto)?.collect()
+    }
+
+    fn row_to_dto(row: &rusqlite::Row) -> Result<KnowledgeBaseDTO, rusqlite::Error> {
+        Ok(KnowledgeBaseDTO {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            embedding_provider: row.get(2)?,
+            embedding_model: row.get(3)?,
+            chunk_size: row.get(4)?,
+        })
+    }
+}
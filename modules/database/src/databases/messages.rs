@@ -1,47 +1,86 @@
-use types::dto::{ConversationNodeID, MessageDTO, MessageUsedRagChunk, RoleType};
+use rusqlite::OptionalExtension;
+use types::dto::{
+    ConversationNodeID, GenerationDTO, MessageDTO, MessageID, MessageSearchFilters, MessageSearchResultDTO,
+    MessageUsedRagChunk, RoleType, SEARCH_MATCH_END, SEARCH_MATCH_START,
+};
 
 use crate::utils::DatabaseConnection;
 
+use super::AttachmentsDatabase;
+
+const MESSAGE_COLUMNS: &str =
+    "id, conversation_id, content, reasoning, timestamp, role, summary, chunks, interrupted, tool_calls, tool_call_id, parent_id,
+     provider_id, model, preset, prompt_tokens, completion_tokens, time_to_first_token, duration, finish_reason, rag_queries";
+
+/// Ids of the branch conversation `?1` shows, from its active message up to the first one.
+const ACTIVE_BRANCH: &str = "WITH RECURSIVE branch(id) AS (
+        SELECT active_message_id FROM conversations WHERE id = ?1
+        UNION ALL
+        SELECT messages.parent_id FROM messages
+        JOIN branch ON messages.id = branch.id
+        WHERE messages.parent_id IS NOT NULL
+    )";
+
 #[derive(Debug, Clone)]
 pub struct MessagesDatabase {
     connection: DatabaseConnection,
+    attachments_db: AttachmentsDatabase,
 }
 
 impl MessagesDatabase {
-    /// # Panics
+    #[must_use]
     pub fn new(connection: DatabaseConnection) -> Self {
-        connection
-            .lock()
-            .unwrap()
-            .execute(
-                "CREATE TABLE IF NOT EXISTS messages (
-                  id INTEGER PRIMARY KEY,
-                  conversation_id INTEGER NOT NULL,
-                  content TEXT NOT NULL,
-                  reasoning TEXT,
-                  timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
-                  role INTEGER NOT NULL,
-                  summary TEXT,
-                  chunks TEXT
-              )",
-                [],
-            )
-            .expect("Failed to create messages table");
-
-        Self { connection }
+        Self {
+            attachments_db: AttachmentsDatabase::new(connection.clone()),
+            connection,
+        }
     }
 
+    /// Messages following the deleted one move up to its parent.
+    ///
     /// # Errors
     /// # Panics
     pub fn delete_message(&self, message_id: i64) -> Result<(), rusqlite::Error> {
-        let connection = self.connection.lock().expect("Failed to lock connection");
+        let mut connection = self.connection.lock().expect("Failed to lock connection");
+        let transaction = connection.transaction()?;
+
+        let Some((conversation_id, parent_id)) = transaction
+            .query_row(
+                "SELECT conversation_id, parent_id FROM messages WHERE id = ?",
+                rusqlite::params![message_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+        else {
+            return Ok(());
+        };
 
-        connection.execute(
+        transaction.execute(
+            "UPDATE messages SET parent_id = ? WHERE parent_id = ?",
+            rusqlite::params![parent_id, message_id],
+        )?;
+
+        transaction.execute(
+            "DELETE FROM attachments WHERE message_id = ?",
+            rusqlite::params![message_id],
+        )?;
+
+        transaction.execute(
             "DELETE FROM messages WHERE id = ?",
             rusqlite::params![message_id],
         )?;
 
-        Ok(())
+        transaction.execute(
+            "DELETE FROM messages_fts WHERE rowid = ?",
+            rusqlite::params![message_id],
+        )?;
+
+        if Self::active_message(&transaction, conversation_id)? == Some(message_id) {
+            let leaf = Self::latest_leaf(&transaction, conversation_id, parent_id)?;
+            Self::set_active(&transaction, conversation_id, leaf)?;
+        }
+
+        transaction.commit()
     }
 
     /// # Errors
@@ -49,11 +88,11 @@ impl MessagesDatabase {
     pub fn get_message(&self, message_id: i64) -> Result<MessageDTO, rusqlite::Error> {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
-        let mut stmt = connection.prepare(
-            "SELECT id, conversation_id, content, reasoning, timestamp, role, summary
+        let mut stmt = connection.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS}
              FROM messages 
-             WHERE id = ?",
-        )?;
+             WHERE id = ?"
+        ))?;
 
         let row_mapper = |row: &rusqlite::Row| -> Result<MessageDTO, rusqlite::Error> {
             let message = Self::row_to_message(row)?;
@@ -64,9 +103,71 @@ impl MessagesDatabase {
 
         let messages: Result<Vec<MessageDTO>, rusqlite::Error> = rows.collect();
 
-        messages
+        let mut message = messages
             .map(|mut v| v.pop())
-            .and_then(|v| v.ok_or(rusqlite::Error::QueryReturnedNoRows))
+            .and_then(|v| v.ok_or(rusqlite::Error::QueryReturnedNoRows))?;
+
+        drop(stmt);
+
+        message.siblings = Self::siblings(&connection, &message)?;
+        drop(connection);
+
+        message.attachments = self.attachments_db.get_attachments(message.id)?;
+
+        Ok(message)
+    }
+
+    /// Messages of every branch of the conversation, oldest first.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn get_all_messages(
+        &self,
+        conversation_id: ConversationNodeID,
+    ) -> Result<Vec<MessageDTO>, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        let mut stmt = connection.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS}
+             FROM messages
+             WHERE conversation_id = ?
+             ORDER BY id"
+        ))?;
+
+        let mut messages = stmt
+            .query_map(rusqlite::params![conversation_id], Self::row_to_message)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        drop(stmt);
+
+        for message in &mut messages {
+            message.siblings = Self::siblings(&connection, message)?;
+        }
+
+        drop(connection);
+
+        for message in &mut messages {
+            message.attachments = self.attachments_db.get_attachments(message.id)?;
+        }
+
+        Ok(messages)
+    }
+
+    /// Last message of the branch the conversation shows.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn get_active_message(
+        &self,
+        conversation_id: ConversationNodeID,
+    ) -> Result<Option<MessageID>, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        connection.query_row(
+            "SELECT active_message_id FROM conversations WHERE id = ?",
+            rusqlite::params![conversation_id],
+            |row| row.get(0),
+        )
     }
 
     /// # Errors
@@ -75,8 +176,17 @@ impl MessagesDatabase {
         &self,
         conversation_id: ConversationNodeID,
     ) -> Result<(), rusqlite::Error> {
+        self.attachments_db
+            .delete_conversation_attachments(conversation_id)?;
+
         let connection = self.connection.lock().expect("Failed to lock connection");
 
+        connection.execute(
+            "DELETE FROM messages_fts
+             WHERE rowid IN (SELECT id FROM messages WHERE conversation_id = ?)",
+            rusqlite::params![conversation_id],
+        )?;
+
         connection.execute(
             "DELETE FROM messages WHERE conversation_id = ?",
             rusqlite::params![conversation_id],
@@ -95,64 +205,182 @@ impl MessagesDatabase {
         role: &RoleType,
         chunks: &[MessageUsedRagChunk],
     ) -> Result<(), rusqlite::Error> {
+        self.insert_message_dto(&MessageDTO {
+            conversation_id,
+            content: content.to_string(),
+            reasoning: Some(reasoning.to_string()),
+            role: role.clone(),
+            chunks: chunks.to_vec(),
+            ..Default::default()
+        })
+    }
+ 
+
+    /// The message continues the branch the conversation shows and becomes
+    /// its active message.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn insert_message_dto(&self, message_dto: &MessageDTO) -> Result<(), rusqlite::Error> {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
+        let parent_id = Self::active_message(&connection, message_dto.conversation_id)?;
+        let generation = message_dto.generation.as_ref();
+
         connection.execute(
             "INSERT INTO messages (
                 conversation_id,
+                parent_id,
                 content,
                 reasoning,
                 role,
-                chunks
-            ) VALUES (?, ?, ?, ?, ?)",
+                chunks,
+                interrupted,
+                tool_calls,
+                tool_call_id,
+                provider_id,
+                model,
+                preset,
+                prompt_tokens,
+                completion_tokens,
+                time_to_first_token,
+                duration,
+                finish_reason,
+                rag_queries
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             rusqlite::params![
+                message_dto.conversation_id,
+                parent_id,
+                message_dto.content,
+                message_dto.reasoning.clone().unwrap_or_default(),
+                Self::message_role_to_int(&message_dto.role),
+                serde_json::to_string(&message_dto.chunks).unwrap_or_default(),
+                message_dto.interrupted,
+                serde_json::to_string(&message_dto.tool_calls).unwrap_or_default(),
+                message_dto.tool_call_id,
+                generation.and_then(|generation| generation.provider_id),
+                generation.map(|generation| generation.model.clone()),
+                generation
+                    .and_then(|generation| generation.preset.as_ref())
+                    .and_then(|preset| serde_json::to_string(preset).ok()),
+                generation.map(|generation| generation.prompt_tokens),
+                generation.map(|generation| generation.completion_tokens),
+                generation.and_then(|generation| generation.time_to_first_token),
+                generation.and_then(|generation| generation.duration),
+                generation.and_then(|generation| generation.finish_reason.clone()),
+                serde_json::to_string(&message_dto.rag_queries).unwrap_or_default(),
+            ],
+        )?;
+
+        let message_id = connection.last_insert_rowid();
+
+        Self::set_active(&connection, message_dto.conversation_id, Some(message_id))?;
+        Self::index_message(&connection, message_id, message_dto)?;
+        drop(connection);
+
+        self.attachments_db
+            .insert_attachments(message_id, &message_dto.attachments)
+    }
+
+    /// Writes the message as it is, below `parent_id`, keeping its time and
+    /// generation. The shown branch is left to the caller.
+    pub(crate) fn insert_imported(
+        connection: &rusqlite::Connection,
+        conversation_id: ConversationNodeID,
+        parent_id: Option<MessageID>,
+        message_dto: &MessageDTO,
+    ) -> Result<MessageID, rusqlite::Error> {
+        let generation = message_dto.generation.as_ref();
+        let timestamp = Some(message_dto.timestamp.as_str()).filter(|timestamp| !timestamp.is_empty());
+
+        connection.execute(
+            "INSERT INTO messages (
                 conversation_id,
+                parent_id,
+                timestamp,
                 content,
                 reasoning,
-                Self::message_role_to_int(role),
-                serde_json::to_string(&chunks).unwrap_or_default(),
+                role,
+                summary,
+                chunks,
+                interrupted,
+                tool_calls,
+                tool_call_id,
+                provider_id,
+                model,
+                preset,
+                prompt_tokens,
+                completion_tokens,
+                time_to_first_token,
+                duration,
+                finish_reason,
+                rag_queries
+            ) VALUES (?, ?, COALESCE(?, CURRENT_TIMESTAMP), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                conversation_id,
+                parent_id,
+                timestamp,
+                message_dto.content,
+                message_dto.reasoning.clone().unwrap_or_default(),
+                Self::message_role_to_int(&message_dto.role),
+                message_dto.summary,
+                serde_json::to_string(&message_dto.chunks).unwrap_or_default(),
+                message_dto.interrupted,
+                serde_json::to_string(&message_dto.tool_calls).unwrap_or_default(),
+                message_dto.tool_call_id,
+                generation.and_then(|generation| generation.provider_id),
+                generation.map(|generation| generation.model.clone()),
+                generation
+                    .and_then(|generation| generation.preset.as_ref())
+                    .and_then(|preset| serde_json::to_string(preset).ok()),
+                generation.map(|generation| generation.prompt_tokens),
+                generation.map(|generation| generation.completion_tokens),
+                generation.and_then(|generation| generation.time_to_first_token),
+                generation.and_then(|generation| generation.duration),
+                generation.and_then(|generation| generation.finish_reason.clone()),
+                serde_json::to_string(&message_dto.rag_queries).unwrap_or_default(),
             ],
         )?;
 
-        Ok(())
-    }
- 
+        let message_id = connection.last_insert_rowid();
 
-    /// # Errors
-    /// # Panics
-    pub fn insert_message_dto(&self, message_dto: MessageDTO) -> Result<(), rusqlite::Error> {
-        self.insert_message(
-            message_dto.conversation_id,
-            &message_dto.content,
-            &message_dto.reasoning.unwrap_or_default(),
-            &message_dto.role,
-            &message_dto.chunks,
-        )
+        Self::index_message(connection, message_id, message_dto)?;
+        AttachmentsDatabase::insert(connection, message_id, &message_dto.attachments)?;
+
+        Ok(message_id)
     }
 
     /// # Errors
     /// # Panics
-    pub fn update_message_dto(&self, message_dto: MessageDTO) -> Result<(), rusqlite::Error> {
+    pub fn update_message_dto(&self, message_dto: &MessageDTO) -> Result<(), rusqlite::Error> {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
         connection.execute(
             "UPDATE messages 
-             SET content = ?, reasoning = ?, role = ?, summary = ?, chunks = ?
+             SET content = ?, reasoning = ?, role = ?, summary = ?, chunks = ?, interrupted = ?,
+                 tool_calls = ?, tool_call_id = ?, rag_queries = ?
              WHERE id = ?",
             rusqlite::params![
                 message_dto.content,
-                message_dto.reasoning.unwrap_or_default(),
+                message_dto.reasoning.clone().unwrap_or_default(),
                 Self::message_role_to_int(&message_dto.role),
-                message_dto.summary.unwrap_or_default(),
+                message_dto.summary.clone().unwrap_or_default(),
                 serde_json::to_string(&message_dto.chunks).unwrap_or_default(),
+                message_dto.interrupted,
+                serde_json::to_string(&message_dto.tool_calls).unwrap_or_default(),
+                message_dto.tool_call_id,
+                serde_json::to_string(&message_dto.rag_queries).unwrap_or_default(),
 
                 message_dto.id,
             ],
         )?;
 
-        Ok(())
+        Self::index_message(&connection, message_dto.id, message_dto)
     }
 
+    /// Newest `limit` messages of the shown branch, only the ones older than
+    /// `known_id` unless it is 0.
+    ///
     /// # Errors
     /// # Panics
     pub fn get_last_messages(
@@ -162,49 +390,264 @@ impl MessagesDatabase {
         limit: usize,
     ) -> Result<Vec<MessageDTO>, rusqlite::Error> {
         let connection = self.connection.lock().expect("Failed to lock connection");
-        let query = if known_id > 0 {
-            "SELECT id, conversation_id, content, reasoning, timestamp, role, summary, chunks
-             FROM messages 
-             WHERE conversation_id = ? AND id < ? 
-             ORDER BY id DESC
-             LIMIT ?"
-        } else {
-            "SELECT id, conversation_id, content, reasoning, timestamp, role, summary, chunks
-             FROM messages 
-             WHERE conversation_id = ? 
-             ORDER BY id DESC
-             LIMIT ?"
-        };
 
-        let mut stmt = connection.prepare(query)?;
+        let mut stmt = connection.prepare(&format!(
+            "{ACTIVE_BRANCH}
+             SELECT {MESSAGE_COLUMNS}
+             FROM messages
+             WHERE id IN (SELECT id FROM branch) AND (?2 = 0 OR id < ?2)
+             ORDER BY id DESC
+             LIMIT ?3"
+        ))?;
 
         let row_mapper = |row: &rusqlite::Row| -> Result<MessageDTO, rusqlite::Error> {
             let message = Self::row_to_message(row)?;
             Ok(message)
         };
 
-        let query = if known_id > 0 {
-            rusqlite::params![conversation_id, known_id, limit]
-        } else {
-            rusqlite::params![conversation_id, limit]
+        let rows = stmt.query_map(rusqlite::params![conversation_id, known_id, limit], row_mapper)?;
+
+        let messages: Result<Vec<MessageDTO>, rusqlite::Error> = rows.collect();
+        let mut messages = messages?;
+        messages.reverse();
+
+        drop(stmt);
+
+        for message in &mut messages {
+            message.siblings = Self::siblings(&connection, message)?;
+        }
+
+        drop(connection);
+
+        for message in &mut messages {
+            message.attachments = self.attachments_db.get_attachments(message.id)?;
+        }
+
+        Ok(messages)
+    }
+
+    /// Makes the conversation show the branch ending at `message_id`, new
+    /// messages continue it. `None` shows no messages, a new one starts a new branch.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn set_active_message(
+        &self,
+        conversation_id: ConversationNodeID,
+        message_id: Option<MessageID>,
+    ) -> Result<(), rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        Self::set_active(&connection, conversation_id, message_id)
+    }
+
+    /// Switches the conversation to the newest branch going through the
+    /// message, unless the shown branch has it already.
+    /// Returns whether the shown branch changed.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn activate_message(&self, message_id: MessageID) -> Result<bool, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        let conversation_id: ConversationNodeID = connection.query_row(
+            "SELECT conversation_id FROM messages WHERE id = ?",
+            rusqlite::params![message_id],
+            |row| row.get(0),
+        )?;
+
+        let is_shown: bool = connection.query_row(
+            &format!("{ACTIVE_BRANCH} SELECT COUNT(*) > 0 FROM branch WHERE id = ?2"),
+            rusqlite::params![conversation_id, message_id],
+            |row| row.get(0),
+        )?;
+
+        if is_shown {
+            return Ok(false);
+        }
+
+        let leaf = Self::latest_leaf(&connection, conversation_id, Some(message_id))?;
+        Self::set_active(&connection, conversation_id, leaf)?;
+
+        Ok(true)
+    }
+
+    /// Best matches first, `query` is taken as plain words, each of them
+    /// also matches as a prefix.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn search_messages(
+        &self,
+        query: &str,
+        filters: &MessageSearchFilters,
+        limit: usize,
+    ) -> Result<Vec<MessageSearchResultDTO>, rusqlite::Error> {
+        let Some(match_query) = Self::match_query(query) else {
+            return Ok(vec![]);
         };
 
-        let rows = stmt.query_map(query, row_mapper)?;
+        let connection = self.connection.lock().expect("Failed to lock connection");
 
-        let messages: Result<Vec<MessageDTO>, rusqlite::Error> = rows.collect();
+        let mut stmt = connection.prepare(
+            "WITH RECURSIVE folder(id) AS (
+                SELECT ?1
+                UNION ALL
+                SELECT conversations.id FROM conversations
+                JOIN folder ON conversations.parent_id = folder.id
+            )
+            SELECT messages.id, messages.conversation_id, conversations.name, messages.role,
+                   messages.timestamp,
+                   snippet(messages_fts, -1, ?8, ?9, '…', 16)
+            FROM messages_fts
+            JOIN messages ON messages.id = messages_fts.rowid
+            JOIN conversations ON conversations.id = messages.conversation_id
+            WHERE messages_fts MATCH ?2
+              AND (?1 IS NULL OR messages.conversation_id IN (SELECT id FROM folder))
+              AND (?3 IS NULL OR messages.role = ?3)
+              AND (?4 IS NULL OR date(messages.timestamp) >= ?4)
+              AND (?5 IS NULL OR date(messages.timestamp) <= ?5)
+              AND (?6 IS NULL OR COALESCE(messages.model, conversations.model) LIKE '%' || ?6 || '%')
+            ORDER BY rank
+            LIMIT ?7",
+        )?;
 
-        messages.map(|mut v| {
-            v.reverse();
-            v
-        })
+        let rows = stmt.query_map(
+            rusqlite::params![
+                filters.folder_id,
+                match_query,
+                filters.role.as_ref().map(Self::message_role_to_int),
+                filters.date_from,
+                filters.date_to,
+                filters.model,
+                limit,
+                SEARCH_MATCH_START.to_string(),
+                SEARCH_MATCH_END.to_string(),
+            ],
+            |row| {
+                Ok(MessageSearchResultDTO {
+                    message_id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    conversation_name: row.get(2)?,
+                    role: Self::message_role_from_int(row.get(3)?)?,
+                    timestamp: row.get(4)?,
+                    snippet: row.get(5)?,
+                })
+            },
+        )?;
+
+        rows.collect()
+    }
+
+    /// Quotes every word so the user input never breaks the FTS5 syntax.
+    fn match_query(query: &str) -> Option<String> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+            .collect();
+
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" "))
+        }
+    }
+
+    fn active_message(
+        connection: &rusqlite::Connection,
+        conversation_id: ConversationNodeID,
+    ) -> Result<Option<MessageID>, rusqlite::Error> {
+        connection
+            .query_row(
+                "SELECT active_message_id FROM conversations WHERE id = ?",
+                rusqlite::params![conversation_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::flatten)
+    }
+
+    pub(crate) fn set_active(
+        connection: &rusqlite::Connection,
+        conversation_id: ConversationNodeID,
+        message_id: Option<MessageID>,
+    ) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "UPDATE conversations SET active_message_id = ? WHERE id = ?",
+            rusqlite::params![message_id, conversation_id],
+        )?;
+
+        Ok(())
+    }
+
+    /// End of the branch below `message_id` taking the newest answer at every
+    /// step, `None` starts from the first messages of the conversation.
+    fn latest_leaf(
+        connection: &rusqlite::Connection,
+        conversation_id: ConversationNodeID,
+        message_id: Option<MessageID>,
+    ) -> Result<Option<MessageID>, rusqlite::Error> {
+        let mut stmt = connection.prepare(
+            "SELECT MAX(id) FROM messages WHERE conversation_id = ? AND parent_id IS ?",
+        )?;
+
+        let mut leaf = message_id;
+
+        while let Some(child) = stmt.query_row(rusqlite::params![conversation_id, leaf], |row| {
+            row.get::<_, Option<MessageID>>(0)
+        })? {
+            leaf = Some(child);
+        }
+
+        Ok(leaf)
+    }
+
+    fn siblings(
+        connection: &rusqlite::Connection,
+        message: &MessageDTO,
+    ) -> Result<Vec<MessageID>, rusqlite::Error> {
+        let mut stmt = connection.prepare_cached(
+            "SELECT id FROM messages WHERE conversation_id = ? AND parent_id IS ? ORDER BY id",
+        )?;
+
+        stmt.query_map(
+            rusqlite::params![message.conversation_id, message.parent_id],
+            |row| row.get(0),
+        )?
+        .collect()
     }
 
-    fn message_role_from_int(role: i32) -> RoleType {
+    /// Replaces the indexed texts of the message.
+    fn index_message(
+        connection: &rusqlite::Connection,
+        message_id: i64,
+        message_dto: &MessageDTO,
+    ) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "DELETE FROM messages_fts WHERE rowid = ?",
+            rusqlite::params![message_id],
+        )?;
+
+        connection.execute(
+            "INSERT INTO messages_fts (rowid, content, reasoning, summary) VALUES (?, ?, ?, ?)",
+            rusqlite::params![
+                message_id,
+                message_dto.content,
+                message_dto.reasoning.clone().unwrap_or_default(),
+                message_dto.summary.clone().unwrap_or_default(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn message_role_from_int(role: i32) -> Result<RoleType, rusqlite::Error> {
         match role {
-            0 => RoleType::User,
-            1 => RoleType::Assistant,
-            2 => RoleType::System,
-            _ => panic!("Invalid role type"),
+            0 => Ok(RoleType::User),
+            1 => Ok(RoleType::Assistant),
+            2 => Ok(RoleType::System),
+            3 => Ok(RoleType::Tool),
+            _ => Err(rusqlite::Error::IntegralValueOutOfRange(5, role.into())),
         }
     }
 
@@ -213,6 +656,7 @@ impl MessagesDatabase {
             RoleType::User => 0,
             RoleType::Assistant => 1,
             RoleType::System => 2,
+            RoleType::Tool => 3,
         }
     }
 
@@ -223,10 +667,45 @@ impl MessagesDatabase {
             content: row.get(2)?,
             reasoning: row.get(3)?,
             timestamp: row.get(4)?,
-            role: Self::message_role_from_int(row.get(5)?),
+            role: Self::message_role_from_int(row.get(5)?)?,
             summary: row.get(6).ok(),
-            chunks: serde_json::from_str::<Vec<MessageUsedRagChunk>>(row.get::<_, String>(7).unwrap_or("[]".to_string()).as_str())
+            chunks: serde_json::from_str::<Vec<MessageUsedRagChunk>>(row.get::<_, String>(7).unwrap_or_else(|_| "[]".to_string()).as_str())
             .unwrap_or_default(),
+            rag_queries: row
+                .get::<_, String>(20)
+                .ok()
+                .and_then(|rag_queries| serde_json::from_str(&rag_queries).ok())
+                .unwrap_or_default(),
+            interrupted: row.get(8).unwrap_or_default(),
+            tool_calls: row
+                .get::<_, String>(9)
+                .ok()
+                .and_then(|tool_calls| serde_json::from_str(&tool_calls).ok())
+                .unwrap_or_default(),
+            tool_call_id: row.get(10).unwrap_or_default(),
+            attachments: Vec::new(),
+            parent_id: row.get(11)?,
+            siblings: Vec::new(),
+            generation: Self::row_to_generation(row)?,
         })
     }
+
+    fn row_to_generation(row: &rusqlite::Row) -> Result<Option<GenerationDTO>, rusqlite::Error> {
+        let Some(model) = row.get::<_, Option<String>>(13)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(GenerationDTO {
+            provider_id: row.get(12)?,
+            model,
+            preset: row
+                .get::<_, Option<String>>(14)?
+                .and_then(|preset| serde_json::from_str(&preset).ok()),
+            prompt_tokens: row.get::<_, Option<usize>>(15)?.unwrap_or_default(),
+            completion_tokens: row.get::<_, Option<usize>>(16)?.unwrap_or_default(),
+            time_to_first_token: row.get(17)?,
+            duration: row.get(18)?,
+            finish_reason: row.get(19)?,
+        }))
+    }
 }
@@ -1,13 +1,21 @@
+mod attachments;
 mod conversations;
+mod import;
+mod knowledge_bases;
 mod messages;
 mod presets;
 mod providers;
 mod storage;
+mod usage;
 mod vector;
 
+pub use attachments::AttachmentsDatabase;
 pub use conversations::ConversationDatabase;
+pub use import::ImportDatabase;
+pub use knowledge_bases::KnowledgeBasesDatabase;
 pub use messages::MessagesDatabase;
 pub use presets::PresetsDatabase;
 pub use providers::ProvidersDatabase;
 pub use storage::StorageDatabase;
+pub use usage::UsageDatabase;
 pub use vector::VectorDatabase;
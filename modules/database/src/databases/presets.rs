@@ -9,23 +9,8 @@ pub struct PresetsDatabase {
 }
 
 impl PresetsDatabase {
-    /// # Panics
-    pub fn new(connection: DatabaseConnection) -> Self {
-        connection
-            .lock()
-            .expect("Failed to lock connection")
-            .execute(
-                "CREATE TABLE IF NOT EXISTS presets (
-                    id INTEGER PRIMARY KEY,
-                    name TEXT NOT NULL,
-                    prompt TEXT NOT NULL,
-                    temperature REAL NOT NULL,
-                    max_tokens INTEGER NOT NULL
-                )",
-                [],
-            )
-            .expect("Failed to create presets table");
-
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
         Self { connection }
     }
 
@@ -37,16 +22,14 @@ impl PresetsDatabase {
         prompt: &str,
         temperature: f32,
         max_tokens: u32,
-    ) -> Result<PresetDTO, String> {
-        let connection = self.connection.lock().unwrap();
+    ) -> rusqlite::Result<PresetDTO> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
         let mut stmt = connection
             .prepare(
                 "INSERT INTO presets (name, prompt, temperature, max_tokens) VALUES (?, ?, ?, ?)",
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
 
-        stmt.execute((name, prompt, temperature, max_tokens))
-            .map_err(|e| e.to_string())?;
+        stmt.execute((name, prompt, temperature, max_tokens))?;
 
         let id = connection.last_insert_rowid();
         Ok(PresetDTO {
@@ -60,11 +43,10 @@ impl PresetsDatabase {
 
     /// # Errors
     /// # Panics
-    pub fn get_preset(&self, id: PresetId) -> Result<PresetDTO, String> {
+    pub fn get_preset(&self, id: PresetId) -> rusqlite::Result<PresetDTO> {
         let connection = self.connection.lock().expect("Failed to lock connection");
         let mut stmt = connection
-            .prepare("SELECT * FROM presets WHERE id = ?")
-            .map_err(|e| e.to_string())?;
+            .prepare("SELECT * FROM presets WHERE id = ?")?;
 
         let preset = stmt
             .query_row((id,), |row| {
@@ -75,20 +57,18 @@ impl PresetsDatabase {
                     temperature: row.get(3)?,
                     max_tokens: row.get(4)?,
                 })
-            })
-            .map_err(|e| e.to_string())?;
+            })?;
 
         Ok(preset)
     }
 
     /// # Errors
     /// # Panics
-    pub fn get_all_presets(&self) -> Result<Vec<PresetDTO>, String> {
+    pub fn get_all_presets(&self) -> rusqlite::Result<Vec<PresetDTO>> {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
         let mut stmt = connection
-            .prepare("SELECT * FROM presets")
-            .map_err(|e| e.to_string())?;
+            .prepare("SELECT * FROM presets")?;
 
         let converter = |row: &rusqlite::Row| -> Result<PresetDTO, rusqlite::Error> {
             Ok(PresetDTO {
@@ -102,23 +82,17 @@ impl PresetsDatabase {
 
         let query = params![];
 
-        let presets = stmt.query_map(query, converter);
-
-        let pres: Result<Vec<PresetDTO>, rusqlite::Error> =
-            presets.map_err(|e| e.to_string())?.collect();
-
-        pres.map_err(|e| e.to_string())
+        stmt.query_map(query, converter)?.collect()
     }
 
     /// # Errors
     /// # Panics
-    pub fn update_preset(&self, dto: &PresetDTO) -> Result<PresetDTO, String> {
+    pub fn update_preset(&self, dto: &PresetDTO) -> rusqlite::Result<PresetDTO> {
         let connection = self.connection.lock().expect("Failed to lock connection");
         let mut stmt = connection
             .prepare(
                 "UPDATE presets SET name = ?, prompt = ?, temperature = ?, max_tokens = ? WHERE id = ?",
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
 
         stmt.execute((
             dto.name.clone(),
@@ -126,21 +100,19 @@ impl PresetsDatabase {
             dto.temperature,
             dto.max_tokens,
             dto.id,
-        ))
-        .map_err(|e| e.to_string())?;
+        ))?;
 
         Ok(dto.clone())
     }
 
     /// # Errors
     /// # Panics
-    pub fn delete_preset(&self, id: PresetId) -> Result<(), String> {
+    pub fn delete_preset(&self, id: PresetId) -> rusqlite::Result<()> {
         let connection = self.connection.lock().expect("Failed to lock connection");
         let mut stmt = connection
-            .prepare("DELETE FROM presets WHERE id = ?")
-            .map_err(|e| e.to_string())?;
+            .prepare("DELETE FROM presets WHERE id = ?")?;
 
-        stmt.execute((id,)).map_err(|e| e.to_string())?;
+        stmt.execute((id,))?;
         Ok(())
     }
 }
@@ -1,5 +1,5 @@
-use rusqlite::params;
-use types::dto::{ProviderDTO, ProviderID};
+use rusqlite::{OptionalExtension, params};
+use types::dto::{ProviderDTO, ProviderID, ProviderKind};
 
 use crate::utils::DatabaseConnection;
 
@@ -9,41 +9,28 @@ pub struct ProvidersDatabase {
 }
 
 impl ProvidersDatabase {
-    /// # Panics
-    pub fn new(connection: DatabaseConnection) -> Self {
-        connection
-            .lock()
-            .expect("Failed to lock connection")
-            .execute(
-                "CREATE TABLE IF NOT EXISTS providers (
-                    id INTEGER PRIMARY KEY,
-                    name TEXT NOT NULL,
-                    url TEXT NOT NULL,
-                    api_key TEXT NOT NULL,
-                    default_model TEXT
-        )",
-                [],
-            )
-            .expect("Failed to create providers table");
-
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
         Self { connection }
     }
 
+    /// Failures are logged and reported as a missing provider.
+    ///
     /// # Panics
     #[must_use] pub fn get_provider(&self, id: ProviderID) -> Option<ProviderDTO> {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
-        let mut stmt = connection
-            .prepare("SELECT id, name, url, api_key, default_model FROM providers WHERE id = ?")
-            .expect("Failed to prepare statement");
-
-        let provider = stmt
-            .query_map([id], |row| Ok(Self::row_to_dto(row)))
-            .expect("Failed to query provider")
-            .next()
-            .and_then(std::result::Result::ok);
-
-        provider
+        connection
+            .query_row(
+                "SELECT id, name, url, api_key, default_model, kind, reasoning_tags FROM providers WHERE id = ?",
+                [id],
+                Self::row_to_dto,
+            )
+            .optional()
+            .unwrap_or_else(|e| {
+                log::error!("Failed to get provider {id}: {e}");
+                None
+            })
     }
 
     /// # Errors
@@ -54,12 +41,22 @@ impl ProvidersDatabase {
         url: &str,
         api_key: &str,
         default_model: &str,
+        kind: ProviderKind,
+        reasoning_tags: &str,
     ) -> rusqlite::Result<ProviderID> {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
         connection.execute(
-            "INSERT INTO providers (name, url, api_key, default_model) VALUES (?, ?, ?, ?)",
-            [name, url, api_key, default_model],
+            "INSERT INTO providers (name, url, api_key, default_model, kind, reasoning_tags)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                name,
+                url,
+                api_key,
+                default_model,
+                Self::kind_to_int(kind),
+                reasoning_tags
+            ],
         )?;
 
         let id = connection.last_insert_rowid() as ProviderID;
@@ -73,8 +70,16 @@ impl ProvidersDatabase {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
         connection.execute(
-            "UPDATE providers SET name = ?, url = ?, api_key = ?, default_model = ? WHERE id = ?",
-            params![dto.name, dto.url, dto.api_key, dto.default_model, dto.id],
+            "UPDATE providers SET name = ?, url = ?, api_key = ?, default_model = ?, kind = ?, reasoning_tags = ? WHERE id = ?",
+            params![
+                dto.name,
+                dto.url,
+                dto.api_key,
+                dto.default_model,
+                Self::kind_to_int(dto.kind),
+                dto.reasoning_tags,
+                dto.id
+            ],
         )?;
 
         Ok(dto.id)
@@ -90,30 +95,45 @@ impl ProvidersDatabase {
         Ok(id)
     }
 
+    /// Failures are logged and reported as no providers.
+    ///
     /// # Panics
     #[must_use] pub fn get_providers(&self) -> Vec<ProviderDTO> {
         let connection = self.connection.lock().expect("Failed to lock connection");
 
-        let mut stmt = connection
-            .prepare("SELECT id, name, url, api_key, default_model FROM providers")
-            .expect("Failed to prepare statement");
-
-        let providers = stmt
-            .query_map([], |row| Ok(Self::row_to_dto(row)))
-            .expect("Failed to query providers")
-            .filter_map(std::result::Result::ok)
-            .collect();
+        let providers = connection
+            .prepare("SELECT id, name, url, api_key, default_model, kind, reasoning_tags FROM providers")
+            .and_then(|mut stmt| stmt.query_map([], Self::row_to_dto)?.collect());
 
-        providers
+        providers.unwrap_or_else(|e| {
+            log::error!("Failed to get providers: {e}");
+            vec![]
+        })
     }
 
-    fn row_to_dto(row: &rusqlite::Row) -> ProviderDTO {
-        ProviderDTO {
-            id: row.get(0).unwrap(),
-            name: row.get(1).unwrap(),
-            url: row.get(2).unwrap(),
-            api_key: row.get(3).unwrap(),
+    fn row_to_dto(row: &rusqlite::Row) -> rusqlite::Result<ProviderDTO> {
+        Ok(ProviderDTO {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            url: row.get(2)?,
+            api_key: row.get(3)?,
             default_model: row.get(4).unwrap_or_default(),
+            kind: Self::kind_from_int(row.get(5).unwrap_or_default()),
+            reasoning_tags: row.get(6).unwrap_or_default(),
+        })
+    }
+
+    const fn kind_from_int(kind: i32) -> ProviderKind {
+        match kind {
+            1 => ProviderKind::Anthropic,
+            _ => ProviderKind::OpenAi,
+        }
+    }
+
+    const fn kind_to_int(kind: ProviderKind) -> i32 {
+        match kind {
+            ProviderKind::OpenAi => 0,
+            ProviderKind::Anthropic => 1,
         }
     }
 }
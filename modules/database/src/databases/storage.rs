@@ -8,22 +8,8 @@ pub struct StorageDatabase {
 }
 
 impl StorageDatabase {
-    /// # Errors
-    /// # Panics
-    pub fn new(connection: DatabaseConnection) -> Self {
-        connection
-            .lock()
-            .expect("Failed to lock connection")
-            .execute(
-                "CREATE TABLE IF NOT EXISTS storage (
-          id INTEGER PRIMARY KEY,
-          key TEXT NOT NULL UNIQUE,
-          value TEXT NOT NULL
-        )",
-                [],
-            )
-            .expect("Failed to create storage table");
-
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
         Self { connection }
     }
 
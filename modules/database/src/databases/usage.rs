@@ -0,0 +1,162 @@
+use rusqlite::params;
+use types::dto::{
+    ConversationNodeID, GenerationDTO, ModelPriceDTO, ProviderID, UsageGrouping, UsageRowDTO,
+};
+
+use crate::DatabaseConnection;
+
+#[derive(Debug, Clone)]
+pub struct UsageDatabase {
+    connection: DatabaseConnection,
+}
+
+impl UsageDatabase {
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn record(
+        &self,
+        conversation_id: ConversationNodeID,
+        generation: &GenerationDTO,
+    ) -> Result<(), rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        connection.execute(
+            "INSERT INTO completion_usage (conversation_id, provider_id, model, prompt_tokens, completion_tokens)
+             VALUES (?, ?, ?, ?, ?)",
+            params![
+                conversation_id,
+                generation.provider_id,
+                generation.model,
+                generation.prompt_tokens,
+                generation.completion_tokens,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Most expensive groups first, days newest first. Only the last `days`
+    /// days are taken unless it is `None`.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn get_usage(
+        &self,
+        grouping: UsageGrouping,
+        days: Option<u32>,
+    ) -> Result<Vec<UsageRowDTO>, rusqlite::Error> {
+        let (label, group_by, order_by) = match grouping {
+            UsageGrouping::Provider => (
+                "COALESCE(providers.name, 'Deleted provider')",
+                "completion_usage.provider_id",
+                "cost DESC, tokens DESC",
+            ),
+            UsageGrouping::Model => (
+                "completion_usage.model || ' (' || COALESCE(providers.name, 'deleted provider') || ')'",
+                "completion_usage.provider_id, completion_usage.model",
+                "cost DESC, tokens DESC",
+            ),
+            UsageGrouping::Conversation => (
+                "COALESCE(conversations.name, 'Deleted conversation')",
+                "completion_usage.conversation_id",
+                "cost DESC, tokens DESC",
+            ),
+            UsageGrouping::Day => (
+                "date(completion_usage.timestamp)",
+                "date(completion_usage.timestamp)",
+                "label DESC",
+            ),
+        };
+
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        let mut stmt = connection.prepare(&format!(
+            "SELECT {label} AS label,
+                    COUNT(*),
+                    SUM(completion_usage.prompt_tokens),
+                    SUM(completion_usage.completion_tokens),
+                    SUM(completion_usage.prompt_tokens * COALESCE(model_prices.prompt_price, 0)
+                        + completion_usage.completion_tokens * COALESCE(model_prices.completion_price, 0)
+                    ) / 1000000.0 AS cost,
+                    SUM(completion_usage.prompt_tokens + completion_usage.completion_tokens) AS tokens,
+                    MAX(model_prices.model IS NULL)
+             FROM completion_usage
+             LEFT JOIN providers ON providers.id = completion_usage.provider_id
+             LEFT JOIN conversations ON conversations.id = completion_usage.conversation_id
+             LEFT JOIN model_prices ON model_prices.provider_id = completion_usage.provider_id
+                                   AND model_prices.model = completion_usage.model
+             WHERE ?1 IS NULL OR completion_usage.timestamp >= datetime('now', '-' || ?1 || ' days')
+             GROUP BY {group_by}
+             ORDER BY {order_by}"
+        ))?;
+
+        stmt.query_map(params![days], |row| {
+            Ok(UsageRowDTO {
+                label: row.get(0)?,
+                requests: row.get(1)?,
+                prompt_tokens: row.get(2)?,
+                completion_tokens: row.get(3)?,
+                cost: row.get(4)?,
+                unpriced: row.get(6)?,
+            })
+        })?
+        .collect()
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn get_model_prices(
+        &self,
+        provider_id: ProviderID,
+    ) -> Result<Vec<ModelPriceDTO>, rusqlite::Error> {
+        let connection = self.connection.lock().expect("Failed to lock connection");
+
+        let mut stmt = connection.prepare(
+            "SELECT provider_id, model, prompt_price, completion_price
+             FROM model_prices WHERE provider_id = ? ORDER BY model",
+        )?;
+
+        stmt.query_map(params![provider_id], |row| {
+            Ok(ModelPriceDTO {
+                provider_id: row.get(0)?,
+                model: row.get(1)?,
+                prompt_price: row.get(2)?,
+                completion_price: row.get(3)?,
+            })
+        })?
+        .collect()
+    }
+
+    /// Replaces all prices of the provider.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn set_model_prices(
+        &self,
+        provider_id: ProviderID,
+        prices: &[ModelPriceDTO],
+    ) -> Result<(), rusqlite::Error> {
+        let mut connection = self.connection.lock().expect("Failed to lock connection");
+        let transaction = connection.transaction()?;
+
+        transaction.execute(
+            "DELETE FROM model_prices WHERE provider_id = ?",
+            params![provider_id],
+        )?;
+
+        for price in prices {
+            transaction.execute(
+                "INSERT OR REPLACE INTO model_prices (provider_id, model, prompt_price, completion_price)
+                 VALUES (?, ?, ?, ?)",
+                params![provider_id, price.model, price.prompt_price, price.completion_price],
+            )?;
+        }
+
+        transaction.commit()
+    }
+}
@@ -1,399 +1,1124 @@
+use std::collections::{HashMap, HashSet};
 
-use rusqlite::params;
-use types::dto::{ChunkRagDTO, ChunkRagId, ConversationNodeID, RagFileDTO, RagFileID};
+use rusqlite::{OptionalExtension, params};
+use types::dto::{ChunkRagDTO, ChunkRagId, FileChunk, IndexedFile, RagFileDTO, RagFileID, RagSource, WatchedPathDTO, WatchedPathID};
 use zerocopy::IntoBytes;
 
 use crate::DatabaseConnection;
 
+/// Tables of one kind of [`RagSource`], their names are never taken from input.
+struct SourceTables {
+    files: &'static str,
+    vectors: &'static str,
+    /// Full text index of the chunks of every vectors table.
+    keywords: &'static str,
+    /// Files and folders indexed again when they change.
+    watched: &'static str,
+    /// Column of the tables holding the id of the source.
+    owner: &'static str,
+    id: i64,
+}
+
+impl SourceTables {
+    const fn new(source: RagSource) -> Self {
+        match source {
+            RagSource::Conversation(conversation_id) => Self {
+                files: "vectors_files",
+                vectors: "vectors",
+                keywords: "vectors_fts",
+                watched: "vectors_watched_paths",
+                owner: "conversation_id",
+                id: conversation_id,
+            },
+            RagSource::KnowledgeBase(knowledge_base_id) => Self {
+                files: "knowledge_base_files",
+                vectors: "knowledge_vectors",
+                keywords: "knowledge_vectors_fts",
+                watched: "knowledge_base_watched_paths",
+                owner: "knowledge_base_id",
+                id: knowledge_base_id,
+            },
+        }
+    }
+
+    fn vectors_table(&self, dimensions: usize) -> String {
+        format!("{}_{dimensions}", self.vectors)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct VectorDatabase {
     connection: DatabaseConnection,
 }
 
 impl VectorDatabase {
-    /// # Errors
-    /// # Panics
-    pub fn new(sqlite_connection: DatabaseConnection) -> Self {
-        sqlite_connection
-            .lock()
-            .expect("Failed to lock connection")
-            .execute(
-                "CREATE TABLE IF NOT EXISTS vectors_files (
-                  id INTEGER PRIMARY KEY,
-                  conversation_id INTEGER NOT NULL,
-                  file_hash TEXT NOT NULL,
-                  file_name TEXT NOT NULL,
-                  dimensions INTEGER NOT NULL,
-                  embedding_model TEXT NOT NULL,
-                  UNIQUE(conversation_id, file_hash, dimensions, embedding_model)
-                )",
-                [],
-            )
-            .expect("Failed to create table");
-
+    #[must_use]
+    pub const fn new(sqlite_connection: DatabaseConnection) -> Self {
         Self {
             connection: sqlite_connection,
         }
     }
 
+    /// # Errors
+    /// # Panics
     pub fn get_chunk_by_id(
         &self,
-        conversation_id: ConversationNodeID,
+        source: RagSource,
         chunk_id: ChunkRagId,
         dimensions: usize,
         embedding_model: &str,
-    ) -> Result<Option<ChunkRagDTO>, String> {
+    ) -> Result<Option<ChunkRagDTO>, rusqlite::Error> {
         let connection = self
             .connection
             .lock()
             .expect("Failed to lock connection");
 
-        let table_name = self.get_vectors_table_name(conversation_id, dimensions, embedding_model);
+        let tables = SourceTables::new(source);
+        let table_name = tables.vectors_table(dimensions);
 
-        let mut stmt = connection
-            .prepare(&format!("SELECT id, file_id, chunk FROM {table_name} WHERE id = ?"))
-            .map_err(|e| format!("Failed to prepare statement: {e}"))?;
-
-        let mut rows = stmt
-            .query(params![chunk_id])
-            .map_err(|e| format!("Failed to query: {e}"))?;
-
-        rows.next().expect("Failed to get next row").map_or(Ok(None), |row| {
-            let id: ChunkRagId = row.get(0).expect("Failed to get chunk id");
-            let file_id: RagFileID = row.get(1).expect("Failed to get file id");
-            let chunk: String = row.get(2).expect("Failed to get chunk");
+        if !Self::table_exists(&connection, &table_name)? {
+            return Ok(None);
+        }
 
-            Ok(Some(ChunkRagDTO { id, file_id, chunk }))
-        })
+        connection
+            .query_row(
+                &format!(
+                    "SELECT id, file_id, chunk, page, start_line, end_line, start_byte, end_byte FROM {table_name}
+                     WHERE id = ? AND {owner} = ? AND embedding_model = ?",
+                    owner = tables.owner
+                ),
+                params![chunk_id, tables.id, embedding_model],
+                |row| {
+                    Ok(ChunkRagDTO {
+                        id: row.get(0)?,
+                        file_id: row.get(1)?,
+                        chunk: row.get(2)?,
+                        page: row.get(3)?,
+                        lines: row
+                            .get::<_, Option<usize>>(4)?
+                            .zip(row.get::<_, Option<usize>>(5)?),
+                        bytes: row
+                            .get::<_, Option<usize>>(6)?
+                            .zip(row.get::<_, Option<usize>>(7)?),
+                    })
+                },
+            )
+            .optional()
     }
 
+    /// Nothing is found while no file was embedded with this model yet.
+    ///
     /// # Errors
     /// # Panics
     pub fn search(
         &self,
-        conversation_id: ConversationNodeID,
+        source: RagSource,
         query_vector: &[f32],
         count: usize,
         embedding_model: &str,
-    ) -> Result<Vec<(f32, ChunkRagId)>, String> {
+    ) -> Result<Vec<(f32, ChunkRagId)>, rusqlite::Error> {
         let connection = self
             .connection
             .lock()
             .expect("Failed to lock connection");
 
-        let dimensions = query_vector.len();
-        let vectors_table_name = self.get_vectors_table_name(conversation_id, dimensions, embedding_model);
-
-        let stmt = connection
-            .prepare(&format!(
-                "SELECT distance, id FROM {vectors_table_name} WHERE embedding MATCH ? ORDER BY distance LIMIT ?"
-            ));
+        let tables = SourceTables::new(source);
+        let vectors_table_name = tables.vectors_table(query_vector.len());
 
-        if stmt.is_err() {
-            log::error!("Failed to prepare statement: {:?}", stmt.err());
+        if !Self::table_exists(&connection, &vectors_table_name)? {
             return Ok(vec![]);
         }
 
-        let mut stmt = stmt
-            .expect("Failed to prepare statement");
-
-        let mut rows = stmt
-            .query(params![query_vector.as_bytes(), count])
-            .expect("Failed to query");
+        let mut stmt = connection.prepare(&format!(
+            "SELECT distance, id FROM {vectors_table_name}
+             WHERE embedding MATCH ? AND k = ? AND {owner} = ? AND embedding_model = ?
+             ORDER BY distance",
+            owner = tables.owner
+        ))?;
+
+        stmt
+            .query_map(params![query_vector.as_bytes(), count, tables.id, embedding_model], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect()
+    }
 
-        let mut chunks = vec![];
+    /// Chunks with any word of `query`, best first by BM25. The ids are ids of
+    /// the vectors table for `dimensions`, like the ones [`Self::search`] finds.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn keyword_search(
+        &self,
+        source: RagSource,
+        query: &str,
+        count: usize,
+        dimensions: usize,
+        embedding_model: &str,
+    ) -> Result<Vec<(f64, ChunkRagId)>, rusqlite::Error> {
+        let Some(match_query) = Self::match_query(query) else {
+            return Ok(vec![]);
+        };
 
-        while let Some(row) = rows.next().expect("Failed to get next row") {
-            let chunk: (f32, ChunkRagId) = (row.get(0).expect("Failed to get distance"), row.get(1).expect("Failed to get chunk"));
-            chunks.push(chunk);
-        }
+        let connection = self
+            .connection
+            .lock()
+            .expect("Failed to lock connection");
 
-        Ok(chunks)
+        let tables = SourceTables::new(source);
+
+        let mut stmt = connection.prepare(&format!(
+            "SELECT rank, chunk_id FROM {keywords}
+             WHERE {keywords} MATCH ? AND {owner} = ? AND embedding_model = ? AND dimensions = ?
+             ORDER BY rank
+             LIMIT ?",
+            keywords = tables.keywords,
+            owner = tables.owner
+        ))?;
+
+        stmt
+            .query_map(params![match_query, tables.id, embedding_model, dimensions, count], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect()
     }
 
     /// # Errors
     /// # Panics
     pub fn delete_rag_file(
         &self,
-        conversation_id: ConversationNodeID,
+        source: RagSource,
         rag_file_id: RagFileID,
-    ) -> Result<(), String> {
-        let connection = self
+    ) -> Result<(), rusqlite::Error> {
+        let mut connection = self
             .connection
             .lock()
             .expect("Failed to lock connection");
+        let transaction = connection.transaction()?;
 
-        let mut table_names = vec![];
+        let tables = SourceTables::new(source);
 
-        let mut stmt = connection
-            .prepare("SELECT dimensions, embedding_model FROM vectors_files WHERE conversation_id = ? AND id = ?")
-            .expect("Failed to prepare statement");
+        let table_names = Self::file_tables(
+            &transaction,
+            &tables,
+            &format!(
+                "SELECT DISTINCT dimensions FROM {files} WHERE {owner} = ? AND id = ?",
+                files = tables.files,
+                owner = tables.owner
+            ),
+            params![tables.id, rag_file_id],
+        )?;
 
-        let mut rows = stmt
-            .query(params![conversation_id, rag_file_id])
-            .expect("Failed to query");
+        for table_name in table_names {
+            transaction.execute(
+                &format!("DELETE FROM {table_name} WHERE {owner} = ? AND file_id = ?", owner = tables.owner),
+                params![tables.id, rag_file_id],
+            )?;
+        }
 
-        while let Some(row) = rows.next().expect("Failed to get next row") {
-            let dimensions: usize = row.get(0).expect("Failed to get dimension");
-            let embedding_model: String = row.get(1).expect("Failed to get embedding model");
+        transaction.execute(
+            &format!("DELETE FROM {keywords} WHERE {owner} = ? AND file_id = ?", keywords = tables.keywords, owner = tables.owner),
+            params![tables.id, rag_file_id],
+        )?;
 
-            let vectors_table_name = self.get_vectors_table_name(conversation_id, dimensions, &embedding_model);
-            table_names.push(vectors_table_name);
-        }
+        transaction.execute(
+            &format!("DELETE FROM {files} WHERE {owner} = ? AND id = ?", files = tables.files, owner = tables.owner),
+            params![tables.id, rag_file_id],
+        )?;
+
+        transaction.commit()
+    }
+
+    /// Deletes the files of the source and stops watching its paths.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn delete_all_files(&self, source: RagSource) -> Result<(), rusqlite::Error> {
+        let mut connection = self
+            .connection
+            .lock()
+            .expect("Failed to lock connection");
+        let transaction = connection.transaction()?;
+
+        let tables = SourceTables::new(source);
+
+        let table_names = Self::file_tables(
+            &transaction,
+            &tables,
+            &format!(
+                "SELECT DISTINCT dimensions FROM {files} WHERE {owner} = ?",
+                files = tables.files,
+                owner = tables.owner
+            ),
+            params![tables.id],
+        )?;
 
         for table_name in table_names {
-            let mut stmt = connection
-                .prepare(&format!("DELETE FROM {table_name} WHERE file_id = ?"))
-                .expect("Failed to prepare statement");
-
-            stmt.execute(params![rag_file_id])
-                .expect("Failed to execute statement");
-
-            let mut check_stmt = connection
-                .prepare(&format!("SELECT COUNT(*) FROM {table_name}"))
-                .expect("Failed to prepare statement");
-
-            let mut check_rows = check_stmt
-                .query([])
-                .expect("Failed to query");
-
-            if let Some(row) = check_rows.next().expect("Failed to get next row") {
-                let count: i64 = row.get(0).expect("Failed to get count");
-                if count == 0 {
-                    drop(check_rows);
-                    let mut drop_stmt = connection
-                        .prepare(&format!("DROP TABLE IF EXISTS {table_name}"))
-                        .expect("Failed to prepare statement");
-
-                    drop_stmt.execute([])
-                        .expect("Failed to execute statement");
-
-                    // VACUUM;
-                    connection.execute("VACUUM", [])
-                        .expect("Failed to execute VACUUM");
-                }
-            }
+            transaction.execute(
+                &format!("DELETE FROM {table_name} WHERE {owner} = ?", owner = tables.owner),
+                params![tables.id],
+            )?;
         }
 
-        let mut stmt = connection
-            .prepare("DELETE FROM vectors_files WHERE conversation_id = ? AND id = ?")
-            .expect("Failed to prepare statement");
+        transaction.execute(
+            &format!("DELETE FROM {keywords} WHERE {owner} = ?", keywords = tables.keywords, owner = tables.owner),
+            params![tables.id],
+        )?;
 
-        stmt.execute(params![conversation_id, rag_file_id])
-            .expect("Failed to execute statement");
+        transaction.execute(
+            &format!("DELETE FROM {files} WHERE {owner} = ?", files = tables.files, owner = tables.owner),
+            params![tables.id],
+        )?;
 
-        Ok(())
+        transaction.execute(
+            &format!("DELETE FROM {watched} WHERE {owner} = ?", watched = tables.watched, owner = tables.owner),
+            params![tables.id],
+        )?;
+
+        transaction.commit()
     }
 
     /// # Errors
     /// # Panics
-    pub fn delete_all_files_in_conversation(
-        &self,
-        conversation_id: ConversationNodeID,
-    ) -> Result<(), String> {
+    pub fn get_files(&self, source: RagSource) -> Result<Vec<RagFileDTO>, rusqlite::Error> {
         let connection = self
             .connection
             .lock()
             .expect("Failed to lock connection");
 
-        let mut table_names = vec![];
-        let mut stmt = connection
-            .prepare("SELECT dimensions, embedding_model FROM vectors_files WHERE conversation_id = ?")
-            .expect("Failed to prepare statement");
+        let tables = SourceTables::new(source);
 
-        let mut rows = stmt
-            .query(params![conversation_id])
-            .expect("Failed to query");
+        let mut stmt = connection.prepare(&format!(
+            "SELECT file_name, id, dimensions, embedding_model, file_path FROM {files} WHERE {owner} = ?",
+            files = tables.files,
+            owner = tables.owner
+        ))?;
 
-        while let Some(row) = rows.next().expect("Failed to get next row") {
-            let dimensions: usize = row.get(0).expect("Failed to get dimension");
-            let embedding_model: String = row.get(1).expect("Failed to get embedding model");
+        let mut files: Vec<RagFileDTO> = stmt
+            .query_map(params![tables.id], Self::row_to_file)?
+            .collect::<Result<_, _>>()?;
 
-            let vectors_table_name = self.get_vectors_table_name(conversation_id, dimensions, &embedding_model);
-            table_names.push(vectors_table_name);
+        for file in &mut files {
+            file.chunks = Self::count_chunks(&connection, &tables, file)?;
         }
 
-        for table_name in table_names {
-            let mut stmt = connection
-                .prepare(&format!("DROP TABLE IF EXISTS {table_name}"))
-                .expect("Failed to prepare statement");
+        Ok(files)
+    }
 
-            stmt.execute([])
-                .expect("Failed to execute statement");
+    /// # Errors
+    /// # Panics
+    pub fn get_file(
+        &self,
+        source: RagSource,
+        file_id: RagFileID,
+    ) -> Result<Option<RagFileDTO>, rusqlite::Error> {
+        let connection = self
+            .connection
+            .lock()
+            .expect("Failed to lock connection");
 
-            connection.execute("VACUUM", [])
-                .expect("Failed to execute VACUUM");
-        }
-            
-        let mut stmt = connection
-            .prepare("DELETE FROM vectors_files WHERE conversation_id = ?")
-            .expect("Failed to prepare statement");
+        let tables = SourceTables::new(source);
+
+        let file = connection
+            .query_row(
+                &format!(
+                    "SELECT file_name, id, dimensions, embedding_model, file_path FROM {files} WHERE {owner} = ? AND id = ?",
+                    files = tables.files,
+                    owner = tables.owner
+                ),
+                params![tables.id, file_id],
+                Self::row_to_file,
+            )
+            .optional()?;
 
-        stmt.execute(params![conversation_id])
-            .expect("Failed to execute statement");
+        let Some(mut file) = file else {
+            return Ok(None);
+        };
 
-        Ok(())
+        file.chunks = Self::count_chunks(&connection, &tables, &file)?;
+
+        Ok(Some(file))
     }
 
+    /// Texts of the chunks stored for the file with the size of their vectors,
+    /// `None` when it was not embedded with the model yet. Files loaded before
+    /// their path was kept are found by their hash.
+    ///
     /// # Errors
     /// # Panics
-    pub fn get_files(
+    pub fn get_file_chunks(
         &self,
-        conversation_id: ConversationNodeID,
-    ) -> Result<Vec<RagFileDTO>, String> {
+        source: RagSource,
+        file: &IndexedFile,
+        embedding_model: &str,
+    ) -> Result<Option<(usize, Vec<String>)>, rusqlite::Error> {
         let connection = self
             .connection
             .lock()
             .expect("Failed to lock connection");
 
-        let mut stmt = connection
-            .prepare("SELECT file_name, id, dimensions, embedding_model FROM vectors_files WHERE conversation_id = ?")
-            .expect("Failed to prepare statement");
-
-        let mut rows = stmt
-            .query(params![conversation_id])
-            .expect("Failed to query");
+        let tables = SourceTables::new(source);
 
-        let mut files = vec![];
+        let Some((file_id, dimensions)) = Self::find_file(&connection, &tables, file, None, embedding_model)? else {
+            return Ok(None);
+        };
 
-        while let Some(row) = rows.next().expect("Failed to get next row") {
-            let file_name: String = row.get(0).expect("Failed to get file name");
-            let id: RagFileID = row.get(1).expect("Failed to get file id");
-            let dimension: usize = row.get(2).expect("Failed to get dimension");
-            let embedding_model: String = row.get(3).expect("Failed to get embedding model");
+        let table_name = tables.vectors_table(dimensions);
 
-            files.push(RagFileDTO { file_name, id, dimension, embedding_model });
+        if !Self::table_exists(&connection, &table_name)? {
+            return Ok(Some((dimensions, vec![])));
         }
 
-        Ok(files)
+        let chunks = connection
+            .prepare(&format!(
+                "SELECT chunk FROM {table_name} WHERE {owner} = ? AND embedding_model = ? AND file_id = ?",
+                owner = tables.owner
+            ))?
+            .query_map(params![tables.id, embedding_model, file_id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some((dimensions, chunks)))
     }
 
-    #[must_use] pub fn get_file(
+    /// Stores the chunks of the file in place of the ones stored for its path.
+    /// Chunks it had before keep their id and vector, so messages answered
+    /// with them still find them, `vectors` has the vectors of the new ones by
+    /// their text. A chunk repeated in the file is stored once.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn replace_file(
         &self,
-        conversation_id: ConversationNodeID,
-        file_id: RagFileID,
-    ) -> Option<RagFileDTO> {
-        let connection = self
+        source: RagSource,
+        file: &IndexedFile,
+        chunks: &[FileChunk],
+        vectors: &HashMap<String, Vec<f32>>,
+        dimensions: usize,
+        embedding_model: &str,
+    ) -> Result<(), rusqlite::Error> {
+        let mut connection = self
             .connection
             .lock()
             .expect("Failed to lock connection");
 
-        let mut stmt = connection
-            .prepare("SELECT file_name, id, dimensions, embedding_model FROM vectors_files WHERE conversation_id = ? AND id = ?")
-            .expect("Failed to prepare statement");
+        let transaction = connection.transaction()?;
+
+        let tables = SourceTables::new(source);
+        let owner = tables.owner;
+
+        let vectors_table_name = Self::create_vectors_table(&transaction, source, dimensions)?;
+
+        let file_id = if let Some((file_id, _dimensions)) =
+            Self::find_file(&transaction, &tables, file, Some(dimensions), embedding_model)?
+        {
+            transaction.execute(
+                &format!(
+                    "UPDATE {files} SET file_path = ?, file_hash = ?, file_name = ?, content = ? WHERE id = ?",
+                    files = tables.files
+                ),
+                params![file.path, file.hash, file.name, file.content, file_id],
+            )?;
+
+            file_id
+        } else {
+            transaction.execute(
+                &format!(
+                    "INSERT INTO {files} ({owner}, file_path, file_hash, file_name, content, dimensions, embedding_model)
+                     VALUES (?, ?, ?, ?, ?, ?, ?)",
+                    files = tables.files
+                ),
+                params![tables.id, file.path, file.hash, file.name, file.content, dimensions, embedding_model],
+            )?;
+
+            transaction.last_insert_rowid()
+        };
+
+        // Chunks of the previous version by their text, which may repeat, the ones not
+        // in this one are deleted at the end
+        let mut stored: HashMap<String, Vec<ChunkRagId>> = HashMap::new();
+        for row in transaction
+            .prepare(&format!(
+                "SELECT chunk, id FROM {vectors_table_name}
+                 WHERE {owner} = ? AND embedding_model = ? AND file_id = ?"
+            ))?
+            .query_map(params![tables.id, embedding_model, file_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        {
+            let (text, chunk_id): (String, ChunkRagId) = row?;
+            stored.entry(text).or_default().push(chunk_id);
+        }
 
-        let mut rows = stmt
-            .query(params![conversation_id, file_id])
-            .expect("Failed to query");
+        let mut written = HashSet::new();
+
+        {
+            let mut update_stmt = transaction.prepare(&format!(
+                "UPDATE {vectors_table_name}
+                 SET page = ?, start_line = ?, end_line = ?, start_byte = ?, end_byte = ?
+                 WHERE id = ?"
+            ))?;
+
+            let mut insert_stmt = transaction.prepare(&format!(
+                "INSERT INTO {vectors_table_name} (embedding, {owner}, embedding_model, file_id, chunk, page, start_line, end_line, start_byte, end_byte)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            ))?;
+
+            let mut keywords_stmt = transaction.prepare(&format!(
+                "INSERT INTO {keywords} (chunk, {owner}, embedding_model, dimensions, file_id, chunk_id)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                keywords = tables.keywords
+            ))?;
+
+            for chunk in chunks {
+                if !written.insert(chunk.text.as_str()) {
+                    continue;
+                }
 
-        rows.next().expect("Failed to get next row").map(|row| {
-            let file_name: String = row.get(0).expect("Failed to get file name");
-            let id: RagFileID = row.get(1).expect("Failed to get file id");
-            let dimension: usize = row.get(2).expect("Failed to get dimension");
-            let embedding_model: String = row.get(3).expect("Failed to get embedding model");
+                if let Some(chunk_id) = stored.get_mut(&chunk.text).and_then(Vec::pop) {
+                    update_stmt.execute(params![
+                        chunk.page,
+                        chunk.lines.map(|(start, _)| start),
+                        chunk.lines.map(|(_, end)| end),
+                        chunk.bytes.map(|(start, _)| start),
+                        chunk.bytes.map(|(_, end)| end),
+                        chunk_id,
+                    ])?;
+
+                    continue;
+                }
 
-            RagFileDTO { file_name, id, dimension, embedding_model }
-        })
+                let Some(vector) = vectors.get(&chunk.text) else {
+                    continue;
+                };
+
+                insert_stmt.execute(params![
+                    vector.as_bytes(),
+                    tables.id,
+                    embedding_model,
+                    file_id,
+                    chunk.text,
+                    chunk.page,
+                    chunk.lines.map(|(start, _)| start),
+                    chunk.lines.map(|(_, end)| end),
+                    chunk.bytes.map(|(start, _)| start),
+                    chunk.bytes.map(|(_, end)| end),
+                ])?;
+
+                keywords_stmt.execute(params![
+                    chunk.text,
+                    tables.id,
+                    embedding_model,
+                    dimensions,
+                    file_id,
+                    transaction.last_insert_rowid(),
+                ])?;
+            }
+
+            let mut delete_stmt = transaction.prepare(&format!("DELETE FROM {vectors_table_name} WHERE id = ?"))?;
+            let mut delete_keywords_stmt = transaction.prepare(&format!(
+                "DELETE FROM {keywords} WHERE {owner} = ? AND dimensions = ? AND chunk_id = ?",
+                keywords = tables.keywords
+            ))?;
+
+            for chunk_id in stored.into_values().flatten() {
+                delete_stmt.execute(params![chunk_id])?;
+                delete_keywords_stmt.execute(params![tables.id, dimensions, chunk_id])?;
+            }
+        }
+
+        transaction.commit()
     }
 
+    /// Hashes of the files embedded with the model by their path, to tell which
+    /// ones changed since.
+    ///
+    /// # Errors
     /// # Panics
-    pub fn insert_records(
+    pub fn get_file_hashes(
         &self,
-        conversation_id: ConversationNodeID,
-        file_hash: &str,
-        file_name: &str,
-        chunks: &[String],
-        vectors: &[Vec<f32>],
+        source: RagSource,
         embedding_model: &str,
-    ) {
+    ) -> Result<HashMap<String, String>, rusqlite::Error> {
         let connection = self
             .connection
             .lock()
             .expect("Failed to lock connection");
 
-        let dimensions = vectors.first()
-            .expect("Failed to get first vector")
-            .len();
-
-        let vectors_table_name = self.get_vectors_table_name(conversation_id, dimensions, embedding_model);
+        let tables = SourceTables::new(source);
 
         connection
-            .execute(
-            &format!("CREATE VIRTUAL TABLE IF NOT EXISTS {vectors_table_name} USING vec0(
-                    id INTEGER PRIMARY KEY,
-                    embedding float[{dimensions}],
-                    file_id INTEGER NOT NULL,
-                    chunk TEXT NOT NULL
-                )
-            "),
-            params![],
+            .prepare(&format!(
+                "SELECT file_path, file_hash FROM {files}
+                 WHERE {owner} = ? AND embedding_model = ? AND file_path IS NOT NULL",
+                files = tables.files,
+                owner = tables.owner
+            ))?
+            .query_map(params![tables.id, embedding_model], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect()
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn add_watched_path(
+        &self,
+        source: RagSource,
+        path: &str,
+        include: &[String],
+        exclude: &[String],
+    ) -> Result<WatchedPathDTO, rusqlite::Error> {
+        let connection = self
+            .connection
+            .lock()
+            .expect("Failed to lock connection");
+
+        let tables = SourceTables::new(source);
+
+        connection.execute(
+            &format!(
+                "INSERT INTO {watched} ({owner}, path, include, exclude) VALUES (?, ?, ?, ?)
+                 ON CONFLICT({owner}, path) DO UPDATE SET include = excluded.include, exclude = excluded.exclude",
+                watched = tables.watched,
+                owner = tables.owner
+            ),
+            params![
+                tables.id,
+                path,
+                serde_json::to_string(include).unwrap_or_default(),
+                serde_json::to_string(exclude).unwrap_or_default(),
+            ],
+        )?;
+
+        connection.query_row(
+            &format!(
+                "SELECT id, path, include, exclude FROM {watched} WHERE {owner} = ? AND path = ?",
+                watched = tables.watched,
+                owner = tables.owner
+            ),
+            params![tables.id, path],
+            |row| Self::row_to_watched_path(row, source),
         )
-        .expect("Failed to create virtual table");
-
-        let mut stmt = connection
-            .prepare("INSERT INTO vectors_files (conversation_id, file_hash, file_name, dimensions, embedding_model) 
-                  VALUES (?, ?, ?, ?, ?) 
-                  ON CONFLICT(conversation_id, file_hash, dimensions, embedding_model) 
-                  DO UPDATE SET file_name = excluded.file_name")
-            .expect("Failed to prepare statement");
-
-        stmt.execute(params![conversation_id, file_hash, file_name, dimensions, embedding_model])
-            .expect("Failed to execute statement");
-
-        let file_id: i32 = connection
-            .prepare("SELECT id FROM vectors_files WHERE conversation_id = ? AND file_hash = ? AND dimensions = ? AND embedding_model = ?")
-            .expect("Failed to prepare statement")
-            .query_row(params![conversation_id, file_hash, dimensions, embedding_model], |row| {
-                row.get(0)
-            })
-            .expect("Failed to get file id");
-
-        let mut stmt = connection
-            .prepare(&format!("INSERT INTO {vectors_table_name} (embedding, file_id, chunk) VALUES (?, ?, ?)"))
-            .expect("Failed to prepare statement");
-
-        for (chunk, vector) in chunks.iter().zip(vectors.iter()) {
-            let mut check_stmt = connection
-                .prepare(&format!("SELECT COUNT(*) FROM {vectors_table_name} WHERE file_id = ? AND chunk = ?"))
-                .expect("Failed to prepare statement");
-
-            let mut check_rows = check_stmt
-                .query(params![file_id, chunk])
-                .expect("Failed to query");
-
-            let count: i64 = check_rows.next().expect("Failed to get next row").map_or(0, |row| row.get(0).expect("Failed to get count"));
-
-            if count > 0 {
-                continue;
-            }
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn get_watched_paths(&self, source: RagSource) -> Result<Vec<WatchedPathDTO>, rusqlite::Error> {
+        let connection = self
+            .connection
+            .lock()
+            .expect("Failed to lock connection");
+
+        let tables = SourceTables::new(source);
+
+        connection
+            .prepare(&format!(
+                "SELECT id, path, include, exclude FROM {watched} WHERE {owner} = ? ORDER BY path",
+                watched = tables.watched,
+                owner = tables.owner
+            ))?
+            .query_map(params![tables.id], |row| Self::row_to_watched_path(row, source))?
+            .collect()
+    }
+
+    /// Watched paths of every chat and knowledge base.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn get_all_watched_paths(&self) -> Result<Vec<WatchedPathDTO>, rusqlite::Error> {
+        let connection = self
+            .connection
+            .lock()
+            .expect("Failed to lock connection");
+
+        let mut watched_paths = vec![];
+
+        for source_of_kind in [RagSource::Conversation, RagSource::KnowledgeBase] {
+            let tables = SourceTables::new(source_of_kind(0));
 
-            stmt.execute(params![vector.as_bytes(), file_id, chunk])
-                .expect("Failed to execute statement");
+            let mut stmt = connection.prepare(&format!(
+                "SELECT id, path, include, exclude, {owner} FROM {watched}",
+                watched = tables.watched,
+                owner = tables.owner
+            ))?;
+
+            for watched_path in stmt.query_map([], |row| Self::row_to_watched_path(row, source_of_kind(row.get(4)?)))? {
+                watched_paths.push(watched_path?);
+            }
         }
+
+        Ok(watched_paths)
     }
 
+    /// # Errors
     /// # Panics
-    #[must_use] pub fn check_by_file_hash(&self, conversation_id: ConversationNodeID, file_hash: &str, embedding_model: &str, dimensions: usize) -> bool {
+    pub fn delete_watched_path(&self, source: RagSource, id: WatchedPathID) -> Result<(), rusqlite::Error> {
         let connection = self
             .connection
             .lock()
             .expect("Failed to lock connection");
 
-        let mut stmt = connection
-            .prepare("SELECT COUNT(*) FROM vectors_files WHERE conversation_id = ? AND file_hash = ? AND dimensions = ? AND embedding_model = ?")
-            .expect("Failed to prepare statement");
+        let tables = SourceTables::new(source);
+
+        connection.execute(
+            &format!(
+                "DELETE FROM {watched} WHERE {owner} = ? AND id = ?",
+                watched = tables.watched,
+                owner = tables.owner
+            ),
+            params![tables.id, id],
+        )?;
+
+        Ok(())
+    }
+
+    /// `None` for files loaded before their text was kept.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn get_file_content(
+        &self,
+        source: RagSource,
+        file_id: RagFileID,
+    ) -> Result<Option<String>, rusqlite::Error> {
+        let connection = self
+            .connection
+            .lock()
+            .expect("Failed to lock connection");
+
+        let tables = SourceTables::new(source);
+
+        let content = connection
+            .query_row(
+                &format!(
+                    "SELECT content FROM {files} WHERE {owner} = ? AND id = ?",
+                    files = tables.files,
+                    owner = tables.owner
+                ),
+                params![tables.id, file_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(content.flatten())
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn check_by_file_hash(&self, source: RagSource, file_hash: &str, embedding_model: &str, dimensions: usize) -> Result<bool, rusqlite::Error> {
+        let connection = self
+            .connection
+            .lock()
+            .expect("Failed to lock connection");
+
+        let tables = SourceTables::new(source);
+
+        let count: i64 = connection.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM {files} WHERE {owner} = ? AND file_hash = ? AND dimensions = ? AND embedding_model = ?",
+                files = tables.files,
+                owner = tables.owner
+            ),
+            params![tables.id, file_hash, dimensions, embedding_model],
+            |row| row.get(0),
+        )?;
+
+        Ok(count > 0)
+    }
+
+    /// Id and vector size of the row of the file, by its path or, for files loaded
+    /// before their path was kept, by its hash.
+    fn find_file(
+        connection: &rusqlite::Connection,
+        tables: &SourceTables,
+        file: &IndexedFile,
+        dimensions: Option<usize>,
+        embedding_model: &str,
+    ) -> Result<Option<(RagFileID, usize)>, rusqlite::Error> {
+        connection
+            .query_row(
+                &format!(
+                    "SELECT id, dimensions FROM {files}
+                     WHERE {owner} = ? AND embedding_model = ? AND (? IS NULL OR dimensions = ?)
+                     AND (file_path = ? OR (file_path IS NULL AND file_hash = ?))
+                     ORDER BY file_path IS NULL
+                     LIMIT 1",
+                    files = tables.files,
+                    owner = tables.owner
+                ),
+                params![tables.id, embedding_model, dimensions, dimensions, file.path, file.hash],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+    }
+
+    /// Vector tables holding the files selected by `query`, it selects their dimensions.
+    fn file_tables(
+        connection: &rusqlite::Connection,
+        tables: &SourceTables,
+        query: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = connection.prepare(query)?;
+
+        let table_names: Vec<String> = stmt
+            .query_map(params, |row| Ok(tables.vectors_table(row.get(0)?)))?
+            .collect::<Result<_, _>>()?;
+
+        let mut existing = vec![];
+        for table_name in table_names {
+            if Self::table_exists(connection, &table_name)? {
+                existing.push(table_name);
+            }
+        }
+
+        Ok(existing)
+    }
+
+    fn count_chunks(
+        connection: &rusqlite::Connection,
+        tables: &SourceTables,
+        file: &RagFileDTO,
+    ) -> Result<usize, rusqlite::Error> {
+        let table_name = tables.vectors_table(file.dimension);
+
+        if !Self::table_exists(connection, &table_name)? {
+            return Ok(0);
+        }
 
-        let mut rows = stmt
-            .query(params![conversation_id, file_hash, dimensions, embedding_model])
-            .expect("Failed to query");
+        connection.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM {table_name} WHERE {owner} = ? AND embedding_model = ? AND file_id = ?",
+                owner = tables.owner
+            ),
+            params![tables.id, file.embedding_model, file.id],
+            |row| row.get(0),
+        )
+    }
 
-        rows.next().expect("Failed to get next row").is_some_and(|row| {
-            let count: i64 = row.get(0).expect("Failed to get count");
-            count > 0
+    fn table_exists(connection: &rusqlite::Connection, table_name: &str) -> Result<bool, rusqlite::Error> {
+        connection.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = ?",
+            [table_name],
+            |row| row.get(0),
+        )
+    }
+
+    fn row_to_watched_path(row: &rusqlite::Row, source: RagSource) -> Result<WatchedPathDTO, rusqlite::Error> {
+        Ok(WatchedPathDTO {
+            id: row.get(0)?,
+            source,
+            path: row.get(1)?,
+            include: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+            exclude: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+        })
+    }
+
+    fn row_to_file(row: &rusqlite::Row) -> Result<RagFileDTO, rusqlite::Error> {
+        Ok(RagFileDTO {
+            file_name: row.get(0)?,
+            id: row.get(1)?,
+            dimension: row.get(2)?,
+            embedding_model: row.get(3)?,
+            file_path: row.get(4)?,
+            chunks: 0,
         })
     }
 
-    fn get_vectors_table_name(&self, conversation_id: ConversationNodeID, dimensions: usize, embedding_model: &str) -> String {
-        format!("vectors_conversation_{conversation_id}_{embedding_model}_{dimensions}").replace('-', "_").replace(':', "_")
+    /// Quotes every word so the user input never breaks the FTS5 syntax, a chunk
+    /// needs only one of them to be found.
+    fn match_query(query: &str) -> Option<String> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect();
+
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" OR "))
+        }
+    }
+
+    /// Chunks of every conversation or knowledge base embedded with `dimensions` wide
+    /// vectors. Searches only look at one source and model, both are partition keys
+    /// so the other vectors are not even read.
+    fn create_vectors_table(
+        connection: &rusqlite::Connection,
+        source: RagSource,
+        dimensions: usize,
+    ) -> Result<String, rusqlite::Error> {
+        let tables = SourceTables::new(source);
+        let table_name = tables.vectors_table(dimensions);
+
+        connection.execute(
+            &format!("CREATE VIRTUAL TABLE IF NOT EXISTS {table_name} USING vec0(
+                    id INTEGER PRIMARY KEY,
+                    embedding float[{dimensions}],
+                    {owner} INTEGER PARTITION KEY,
+                    embedding_model TEXT PARTITION KEY,
+                    file_id INTEGER,
+                    +chunk TEXT,
+                    +page INTEGER,
+                    +start_line INTEGER,
+                    +end_line INTEGER,
+                    +start_byte INTEGER,
+                    +end_byte INTEGER
+                )
+            ", owner = tables.owner),
+            params![],
+        )?;
+
+        Ok(table_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    use rusqlite::{Connection, ToSql, ffi::sqlite3_auto_extension, params};
+    use sqlite_vec::sqlite3_vec_init;
+    use types::dto::{FileChunk, IndexedFile, RagSource};
+    use zerocopy::IntoBytes;
+
+    use super::VectorDatabase;
+    use crate::create_database_connection;
+
+    const CONVERSATIONS: i64 = 40;
+    const CHUNKS: usize = 2000;
+    const DIMENSIONS: usize = 384;
+    const QUERIES: usize = 200;
+    const K: usize = 5;
+
+    const fn embedding_model(conversation_id: i64) -> &'static str {
+        if conversation_id % 2 == 0 {
+            "text-embedding-3-small"
+        } else {
+            "nomic-embed-text:latest"
+        }
+    }
+
+    /// Every layout gets the same vectors and queries.
+    struct Vectors(u64);
+
+    impl Vectors {
+        #[allow(clippy::cast_precision_loss)]
+        fn next(&mut self) -> Vec<f32> {
+            (0..DIMENSIONS)
+                .map(|_| {
+                    self.0 = self
+                        .0
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1_442_695_040_888_963_407);
+                    (self.0 >> 33) as f32 / (1_u64 << 31) as f32 - 0.5
+                })
+                .collect()
+        }
+    }
+
+    fn connection() -> Connection {
+        unsafe {
+            #[allow(clippy::missing_transmute_annotations)]
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_vec_init as *const ())));
+        }
+
+        Connection::open_in_memory().expect("Failed to open database")
+    }
+
+    /// Fills the tables `table` names with the chunks of every conversation, then
+    /// times the queries `query` makes for a table, conversation and model.
+    fn run(
+        create: impl Fn(&str) -> String,
+        table: impl Fn(i64) -> String,
+        query: impl Fn(&str) -> String,
+    ) -> Duration {
+        let mut connection = connection();
+        let mut vectors = Vectors(42);
+
+        let transaction = connection.transaction().unwrap();
+        for conversation_id in 1..=CONVERSATIONS {
+            let table = table(conversation_id);
+            transaction.execute_batch(&create(&table)).unwrap();
+
+            let mut insert = transaction
+                .prepare(&format!(
+                    "INSERT INTO {table} (embedding, conversation_id, embedding_model, chunk)
+                     VALUES (?, ?, ?, ?)"
+                ))
+                .unwrap();
+
+            for index in 0..CHUNKS {
+                insert
+                    .execute(params![
+                        vectors.next().as_bytes(),
+                        conversation_id,
+                        embedding_model(conversation_id),
+                        format!("chunk {conversation_id} {index}")
+                    ])
+                    .unwrap();
+            }
+        }
+        transaction.commit().unwrap();
+
+        let mut vectors = Vectors(7);
+        let queries: Vec<Vec<f32>> = (0..QUERIES).map(|_| vectors.next()).collect();
+
+        let started = Instant::now();
+        for (conversation_id, query_vector) in (1..=CONVERSATIONS).cycle().zip(&queries) {
+            let mut statement = connection.prepare_cached(&query(&table(conversation_id))).unwrap();
+            let values: [&dyn ToSql; 4] = [
+                &query_vector.as_bytes(),
+                &K,
+                &conversation_id,
+                &embedding_model(conversation_id),
+            ];
+
+            // Queries of the per-conversation tables take only the vector and k
+            for (index, value) in values.into_iter().enumerate().take(statement.parameter_count()) {
+                statement.raw_bind_parameter(index + 1, value).unwrap();
+            }
+
+            let found = statement.raw_query().mapped(|row| row.get::<_, i64>(0)).count();
+
+            assert_eq!(found, K);
+        }
+
+        started.elapsed()
+    }
+
+    fn shared(conversation_column: &str, model_column: &str) -> Duration {
+        run(
+            |table| {
+                format!(
+                    "CREATE VIRTUAL TABLE IF NOT EXISTS {table} USING vec0(
+                        id INTEGER PRIMARY KEY,
+                        embedding float[{DIMENSIONS}],
+                        {conversation_column},
+                        {model_column},
+                        +chunk TEXT
+                    );"
+                )
+            },
+            |_conversation_id| format!("vectors_{DIMENSIONS}"),
+            |table| {
+                format!(
+                    "SELECT id FROM {table}
+                     WHERE embedding MATCH ? AND k = ? AND conversation_id = ? AND embedding_model = ?"
+                )
+            },
+        )
+    }
+
+    /// Filtered KNN over the table layouts vectors were kept in, the shared table
+    /// must be as fast as a table per conversation as long as both owner columns
+    /// are partition keys. `cargo test --release -p database knn -- --ignored`.
+    #[test]
+    #[ignore = "benchmark, takes about half a minute in a release build"]
+    fn knn_by_table_layout() {
+        let per_conversation = run(
+            |table| {
+                // The old tables had no owner columns, these are only stored to share the insert
+                format!(
+                    "CREATE VIRTUAL TABLE {table} USING vec0(
+                        id INTEGER PRIMARY KEY,
+                        embedding float[{DIMENSIONS}],
+                        +conversation_id INTEGER,
+                        +embedding_model TEXT,
+                        +chunk TEXT
+                    );"
+                )
+            },
+            |conversation_id| {
+                format!("vectors_conversation_{conversation_id}_{}_{DIMENSIONS}", embedding_model(conversation_id))
+                    .replace(['-', ':', '.'], "_")
+            },
+            |table| format!("SELECT id FROM {table} WHERE embedding MATCH ? AND k = ?"),
+        );
+        let partitioned = shared(
+            "conversation_id INTEGER PARTITION KEY",
+            "embedding_model TEXT PARTITION KEY",
+        );
+        let model_metadata = shared("conversation_id INTEGER PARTITION KEY", "embedding_model TEXT");
+        let unpartitioned = shared("conversation_id INTEGER", "embedding_model TEXT");
+
+        assert!(
+            partitioned < per_conversation * 2,
+            "shared table {partitioned:?}, per-conversation tables {per_conversation:?}"
+        );
+        assert!(
+            partitioned < model_metadata,
+            "both partition keys {partitioned:?}, model as metadata column {model_metadata:?}"
+        );
+        assert!(
+            partitioned * 10 < unpartitioned,
+            "partition keys {partitioned:?}, no partition keys {unpartitioned:?}"
+        );
+    }
+
+    fn chunk(text: &str) -> FileChunk {
+        FileChunk {
+            text: text.to_string(),
+            page: None,
+            lines: None,
+            bytes: None,
+        }
+    }
+
+    #[test]
+    fn replacing_a_file_deletes_every_copy_of_a_repeated_chunk() {
+        let connection = create_database_connection(":memory:").expect("Failed to open database");
+        let database = VectorDatabase::new(connection.clone());
+        let source = RagSource::Conversation(1);
+        let file = IndexedFile {
+            path: "notes.txt".to_string(),
+            name: "notes.txt".to_string(),
+            hash: "hash".to_string(),
+            content: "same\nsame\nother".to_string(),
+        };
+        let vectors = HashMap::from([
+            ("same".to_string(), vec![1.0, 0.0]),
+            ("other".to_string(), vec![0.0, 1.0]),
+        ]);
+
+        database
+            .replace_file(source, &file, &[chunk("same"), chunk("other")], &vectors, 2, "model")
+            .unwrap();
+
+        // Older versions stored every copy of a repeated text
+        {
+            let connection = connection.lock().unwrap();
+            connection
+                .execute(
+                    "INSERT INTO vectors_2 (embedding, conversation_id, embedding_model, file_id, chunk)
+                     SELECT embedding, conversation_id, embedding_model, file_id, chunk FROM vectors_2 WHERE chunk = 'same'",
+                    [],
+                )
+                .unwrap();
+            connection
+                .execute(
+                    "INSERT INTO vectors_fts (chunk, conversation_id, embedding_model, dimensions, file_id, chunk_id)
+                     SELECT 'same', 1, 'model', 2, id, last_insert_rowid() FROM vectors_files",
+                    [],
+                )
+                .unwrap();
+        }
+
+        database
+            .replace_file(source, &file, &[chunk("same")], &vectors, 2, "model")
+            .unwrap();
+
+        let connection = connection.lock().unwrap();
+        let chunks = |table: &str| -> Vec<String> {
+            connection
+                .prepare(&format!("SELECT chunk FROM {table}"))
+                .unwrap()
+                .query_map([], |row| row.get(0))
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+
+        assert_eq!(chunks("vectors_2"), ["same"]);
+        assert_eq!(chunks("vectors_fts"), ["same"]);
     }
 }
@@ -2,3 +2,4 @@ mod utils;
 pub use utils::*;
 
 pub mod databases;
+pub mod migrations;
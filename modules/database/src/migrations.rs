@@ -0,0 +1,911 @@
+//! Versioned schema of the application database.
+//!
+//! The version is kept in `PRAGMA user_version`, every migration moves the
+//! schema one version forward. Databases written before versioning existed have
+//! version 0, so every migration must also accept a schema that already has some
+//! of its tables or columns.
+
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::{Connection, Transaction, params};
+use types::dto::{
+    ChunkRagId, ConversationNodeID, DEFAULT_RAG_DISTANCE_CUTOFF, MessageUsedRagChunk, RagSource,
+};
+
+type Migration = fn(&Transaction) -> rusqlite::Result<()>;
+
+/// Migration `i` upgrades the schema from version `i` to `i + 1`, new ones
+/// are only ever appended.
+const MIGRATIONS: &[Migration] = &[
+    initial_schema,
+    provider_kind_and_reasoning_tags,
+    message_interrupted,
+    tool_calls,
+    attachments,
+    message_search,
+    message_branches,
+    context_size,
+    message_generation,
+    usage,
+    import_source,
+    shared_vectors,
+    knowledge_bases,
+    chunk_pages,
+    chunk_lines,
+    hybrid_search,
+    rerank,
+    query_rewriting,
+    chunk_spans,
+    watched_paths,
+];
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+pub const SCHEMA_VERSION: i32 = MIGRATIONS.len() as i32;
+
+/// Brings the schema up to [`SCHEMA_VERSION`] in one transaction,
+/// nothing is changed if any migration fails.
+///
+/// # Errors
+/// Fails for a database written by a newer version of the application.
+pub fn migrate(connection: &mut Connection) -> Result<(), String> {
+    let version = schema_version(connection)
+        .map_err(|e| format!("Failed to read schema version: {e}"))?;
+
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "Database schema version {version} is newer than supported version {SCHEMA_VERSION}, \
+            update the application to open it"
+        ));
+    }
+
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let transaction = connection
+        .transaction()
+        .map_err(|e| format!("Failed to start migration: {e}"))?;
+
+    #[allow(clippy::cast_sign_loss)]
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(&transaction).map_err(|e| format!("Migration {} failed: {e}", index + 1))?;
+        log::info!("Database migrated to version {}", index + 1);
+    }
+
+    transaction
+        .pragma_update(None, "user_version", SCHEMA_VERSION)
+        .map_err(|e| format!("Failed to update schema version: {e}"))?;
+
+    transaction
+        .commit()
+        .map_err(|e| format!("Failed to commit migration: {e}"))
+}
+
+/// # Errors
+pub fn schema_version(connection: &Connection) -> rusqlite::Result<i32> {
+    connection.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+/// `ALTER TABLE ... ADD COLUMN` that tolerates the column being there already.
+fn add_column(
+    transaction: &Transaction,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<()> {
+    let exists: bool = transaction.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?) WHERE name = ?",
+        [table, column],
+        |row| row.get(0),
+    )?;
+
+    if !exists {
+        transaction.execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Tables as they were before versioning.
+fn initial_schema(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE IF NOT EXISTS providers (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            url TEXT NOT NULL,
+            api_key TEXT NOT NULL,
+            default_model TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS storage (
+            id INTEGER PRIMARY KEY,
+            key TEXT NOT NULL UNIQUE,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS presets (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            prompt TEXT NOT NULL,
+            temperature REAL NOT NULL,
+            max_tokens INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS conversations (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            parent_id INTEGER,
+            type INTEGER NOT NULL,
+            ordr INTEGER DEFAULT 0,
+            preset_id INTEGER,
+            max_messages INTEGER NOT NULL,
+            embedding_provider INTEGER,
+            embedding_model TEXT,
+            rag_chunk_size INTEGER NOT NULL,
+            rag_chunks_count INTEGER NOT NULL,
+            summary_enabled INTEGER DEFAULT 0,
+            summary_model TEXT,
+            summary_provider INTEGER,
+            provider INTEGER,
+            model TEXT,
+            prompt TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS messages (
+            id INTEGER PRIMARY KEY,
+            conversation_id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            reasoning TEXT,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+            role INTEGER NOT NULL,
+            summary TEXT,
+            chunks TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS vectors_files (
+            id INTEGER PRIMARY KEY,
+            conversation_id INTEGER NOT NULL,
+            file_hash TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            dimensions INTEGER NOT NULL,
+            embedding_model TEXT NOT NULL,
+            UNIQUE(conversation_id, file_hash, dimensions, embedding_model)
+        );",
+    )?;
+
+    // Databases made from `initial_config.sql` predate the default model
+    add_column(transaction, "providers", "default_model", "TEXT")
+}
+
+fn provider_kind_and_reasoning_tags(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "providers", "kind", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(transaction, "providers", "reasoning_tags", "TEXT NOT NULL DEFAULT ''")
+}
+
+fn message_interrupted(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "messages", "interrupted", "INTEGER NOT NULL DEFAULT 0")
+}
+
+fn tool_calls(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "messages", "tool_calls", "TEXT")?;
+    add_column(transaction, "messages", "tool_call_id", "TEXT")?;
+    add_column(transaction, "conversations", "tools_enabled", "INTEGER NOT NULL DEFAULT 0")
+}
+
+fn attachments(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE IF NOT EXISTS attachments (
+            id INTEGER PRIMARY KEY,
+            message_id INTEGER NOT NULL,
+            mime_type TEXT NOT NULL,
+            data BLOB NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS attachments_message_id ON attachments (message_id);",
+    )
+}
+
+/// Full-text index of the message texts, the rowid is the message id.
+/// [`crate::databases::MessagesDatabase`] keeps it up to date.
+fn message_search(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+            content,
+            reasoning,
+            summary,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        DELETE FROM messages_fts;
+
+        INSERT INTO messages_fts (rowid, content, reasoning, summary)
+            SELECT id, content, COALESCE(reasoning, ''), COALESCE(summary, '') FROM messages;",
+    )
+}
+
+/// Messages form a tree, a conversation shows the branch ending at its active
+/// message. Existing conversations become a single branch.
+fn message_branches(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "messages", "parent_id", "INTEGER")?;
+    add_column(transaction, "conversations", "active_message_id", "INTEGER")?;
+
+    transaction.execute_batch(
+        "UPDATE messages SET parent_id = (
+            SELECT MAX(previous.id) FROM messages AS previous
+            WHERE previous.conversation_id = messages.conversation_id
+              AND previous.id < messages.id
+        )
+        WHERE parent_id IS NULL;
+
+        UPDATE conversations SET active_message_id = (
+            SELECT MAX(id) FROM messages WHERE messages.conversation_id = conversations.id
+        )
+        WHERE active_message_id IS NULL;
+
+        CREATE INDEX IF NOT EXISTS messages_parent_id ON messages (conversation_id, parent_id);",
+    )
+}
+
+fn context_size(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "conversations", "context_size", "INTEGER NOT NULL DEFAULT 0")
+}
+
+/// How assistant messages were generated, `model` is NULL for the other messages.
+fn message_generation(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "messages", "provider_id", "INTEGER")?;
+    add_column(transaction, "messages", "model", "TEXT")?;
+    add_column(transaction, "messages", "preset", "TEXT")?;
+    add_column(transaction, "messages", "prompt_tokens", "INTEGER")?;
+    add_column(transaction, "messages", "completion_tokens", "INTEGER")?;
+    add_column(transaction, "messages", "time_to_first_token", "INTEGER")?;
+    add_column(transaction, "messages", "duration", "INTEGER")?;
+    add_column(transaction, "messages", "finish_reason", "TEXT")
+}
+
+/// Tokens of every completion, kept when their messages are deleted, and the
+/// prices to estimate what they cost. Generated messages are counted from their metadata.
+fn usage(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE IF NOT EXISTS completion_usage (
+            id INTEGER PRIMARY KEY,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+            conversation_id INTEGER,
+            provider_id INTEGER,
+            model TEXT NOT NULL,
+            prompt_tokens INTEGER NOT NULL,
+            completion_tokens INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS completion_usage_timestamp ON completion_usage (timestamp);
+
+        CREATE TABLE IF NOT EXISTS model_prices (
+            provider_id INTEGER NOT NULL,
+            model TEXT NOT NULL,
+            prompt_price REAL NOT NULL,
+            completion_price REAL NOT NULL,
+            PRIMARY KEY (provider_id, model)
+        );
+
+        INSERT INTO completion_usage (timestamp, conversation_id, provider_id, model, prompt_tokens, completion_tokens)
+            SELECT timestamp, conversation_id, provider_id, model,
+                   COALESCE(prompt_tokens, 0), COALESCE(completion_tokens, 0)
+            FROM messages
+            WHERE model IS NOT NULL;",
+    )
+}
+
+/// Origin of imported chats, so importing them again is noticed.
+fn import_source(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "conversations", "import_source", "TEXT")?;
+
+    transaction.execute_batch(
+        "CREATE INDEX IF NOT EXISTS conversations_import_source ON conversations (import_source);",
+    )
+}
+
+/// Vectors move from a table per conversation and model to one table per
+/// dimension. Chunks get new ids, the ones messages were answered with are renumbered.
+fn shared_vectors(transaction: &Transaction) -> rusqlite::Result<()> {
+    let embedded: Vec<(ConversationNodeID, usize, String)> = transaction
+        .prepare("SELECT DISTINCT conversation_id, dimensions, embedding_model FROM vectors_files")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_, _>>()?;
+
+    let mut chunk_ids: HashMap<(ConversationNodeID, usize, String), HashMap<ChunkRagId, ChunkRagId>> =
+        HashMap::new();
+    let mut legacy_tables = HashSet::new();
+
+    for (conversation_id, dimensions, embedding_model) in embedded {
+        let legacy_table = format!("vectors_conversation_{conversation_id}_{embedding_model}_{dimensions}")
+            .replace(['-', ':'], "_");
+
+        // Names of some models made invalid SQL, their tables were never created
+        let exists: bool = transaction.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = ?",
+            [&legacy_table],
+            |row| row.get(0),
+        )?;
+
+        if !exists {
+            continue;
+        }
+
+        // Models whose names differ only in `-` and `:` wrote to the same table,
+        // its rows are copied once with the model of their file
+        if !legacy_tables.insert(legacy_table.clone()) {
+            log::warn!("Vectors of {embedding_model} shared {legacy_table} with another model");
+            continue;
+        }
+
+        let table_name = format!("vectors_{dimensions}");
+        create_vectors_table(transaction, &table_name, "conversation_id", dimensions, "+chunk TEXT")?;
+
+        let mut select = transaction.prepare(&format!(
+            "SELECT vectors.id, vectors.embedding, vectors.file_id, vectors.chunk, files.embedding_model
+             FROM \"{legacy_table}\" AS vectors
+             LEFT JOIN vectors_files AS files ON files.id = vectors.file_id"
+        ))?;
+        let mut insert = transaction.prepare(&format!(
+            "INSERT INTO {table_name} (embedding, conversation_id, embedding_model, file_id, chunk)
+             VALUES (?, ?, ?, ?, ?)"
+        ))?;
+
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            let embedding: Vec<u8> = row.get(1)?;
+            let file_id: i64 = row.get(2)?;
+            let chunk: String = row.get(3)?;
+            let model = row.get::<_, Option<String>>(4)?.unwrap_or_else(|| embedding_model.clone());
+
+            insert.execute(params![embedding, conversation_id, model, file_id, chunk])?;
+            chunk_ids
+                .entry((conversation_id, dimensions, model))
+                .or_default()
+                .insert(row.get(0)?, transaction.last_insert_rowid());
+        }
+    }
+
+    for legacy_table in legacy_tables {
+        transaction.execute(&format!("DROP TABLE \"{legacy_table}\""), [])?;
+    }
+
+    let messages: Vec<(i64, ConversationNodeID, String)> = transaction
+        .prepare("SELECT id, conversation_id, chunks FROM messages WHERE chunks LIKE '[{%'")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_, _>>()?;
+
+    for (message_id, conversation_id, chunks) in messages {
+        let Ok(chunks) = serde_json::from_str::<Vec<MessageUsedRagChunk>>(&chunks) else {
+            continue;
+        };
+
+        // Chunks of files deleted before are dropped, they were not shown anyway
+        let chunks: Vec<MessageUsedRagChunk> = chunks
+            .into_iter()
+            .filter_map(|mut chunk| {
+                let key = (conversation_id, chunk.dimension, chunk.embedding_model.clone());
+                chunk.chunk_id = *chunk_ids.get(&key)?.get(&chunk.chunk_id)?;
+                Some(chunk)
+            })
+            .collect();
+
+        transaction.execute(
+            "UPDATE messages SET chunks = ? WHERE id = ?",
+            params![serde_json::to_string(&chunks).unwrap_or_default(), message_id],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// vec0 table of chunks as a migration made it, `auxiliary` are its `+column`s.
+/// The DDL is spelled out here, later changes to the vector tables must not
+/// change what an old migration does.
+fn create_vectors_table(
+    transaction: &Transaction,
+    table_name: &str,
+    owner: &str,
+    dimensions: usize,
+    auxiliary: &str,
+) -> rusqlite::Result<()> {
+    transaction.execute_batch(&format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS {table_name} USING vec0(
+            id INTEGER PRIMARY KEY,
+            embedding float[{dimensions}],
+            {owner} INTEGER PARTITION KEY,
+            embedding_model TEXT PARTITION KEY,
+            file_id INTEGER,
+            {auxiliary}
+        );"
+    ))
+}
+
+/// Files shared by many chats, linked to chats and folders. Their vectors are
+/// kept in `knowledge_vectors_{dimensions}` tables made on first use.
+fn knowledge_bases(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE IF NOT EXISTS knowledge_bases (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            embedding_provider INTEGER,
+            embedding_model TEXT,
+            chunk_size INTEGER NOT NULL DEFAULT 512
+        );
+
+        CREATE TABLE IF NOT EXISTS knowledge_base_links (
+            knowledge_base_id INTEGER NOT NULL,
+            node_id INTEGER NOT NULL,
+            PRIMARY KEY (knowledge_base_id, node_id)
+        );
+
+        CREATE INDEX IF NOT EXISTS knowledge_base_links_node_id ON knowledge_base_links (node_id);
+
+        CREATE TABLE IF NOT EXISTS knowledge_base_files (
+            id INTEGER PRIMARY KEY,
+            knowledge_base_id INTEGER NOT NULL,
+            file_hash TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            dimensions INTEGER NOT NULL,
+            embedding_model TEXT NOT NULL,
+            UNIQUE(knowledge_base_id, file_hash, dimensions, embedding_model)
+        );",
+    )
+}
+
+/// Vector tables get the page a chunk is on.
+fn chunk_pages(transaction: &Transaction) -> rusqlite::Result<()> {
+    rebuild_vectors_tables(transaction, "chunk", "+chunk TEXT, +page INTEGER")
+}
+
+/// Vector tables get the lines of a source file a chunk spans.
+fn chunk_lines(transaction: &Transaction) -> rusqlite::Result<()> {
+    rebuild_vectors_tables(
+        transaction,
+        "chunk, page",
+        "+chunk TEXT, +page INTEGER, +start_line INTEGER, +end_line INTEGER",
+    )
+}
+
+/// vec0 cannot add columns, so every vector table is created again with the
+/// `auxiliary` columns and filled with the ids its chunks had. `kept_columns`
+/// are the auxiliary columns the tables had before.
+fn rebuild_vectors_tables(
+    transaction: &Transaction,
+    kept_columns: &str,
+    auxiliary: &str,
+) -> rusqlite::Result<()> {
+    for VectorsTable { name: table_name, owner, dimensions, .. } in vectors_tables(transaction)? {
+        let columns = format!("id, embedding, {owner}, embedding_model, file_id, {kept_columns}");
+
+        transaction.execute_batch(&format!(
+            "CREATE TEMP TABLE rebuilt_vectors AS SELECT {columns} FROM {table_name};
+             DROP TABLE {table_name};"
+        ))?;
+
+        create_vectors_table(transaction, &table_name, owner, dimensions, auxiliary)?;
+
+        transaction.execute_batch(&format!(
+            "INSERT INTO {table_name} ({columns}) SELECT {columns} FROM temp.rebuilt_vectors;
+             DROP TABLE temp.rebuilt_vectors;"
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Chunks are also found by their words, the ranking of both searches is fused.
+fn hybrid_search(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "conversations", "rag_vector_weight", "REAL NOT NULL DEFAULT 1.0")?;
+    add_column(transaction, "conversations", "rag_keyword_weight", "REAL NOT NULL DEFAULT 1.0")?;
+    add_column(
+        transaction,
+        "conversations",
+        "rag_distance_cutoff",
+        &format!("REAL NOT NULL DEFAULT {DEFAULT_RAG_DISTANCE_CUTOFF}"),
+    )?;
+
+    for (keywords, owner) in [
+        ("vectors_fts", "conversation_id"),
+        ("knowledge_vectors_fts", "knowledge_base_id"),
+    ] {
+        transaction.execute_batch(&format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS {keywords} USING fts5(
+                chunk,
+                {owner} UNINDEXED,
+                embedding_model UNINDEXED,
+                dimensions UNINDEXED,
+                file_id UNINDEXED,
+                chunk_id UNINDEXED,
+                tokenize = 'unicode61 remove_diacritics 2'
+            );
+
+            DELETE FROM {keywords};"
+        ))?;
+    }
+
+    for VectorsTable { name, source, owner, dimensions } in vectors_tables(transaction)? {
+        let keywords = match source {
+            RagSource::Conversation(_) => "vectors_fts",
+            RagSource::KnowledgeBase(_) => "knowledge_vectors_fts",
+        };
+
+        transaction.execute(
+            &format!(
+                "INSERT INTO {keywords} (chunk, {owner}, embedding_model, dimensions, file_id, chunk_id)
+                 SELECT chunk, {owner}, embedding_model, ?, file_id, id FROM {name}"
+            ),
+            params![dimensions],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Retrieved chunks can be reordered by a rerank model.
+fn rerank(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "conversations", "rag_rerank_candidates", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(transaction, "conversations", "rerank_provider", "INTEGER")?;
+    add_column(transaction, "conversations", "rerank_model", "TEXT")
+}
+
+/// Messages can be rewritten into standalone queries, the searched ones are kept.
+fn query_rewriting(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "messages", "rag_queries", "TEXT NOT NULL DEFAULT '[]'")?;
+    add_column(transaction, "conversations", "rag_query_rewrite", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(transaction, "conversations", "rag_sub_queries", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(transaction, "conversations", "rag_hyde", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(transaction, "conversations", "rewrite_provider", "INTEGER")?;
+    add_column(transaction, "conversations", "rewrite_model", "TEXT")
+}
+
+/// Chunks know their byte range in the text of their file, which is kept.
+fn chunk_spans(transaction: &Transaction) -> rusqlite::Result<()> {
+    add_column(transaction, "vectors_files", "content", "TEXT")?;
+    add_column(transaction, "knowledge_base_files", "content", "TEXT")?;
+
+    rebuild_vectors_tables(
+        transaction,
+        "chunk, page, start_line, end_line",
+        "+chunk TEXT, +page INTEGER, +start_line INTEGER, +end_line INTEGER, \
+         +start_byte INTEGER, +end_byte INTEGER",
+    )
+}
+
+/// Files are known by their path, a changed file replaces its old version
+/// instead of being added next to it. Files loaded before keep no path.
+/// Files and folders can be watched to be indexed again when they change.
+fn watched_paths(transaction: &Transaction) -> rusqlite::Result<()> {
+    for (files, owner) in [
+        ("vectors_files", "conversation_id"),
+        ("knowledge_base_files", "knowledge_base_id"),
+    ] {
+        let columns = format!("id, {owner}, file_hash, file_name, dimensions, embedding_model, content");
+
+        transaction.execute_batch(&format!(
+            "CREATE TABLE rebuilt_files (
+                id INTEGER PRIMARY KEY,
+                {owner} INTEGER NOT NULL,
+                file_path TEXT,
+                file_hash TEXT NOT NULL,
+                file_name TEXT NOT NULL,
+                dimensions INTEGER NOT NULL,
+                embedding_model TEXT NOT NULL,
+                content TEXT,
+                UNIQUE({owner}, file_path, dimensions, embedding_model)
+            );
+            INSERT INTO rebuilt_files ({columns}) SELECT {columns} FROM {files};
+            DROP TABLE {files};
+            ALTER TABLE rebuilt_files RENAME TO {files};
+
+            CREATE TABLE IF NOT EXISTS {watched} (
+                id INTEGER PRIMARY KEY,
+                {owner} INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                include TEXT NOT NULL DEFAULT '[]',
+                exclude TEXT NOT NULL DEFAULT '[]',
+                UNIQUE({owner}, path)
+            );",
+            watched = files.replace("files", "watched_paths")
+        ))?;
+    }
+
+    Ok(())
+}
+
+struct VectorsTable {
+    name: String,
+    /// Only the kind of the source matters for the table, not its id.
+    source: RagSource,
+    owner: &'static str,
+    dimensions: usize,
+}
+
+/// The vec0 tables of chunks, one for every source kind and vector size.
+fn vectors_tables(transaction: &Transaction) -> rusqlite::Result<Vec<VectorsTable>> {
+    let table_names: Vec<String> = transaction
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE sql LIKE 'CREATE VIRTUAL TABLE%'
+             AND (name GLOB 'vectors_[0-9]*' OR name GLOB 'knowledge_vectors_[0-9]*')",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+
+    Ok(table_names
+        .into_iter()
+        .filter_map(|name| {
+            let dimensions = name.rsplit('_').next()?.parse::<usize>().ok()?;
+
+            let (source, owner) = if name.starts_with("knowledge_vectors_") {
+                (RagSource::KnowledgeBase(0), "knowledge_base_id")
+            } else {
+                (RagSource::Conversation(0), "conversation_id")
+            };
+
+            Some(VectorsTable {
+                name,
+                source,
+                owner,
+                dimensions,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{Connection, ffi::sqlite3_auto_extension};
+    use sqlite_vec::sqlite3_vec_init;
+    use types::dto::MessageUsedRagChunk;
+
+    use super::{SCHEMA_VERSION, migrate, schema_version};
+
+    /// Tables as the application made them before versioning, with a file of
+    /// conversation 1 embedded by `nomic-embed:latest` and an answer that used two
+    /// of its chunks and one of a file deleted since.
+    const BASELINE_SCHEMA: &str = "
+        CREATE TABLE providers (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            url TEXT NOT NULL,
+            api_key TEXT NOT NULL
+        );
+        CREATE TABLE storage (
+            id INTEGER PRIMARY KEY,
+            key TEXT NOT NULL UNIQUE,
+            value TEXT NOT NULL
+        );
+        CREATE TABLE conversations (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            parent_id INTEGER,
+            type INTEGER NOT NULL,
+            ordr INTEGER DEFAULT 0,
+            preset_id INTEGER,
+            max_messages INTEGER NOT NULL,
+            embedding_provider INTEGER,
+            embedding_model TEXT,
+            rag_chunk_size INTEGER NOT NULL,
+            rag_chunks_count INTEGER NOT NULL,
+            summary_enabled INTEGER DEFAULT 0,
+            summary_model TEXT,
+            summary_provider INTEGER,
+            provider INTEGER,
+            model TEXT,
+            prompt TEXT
+        );
+        CREATE TABLE messages (
+            id INTEGER PRIMARY KEY,
+            conversation_id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            reasoning TEXT,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+            role INTEGER NOT NULL,
+            summary TEXT,
+            chunks TEXT
+        );
+        CREATE TABLE vectors_files (
+            id INTEGER PRIMARY KEY,
+            conversation_id INTEGER NOT NULL,
+            file_hash TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            dimensions INTEGER NOT NULL,
+            embedding_model TEXT NOT NULL,
+            UNIQUE(conversation_id, file_hash, dimensions, embedding_model)
+        );
+        CREATE VIRTUAL TABLE vectors_conversation_1_nomic_embed_latest_4 USING vec0(
+            id INTEGER PRIMARY KEY,
+            embedding float[4],
+            file_id INTEGER NOT NULL,
+            chunk TEXT NOT NULL
+        );
+
+        INSERT INTO providers VALUES (1, 'Ollama Native', 'http://127.0.0.1:11434/v1', '0');
+        INSERT INTO conversations (id, name, type, max_messages, rag_chunk_size, rag_chunks_count)
+            VALUES (1, 'Chat', 0, 10, 512, 4);
+        INSERT INTO vectors_files VALUES (1, 1, 'hash', 'notes.md', 4, 'nomic-embed:latest');
+        INSERT INTO vectors_conversation_1_nomic_embed_latest_4 VALUES
+            (10, '[1, 0, 0, 0]', 1, 'first chunk'),
+            (11, '[0, 1, 0, 0]', 1, 'second chunk');
+        INSERT INTO messages (id, conversation_id, content, role, chunks) VALUES
+            (1, 1, 'What is in my notes?', 0, NULL),
+            (2, 1, 'Two chunks about apples', 1, '[{\"chunk_id\":11,\"dimension\":4,\"embedding_model\":\"nomic-embed:latest\"},\
+                {\"chunk_id\":10,\"dimension\":4,\"embedding_model\":\"nomic-embed:latest\"},\
+                {\"chunk_id\":7,\"dimension\":4,\"embedding_model\":\"nomic-embed:latest\"}]');
+    ";
+
+    fn baseline_connection() -> Connection {
+        unsafe {
+            #[allow(clippy::missing_transmute_annotations)]
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_vec_init as *const ())));
+        }
+
+        let connection = Connection::open_in_memory().expect("Failed to open database");
+        connection
+            .execute_batch(BASELINE_SCHEMA)
+            .expect("Failed to create the baseline schema");
+        connection
+    }
+
+    fn columns(connection: &Connection, table: &str) -> Vec<String> {
+        connection
+            .prepare("SELECT name FROM pragma_table_info(?)")
+            .and_then(|mut stmt| stmt.query_map([table], |row| row.get(0))?.collect())
+            .expect("Failed to read the columns")
+    }
+
+    fn schema(connection: &Connection) -> Vec<(String, Option<String>)> {
+        connection
+            .prepare("SELECT name, sql FROM sqlite_master ORDER BY name")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect()
+            })
+            .expect("Failed to read the schema")
+    }
+
+    #[test]
+    fn upgrades_the_baseline_schema() {
+        let mut connection = baseline_connection();
+
+        migrate(&mut connection).expect("Failed to migrate");
+
+        assert_eq!(schema_version(&connection).unwrap(), SCHEMA_VERSION);
+
+        let providers = columns(&connection, "providers");
+        for column in ["default_model", "kind", "reasoning_tags"] {
+            assert!(providers.iter().any(|name| name == column), "providers.{column} is missing");
+        }
+
+        let messages = columns(&connection, "messages");
+        for column in ["interrupted", "tool_calls", "tool_call_id"] {
+            assert!(messages.iter().any(|name| name == column), "messages.{column} is missing");
+        }
+
+        assert!(!columns(&connection, "attachments").is_empty());
+
+        let content: String = connection
+            .query_row("SELECT content FROM messages WHERE id = 2", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(content, "Two chunks about apples");
+
+        let legacy_tables: i64 = connection
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name LIKE 'vectors_conversation_%'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(legacy_tables, 0);
+
+        let chunk_id = |text: &str| -> i64 {
+            connection
+                .query_row(
+                    "SELECT id FROM vectors_4
+                     WHERE conversation_id = 1 AND embedding_model = 'nomic-embed:latest' AND chunk = ?",
+                    [text],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        let first = chunk_id("first chunk");
+        let second = chunk_id("second chunk");
+
+        let chunks: String = connection
+            .query_row("SELECT chunks FROM messages WHERE id = 2", [], |row| row.get(0))
+            .unwrap();
+        let chunks: Vec<MessageUsedRagChunk> = serde_json::from_str(&chunks).unwrap();
+        let chunk_ids: Vec<i64> = chunks.iter().map(|chunk| chunk.chunk_id).collect();
+        assert_eq!(chunk_ids, vec![second, first]);
+
+        let keyword_ids: Vec<i64> = connection
+            .prepare(
+                "SELECT chunk_id FROM vectors_fts
+                 WHERE vectors_fts MATCH 'chunk' AND conversation_id = 1 AND dimensions = 4
+                 ORDER BY chunk_id",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(keyword_ids, vec![first.min(second), first.max(second)]);
+
+        let found: i64 = connection
+            .query_row(
+                "SELECT rowid FROM messages_fts WHERE messages_fts MATCH 'apples'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(found, 2);
+    }
+
+    #[test]
+    fn keeps_the_model_of_vectors_in_a_shared_legacy_table() {
+        let mut connection = baseline_connection();
+        connection
+            .execute_batch(
+                "INSERT INTO vectors_files VALUES (2, 1, 'other', 'other.md', 4, 'nomic-embed-latest');
+                 INSERT INTO vectors_conversation_1_nomic_embed_latest_4 VALUES
+                     (12, '[0, 0, 1, 0]', 2, 'other chunk');",
+            )
+            .expect("Failed to add the second model");
+
+        migrate(&mut connection).expect("Failed to migrate");
+
+        let models: Vec<(String, String)> = connection
+            .prepare("SELECT chunk, embedding_model FROM vectors_4 ORDER BY chunk")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            models,
+            vec![
+                ("first chunk".to_string(), "nomic-embed:latest".to_string()),
+                ("other chunk".to_string(), "nomic-embed-latest".to_string()),
+                ("second chunk".to_string(), "nomic-embed:latest".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn refuses_a_newer_schema_without_changing_it() {
+        let mut connection = baseline_connection();
+        connection
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .expect("Failed to set the schema version");
+        let schema_before = schema(&connection);
+
+        let error = migrate(&mut connection).expect_err("Migrated a newer schema");
+
+        assert!(error.contains("newer"), "{error}");
+        assert_eq!(schema(&connection), schema_before);
+        assert_eq!(schema_version(&connection).unwrap(), SCHEMA_VERSION + 1);
+    }
+
+    #[test]
+    fn migrating_again_changes_nothing() {
+        let mut connection = baseline_connection();
+        migrate(&mut connection).expect("Failed to migrate");
+
+        let schema_before = schema(&connection);
+        let chunks_before: String = connection
+            .query_row("SELECT chunks FROM messages WHERE id = 2", [], |row| row.get(0))
+            .unwrap();
+
+        migrate(&mut connection).expect("Failed to migrate again");
+
+        let chunks_after: String = connection
+            .query_row("SELECT chunks FROM messages WHERE id = 2", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(schema(&connection), schema_before);
+        assert_eq!(chunks_after, chunks_before);
+        assert_eq!(schema_version(&connection).unwrap(), SCHEMA_VERSION);
+    }
+}
@@ -3,16 +3,25 @@ use std::sync::{Arc, Mutex};
 use rusqlite::ffi::sqlite3_auto_extension;
 use sqlite_vec::sqlite3_vec_init;
 
+use crate::migrations;
+
 pub type DatabaseConnection = Arc<Mutex<rusqlite::Connection>>;
 
-/// # Panics
-#[must_use] pub fn create_database_connection(db_path: &str) -> DatabaseConnection {
+/// Opens the database and brings its schema up to date.
+///
+/// # Errors
+/// When the file can't be opened or the schema can't be migrated.
+pub fn create_database_connection(db_path: &str) -> Result<DatabaseConnection, String> {
     unsafe {
         #[allow(clippy::missing_transmute_annotations)]
         sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_vec_init as *const ())));
     }
 
-    let connection = rusqlite::Connection::open(db_path).expect("Failed to open database");
+    let mut connection = rusqlite::Connection::open(db_path)
+        .map_err(|e| format!("Failed to open database {db_path}: {e}"))?;
+
+    migrations::migrate(&mut connection)
+        .map_err(|e| format!("Failed to migrate database: {e}"))?;
 
-    Arc::new(Mutex::new(connection))
+    Ok(Arc::new(Mutex::new(connection)))
 }
@@ -1,9 +1,13 @@
-use api::open_ai_api::OpenAiApi;
+use api::lm_backend::LmApi;
 use database::{
     DatabaseConnection,
     databases::{ProvidersDatabase, StorageDatabase},
 };
-use types::dto::{ProviderDTO, ProviderID};
+use types::{
+    common::NotificationAction,
+    dto::{ProviderDTO, ProviderID},
+    error::{AppError, AppResult},
+};
 use utils::event_system::{Event, EventSystem};
 
 #[derive(Debug, Clone)]
@@ -11,14 +15,14 @@ pub struct AppSettings {
     #[allow(dead_code)]
     storage: StorageDatabase,
     providers_db: ProvidersDatabase,
-    lm_api: OpenAiApi,
+    lm_api: LmApi,
     event_system: EventSystem,
 }
 
 impl AppSettings {
     pub fn new(
         connection: DatabaseConnection,
-        lm_api: OpenAiApi,
+        lm_api: LmApi,
         event_system: EventSystem,
     ) -> Self {
         Self {
@@ -35,10 +39,10 @@ impl AppSettings {
 
     /// # Errors
     /// # Panics
-    pub fn delete_provider(&mut self, id: ProviderID) -> Result<(), String> {
+    pub fn delete_provider(&mut self, id: ProviderID) -> AppResult<()> {
         self.providers_db
             .delete_provider(id)
-            .map_err(|e| e.to_string())?;
+            .map_err(AppError::database)?;
 
         self.event_system
             .dispatch(Event::ProvidersUpdate(
@@ -57,11 +61,18 @@ impl AppSettings {
     pub fn add_provider(
         &mut self,
         dto: &ProviderDTO,
-    ) -> Result<ProviderID, String> {
+    ) -> AppResult<ProviderID> {
         let provider_id = self
             .providers_db
-            .add_provider(&dto.name, &dto.url, &dto.api_key, &dto.default_model)
-            .map_err(|e| e.to_string())?;
+            .add_provider(
+                &dto.name,
+                &dto.url,
+                &dto.api_key,
+                &dto.default_model,
+                dto.kind,
+                &dto.reasoning_tags,
+            )
+            .map_err(AppError::database)?;
 
         self.event_system
             .dispatch(Event::ProvidersUpdate(
@@ -73,10 +84,10 @@ impl AppSettings {
 
     /// # Errors
     /// # Panics
-    pub fn update_provider(&mut self, dto: &ProviderDTO) -> Result<(), String> {
+    pub fn update_provider(&mut self, dto: &ProviderDTO) -> AppResult<()> {
         self.providers_db
             .update_provider(dto)
-            .map_err(|e| e.to_string())?;
+            .map_err(AppError::database)?;
 
         self.event_system
             .dispatch(Event::ProvidersUpdate(
@@ -88,9 +99,16 @@ impl AppSettings {
 
     /// # Errors
     /// # Panics
-    pub async fn get_models(&self, provider: &ProviderDTO) -> Result<Vec<String>, String> {
+    pub async fn get_models(&self, provider: &ProviderDTO) -> AppResult<Vec<String>> {
         self.lm_api
             .get_models(provider.id)
             .await
+            .inspect_err(|e| {
+                self.event_system.clone().notify_error(
+                    format!("Failed to load models of {}", provider.name),
+                    e,
+                    Some(NotificationAction::OpenProviders),
+                );
+            })
     }
 }
@@ -0,0 +1,251 @@
+use std::sync::LazyLock;
+
+use tiktoken_rs::{
+    CoreBPE, cl100k_base,
+    model::get_context_size,
+    o200k_base,
+    tokenizer::{Tokenizer, get_tokenizer},
+};
+use types::{
+    common::ContextUsage,
+    dto::{MessageDTO, RoleType},
+};
+
+/// Role markers and separators the providers add around every message.
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Rough price of an image for vision models, the real one depends on its size.
+const IMAGE_TOKENS: usize = 1_000;
+
+static CL100K_BASE: LazyLock<CoreBPE> =
+    LazyLock::new(|| cl100k_base().expect("Failed to get tokenizer"));
+static O200K_BASE: LazyLock<CoreBPE> =
+    LazyLock::new(|| o200k_base().expect("Failed to get tokenizer"));
+
+/// Counts what goes into a request against the context window of the model,
+/// the tokens reserved for the answer are left out from the start.
+pub struct ContextBuilder {
+    bpe: &'static CoreBPE,
+    /// `None` takes everything, the context window of the model is not known.
+    limit: Option<usize>,
+    used: usize,
+}
+
+impl ContextBuilder {
+    /// `context_size` of 0 takes the known size of the model, nothing is
+    /// left out for a model of unknown size.
+    #[must_use]
+    pub fn new(model_name: &str, context_size: usize, answer_tokens: usize) -> Self {
+        let bpe: &'static CoreBPE = match get_tokenizer(model_name) {
+            Some(Tokenizer::Cl100kBase) => &CL100K_BASE,
+            // Other vendors do not publish their tokenizers, this one is close enough
+            _ => &O200K_BASE,
+        };
+
+        let context_size = if context_size == 0 {
+            model_context_size(model_name)
+        } else {
+            Some(context_size)
+        };
+
+        Self {
+            bpe,
+            limit: context_size.map(|context_size| context_size.saturating_sub(answer_tokens)),
+            used: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_limited(&self) -> bool {
+        self.limit.is_some()
+    }
+
+    fn fits(&self, tokens: usize) -> bool {
+        self.limit.is_none_or(|limit| self.used + tokens <= limit)
+    }
+
+    #[must_use]
+    pub fn count(&self, text: &str) -> usize {
+        if text.is_empty() {
+            return 0;
+        }
+
+        self.bpe.encode_ordinary(text).len()
+    }
+
+    #[must_use]
+    pub fn message_tokens(&self, message: &MessageDTO) -> usize {
+        let tool_calls: usize = message
+            .tool_calls
+            .iter()
+            .map(|tool_call| self.count(&tool_call.name) + self.count(&tool_call.arguments))
+            .sum();
+
+        MESSAGE_OVERHEAD_TOKENS
+            + self.count(&message.content)
+            + self.count(message.reasoning.as_deref().unwrap_or_default())
+            + tool_calls
+            + message.attachments.len() * IMAGE_TOKENS
+    }
+
+    /// Takes a system message even if it does not fit, for what the request
+    /// cannot go without.
+    pub fn reserve(&mut self, text: &str) {
+        if !text.is_empty() {
+            self.used += MESSAGE_OVERHEAD_TOKENS + self.count(text);
+        }
+    }
+
+    pub fn reserve_message(&mut self, message: &MessageDTO) {
+        self.used += self.message_tokens(message);
+    }
+
+    /// Takes the message only if it fits.
+    pub fn try_reserve_message(&mut self, message: &MessageDTO) -> bool {
+        let tokens = self.message_tokens(message);
+
+        if !self.fits(tokens) {
+            return false;
+        }
+
+        self.used += tokens;
+        true
+    }
+
+    /// Takes a piece of a message only if it fits.
+    pub fn try_reserve(&mut self, text: &str) -> bool {
+        let tokens = self.count(text);
+
+        if !self.fits(tokens) {
+            return false;
+        }
+
+        self.used += tokens;
+        true
+    }
+
+    /// Newest messages of `history` (oldest first) that fit, the oldest
+    /// turns are dropped first.
+    pub fn fit_history(&mut self, history: Vec<MessageDTO>) -> Vec<MessageDTO> {
+        let mut fitting = vec![];
+
+        for message in history.into_iter().rev() {
+            if !self.try_reserve_message(&message) {
+                break;
+            }
+
+            fitting.push(message);
+        }
+
+        fitting.reverse();
+
+        // Tool results cut off from the call that asked for them are rejected by providers
+        while fitting
+            .first()
+            .is_some_and(|message| message.role == RoleType::Tool)
+        {
+            let orphaned = fitting.remove(0);
+            self.used -= self.message_tokens(&orphaned);
+        }
+
+        fitting
+    }
+
+    #[must_use]
+    pub const fn usage(&self, messages: usize) -> ContextUsage {
+        ContextUsage {
+            messages,
+            tokens: self.used,
+            limit: self.limit,
+        }
+    }
+}
+
+/// `None` for models of unknown size. tiktoken only knows the `OpenAI` models
+/// and takes 4096 tokens for the rest, none of the models it knows has that size.
+fn model_context_size(model_name: &str) -> Option<usize> {
+    let model_name = model_name.to_lowercase();
+
+    if model_name.contains("claude") {
+        Some(200_000)
+    } else if model_name.contains("gemini") {
+        Some(1_048_576)
+    } else {
+        match get_context_size(&model_name) {
+            4096 => None,
+            context_size => Some(context_size),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::dto::{MessageDTO, RoleType};
+
+    use super::{ContextBuilder, MESSAGE_OVERHEAD_TOKENS};
+
+    fn message(role: RoleType, content: &str) -> MessageDTO {
+        MessageDTO {
+            role,
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn contents(messages: &[MessageDTO]) -> Vec<&str> {
+        messages.iter().map(|message| message.content.as_str()).collect()
+    }
+
+    #[test]
+    fn drops_the_oldest_messages_first() {
+        let history = vec![
+            message(RoleType::User, "one"),
+            message(RoleType::Assistant, "two"),
+            message(RoleType::User, "three"),
+            message(RoleType::Assistant, "four"),
+        ];
+        let message_tokens = ContextBuilder::new("gpt-4o", 0, 0).message_tokens(&history[0]);
+        assert_eq!(message_tokens, MESSAGE_OVERHEAD_TOKENS + 1);
+
+        let mut context = ContextBuilder::new("gpt-4o", 3 * message_tokens + 10, 10);
+        let fitting = context.fit_history(history);
+
+        assert_eq!(contents(&fitting), ["two", "three", "four"]);
+        assert_eq!(context.usage(fitting.len()).tokens, 3 * message_tokens);
+    }
+
+    #[test]
+    fn drops_tool_results_cut_off_from_their_call() {
+        let history = vec![
+            message(RoleType::User, "one"),
+            message(RoleType::Assistant, "two"),
+            message(RoleType::Tool, "three"),
+            message(RoleType::Tool, "four"),
+            message(RoleType::Assistant, "five"),
+        ];
+        let message_tokens = MESSAGE_OVERHEAD_TOKENS + 1;
+
+        let mut context = ContextBuilder::new("gpt-4o", 3 * message_tokens, 0);
+        let fitting = context.fit_history(history);
+
+        assert_eq!(contents(&fitting), ["five"]);
+        assert_eq!(context.usage(fitting.len()).tokens, message_tokens);
+    }
+
+    #[test]
+    fn takes_everything_for_a_model_of_unknown_size() {
+        let history: Vec<MessageDTO> = (0..1_000)
+            .map(|_| message(RoleType::User, &"word ".repeat(100)))
+            .collect();
+
+        let mut context = ContextBuilder::new("llama3.2", 0, 1_000);
+        assert!(!context.is_limited());
+        assert_eq!(context.fit_history(history).len(), 1_000);
+        assert_eq!(context.usage(1_000).limit, None);
+
+        let context = ContextBuilder::new("llama3.2", 8_192, 1_000);
+        assert_eq!(context.usage(0).limit, Some(7_192));
+
+        let context = ContextBuilder::new("gpt-4o", 0, 1_000);
+        assert_eq!(context.usage(0).limit, Some(127_000));
+    }
+}
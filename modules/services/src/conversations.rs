@@ -1,17 +1,26 @@
 use std::{
+    collections::HashMap,
     fmt::Debug,
+    path::{Path, PathBuf},
     sync::{Arc, RwLock},
 };
 
 use database::{
-    databases::{ConversationDatabase, MessagesDatabase, PresetsDatabase, ProvidersDatabase}, DatabaseConnection
+    databases::{ConversationDatabase, ImportDatabase, MessagesDatabase, PresetsDatabase, ProvidersDatabase}, DatabaseConnection
 };
 use types::dto::{
-    ConversationNodeDTO, ConversationNodeID, MessageDTO, MessageID, PresetDTO, PresetId, ProviderDTO, ProviderID, RoleType
+    ConversationNodeDTO, ConversationNodeID, ExportFormat, ImportReportDTO, MessageDTO, MessageID, MessageSearchFilters, MessageSearchResultDTO, PresetDTO, PresetId, ProviderDTO, ProviderID, RagSource, RoleType
 };
+use types::error::{AppError, AppResult};
 use utils::event_system::{Event, EventSystem};
 
-use crate::VectorService;
+use crate::{
+    VectorService,
+    export::{self, ChatExport, Citation},
+    import,
+};
+
+const SEARCH_RESULTS_LIMIT: usize = 50;
 
 struct SharedState {
     pub conversation_db: ConversationDatabase,
@@ -19,6 +28,7 @@ struct SharedState {
     pub preset_db: PresetsDatabase,
     pub vector_service: VectorService,
     pub provider_db: ProvidersDatabase,
+    pub import_db: ImportDatabase,
 }
 
 #[derive(Clone)]
@@ -46,6 +56,7 @@ impl ConversationsService {
                 messages_db: MessagesDatabase::new(connection.clone()),
                 preset_db: PresetsDatabase::new(connection.clone()),
                 provider_db: ProvidersDatabase::new(connection.clone()),
+                import_db: ImportDatabase::new(connection.clone()),
                 vector_service,
             })),
         }
@@ -57,36 +68,36 @@ impl ConversationsService {
         &mut self,
         conversation_id: ConversationNodeID,
         new_dto: &ConversationNodeDTO,
-    ) -> Result<(), String> {
+    ) -> AppResult<()> {
         let state = self.state_write();
 
-        let result = state
+        state
             .conversation_db
             .update(conversation_id, new_dto)
-            .map_err(|e| format!("Failed to rename conversation: {e}"));
+            .map_err(AppError::database)?;
 
         let dto = state
             .conversation_db
             .get_conversation(conversation_id)
-            .expect("Failed to get conversation after rename");
+            .map_err(AppError::database)?;
 
         self.event_system
             .clone()
             .dispatch(Event::ConversationUpdate(dto));
 
-        result
+        Ok(())
     }
 
     /// # Errors
-    pub fn delete_conversation(&self, conversation_id: ConversationNodeID) -> Result<(), String> {
+    pub fn delete_conversation(&self, conversation_id: ConversationNodeID) -> AppResult<()> {
         let state = self.state_read();
 
         self.delete_conversation_recursively(conversation_id, &state)
     }
 
-    fn delete_conversation_recursively(&self, conversation_id: ConversationNodeID, state: &SharedState) -> Result<(), String> {
+    fn delete_conversation_recursively(&self, conversation_id: ConversationNodeID, state: &SharedState) -> AppResult<()> {
         let all_children = state.conversation_db.get_all_children_recursively(conversation_id)
-            .map_err(|e| format!("Failed to get all children: {e}"))?;
+            .map_err(AppError::database)?;
 
         for child in all_children {
             self.delete_conversation(child.id)?;
@@ -95,14 +106,17 @@ impl ConversationsService {
         state
             .conversation_db
             .delete(conversation_id)
-            .map_err(|e| format!("Failed to delete conversation: {e}"))?;
+            .map_err(AppError::database)?;
 
         state
             .messages_db
             .delete_messages(conversation_id)
-            .map_err(|e| format!("Failed to delete messages: {e}"))?;
+            .map_err(AppError::database)?;
 
-        state.vector_service.delete_all_files(conversation_id)?;
+        state
+            .vector_service
+            .delete_all_files(RagSource::Conversation(conversation_id))?;
+        state.vector_service.detach_node(conversation_id)?;
 
         self.event_system
             .clone()
@@ -129,13 +143,13 @@ impl ConversationsService {
         moving: ConversationNodeID,
         new_parent: ConversationNodeID,
         new_index: usize,
-    ) -> Result<(), String> {
+    ) -> AppResult<()> {
         let state = self.state_write();
 
         state
             .conversation_db
             .move_conversation(moving, new_parent, new_index)
-            .map_err(|e| format!("Failed to move conversation: {e}"))
+            .map_err(AppError::database)
     }
 
     /// # Errors
@@ -145,7 +159,7 @@ impl ConversationsService {
         content: &str,
         reasoning: &str,
         role: &RoleType,
-    ) -> Result<(), String> {
+    ) -> AppResult<()> {
         let state = self.state_write();
 
         let result = state
@@ -157,15 +171,15 @@ impl ConversationsService {
                 role,
                 &[],
             )
-            .map_err(|e| format!("Failed to write message: {e}"));
+            .map_err(AppError::database);
 
         let inseted_message = state
             .messages_db
             .get_last_messages(conversation_id, 0, 1)
-            .map_err(|e| format!("Failed to get last messages: {e}"))?
+            .map_err(AppError::database)?
             .into_iter()
             .next()
-            .ok_or("Failed to get last message")?;
+            .ok_or_else(|| AppError::not_found("Inserted message"))?;
 
         self.event_system
             .clone()
@@ -174,42 +188,68 @@ impl ConversationsService {
         result
     }
 
+    /// Inserts a complete message and notifies the conversation about it.
+    ///
+    /// # Errors
+    pub fn write_message_dto(&self, message_dto: &MessageDTO) -> AppResult<MessageDTO> {
+        let state = self.state_write();
+
+        state
+            .messages_db
+            .insert_message_dto(message_dto)
+            .map_err(AppError::database)?;
+
+        let inseted_message = state
+            .messages_db
+            .get_last_messages(message_dto.conversation_id, 0, 1)
+            .map_err(AppError::database)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::not_found("Inserted message"))?;
+
+        self.event_system
+            .clone()
+            .dispatch(Event::ConversationReceiveMessage(inseted_message.clone()));
+
+        Ok(inseted_message)
+    }
+
     /// # Errors
     pub fn get_last_messages(
         &self,
         conversation_id: ConversationNodeID,
         known_id: MessageID,
         limit: usize,
-    ) -> Result<Vec<MessageDTO>, String> {
+    ) -> AppResult<Vec<MessageDTO>> {
         let state = self.state_read();
 
         state
             .messages_db
             .get_last_messages(conversation_id, known_id, limit)
-            .map_err(|e| format!("Failed to get last messages: {e}"))
+            .map_err(AppError::database)
     }
 
     /// # Errors
     pub fn get_conversation(
         &self,
         conversation_id: ConversationNodeID,
-    ) -> Result<ConversationNodeDTO, String> {
+    ) -> AppResult<ConversationNodeDTO> {
         let state = self.state_read();
 
         state
             .conversation_db
             .get_conversation(conversation_id)
-            .map_err(|e| format!("Failed to get conversation: {e}"))
+            .map_err(AppError::database)
     }
 
     /// # Errors
-    pub fn delete_message(&mut self, message_id: MessageID) -> Result<(), String> {
+    pub fn delete_message(&mut self, message_id: MessageID) -> AppResult<()> {
         let state = self.state_write();
 
         let result = state
             .messages_db
             .delete_message(message_id)
-            .map_err(|e| format!("Failed to delete message: {e}"));
+            .map_err(AppError::database);
 
         self.event_system
             .clone()
@@ -219,17 +259,17 @@ impl ConversationsService {
     }
 
     /// # Errors
-    pub fn insert_message_dto(&self, message_dto: &MessageDTO) -> Result<(), String> {
+    pub fn insert_message_dto(&self, message_dto: &MessageDTO) -> AppResult<()> {
         let state = self.state_read();
 
         state
             .messages_db
-            .insert_message_dto(message_dto.clone())
-            .map_err(|e| format!("Failed to upsert message DTO: {e}"))
+            .insert_message_dto(message_dto)
+            .map_err(AppError::database)
     }
 
     /// # Errors
-    pub fn update_message_dto(&self, message_dto: &MessageDTO) -> Result<(), String> {
+    pub fn update_message_dto(&self, message_dto: &MessageDTO) -> AppResult<()> {
         let state = self.state_read();
 
         self.event_system
@@ -240,20 +280,50 @@ impl ConversationsService {
 
         state
             .messages_db
-            .update_message_dto(message_dto.clone())
-            .map_err(|e| format!("Failed to upsert message DTO: {e}"))
+            .update_message_dto(message_dto)
+            .map_err(AppError::database)
+
+    }
+
+    /// Moves the conversation to the branch ending at `message_id`, see
+    /// [`MessagesDatabase::set_active_message`].
+    ///
+    /// # Errors
+    pub fn set_active_message(
+        &self,
+        conversation_id: ConversationNodeID,
+        message_id: Option<MessageID>,
+    ) -> AppResult<()> {
+        let state = self.state_write();
+
+        state
+            .messages_db
+            .set_active_message(conversation_id, message_id)
+            .map_err(AppError::database)
+    }
+
+    /// Makes the message visible in its conversation, returns whether
+    /// another branch is shown now.
+    ///
+    /// # Errors
+    pub fn activate_message(&self, message_id: MessageID) -> AppResult<bool> {
+        let state = self.state_write();
 
+        state
+            .messages_db
+            .activate_message(message_id)
+            .map_err(AppError::database)
     }
 
     /// # Panics
     /// # Errors
-    pub fn get_message(&self, message_id: MessageID) -> Result<MessageDTO, String> {
+    pub fn get_message(&self, message_id: MessageID) -> AppResult<MessageDTO> {
         let state = self.state_read();
 
         state
             .messages_db
             .get_message(message_id)
-            .map_err(|e| format!("Failed to get message: {e}"))
+            .map_err(AppError::database)
     }
 
     /// # Errors
@@ -261,13 +331,13 @@ impl ConversationsService {
     pub fn get_last_summary(
         &self,
         conversation_id: ConversationNodeID,
-    ) -> Result<Option<MessageDTO>, String> {
+    ) -> AppResult<Option<MessageDTO>> {
         let state = self.state_read();
 
         let messages = state
             .messages_db
             .get_last_messages(conversation_id, 0, 5)
-            .map_err(|e| format!("Failed to get last messages: {e}"))?;
+            .map_err(AppError::database)?;
 
         for message in messages.iter().rev() {
             if let Some(summary) = &message.summary {
@@ -310,13 +380,58 @@ impl ConversationsService {
     pub fn get_children(
         &self,
         conversation_id: ConversationNodeID,
-    ) -> Result<Vec<ConversationNodeDTO>, String> {
+    ) -> AppResult<Vec<ConversationNodeDTO>> {
         let state = self.state_read();
 
         state
             .conversation_db
             .get_children(conversation_id)
-            .map_err(|e| format!("Failed to get children: {e}"))
+            .map_err(AppError::database)
+    }
+
+    /// Every folder of the tree, at any depth.
+    ///
+    /// # Errors
+    pub fn get_folders(&self) -> AppResult<Vec<ConversationNodeDTO>> {
+        let state = self.state_read();
+
+        let conversations = state
+            .conversation_db
+            .get_all_children_recursively(ConversationNodeID::default())
+            .map_err(AppError::database)?;
+
+        Ok(conversations
+            .into_iter()
+            .filter(|conversation| !conversation.is_chat())
+            .collect())
+    }
+
+    /// Every chat and folder of the tree, at any depth.
+    ///
+    /// # Errors
+    pub fn get_all_nodes(&self) -> AppResult<Vec<ConversationNodeDTO>> {
+        let state = self.state_read();
+
+        state
+            .conversation_db
+            .get_all_children_recursively(ConversationNodeID::default())
+            .map_err(AppError::database)
+    }
+
+    /// Messages of all conversations matching `query`, best matches first.
+    ///
+    /// # Errors
+    pub fn search_messages(
+        &self,
+        query: &str,
+        filters: &MessageSearchFilters,
+    ) -> AppResult<Vec<MessageSearchResultDTO>> {
+        let state = self.state_read();
+
+        state
+            .messages_db
+            .search_messages(query, filters, SEARCH_RESULTS_LIMIT)
+            .map_err(AppError::database)
     }
 
     /// # Errors
@@ -324,13 +439,13 @@ impl ConversationsService {
         &self,
         name: &str,
         parent_id: ConversationNodeID,
-    ) -> Result<ConversationNodeDTO, String> {
+    ) -> AppResult<ConversationNodeDTO> {
         let state = self.state_read();
 
         state
             .conversation_db
             .add_folder(name, parent_id, 20)
-            .map_err(|e| format!("Failed to add folder: {e}"))
+            .map_err(AppError::database)
     }
 
     /// # Errors
@@ -338,13 +453,13 @@ impl ConversationsService {
         &self,
         name: &str,
         parent_id: ConversationNodeID,
-    ) -> Result<ConversationNodeDTO, String> {
+    ) -> AppResult<ConversationNodeDTO> {
         let state = self.state_read();
 
         state
             .conversation_db
             .add_chat(name, parent_id, 20)
-            .map_err(|e| format!("Failed to add chat: {e}"))
+            .map_err(AppError::database)
     }
 
     pub fn update_subscribe<TMessage>(
@@ -385,13 +500,142 @@ impl ConversationsService {
         &self,
         id: ConversationNodeID,
         preset_id: Option<PresetId>,
-    ) -> Result<(), String> {
+    ) -> AppResult<()> {
         let state = self.state_write();
 
         state
             .conversation_db
             .set_preset(id, preset_id)
-            .map_err(|e| format!("Failed to set preset: {e}"))
+            .map_err(AppError::database)
+    }
+
+    /// Writes the chat into `directory`, a folder becomes a directory tree
+    /// of its chats. Returns the path of the written file or directory.
+    ///
+    /// # Errors
+    pub fn export(
+        &self,
+        node_id: ConversationNodeID,
+        format: ExportFormat,
+        directory: &Path,
+    ) -> AppResult<PathBuf> {
+        let state = self.state_read();
+
+        let node = state
+            .conversation_db
+            .get_conversation(node_id)
+            .map_err(AppError::database)?;
+
+        let mut children: HashMap<ConversationNodeID, Vec<ConversationNodeDTO>> = HashMap::new();
+        for child in state
+            .conversation_db
+            .get_all_children_recursively(node_id)
+            .map_err(AppError::database)?
+        {
+            children.entry(child.parent_id).or_default().push(child);
+        }
+
+        Self::export_node(&state, node, &mut children, format, directory)
+    }
+
+    /// Imports JSON exports of this app and `ChatGPT` into the folder, every
+    /// directory of `paths` becomes a folder. Chats imported before are skipped.
+    ///
+    /// # Errors
+    /// # Panics
+    pub fn import(
+        &self,
+        parent_id: ConversationNodeID,
+        paths: &[PathBuf],
+    ) -> AppResult<ImportReportDTO> {
+        let mut skipped = vec![];
+        let nodes = import::read_paths(paths, &mut skipped);
+
+        let mut report = self
+            .state_read()
+            .import_db
+            .import(parent_id, &nodes)
+            .map_err(AppError::database)?;
+        report.skipped.extend(skipped);
+
+        Ok(report)
+    }
+
+    fn export_node(
+        state: &SharedState,
+        node: ConversationNodeDTO,
+        children: &mut HashMap<ConversationNodeID, Vec<ConversationNodeDTO>>,
+        format: ExportFormat,
+        directory: &Path,
+    ) -> AppResult<PathBuf> {
+        if node.is_chat() {
+            let path = export::unique_path(directory, &node.name, Some(format.extension()));
+            let content = Self::chat_export(state, node)?.render(format)?;
+            export::write_file(&path, &content)?;
+
+            return Ok(path);
+        }
+
+        let path = export::unique_path(directory, &node.name, None);
+        export::create_directory(&path)?;
+
+        for child in children.remove(&node.id).unwrap_or_default() {
+            Self::export_node(state, child, children, format, &path)?;
+        }
+
+        Ok(path)
+    }
+
+    fn chat_export(state: &SharedState, conversation: ConversationNodeDTO) -> AppResult<ChatExport> {
+        let messages = state
+            .messages_db
+            .get_all_messages(conversation.id)
+            .map_err(AppError::database)?;
+
+        let active_message_id = state
+            .messages_db
+            .get_active_message(conversation.id)
+            .map_err(AppError::database)?;
+
+        let mut citations = HashMap::new();
+        for message in messages.iter().filter(|message| !message.chunks.is_empty()) {
+            // Chunks of deleted files are left out, like in the chat
+            let message_citations: Vec<Citation> = message
+                .chunks
+                .iter()
+                .filter_map(|used| {
+                    let chunk = state
+                        .vector_service
+                        .get_chunk(
+                            used.source(conversation.id),
+                            used.chunk_id,
+                            used.dimension,
+                            &used.embedding_model,
+                        )
+                        .ok()??;
+                    let file = state
+                        .vector_service
+                        .get_file(used.source(conversation.id), chunk.file_id)
+                        .ok()??;
+
+                    Some(Citation {
+                        file_name: chunk.location(&file.file_name),
+                        chunk: chunk.chunk,
+                    })
+                })
+                .collect();
+
+            if !message_citations.is_empty() {
+                citations.insert(message.id, message_citations);
+            }
+        }
+
+        Ok(ChatExport {
+            conversation,
+            active_message_id,
+            messages,
+            citations,
+        })
     }
 
     fn state_write(&self) -> std::sync::RwLockWriteGuard<SharedState> {
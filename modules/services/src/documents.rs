@@ -0,0 +1,748 @@
+use std::{
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use types::{
+    dto::FileChunk,
+    error::{AppError, AppResult},
+};
+
+/// Joins pages and chapters in the text of a document.
+const SECTION_SEPARATOR: &str = "\n\n";
+/// Joins the rows of a table.
+const ROW_SEPARATOR: &str = "\n";
+
+/// How the text of a document is split into chunks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Markdown,
+    Text,
+    /// Split along the syntax tree, so functions and classes stay whole.
+    Code(CodeLanguage),
+    /// Every section is a row, rows are grouped into chunks with [`group_rows`].
+    Table {
+        /// Line repeated at the start of every chunk, empty for records without one.
+        header: String,
+    },
+}
+
+/// Languages with a grammar to split their source code by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeLanguage {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+    Java,
+    C,
+    Cpp,
+}
+
+impl CodeLanguage {
+    /// `extension` is lowercase, without the dot.
+    #[must_use]
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "rs" => Some(Self::Rust),
+            "py" | "pyi" => Some(Self::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "go" => Some(Self::Go),
+            "java" => Some(Self::Java),
+            "c" | "h" => Some(Self::C),
+            "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => Some(Self::Cpp),
+            _ => None,
+        }
+    }
+}
+
+/// Part of a document that is chunked apart from the rest, like a page of a PDF.
+#[derive(Debug, Clone)]
+pub struct DocumentSection {
+    pub text: String,
+    /// Counted from 1, `None` for documents without pages.
+    pub page: Option<usize>,
+    /// Line of the file the section starts on, `None` when the text was
+    /// extracted and its lines are not the lines of the file.
+    pub line: Option<usize>,
+}
+
+/// Text extracted from a file for RAG.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub format: DocumentFormat,
+    pub sections: Vec<DocumentSection>,
+    /// Hash of the file as it was read, a file loaded twice is recognized by it.
+    pub hash: String,
+}
+
+impl Document {
+    /// Length of the whole text, to report the chunking progress against.
+    #[must_use]
+    pub fn text_len(&self) -> usize {
+        self.sections.iter().map(|section| section.text.len()).sum()
+    }
+
+    /// The sections joined into the text the byte ranges of chunks point into.
+    #[must_use]
+    pub fn text(&self) -> String {
+        self.sections
+            .iter()
+            .map(|section| section.text.as_str())
+            .collect::<Vec<_>>()
+            .join(self.separator())
+    }
+
+    /// Where each section starts in [`Document::text`].
+    #[must_use]
+    pub fn section_offsets(&self) -> Vec<usize> {
+        let mut offset = 0;
+
+        self.sections
+            .iter()
+            .map(|section| {
+                let start = offset;
+                offset += section.text.len() + self.separator().len();
+                start
+            })
+            .collect()
+    }
+
+    /// Rows of a table are lines, other sections are paragraphs.
+    const fn separator(&self) -> &'static str {
+        match self.format {
+            DocumentFormat::Table { .. } => ROW_SEPARATOR,
+            _ => SECTION_SEPARATOR,
+        }
+    }
+}
+
+/// The hash [`read_document`] gives the file, to tell whether it changed
+/// without extracting its text.
+///
+/// # Errors
+pub fn read_hash(path: &Path) -> AppResult<String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| AppError::file(format!("{}: {e}", path.display())))?;
+
+    Ok(hash_bytes(&bytes))
+}
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    seahash::hash(bytes).to_string()
+}
+
+/// Reads a file and extracts its text, the type of the file is recognized by
+/// its content and then by its extension.
+///
+/// # Errors
+pub fn read_document(path: &Path) -> AppResult<Document> {
+    let bytes =
+        std::fs::read(path).map_err(|e| AppError::file(format!("{}: {e}", path.display())))?;
+
+    let hash = hash_bytes(&bytes);
+
+    // Some writers put bytes before the header, readers look for it in the first kilobyte
+    let is_pdf = bytes[..bytes.len().min(1024)]
+        .windows(5)
+        .any(|window| window == b"%PDF-")
+        || path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+
+    if is_pdf {
+        return Ok(Document {
+            format: DocumentFormat::Text,
+            sections: read_pdf(path, &bytes)?,
+            hash,
+        });
+    }
+
+    if bytes.starts_with(b"PK\x03\x04") {
+        return read_zip(path, bytes, hash);
+    }
+
+    let text = String::from_utf8(bytes).map_err(|_| {
+        AppError::validation(format!(
+            "{}: not a text file or a supported document",
+            path.display()
+        ))
+    })?;
+
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let (format, sections) = if let Some(language) = CodeLanguage::from_extension(&extension) {
+        (DocumentFormat::Code(language), file_text(text))
+    } else {
+        match extension.as_str() {
+            "csv" => read_csv(path, &text, b',')?,
+            "tsv" => read_csv(path, &text, b'\t')?,
+            "json" => read_json(path, &text)?,
+            "jsonl" | "ndjson" => read_json_lines(&text),
+            "md" | "markdown" => (DocumentFormat::Markdown, file_text(text)),
+            "html" | "htm" | "xhtml" => (
+                DocumentFormat::Markdown,
+                single_section(html_to_markdown(&text)),
+            ),
+            _ if is_html(&text) => (
+                DocumentFormat::Markdown,
+                single_section(html_to_markdown(&text)),
+            ),
+            _ => (DocumentFormat::Text, file_text(text)),
+        }
+    };
+
+    if sections
+        .iter()
+        .all(|section| section.text.trim().is_empty())
+    {
+        return Err(AppError::validation(format!(
+            "{}: the file has no text",
+            path.display()
+        )));
+    }
+
+    Ok(Document {
+        format,
+        sections,
+        hash,
+    })
+}
+
+/// Joins the rows of a table into chunks of at most `chunk_size` tokens, each
+/// starting with the header. A row longer than that is a chunk on its own.
+#[must_use]
+pub fn group_rows(
+    header: &str,
+    rows: &[DocumentSection],
+    chunk_size: usize,
+    count_tokens: impl Fn(&str) -> usize,
+) -> Vec<FileChunk> {
+    let header_tokens = count_tokens(header);
+
+    let mut chunks = Vec::new();
+    let mut text = String::new();
+    let mut tokens = 0;
+    // Where the rows of the chunk start in the text of the document
+    let mut start = 0;
+    let mut offset = 0;
+
+    let mut push_chunk = |text: &str, start: usize| {
+        chunks.push(FileChunk {
+            text: if header.is_empty() {
+                text.to_string()
+            } else {
+                format!("{header}\n{text}")
+            },
+            page: None,
+            lines: None,
+            bytes: Some((start, start + text.len())),
+        });
+    };
+
+    for row in rows {
+        // One more for the line break
+        let row_tokens = count_tokens(&row.text) + 1;
+
+        if !text.is_empty() && header_tokens + tokens + row_tokens > chunk_size {
+            push_chunk(&text, start);
+            text.clear();
+            tokens = 0;
+        }
+
+        if text.is_empty() {
+            start = offset;
+        } else {
+            text.push_str(ROW_SEPARATOR);
+        }
+
+        text.push_str(&row.text);
+        tokens += row_tokens;
+        offset += row.text.len() + ROW_SEPARATOR.len();
+    }
+
+    if !text.is_empty() {
+        push_chunk(&text, start);
+    }
+
+    chunks
+}
+
+/// Turns the byte offsets splitters give chunks of a section into the lines
+/// of the file they span. Chunks have to come in the order of their offsets.
+pub struct LineCounter<'a> {
+    text: &'a str,
+    offset: usize,
+    line: usize,
+}
+
+impl<'a> LineCounter<'a> {
+    #[must_use]
+    pub const fn new(text: &'a str, first_line: usize) -> Self {
+        Self {
+            text,
+            offset: 0,
+            line: first_line,
+        }
+    }
+
+    /// First and last line of the chunk starting at `offset`.
+    pub fn lines(&mut self, offset: usize, chunk: &str) -> (usize, usize) {
+        if let Some(skipped) = self.text.get(self.offset..offset) {
+            self.line += skipped.matches('\n').count();
+            self.offset = offset;
+        }
+
+        (
+            self.line,
+            self.line + chunk.trim_end().matches('\n').count(),
+        )
+    }
+}
+
+/// Text extracted from the file, its lines are not the lines of the file.
+fn single_section(text: String) -> Vec<DocumentSection> {
+    vec![DocumentSection {
+        text,
+        page: None,
+        line: None,
+    }]
+}
+
+/// The file itself is the text, chunks can point at its lines.
+fn file_text(text: String) -> Vec<DocumentSection> {
+    vec![DocumentSection {
+        text,
+        page: None,
+        line: Some(1),
+    }]
+}
+
+fn is_html(text: &str) -> bool {
+    let start: String = text.trim_start().chars().take(16).collect();
+    let start = start.to_lowercase();
+
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
+
+fn html_to_markdown(html: &str) -> String {
+    html2md::parse_html(&strip_html_elements(
+        html,
+        &["head", "script", "style", "noscript"],
+    ))
+    .trim()
+    .to_string()
+}
+
+/// The converter keeps the text of every element, including titles, scripts
+/// and styles nobody reads on the page.
+fn strip_html_elements(html: &str, tags: &[&str]) -> String {
+    // ASCII lowercasing keeps the byte offsets of the original
+    let lowercase = html.to_ascii_lowercase();
+    let mut result = String::with_capacity(html.len());
+    let mut position = 0;
+
+    while position < html.len() {
+        let next = tags
+            .iter()
+            .filter_map(|tag| {
+                let start = position + lowercase[position..].find(&format!("<{tag}"))?;
+                // `<header>` is not `<head>`
+                let after = lowercase.as_bytes().get(start + tag.len() + 1)?;
+                (after.is_ascii_whitespace() || *after == b'>' || *after == b'/')
+                    .then_some((start, *tag))
+            })
+            .min_by_key(|(start, _)| *start);
+
+        let Some((start, tag)) = next else {
+            break;
+        };
+
+        result.push_str(&html[position..start]);
+
+        // An element left open, like a `<head>` without its end tag, loses only the tag
+        let end = lowercase[start..]
+            .find(&format!("</{tag}"))
+            .map_or(start, |end| start + end);
+
+        position = lowercase[end..]
+            .find('>')
+            .map_or(html.len(), |close| end + close + 1);
+    }
+
+    if position < html.len() {
+        result.push_str(&html[position..]);
+    }
+
+    result
+}
+
+/// DOCX and EPUB files are ZIP archives, they are told apart by what is inside.
+fn read_zip(path: &Path, bytes: Vec<u8>, hash: String) -> AppResult<Document> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| AppError::parse(format!("{}: {e}", path.display())))?;
+
+    let sections = if archive.index_for_name("word/document.xml").is_some() {
+        read_docx(path, &mut archive)?
+    } else if archive.index_for_name("META-INF/container.xml").is_some() {
+        read_epub(path, &mut archive)?
+    } else {
+        return Err(AppError::validation(format!(
+            "{}: not a text file or a supported document",
+            path.display()
+        )));
+    };
+
+    if sections
+        .iter()
+        .all(|section| section.text.trim().is_empty())
+    {
+        return Err(AppError::validation(format!(
+            "{}: the document has no text",
+            path.display()
+        )));
+    }
+
+    Ok(Document {
+        format: DocumentFormat::Markdown,
+        sections,
+        hash,
+    })
+}
+
+fn read_zip_entry(
+    path: &Path,
+    archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>,
+    name: &str,
+) -> AppResult<String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| AppError::parse(format!("{}: {name}: {e}", path.display())))?;
+
+    let mut content = String::new();
+    entry
+        .read_to_string(&mut content)
+        .map_err(|e| AppError::parse(format!("{}: {name}: {e}", path.display())))?;
+
+    Ok(content)
+}
+
+fn parse_xml<'a>(path: &Path, xml: &'a str) -> AppResult<roxmltree::Document<'a>> {
+    roxmltree::Document::parse(xml).map_err(|e| AppError::parse(format!("{}: {e}", path.display())))
+}
+
+/// The body as Markdown, so headings and tables survive the chunking.
+fn read_docx(
+    path: &Path,
+    archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>,
+) -> AppResult<Vec<DocumentSection>> {
+    let xml = read_zip_entry(path, archive, "word/document.xml")?;
+    let xml = parse_xml(path, &xml)?;
+
+    let mut blocks = Vec::new();
+    docx_blocks(xml.root_element(), &mut blocks);
+
+    Ok(single_section(blocks.join("\n\n")))
+}
+
+fn docx_blocks(node: roxmltree::Node, blocks: &mut Vec<String>) {
+    for child in node.children().filter(roxmltree::Node::is_element) {
+        match child.tag_name().name() {
+            "p" => {
+                let text = docx_paragraph(child);
+
+                if !text.trim().is_empty() {
+                    blocks.push(text);
+                }
+            }
+            "tbl" => blocks.push(docx_table(child)),
+            _ => docx_blocks(child, blocks),
+        }
+    }
+}
+
+fn docx_paragraph(paragraph: roxmltree::Node) -> String {
+    let mut text = String::new();
+
+    // Only runs hold the text, tabs and breaks of the paragraph properties are layout
+    for node in paragraph.descendants().filter(|node| {
+        node.parent_element()
+            .is_some_and(|parent| parent.tag_name().name() == "r")
+    }) {
+        match node.tag_name().name() {
+            "t" => text.push_str(node.text().unwrap_or_default()),
+            "tab" => text.push('\t'),
+            "br" | "cr" => text.push('\n'),
+            _ => {}
+        }
+    }
+
+    let properties = paragraph
+        .children()
+        .find(|node| node.tag_name().name() == "pPr");
+
+    let style = properties
+        .and_then(|properties| {
+            properties
+                .children()
+                .find(|node| node.tag_name().name() == "pStyle")
+        })
+        .and_then(|style| {
+            style
+                .attributes()
+                .find(|attribute| attribute.name() == "val")
+        })
+        .map(|attribute| attribute.value().to_lowercase())
+        .unwrap_or_default();
+
+    let is_list_item = properties.is_some_and(|properties| {
+        properties
+            .children()
+            .any(|node| node.tag_name().name() == "numPr")
+    });
+
+    if style == "title" {
+        return format!("# {text}");
+    }
+
+    if let Some(level) = style
+        .strip_prefix("heading")
+        .and_then(|level| level.trim().parse::<usize>().ok())
+    {
+        return format!("{} {text}", "#".repeat(level.clamp(1, 6)));
+    }
+
+    if is_list_item {
+        return format!("- {text}");
+    }
+
+    text
+}
+
+fn docx_table(table: roxmltree::Node) -> String {
+    let rows: Vec<String> = table
+        .children()
+        .filter(|node| node.tag_name().name() == "tr")
+        .map(|row| {
+            let cells: Vec<String> = row
+                .children()
+                .filter(|node| node.tag_name().name() == "tc")
+                .map(|cell| {
+                    cell.descendants()
+                        .filter(|node| node.tag_name().name() == "p")
+                        .map(docx_paragraph)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                        .replace('|', "\\|")
+                })
+                .collect();
+
+            format!("| {} |", cells.join(" | "))
+        })
+        .collect();
+
+    let Some(first_row) = rows.first() else {
+        return String::new();
+    };
+
+    // Markdown wants a separator under the first row to read it as a table
+    let columns = first_row.matches(" | ").count() + 1;
+    let separator = format!("|{}", " --- |".repeat(columns));
+
+    let mut lines = vec![first_row.clone(), separator];
+    lines.extend(rows.into_iter().skip(1));
+
+    lines.join("\n")
+}
+
+/// One section for every chapter, in the reading order of the book.
+fn read_epub(
+    path: &Path,
+    archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>,
+) -> AppResult<Vec<DocumentSection>> {
+    let container = read_zip_entry(path, archive, "META-INF/container.xml")?;
+    let container = parse_xml(path, &container)?;
+
+    let package_path = container
+        .descendants()
+        .find(|node| node.tag_name().name() == "rootfile")
+        .and_then(|node| node.attribute("full-path"))
+        .ok_or_else(|| AppError::parse(format!("{}: the book has no package", path.display())))?
+        .to_string();
+
+    let package = read_zip_entry(path, archive, &package_path)?;
+    let package = parse_xml(path, &package)?;
+
+    // Chapters are referenced relative to the package
+    let base = package_path
+        .rsplit_once('/')
+        .map(|(base, _)| format!("{base}/"))
+        .unwrap_or_default();
+
+    let manifest: Vec<(&str, &str)> = package
+        .descendants()
+        .filter(|node| node.tag_name().name() == "item")
+        .filter_map(|node| Some((node.attribute("id")?, node.attribute("href")?)))
+        .collect();
+
+    let mut sections = Vec::new();
+
+    for item in package
+        .descendants()
+        .filter(|node| node.tag_name().name() == "itemref")
+        .filter_map(|node| node.attribute("idref"))
+    {
+        let Some((_, href)) = manifest.iter().find(|(id, _)| *id == item) else {
+            continue;
+        };
+
+        let href = href.split('#').next().unwrap_or_default();
+        let chapter = read_zip_entry(path, archive, &format!("{base}{href}"))?;
+        let text = html_to_markdown(&chapter);
+
+        if !text.is_empty() {
+            sections.push(DocumentSection {
+                text,
+                page: None,
+                line: None,
+            });
+        }
+    }
+
+    Ok(sections)
+}
+
+/// One section for every record, the first line is the header.
+fn read_csv(
+    path: &Path,
+    text: &str,
+    delimiter: u8,
+) -> AppResult<(DocumentFormat, Vec<DocumentSection>)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let header = reader
+        .headers()
+        .map_err(|e| AppError::parse(format!("{}: {e}", path.display())))?;
+    let header = csv_line(path, header, delimiter)?;
+
+    let mut sections = Vec::new();
+
+    for record in reader.records() {
+        let record = record.map_err(|e| AppError::parse(format!("{}: {e}", path.display())))?;
+
+        sections.push(DocumentSection {
+            text: csv_line(path, &record, delimiter)?,
+            page: None,
+            line: None,
+        });
+    }
+
+    Ok((DocumentFormat::Table { header }, sections))
+}
+
+/// Writes the record back, so fields with delimiters keep their quotes.
+fn csv_line(path: &Path, record: &csv::StringRecord, delimiter: u8) -> AppResult<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+
+    writer
+        .write_record(record)
+        .map_err(|e| AppError::parse(format!("{}: {e}", path.display())))?;
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| AppError::parse(format!("{}: {e}", path.display())))?;
+
+    Ok(String::from_utf8_lossy(&bytes).trim_end().to_string())
+}
+
+/// Elements of a top level array are grouped like table rows, anything else is
+/// chunked as pretty-printed text.
+fn read_json(path: &Path, text: &str) -> AppResult<(DocumentFormat, Vec<DocumentSection>)> {
+    let value: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| AppError::parse(format!("{}: {e}", path.display())))?;
+
+    if let serde_json::Value::Array(items) = value {
+        let sections = items
+            .iter()
+            .map(|item| DocumentSection {
+                text: item.to_string(),
+                page: None,
+                line: None,
+            })
+            .collect();
+
+        return Ok((
+            DocumentFormat::Table {
+                header: String::new(),
+            },
+            sections,
+        ));
+    }
+
+    let text = serde_json::to_string_pretty(&value)
+        .map_err(|e| AppError::parse(format!("{}: {e}", path.display())))?;
+
+    Ok((DocumentFormat::Text, single_section(text)))
+}
+
+fn read_json_lines(text: &str) -> (DocumentFormat, Vec<DocumentSection>) {
+    let sections = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| DocumentSection {
+            text: line.trim().to_string(),
+            page: None,
+            line: None,
+        })
+        .collect();
+
+    (
+        DocumentFormat::Table {
+            header: String::new(),
+        },
+        sections,
+    )
+}
+
+/// One section for every page that has text.
+fn read_pdf(path: &Path, bytes: &[u8]) -> AppResult<Vec<DocumentSection>> {
+    // The extractor panics on some broken fonts instead of returning an error
+    let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| AppError::parse(format!("{}: the PDF could not be read", path.display())))?
+        .map_err(|e| AppError::parse(format!("{}: {e}", path.display())))?;
+
+    let sections: Vec<DocumentSection> = pages
+        .into_iter()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(index, text)| DocumentSection {
+            text,
+            page: Some(index + 1),
+            line: None,
+        })
+        .collect();
+
+    if sections.is_empty() {
+        // Scanned pages are images, there is no text to take from them
+        return Err(AppError::validation(format!(
+            "{}: the PDF has no text, it may be scanned",
+            path.display()
+        )));
+    }
+
+    Ok(sections)
+}
@@ -0,0 +1,318 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use types::{
+    dto::{
+        AttachmentDTO, CONVERSATION_EXPORT_VERSION, ConversationExportDTO, ConversationNodeDTO,
+        ExportFormat, MessageDTO, MessageID, RoleType,
+    },
+    error::{AppError, AppResult},
+};
+
+/// Retrieved chunk a question was answered with.
+pub struct Citation {
+    /// With the page the chunk is on, like `spec.pdf p. 42`.
+    pub file_name: String,
+    pub chunk: String,
+}
+
+/// A chat with everything its export is written from.
+pub struct ChatExport {
+    pub conversation: ConversationNodeDTO,
+    pub active_message_id: Option<MessageID>,
+    /// Every branch, oldest first.
+    pub messages: Vec<MessageDTO>,
+    pub citations: HashMap<MessageID, Vec<Citation>>,
+}
+
+impl ChatExport {
+    /// # Errors
+    pub fn render(self, format: ExportFormat) -> AppResult<String> {
+        match format {
+            ExportFormat::Markdown => Ok(self.markdown()),
+            ExportFormat::Html => Ok(self.html()),
+            ExportFormat::Json => {
+                let document = ConversationExportDTO {
+                    version: CONVERSATION_EXPORT_VERSION,
+                    conversation: self.conversation,
+                    active_message_id: self.active_message_id,
+                    messages: self.messages,
+                };
+
+                Ok(serde_json::to_string_pretty(&document)?)
+            }
+        }
+    }
+
+    /// Messages of the shown branch, oldest first. Markdown and HTML are
+    /// read by people, they get what the chat shows.
+    fn branch(&self) -> Vec<&MessageDTO> {
+        let by_id: HashMap<MessageID, &MessageDTO> = self
+            .messages
+            .iter()
+            .map(|message| (message.id, message))
+            .collect();
+
+        let mut branch = vec![];
+        let mut next = self.active_message_id;
+
+        while let Some(message) = next.and_then(|id| by_id.get(&id)) {
+            branch.push(*message);
+            next = message.parent_id;
+        }
+
+        branch.reverse();
+        branch
+    }
+
+    fn markdown(&self) -> String {
+        let mut markdown = format!("# {}\n", self.conversation.name);
+
+        if let Some(model) = self
+            .conversation
+            .model
+            .as_ref()
+            .filter(|model| !model.is_empty())
+        {
+            let _ = write!(markdown, "\n_Model: {model}_\n");
+        }
+
+        if !self.conversation.prompt.is_empty() {
+            let _ = write!(
+                markdown,
+                "\n> {}\n",
+                self.conversation.prompt.replace('\n', "\n> ")
+            );
+        }
+
+        for message in self.branch() {
+            let _ = write!(markdown, "\n## {}\n\n", role_title(&message.role));
+
+            if let Some(reasoning) = message.reasoning.as_ref().filter(|r| !r.is_empty()) {
+                let _ = write!(
+                    markdown,
+                    "<details>\n<summary>Reasoning</summary>\n\n{reasoning}\n\n</details>\n\n"
+                );
+            }
+
+            if message.role == RoleType::Tool {
+                let _ = writeln!(markdown, "```\n{}\n```", message.content);
+            } else if !message.content.is_empty() {
+                let _ = writeln!(markdown, "{}", message.content);
+            }
+
+            for tool_call in &message.tool_calls {
+                let _ = write!(
+                    markdown,
+                    "\n_Called `{}({})`_\n",
+                    tool_call.name, tool_call.arguments
+                );
+            }
+
+            for attachment in message.attachments.iter().filter(|a| a.is_image()) {
+                let _ = write!(markdown, "\n![image]({})\n", data_url(attachment));
+            }
+
+            if let Some(citations) = self.citations.get(&message.id) {
+                markdown.push_str("\n<details>\n<summary>Sources</summary>\n\n");
+
+                for citation in citations {
+                    let _ = write!(
+                        markdown,
+                        "**{}**\n\n> {}\n\n",
+                        citation.file_name,
+                        citation.chunk.trim().replace('\n', "\n> ")
+                    );
+                }
+
+                markdown.push_str("</details>\n");
+            }
+        }
+
+        markdown
+    }
+
+    fn html(&self) -> String {
+        let mut body = String::new();
+
+        let _ = write!(body, "<h1>{}</h1>", escape_html(&self.conversation.name));
+
+        if let Some(model) = self
+            .conversation
+            .model
+            .as_ref()
+            .filter(|model| !model.is_empty())
+        {
+            let _ = write!(body, "<p class=\"meta\">Model: {}</p>", escape_html(model));
+        }
+
+        if !self.conversation.prompt.is_empty() {
+            let _ = write!(
+                body,
+                "<blockquote class=\"text\">{}</blockquote>",
+                escape_html(&self.conversation.prompt)
+            );
+        }
+
+        for message in self.branch() {
+            let role = message.role.to_string();
+
+            let _ = write!(
+                body,
+                "<section class=\"message {role}\"><h2>{}</h2>",
+                role_title(&message.role)
+            );
+
+            if let Some(reasoning) = message.reasoning.as_ref().filter(|r| !r.is_empty()) {
+                let _ = write!(
+                    body,
+                    "<details><summary>Reasoning</summary><div class=\"text\">{}</div></details>",
+                    escape_html(reasoning)
+                );
+            }
+
+            if !message.content.is_empty() {
+                let _ = write!(
+                    body,
+                    "<div class=\"text\">{}</div>",
+                    escape_html(&message.content)
+                );
+            }
+
+            for tool_call in &message.tool_calls {
+                let _ = write!(
+                    body,
+                    "<p class=\"meta\">Called <code>{}({})</code></p>",
+                    escape_html(&tool_call.name),
+                    escape_html(&tool_call.arguments)
+                );
+            }
+
+            for attachment in message.attachments.iter().filter(|a| a.is_image()) {
+                let _ = write!(body, "<img src=\"{}\">", data_url(attachment));
+            }
+
+            if let Some(citations) = self.citations.get(&message.id) {
+                body.push_str("<details><summary>Sources</summary>");
+
+                for citation in citations {
+                    let _ = write!(
+                        body,
+                        "<p><b>{}</b></p><blockquote class=\"text\">{}</blockquote>",
+                        escape_html(&citation.file_name),
+                        escape_html(citation.chunk.trim())
+                    );
+                }
+
+                body.push_str("</details>");
+            }
+
+            body.push_str("</section>");
+        }
+
+        format!(
+            "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+body {{ max-width: 860px; margin: 0 auto; padding: 24px; font-family: sans-serif; color: #222; }}
+.message {{ border-radius: 6px; padding: 4px 16px; margin: 12px 0; background: #f4f4f4; }}
+.message.user {{ background: #e6effa; }}
+.message.tool, .message.system {{ background: #f9f4e6; }}
+.text {{ white-space: pre-wrap; }}
+.meta {{ color: #777; font-size: 0.9em; }}
+img {{ max-width: 100%; }}
+blockquote {{ margin: 8px 0; padding-left: 12px; border-left: 3px solid #ccc; }}
+</style>
+</head>
+<body>{body}</body>
+</html>
+",
+            title = escape_html(&self.conversation.name)
+        )
+    }
+}
+
+/// Path in `directory` named after the node that no other file takes yet,
+/// `extension` is `None` for folders.
+#[must_use]
+pub fn unique_path(directory: &Path, name: &str, extension: Option<&str>) -> PathBuf {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|char| match char {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            char if char.is_control() => '_',
+            char => char,
+        })
+        .collect();
+    let name = if name.is_empty() { "Untitled" } else { &name };
+
+    let path_for = |suffix: String| {
+        let file_name = extension.map_or_else(
+            || format!("{name}{suffix}"),
+            |extension| format!("{name}{suffix}.{extension}"),
+        );
+        directory.join(file_name)
+    };
+
+    let mut path = path_for(String::new());
+    let mut copy = 1;
+
+    while path.exists() {
+        copy += 1;
+        path = path_for(format!(" ({copy})"));
+    }
+
+    path
+}
+
+/// # Errors
+pub fn write_file(path: &Path, content: &str) -> AppResult<()> {
+    std::fs::write(path, content).map_err(|e| AppError::file(format!("{}: {e}", path.display())))
+}
+
+/// # Errors
+pub fn create_directory(path: &Path) -> AppResult<()> {
+    std::fs::create_dir_all(path).map_err(|e| AppError::file(format!("{}: {e}", path.display())))
+}
+
+const fn role_title(role: &RoleType) -> &'static str {
+    match role {
+        RoleType::User => "User",
+        RoleType::Assistant => "Assistant",
+        RoleType::System => "System",
+        RoleType::Tool => "Tool",
+    }
+}
+
+fn data_url(attachment: &AttachmentDTO) -> String {
+    format!(
+        "data:{};base64,{}",
+        attachment.mime_type,
+        STANDARD.encode(&attachment.data)
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
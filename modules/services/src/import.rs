@@ -0,0 +1,415 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use types::dto::{
+    CONVERSATION_EXPORT_VERSION, ConversationExportDTO, ConversationNodeDTO, ConversationType,
+    ImportNodeDTO, MessageDTO, RoleType,
+};
+
+/// Reads files and directories to import, a directory becomes a folder.
+/// What cannot be imported is described in `skipped`.
+pub fn read_paths(paths: &[PathBuf], skipped: &mut Vec<String>) -> Vec<ImportNodeDTO> {
+    let mut nodes = vec![];
+
+    for path in paths {
+        if path.is_dir() {
+            nodes.extend(read_directory(path, skipped));
+        } else {
+            nodes.extend(read_file(path, skipped));
+        }
+    }
+
+    nodes
+}
+
+fn read_directory(path: &Path, skipped: &mut Vec<String>) -> Option<ImportNodeDTO> {
+    let mut entries: Vec<PathBuf> = match std::fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .collect(),
+        Err(e) => {
+            skipped.push(format!("{}: {e}", path.display()));
+            return None;
+        }
+    };
+    entries.sort();
+
+    let mut children = vec![];
+
+    for entry in entries {
+        let is_hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+        if is_hidden {
+            continue;
+        }
+
+        if entry.is_dir() {
+            children.extend(read_directory(&entry, skipped));
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            children.extend(read_file(&entry, skipped));
+        } else {
+            // Markdown and HTML exports are for reading, only JSON keeps everything
+            skipped.push(format!("{}: not a JSON export", entry.display()));
+        }
+    }
+
+    if children.is_empty() {
+        return None;
+    }
+
+    Some(ImportNodeDTO::Folder {
+        name: path.file_name().map_or_else(
+            || "Imported".to_string(),
+            |name| name.to_string_lossy().to_string(),
+        ),
+        children,
+    })
+}
+
+fn read_file(path: &Path, skipped: &mut Vec<String>) -> Vec<ImportNodeDTO> {
+    let value = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str::<Value>(&content).map_err(|e| e.to_string()));
+
+    let value = match value {
+        Ok(value) => value,
+        Err(e) => {
+            skipped.push(format!("{}: {e}", path.display()));
+            return vec![];
+        }
+    };
+
+    if value.get("conversation").is_some() && value.get("version").is_some() {
+        return match read_export(value) {
+            Ok(node) => vec![node],
+            Err(e) => {
+                skipped.push(format!("{}: {e}", path.display()));
+                vec![]
+            }
+        };
+    }
+
+    // ChatGPT exports all conversations to one file
+    let conversations = match value {
+        Value::Array(conversations) => conversations,
+        value if value.get("mapping").is_some() => vec![value],
+        _ => {
+            skipped.push(format!("{}: not a supported export", path.display()));
+            return vec![];
+        }
+    };
+
+    conversations
+        .iter()
+        .filter_map(|conversation| read_chatgpt_conversation(conversation, skipped))
+        .collect()
+}
+
+fn read_export(value: Value) -> Result<ImportNodeDTO, String> {
+    let chat: ConversationExportDTO = serde_json::from_value(value).map_err(|e| e.to_string())?;
+
+    if chat.version > CONVERSATION_EXPORT_VERSION {
+        return Err("exported by a newer version of the application".to_string());
+    }
+
+    // The same messages exported twice are the same chat, even if it was renamed
+    let mut fingerprint = String::new();
+    for message in &chat.messages {
+        fingerprint.push_str(&message.timestamp);
+        fingerprint.push_str(&message.role.to_string());
+        fingerprint.push_str(&message.content);
+    }
+    if chat.messages.is_empty() {
+        fingerprint.push_str(&chat.conversation.name);
+    }
+
+    Ok(ImportNodeDTO::Chat {
+        source: format!("export:{}", seahash::hash(fingerprint.as_bytes())),
+        chat: Box::new(chat),
+    })
+}
+
+/// Takes the branch ending at `current_node`, the one shown in `ChatGPT`.
+fn read_chatgpt_conversation(
+    conversation: &Value,
+    skipped: &mut Vec<String>,
+) -> Option<ImportNodeDTO> {
+    let title = conversation
+        .get("title")
+        .and_then(Value::as_str)
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or("Untitled");
+
+    let Some(mapping) = conversation.get("mapping").and_then(Value::as_object) else {
+        skipped.push(format!("{title}: no messages"));
+        return None;
+    };
+
+    let mut branch = vec![];
+    let mut next = conversation.get("current_node").and_then(Value::as_str);
+
+    while let Some(node) = next.and_then(|id| mapping.get(id)) {
+        branch.push(node);
+        next = node.get("parent").and_then(Value::as_str);
+    }
+    branch.reverse();
+
+    let mut messages: Vec<MessageDTO> = vec![];
+    let mut reasoning = String::new();
+    let mut left_out = 0;
+    let mut files_left_out = 0;
+
+    // The root node has no message
+    let branch_messages = branch
+        .iter()
+        .filter_map(|node| node.get("message"))
+        .filter(|message| !message.is_null());
+
+    for message in branch_messages {
+        match read_chatgpt_message(message, &mut files_left_out) {
+            ChatGptMessage::Message(mut message) => {
+                if message.role == RoleType::Assistant && !reasoning.is_empty() {
+                    message.reasoning = Some(std::mem::take(&mut reasoning));
+                }
+
+                message.id = messages.last().map_or(1, |previous| previous.id + 1);
+                message.parent_id = messages.last().map(|previous| previous.id);
+                messages.push(*message);
+            }
+            ChatGptMessage::Reasoning(thoughts) => reasoning.push_str(&thoughts),
+            ChatGptMessage::Hidden => {}
+            ChatGptMessage::Unsupported => left_out += 1,
+        }
+    }
+
+    if left_out > 0 {
+        skipped.push(format!("{title}: {left_out} messages with tools left out"));
+    }
+
+    if files_left_out > 0 {
+        skipped.push(format!("{title}: {files_left_out} files or images left out"));
+    }
+
+    let id = conversation
+        .get("conversation_id")
+        .or_else(|| conversation.get("id"))
+        .and_then(Value::as_str)
+        .map_or_else(
+            || {
+                seahash::hash(format!("{title}{:?}", conversation.get("create_time")).as_bytes())
+                    .to_string()
+            },
+            ToString::to_string,
+        );
+
+    let model = conversation
+        .get("default_model_slug")
+        .and_then(Value::as_str)
+        .map(ToString::to_string);
+
+    Some(ImportNodeDTO::Chat {
+        source: format!("chatgpt:{id}"),
+        chat: Box::new(ConversationExportDTO {
+            version: CONVERSATION_EXPORT_VERSION,
+            conversation: ConversationNodeDTO {
+                name: title.to_string(),
+                tp: ConversationType::Chat,
+                model,
+                ..ConversationNodeDTO::empty_with_id(0)
+            },
+            active_message_id: messages.last().map(|message| message.id),
+            messages,
+        }),
+    })
+}
+
+enum ChatGptMessage {
+    Message(Box<MessageDTO>),
+    /// Thoughts of a reasoning model, they belong to the answer after them.
+    Reasoning(String),
+    /// Empty, or not shown in `ChatGPT` either.
+    Hidden,
+    Unsupported,
+}
+
+/// Files and images of the message are counted in `files_left_out`, its text is kept.
+fn read_chatgpt_message(message: &Value, files_left_out: &mut usize) -> ChatGptMessage {
+    let is_hidden = message
+        .pointer("/metadata/is_visually_hidden_from_conversation")
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
+
+    if is_hidden {
+        return ChatGptMessage::Hidden;
+    }
+
+    let role = match message.pointer("/author/role").and_then(Value::as_str) {
+        Some("user") => RoleType::User,
+        Some("system") => RoleType::System,
+        // Calls of ChatGPT's own tools, this client cannot answer them
+        Some("assistant") if message.get("recipient").and_then(Value::as_str) == Some("all") => {
+            RoleType::Assistant
+        }
+        _ => return ChatGptMessage::Unsupported,
+    };
+
+    let content = message.get("content").unwrap_or(&Value::Null);
+
+    let text = match content.get("content_type").and_then(Value::as_str) {
+        Some("text" | "multimodal_text") => {
+            let parts = content.get("parts").and_then(Value::as_array);
+            *files_left_out += parts.map_or(0, |parts| parts.iter().filter(|part| !part.is_string()).count());
+
+            parts
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        Some("code") => format!(
+            "```\n{}\n```",
+            content
+                .get("text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+        ),
+        Some("thoughts") => {
+            let thoughts = content
+                .get("thoughts")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|thought| thought.get("content").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+
+            return ChatGptMessage::Reasoning(thoughts);
+        }
+        Some("reasoning_recap" | "user_editable_context") => return ChatGptMessage::Hidden,
+        _ => return ChatGptMessage::Unsupported,
+    };
+
+    if text.trim().is_empty() {
+        return ChatGptMessage::Hidden;
+    }
+
+    // Seconds with a fraction
+    #[allow(clippy::cast_possible_truncation)]
+    let timestamp = message
+        .get("create_time")
+        .and_then(Value::as_f64)
+        .and_then(|time| chrono::DateTime::from_timestamp(time as i64, 0))
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+
+    ChatGptMessage::Message(Box::new(MessageDTO {
+        content: text,
+        role,
+        timestamp,
+        ..Default::default()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use database::{create_database_connection, databases::ImportDatabase};
+    use serde_json::{Value, json};
+    use types::dto::{ImportNodeDTO, RoleType};
+
+    use super::read_chatgpt_conversation;
+
+    fn node(parent: Option<&str>, message: &Value) -> Value {
+        json!({ "parent": parent, "message": message })
+    }
+
+    fn message(role: &str, content: &Value) -> Value {
+        json!({ "author": { "role": role }, "recipient": "all", "content": content })
+    }
+
+    fn text(text: &str) -> Value {
+        json!({ "content_type": "text", "parts": [text] })
+    }
+
+    /// "Hello" was answered twice, the second answer after some thoughts is
+    /// the one shown. The question after it has an image.
+    fn conversation() -> Value {
+        json!({
+            "title": "Apples",
+            "conversation_id": "apples",
+            "current_node": "question",
+            "mapping": {
+                "root": node(None, &Value::Null),
+                "hello": node(Some("root"), &message("user", &text("Hello"))),
+                "first": node(Some("hello"), &message("assistant", &text("First answer"))),
+                "thoughts": node(Some("hello"), &message("assistant", &json!({
+                    "content_type": "thoughts",
+                    "thoughts": [{ "content": "They said hello" }],
+                }))),
+                "second": node(Some("thoughts"), &message("assistant", &text("Second answer"))),
+                "question": node(Some("second"), &message("user", &json!({
+                    "content_type": "multimodal_text",
+                    "parts": [{ "content_type": "image_asset_pointer" }, "What is on it?"],
+                }))),
+            },
+        })
+    }
+
+    #[test]
+    fn reads_the_current_branch() {
+        let mut skipped = vec![];
+        let Some(ImportNodeDTO::Chat { source, chat }) =
+            read_chatgpt_conversation(&conversation(), &mut skipped)
+        else {
+            panic!("No chat read");
+        };
+
+        assert_eq!(source, "chatgpt:apples");
+        assert_eq!(chat.conversation.name, "Apples");
+
+        let messages: Vec<(RoleType, &str, Option<&str>)> = chat
+            .messages
+            .iter()
+            .map(|message| (message.role.clone(), message.content.as_str(), message.reasoning.as_deref()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (RoleType::User, "Hello", None),
+                (RoleType::Assistant, "Second answer", Some("They said hello")),
+                (RoleType::User, "What is on it?", None),
+            ]
+        );
+
+        let parents: Vec<_> = chat.messages.iter().map(|message| message.parent_id).collect();
+        assert_eq!(parents, vec![None, Some(1), Some(2)]);
+        assert_eq!(chat.active_message_id, Some(3));
+
+        assert_eq!(skipped, vec!["Apples: 1 files or images left out".to_string()]);
+    }
+
+    #[test]
+    fn skips_a_chat_imported_before() {
+        let mut skipped = vec![];
+        let nodes: Vec<ImportNodeDTO> =
+            read_chatgpt_conversation(&conversation(), &mut skipped).into_iter().collect();
+
+        let connection = create_database_connection(":memory:").expect("Failed to open database");
+        let database = ImportDatabase::new(connection);
+
+        let first = database.import(0, &nodes).expect("Failed to import");
+        assert_eq!((first.chats, first.messages), (1, 3));
+        assert!(first.duplicates.is_empty());
+
+        let second = database.import(0, &nodes).expect("Failed to import again");
+        assert_eq!(second.chats, 0);
+        assert_eq!(second.duplicates, vec!["Apples".to_string()]);
+    }
+}
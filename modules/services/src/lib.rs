@@ -1,7 +1,13 @@
 mod app_settings;
+mod context;
 mod conversations;
+mod documents;
+mod export;
+mod import;
 mod messaging;
 mod presets;
+mod tools;
+mod usage;
 mod vector;
 
 pub use app_settings::AppSettings;
@@ -9,4 +15,6 @@ pub use conversations::ConversationsService;
 pub use messaging::MessagingEvent;
 pub use messaging::MessagingService;
 pub use presets::PresetsService;
+pub use tools::{Tool, ToolRegistry};
+pub use usage::UsageService;
 pub use vector::VectorService;
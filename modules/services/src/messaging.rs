@@ -1,81 +1,197 @@
-use std::pin::Pin;
+use std::{fmt::Write, pin::Pin, time::Instant};
 
-use api::{ComplitationStatus, open_ai_api::OpenAiApi};
-use database::{DatabaseConnection, databases::ProvidersDatabase};
+use api::{CancellationToken, CompletionInfo, ComplitationStatus, MessageChunk, lm_backend::LmApi};
+use database::{
+    DatabaseConnection,
+    databases::{ProvidersDatabase, UsageDatabase},
+};
 use iced::futures::{Stream, StreamExt};
-use types::dto::{ChunkRagDTO, ConversationNodeID, LmModel, MessageDTO, PresetDTO};
+use types::{
+    common::{ContextUsage, NotificationAction},
+    dto::{
+        AttachmentDTO, ConversationNodeDTO, ConversationNodeID, GenerationDTO, LmModel, MessageDTO,
+        MessageID, PresetDTO, RoleType, ToolDefinitionDTO,
+    },
+    error::{AppError, AppResult},
+};
 use utils::event_system::{Event, EventSystem};
 
-use crate::{ConversationsService, VectorService};
+use crate::{ConversationsService, ToolRegistry, VectorService, context::ContextBuilder};
+
+/// Rounds of tool calls in one answer, the last round is requested without tools.
+const MAX_TOOL_ITERATIONS: usize = 8;
+
+/// Latest messages of the history shown to the model rewriting the query.
+const REWRITE_HISTORY_MESSAGES: usize = 6;
+
+const CITATION_PROMPT: &str = "Cite the chunks an answer uses by their id in square brackets, like [1] or [2][3].";
+
+const HYDE_PROMPT: &str = "Write a short passage that answers the question, \
+    as it could appear in a document. Reply only with the passage.";
 
 #[derive(Clone)]
 pub struct MessagingService {
     conversations_service: ConversationsService,
-    lm_api: OpenAiApi,
+    lm_api: LmApi,
     vector_service: VectorService,
     providers_db: ProvidersDatabase,
+    usage_db: UsageDatabase,
     event_system: EventSystem,
+    tools: ToolRegistry,
 }
 
 #[derive(Debug, Clone)]
 pub enum MessagingEvent {
     ReceiveMessage(ComplitationStatus),
-    Error(String),
+    /// Sent right before the answer ends or is cancelled.
+    Generated(GenerationDTO),
+    Error(AppError),
+}
+
+/// Everything a completion needs, gathered before the request is sent.
+struct CompletionRequest {
+    lm_model: LmModel,
+    messages: Vec<MessageDTO>,
+    preset: Option<PresetDTO>,
+    tools: Vec<ToolDefinitionDTO>,
+}
+
+/// When one completion was requested and when its first token came in.
+struct Latency {
+    started: Instant,
+    first_token: Option<Instant>,
+}
+
+impl Latency {
+    fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            first_token: None,
+        }
+    }
+
+    fn received(&mut self, chunk: &MessageChunk) {
+        if self.first_token.is_none() && !(chunk.content.is_empty() && chunk.reasoning_content.is_empty()) {
+            self.first_token = Some(Instant::now());
+        }
+    }
+
+    fn fill(&self, generation: &mut GenerationDTO) {
+        generation.time_to_first_token = self
+            .first_token
+            .and_then(|received| u64::try_from((received - self.started).as_millis()).ok());
+        generation.duration = u64::try_from(self.started.elapsed().as_millis()).ok();
+    }
 }
 
 impl MessagingService {
     #[must_use]
     pub fn new(
         conversations_service: ConversationsService,
-        lm_api: OpenAiApi,
+        lm_api: LmApi,
         vector_service: VectorService,
         connection: DatabaseConnection,
         event_system: EventSystem,
+        tools: ToolRegistry,
     ) -> Self {
         Self {
             event_system,
+            tools,
             conversations_service,
             lm_api,
             vector_service,
-            providers_db: ProvidersDatabase::new(connection),
+            providers_db: ProvidersDatabase::new(connection.clone()),
+            usage_db: UsageDatabase::new(connection),
         }
     }
 
     /// # Errors
-    /// # Panics
     pub fn send_message(
         &self,
         conversation_id: ConversationNodeID,
         message: String,
-    ) -> Result<Pin<Box<dyn Stream<Item = MessagingEvent> + Send>>, String> {
+        attachments: Vec<AttachmentDTO>,
+        cancel: CancellationToken,
+    ) -> AppResult<Pin<Box<dyn Stream<Item = MessagingEvent> + Send>>> {
         let user_message = MessageDTO {
-            id: 0,
             conversation_id,
             content: message,
-            reasoning: None,
             role: types::dto::RoleType::User,
-            timestamp: String::new(),
-            summary: None,
-            chunks: Vec::new(),
+            attachments,
+            ..Default::default()
         };
 
-        let message_for_write = user_message;
-        self.conversations_service.write_message(
-            conversation_id,
-            &message_for_write.content.clone(),
-            &message_for_write.reasoning.unwrap_or_default(),
-            &message_for_write.role,
-        )?;
+        self.conversations_service
+            .write_message_dto(&user_message)
+            .inspect_err(|e| self.report_error(conversation_id, "Failed to send message", e))?;
+
+        self.generate_message(conversation_id, cancel)
+    }
+
+    /// Answers the user message again, the new answer becomes an alternative
+    /// of the one `message_id` belongs to.
+    ///
+    /// # Errors
+    pub fn regenerate_message(
+        &self,
+        message_id: MessageID,
+        cancel: CancellationToken,
+    ) -> AppResult<Pin<Box<dyn Stream<Item = MessagingEvent> + Send>>> {
+        let mut message = self.conversations_service.get_message(message_id)?;
+        let conversation_id = message.conversation_id;
+
+        // Tool calls and their results belong to the answer, regenerate all of them
+        while message.role != RoleType::User {
+            let parent_id = message
+                .parent_id
+                .ok_or_else(|| AppError::not_found("User message to answer"))?;
+
+            message = self.conversations_service.get_message(parent_id)?;
+        }
+
+        self.conversations_service
+            .set_active_message(conversation_id, Some(message.id))?;
+
+        self.generate_message(conversation_id, cancel)
+    }
+
+    /// Sends an edited copy of the user message as its alternative and answers it.
+    ///
+    /// # Errors
+    pub fn resend_message(
+        &self,
+        message_id: MessageID,
+        content: String,
+        cancel: CancellationToken,
+    ) -> AppResult<Pin<Box<dyn Stream<Item = MessagingEvent> + Send>>> {
+        let message = self.conversations_service.get_message(message_id)?;
 
-        self.generate_message(conversation_id)
+        self.conversations_service
+            .set_active_message(message.conversation_id, message.parent_id)?;
+
+        self.send_message(message.conversation_id, content, message.attachments, cancel)
     }
 
     /// # Errors
-    /// # Panics
     pub async fn summarize(
         &self,
         conversation_id: ConversationNodeID,
-    ) -> Result<MessageDTO, String> {
+        cancel: CancellationToken,
+    ) -> AppResult<MessageDTO> {
+        self.summarize_conversation(conversation_id, cancel)
+            .await
+            .inspect_err(|e| {
+                if !matches!(e, AppError::Cancelled) {
+                    self.report_error(conversation_id, "Failed to summarize the conversation", e);
+                }
+            })
+    }
+
+    async fn summarize_conversation(
+        &self,
+        conversation_id: ConversationNodeID,
+        cancel: CancellationToken,
+    ) -> AppResult<MessageDTO> {
         let conversation = self
             .conversations_service
             .get_conversation(conversation_id)?;
@@ -90,14 +206,14 @@ impl MessagingService {
             if let Some(provider) = provider {
                 provider
             } else {
-                return Err("No summary model found".to_string());
+                return Err(AppError::not_found("Summary model"));
             }
         } else {
             summary_model
         };
 
         if summary_model.is_empty() {
-            return Err("No summary model found".to_string());
+            return Err(AppError::not_found("Summary model"));
         }
 
         if let Some(summary_provider_id) = conversation.summary_provider {
@@ -132,20 +248,22 @@ impl MessagingService {
                 }
             }
 
-            let summary_provider = self.providers_db.get_provider(summary_provider_id);
+            let lm_model = LmModel {
+                model_name: summary_model,
+                provider: self.providers_db.get_provider(summary_provider_id),
+            };
+            let request = vec![MessageDTO {
+                content: history_message_content,
+                role: types::dto::RoleType::User,
+                ..Default::default()
+            }];
 
             let mut stream = self.lm_api.chat_completions(
-                LmModel {
-                    model_name: summary_model,
-                    provider: summary_provider,
-                },
+                lm_model.clone(),
+                request.clone(),
+                Some(preset.clone()),
                 vec![],
-                Some(preset),
-                MessageDTO {
-                    content: history_message_content,
-                    role: types::dto::RoleType::User,
-                    ..Default::default()
-                },
+                cancel.clone(),
             )?;
 
             let mut summary = String::new();
@@ -156,13 +274,22 @@ impl MessagingService {
                         summary.push_str(&message.content);
                     }
 
-                    ComplitationStatus::End => {
+                    ComplitationStatus::End(info) => {
+                        let generation = Self::generation(
+                            &lm_model,
+                            Some(&preset),
+                            &request,
+                            &summary,
+                            "",
+                            Some(&info),
+                        );
+                        self.record_usage(conversation_id, &generation);
+
                         let mut last_message = self
                             .conversations_service
                             .get_last_messages(conversation_id, 0, 1)?
-                            .last()
-                            .expect("Failed to get last message")
-                            .clone();
+                            .pop()
+                            .ok_or_else(|| AppError::not_found("Message to summarize"))?;
 
                         last_message.summary = Some(summary.clone());
                         self.conversations_service
@@ -181,183 +308,719 @@ impl MessagingService {
 
                     ComplitationStatus::Error(err) => {
                         log::error!("[SUMMARY]: {err:?}");
-                        break;
+                        return Err(err);
                     }
+
+                    ComplitationStatus::Cancelled => return Err(AppError::Cancelled),
+                    ComplitationStatus::ToolCalls(_) => break,
                 }
             }
         };
 
-        Err("Failed to summarize conversation".to_string())
+        Err(AppError::parse("The summary model did not finish its answer"))
     }
 
+    /// Generation stops as soon as `cancel` is cancelled, the stream then
+    /// ends with [`ComplitationStatus::Cancelled`]. Failures before the
+    /// request is sent come out of the stream as [`MessagingEvent::Error`].
+    ///
     /// # Errors
-    /// # Panics
     pub fn generate_message(
         &self,
         conversation_id: ConversationNodeID,
-    ) -> Result<Pin<Box<dyn Stream<Item = MessagingEvent> + Send>>, String> {
+        cancel: CancellationToken,
+    ) -> AppResult<Pin<Box<dyn Stream<Item = MessagingEvent> + Send>>> {
         let self_cp = self.clone();
         let stream = Box::pin(async_fn_stream::fn_stream(async move |output| {
-            let mut messages = self_cp
-                .get_messages(conversation_id)
-                .expect("Failed to get messages");
+            let request = match self_cp.prepare_completion(conversation_id, &cancel).await {
+                Ok(request) => request,
+                Err(e) => {
+                    log::error!("Failed to prepare completion: {e}");
+                    self_cp.report_error(conversation_id, "Failed to generate message", &e);
+                    output.emit(MessagingEvent::Error(e)).await;
+                    return;
+                }
+            };
 
-            let mut user_message = messages.last().expect("Failed to get last message").clone();
-            messages.remove(messages.len() - 1);            
+            self_cp
+                .complete_with_tools(conversation_id, request, cancel, &output)
+                .await;
+        }));
 
-            assert!(
-                !(user_message.role != types::dto::RoleType::User),
-                "Last message is not a user message"
-            );
+        Ok(stream)
+    }
 
-            let last_summary = self_cp
-                .conversations_service
-                .get_last_summary(conversation_id)
-                .unwrap_or_default();
+    async fn prepare_completion(
+        &self,
+        conversation_id: ConversationNodeID,
+        cancel: &CancellationToken,
+    ) -> AppResult<CompletionRequest> {
+        let conversation = self
+            .conversations_service
+            .get_conversation(conversation_id)?;
 
-            if let Some(last_summary) = last_summary {
-                let last_summary = last_summary.summary.unwrap_or_default();
-                messages.push(MessageDTO {
-                    content: format!("<LAST_SUMMARY>{last_summary}</LAST_SUMMARY>"),
-                    role: types::dto::RoleType::System,
-                    ..Default::default()
-                });
+        let lm_model = resolve_chat_model(&self.providers_db, &conversation)?;
+        let preset = self.conversations_service.get_preset(conversation_id);
+
+        let mut history = self.get_messages(&conversation)?;
+
+        let mut user_message = history
+            .pop()
+            .ok_or_else(|| AppError::not_found("Message to answer"))?;
+
+        if user_message.role != RoleType::User {
+            return Err(AppError::validation("The last message is not a user message"));
+        }
+
+        // The question, prompts, summary and retrieved chunks go first, history gets what is left
+        let mut context =
+            Self::context_builder(&conversation, &lm_model.model_name, preset.as_ref());
+        context.reserve_message(&user_message);
+
+        let summary = self
+            .summary_message(conversation_id)
+            .filter(|summary| context.try_reserve_message(summary));
+
+        let rag = self
+            .rag_process(&conversation, &history, &mut user_message, &mut context, cancel)
+            .await?;
+
+        let mut messages = context.fit_history(history);
+        messages.extend(summary);
+        messages.extend(rag);
+
+        if !conversation.prompt.is_empty() {
+            messages.push(MessageDTO {
+                content: conversation.prompt.clone(),
+                role: types::dto::RoleType::System,
+                ..Default::default()
+            });
+        }
+
+        messages.push(user_message);
+
+        let tools = if conversation.tools_enabled {
+            self.tools.definitions()
+        } else {
+            vec![]
+        };
+
+        Ok(CompletionRequest {
+            lm_model,
+            messages,
+            preset,
+            tools,
+        })
+    }
+
+    /// What of the conversation goes into the next request. Retrieved chunks
+    /// are left out, they depend on the question.
+    ///
+    /// # Errors
+    pub fn context_usage(&self, conversation_id: ConversationNodeID) -> AppResult<ContextUsage> {
+        let conversation = self
+            .conversations_service
+            .get_conversation(conversation_id)?;
+
+        let lm_model = resolve_chat_model(&self.providers_db, &conversation)?;
+        let preset = self.conversations_service.get_preset(conversation_id);
+
+        let mut context =
+            Self::context_builder(&conversation, &lm_model.model_name, preset.as_ref());
+
+        if let Some(summary) = self.summary_message(conversation_id) {
+            context.try_reserve_message(&summary);
+        }
+
+        let history = context.fit_history(self.get_messages(&conversation)?);
+
+        Ok(context.usage(history.len()))
+    }
+
+    /// Streams completions to `output`, running requested tools and feeding
+    /// their results back until the model answers with text.
+    async fn complete_with_tools(
+        &self,
+        conversation_id: ConversationNodeID,
+        request: CompletionRequest,
+        cancel: CancellationToken,
+        output: &async_fn_stream::StreamEmitter<MessagingEvent>,
+    ) {
+        let CompletionRequest {
+            lm_model,
+            mut messages,
+            preset,
+            mut tools,
+        } = request;
+
+        for iteration in 0..MAX_TOOL_ITERATIONS {
+            if iteration == MAX_TOOL_ITERATIONS - 1 {
+                tools.clear();
             }
 
+            let mut latency = Latency::start();
+            let mut completions = match self.lm_api.chat_completions(
+                lm_model.clone(),
+                messages.clone(),
+                preset.clone(),
+                tools.clone(),
+                cancel.clone(),
+            ) {
+                Ok(completions) => completions,
+                Err(e) => {
+                    self.report_error(conversation_id, "Failed to generate message", &e);
+                    output.emit(MessagingEvent::Error(e)).await;
+                    return;
+                }
+            };
+
+            let mut content = String::new();
+            let mut reasoning = String::new();
+            let mut tool_calls = vec![];
+            let mut info = None;
+            // A failed or cancelled answer is not followed by its tools
+            let mut interrupted = false;
 
-            self_cp
-                .rag_process(conversation_id, &mut user_message, &mut messages)
-                .await;
+            while let Some(completion) = completions.next().await {
+                match completion {
+                    ComplitationStatus::Message(ref chunk) => {
+                        latency.received(chunk);
+                        content.push_str(&chunk.content);
+                        reasoning.push_str(&chunk.reasoning_content);
+                    }
+                    ComplitationStatus::ToolCalls(ref calls) => {
+                        tool_calls.clone_from(calls);
+                    }
+                    ComplitationStatus::Error(ref e) => {
+                        interrupted = true;
+                        self.report_error(conversation_id, "Failed to generate message", e);
+                    }
+                    // The answer goes on after the tools, so it is not over yet
+                    ComplitationStatus::End(ref end_info) if !tool_calls.is_empty() => {
+                        info = Some(end_info.clone());
+                        continue;
+                    }
+                    ComplitationStatus::End(ref end_info) => {
+                        let mut generation = Self::generation(
+                            &lm_model,
+                            preset.as_ref(),
+                            &messages,
+                            &content,
+                            &reasoning,
+                            Some(end_info),
+                        );
+                        latency.fill(&mut generation);
+                        self.record_usage(conversation_id, &generation);
+                        output.emit(MessagingEvent::Generated(generation)).await;
+                    }
+                    ComplitationStatus::Cancelled => {
+                        interrupted = true;
+                        let mut generation = Self::generation(
+                            &lm_model,
+                            preset.as_ref(),
+                            &messages,
+                            &content,
+                            &reasoning,
+                            None,
+                        );
+                        latency.fill(&mut generation);
+                        self.record_usage(conversation_id, &generation);
+                        output.emit(MessagingEvent::Generated(generation)).await;
+                    }
+                    ComplitationStatus::Start => {}
+                }
+
+                output
+                    .emit(MessagingEvent::ReceiveMessage(completion))
+                    .await;
+            }
+
+            if tool_calls.is_empty() || interrupted {
+                return;
+            }
+
+            let mut generation = Self::generation(
+                &lm_model,
+                preset.as_ref(),
+                &messages,
+                &content,
+                &reasoning,
+                info.as_ref(),
+            );
+            latency.fill(&mut generation);
+            self.record_usage(conversation_id, &generation);
+
+            let assistant_message = MessageDTO {
+                conversation_id,
+                content,
+                reasoning: if reasoning.trim().is_empty() {
+                    None
+                } else {
+                    Some(reasoning.trim().to_string())
+                },
+                role: RoleType::Assistant,
+                tool_calls: tool_calls.clone(),
+                generation: Some(generation),
+                ..Default::default()
+            };
 
-            let conversation = self_cp
+            if let Err(e) = self
                 .conversations_service
-                .get_conversation(conversation_id)
-                .expect("Failed to get conversation");
-
-            let conversation_prompt = conversation.prompt.clone();
-            if !conversation_prompt.is_empty() {
-                messages.push(MessageDTO {
-                    content: conversation_prompt,
-                    role: types::dto::RoleType::System,
-                    ..Default::default()
-                });
+                .write_message_dto(&assistant_message)
+            {
+                self.report_error(conversation_id, "Failed to save the answer", &e);
+                output.emit(MessagingEvent::Error(e)).await;
+                return;
             }
+            messages.push(assistant_message);
 
-            if let Some(provider_id) = conversation.provider {
-                let provider = self_cp.providers_db.get_provider(provider_id);
+            // The stream ended before it saw the cancellation. The turn is kept,
+            // and every call still gets a result or the next request is refused.
+            let cancelled = cancel.is_cancelled();
 
-                let model = conversation.model.unwrap_or_default();
-                let model = if model.is_empty() {
-                    provider.map(|p| p.default_model).unwrap_or_default()
+            for tool_call in tool_calls {
+                let content = if cancelled {
+                    "The user cancelled the answer before this tool ran.".to_string()
                 } else {
-                    model
+                    self.tools.call(&tool_call)
                 };
 
-                if !model.is_empty() {
-                    let lm_model = LmModel {
-                        model_name: model,
-                        provider: self_cp.providers_db.get_provider(provider_id),
-                    };
-
-                    let preset = self_cp.conversations_service.get_preset(conversation_id);
-
-                    if let Ok(ref mut completions) =
-                        self_cp.lm_api.chat_completions(lm_model, messages, preset, user_message)
-                    {
-                        while let Some(completion) = completions.next().await {
-                            output
-                                .emit(MessagingEvent::ReceiveMessage(completion))
-                                .await;
-                        }
-                    } else {
-                        output
-                            .emit(MessagingEvent::Error(
-                                "Failed to get completions".to_string(),
-                            ))
-                            .await;
-                    }
+                let tool_message = MessageDTO {
+                    conversation_id,
+                    content,
+                    role: RoleType::Tool,
+                    tool_call_id: Some(tool_call.id),
+                    ..Default::default()
+                };
 
+                if let Err(e) = self.conversations_service.write_message_dto(&tool_message) {
+                    self.report_error(conversation_id, "Failed to save the tool result", &e);
+                    output.emit(MessagingEvent::Error(e)).await;
                     return;
                 }
+                messages.push(tool_message);
             }
 
-            output
-                .emit(MessagingEvent::Error(
-                    "No provider or model found".to_string(),
-                ))
-                .await;
-        }));
+            if cancelled {
+                output
+                    .emit(MessagingEvent::ReceiveMessage(ComplitationStatus::Cancelled))
+                    .await;
+                return;
+            }
+        }
+    }
 
-        Ok(stream)
+    /// Usage the provider did not report is counted with a tokenizer.
+    fn generation(
+        lm_model: &LmModel,
+        preset: Option<&PresetDTO>,
+        messages: &[MessageDTO],
+        content: &str,
+        reasoning: &str,
+        info: Option<&CompletionInfo>,
+    ) -> GenerationDTO {
+        let info = info.cloned().unwrap_or_default();
+        let tokenizer = ContextBuilder::new(&lm_model.model_name, 0, 0);
+
+        let prompt_tokens = info.prompt_tokens.unwrap_or_else(|| {
+            let preset_prompt = preset.map_or(0, |preset| tokenizer.count(&preset.prompt));
+
+            preset_prompt
+                + messages
+                    .iter()
+                    .map(|message| tokenizer.message_tokens(message))
+                    .sum::<usize>()
+        });
+
+        GenerationDTO {
+            provider_id: lm_model.provider.as_ref().map(|provider| provider.id),
+            model: lm_model.model_name.clone(),
+            preset: preset.cloned(),
+            prompt_tokens,
+            completion_tokens: info
+                .completion_tokens
+                .unwrap_or_else(|| tokenizer.count(content) + tokenizer.count(reasoning)),
+            time_to_first_token: None,
+            duration: None,
+            finish_reason: info.finish_reason,
+        }
     }
 
-    fn get_messages(&self, conversation_id: ConversationNodeID) -> Result<Vec<MessageDTO>, String> {
-        let conversation = self
+    /// Usage is kept apart from the messages so that deleting them does not
+    /// change the totals. A failure here must not break the answer.
+    fn record_usage(&self, conversation_id: ConversationNodeID, generation: &GenerationDTO) {
+        if let Err(e) = self.usage_db.record(conversation_id, generation) {
+            log::error!("Failed to record usage: {e}");
+        }
+    }
+
+    /// Tells the user about a failure and, when it is a setting, where to fix it.
+    fn report_error(&self, conversation_id: ConversationNodeID, title: &str, error: &AppError) {
+        let action = match error {
+            AppError::Validation(_) | AppError::NotFound(_) | AppError::Http(404, _) => {
+                Some(NotificationAction::OpenModelPicker(conversation_id))
+            }
+            AppError::Http(401 | 403, _) => Some(NotificationAction::OpenProviders),
+            _ => None,
+        };
+
+        self.event_system.clone().notify_error(title, error, action);
+    }
+
+    /// Budget of the request with the prompts already taken out.
+    fn context_builder(
+        conversation: &ConversationNodeDTO,
+        model_name: &str,
+        preset: Option<&PresetDTO>,
+    ) -> ContextBuilder {
+        let preset = preset.cloned().unwrap_or_default();
+
+        let mut context =
+            ContextBuilder::new(model_name, conversation.context_size, preset.max_tokens as usize);
+        context.reserve(&preset.prompt);
+        context.reserve(&conversation.prompt);
+
+        if !context.is_limited() {
+            log::warn!(
+                "Context size of {model_name} is unknown, set it in the chat settings \
+                to fit the history into it"
+            );
+        }
+
+        context
+    }
+
+    fn summary_message(&self, conversation_id: ConversationNodeID) -> Option<MessageDTO> {
+        let last_summary = self
             .conversations_service
-            .get_conversation(conversation_id)?;
+            .get_last_summary(conversation_id)
+            .unwrap_or_default()?
+            .summary
+            .unwrap_or_default();
+
+        Some(MessageDTO {
+            content: format!("<LAST_SUMMARY>{last_summary}</LAST_SUMMARY>"),
+            role: types::dto::RoleType::System,
+            ..Default::default()
+        })
+    }
 
+    /// At most `max_messages` last messages, oldest first.
+    fn get_messages(&self, conversation: &ConversationNodeDTO) -> AppResult<Vec<MessageDTO>> {
         let max_messages = conversation.max_messages;
         if max_messages > 0 {
             self.conversations_service
-                .get_last_messages(conversation_id, 0, max_messages)
+                .get_last_messages(conversation.id, 0, max_messages)
         } else {
             Ok(vec![])
         }
     }
 
+    /// Retrieved chunks that fit into `context`, best ranked first.
     async fn rag_process(
         &self,
-        conversation_id: ConversationNodeID,
+        conversation: &ConversationNodeDTO,
+        history: &[MessageDTO],
         user_message: &mut MessageDTO,
-        messages: &mut Vec<MessageDTO>,
-    ) {
-        let conversation = self
-            .conversations_service
-            .get_conversation(conversation_id)
-            .expect("Failed to get conversation");
-
-        let embedding_model = LmModel {
-            model_name: conversation.embedding_model.unwrap_or_default(),
-            provider: conversation
-                .embedding_provider
-                .and_then(|p| self.providers_db.get_provider(p)),
-        };
+        context: &mut ContextBuilder,
+        cancel: &CancellationToken,
+    ) -> AppResult<Option<MessageDTO>> {
+        let conversation_id = conversation.id;
 
-        if embedding_model.provider.is_none() || embedding_model.model_name.is_empty() {
-            return;
+        // Rewriting the query costs requests to the model, only worth it with something to search
+        if !self.vector_service.has_sources(conversation_id)? {
+            return Ok(None);
         }
 
-        let user_message_for_search = user_message.clone();
-        let chunks = self
+        let queries = self.rag_queries(conversation, history, user_message, cancel).await;
+
+        let found = self
             .vector_service
-            .search(
-                embedding_model,
-                user_message_for_search.content,
-                conversation_id,
-            )
-            .await;
+            .search(queries.clone(), conversation_id)
+            .await?;
 
-        user_message.chunks = chunks;
-        self.conversations_service.update_message_dto(user_message).expect("Failed to update message");
+        let mut used = vec![];
+        let mut chunks = vec![];
+        for chunk in found {
+            let source = chunk.source(conversation_id);
 
-        let chunks: Vec<Option<ChunkRagDTO>> = user_message.chunks.iter().map(|chunk| {
-            self.vector_service.get_chunk(conversation_id, chunk.chunk_id, chunk.dimension, &chunk.embedding_model)
-                .expect("Failed to get chunk")
-        }).collect();
+            let Some(chunk_dto) = self.vector_service.get_chunk(
+                source,
+                chunk.chunk_id,
+                chunk.dimension,
+                &chunk.embedding_model,
+            )?
+            else {
+                continue;
+            };
 
-        let chunks = chunks.iter().filter(|chunk| {
-            chunk.is_some()
-        });
+            let location = self
+                .vector_service
+                .get_file(source, chunk_dto.file_id)?
+                .map(|file| chunk_dto.location(&file.file_name))
+                .unwrap_or_default();
+
+            // Numbered as the message shows them, the answer cites them with `[n]`
+            let text = format!(
+                "<chunk id=\"{}\" source=\"{location}\">{}</chunk>",
+                chunks.len() + 1,
+                chunk_dto.chunk
+            );
+            if !context.try_reserve(&text) {
+                break;
+            }
 
-        let chunks: String = chunks.map(|chunk| {
-            let chunk = chunk.as_ref().unwrap();
-            format!("<chunk>{}</chunk>", chunk.chunk)
-        }).collect::<Vec<_>>().join("\n");
+            chunks.push(text);
+            used.push(chunk);
+        }
 
-        messages.push(MessageDTO {
-            content: format!("<retrieved_context>{}</retrieved_context>", chunks),
+        user_message.chunks = used;
+        user_message.rag_queries = if conversation.rag_query_rewrite {
+            queries
+        } else {
+            vec![]
+        };
+        self.conversations_service.update_message_dto(user_message)?;
+
+        if chunks.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(MessageDTO {
+            content: format!(
+                "{CITATION_PROMPT}\n<retrieved_context>{}</retrieved_context>",
+                chunks.join("\n")
+            ),
             role: types::dto::RoleType::System,
             ..Default::default()
-        });
+        }))
+    }
+
+    /// What the files are searched with: the message itself, or with query
+    /// rewriting a standalone query, the sub-queries and a hypothetical answer.
+    /// The message is searched as it is when the rewriting model fails.
+    async fn rag_queries(
+        &self,
+        conversation: &ConversationNodeDTO,
+        history: &[MessageDTO],
+        user_message: &MessageDTO,
+        cancel: &CancellationToken,
+    ) -> Vec<String> {
+        if !conversation.rag_query_rewrite {
+            return vec![user_message.content.clone()];
+        }
+
+        let lm_model = match self.rewrite_model(conversation) {
+            Ok(lm_model) => lm_model,
+            Err(e) => {
+                log::warn!("No model to rewrite the query, searching with the message: {e}");
+                return vec![user_message.content.clone()];
+            }
+        };
+
+        let mut queries = self
+            .rewrite_query(conversation, &lm_model, history, user_message, cancel)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to rewrite the query, searching with the message: {e}");
+                vec![]
+            });
+
+        if queries.is_empty() {
+            queries.push(user_message.content.clone());
+        }
+
+        if conversation.rag_hyde {
+            match self
+                .complete_text(
+                    conversation.id,
+                    &lm_model,
+                    queries[0].clone(),
+                    HYDE_PROMPT.to_string(),
+                    cancel,
+                )
+                .await
+            {
+                Ok(answer) if !answer.trim().is_empty() => queries.push(answer.trim().to_string()),
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to write a hypothetical answer: {e}"),
+            }
+        }
+
+        queries
+    }
+
+    /// Standalone version of the message first, then its sub-queries.
+    async fn rewrite_query(
+        &self,
+        conversation: &ConversationNodeDTO,
+        lm_model: &LmModel,
+        history: &[MessageDTO],
+        user_message: &MessageDTO,
+        cancel: &CancellationToken,
+    ) -> AppResult<Vec<String>> {
+        let recent: Vec<&MessageDTO> = history
+            .iter()
+            .filter(|message| {
+                matches!(message.role, RoleType::User | RoleType::Assistant) && !message.content.is_empty()
+            })
+            .rev()
+            .take(REWRITE_HISTORY_MESSAGES)
+            .collect();
+
+        let mut transcript = String::new();
+        for message in recent.into_iter().rev() {
+            let _ = writeln!(
+                transcript,
+                "<{role}>{content}</{role}>",
+                role = message.role.to_string(),
+                content = message.content
+            );
+        }
+        let _ = write!(transcript, "<last_message>{}</last_message>", user_message.content);
+
+        let sub_queries = conversation.rag_sub_queries;
+        let prompt = if sub_queries == 0 {
+            "Rewrite the last message into a standalone search query, resolving \
+            what it refers to in the conversation. Reply only with the query."
+                .to_string()
+        } else {
+            format!(
+                "Rewrite the last message into a standalone search query, resolving \
+                what it refers to in the conversation. Put it on the first line, then up to \
+                {sub_queries} shorter queries for separate parts of it, one per line. \
+                Reply only with the queries."
+            )
+        };
+
+        let answer = self
+            .complete_text(conversation.id, lm_model, transcript, prompt, cancel)
+            .await?;
+
+        let mut queries: Vec<String> = vec![];
+        for line in answer.lines() {
+            let query = strip_list_marker(line).trim_matches('"').trim();
+
+            if !query.is_empty() && !queries.iter().any(|known| known == query) {
+                queries.push(query.to_string());
+            }
+        }
+
+        queries.truncate(sub_queries + 1);
+
+        Ok(queries)
+    }
+
+    /// The rewrite model of the chat, or the chat model when none is selected.
+    fn rewrite_model(&self, conversation: &ConversationNodeDTO) -> AppResult<LmModel> {
+        let rewrite_model = conversation
+            .rewrite_model
+            .clone()
+            .filter(|model| !model.is_empty())
+            .zip(conversation.rewrite_provider.and_then(|p_id| self.providers_db.get_provider(p_id)));
+
+        match rewrite_model {
+            Some((model_name, provider)) => Ok(LmModel {
+                model_name,
+                provider: Some(provider),
+            }),
+            None => resolve_chat_model(&self.providers_db, conversation),
+        }
+    }
+
+    /// Answer to a single message outside of the chat history, its usage is
+    /// counted for the chat.
+    async fn complete_text(
+        &self,
+        conversation_id: ConversationNodeID,
+        lm_model: &LmModel,
+        content: String,
+        prompt: String,
+        cancel: &CancellationToken,
+    ) -> AppResult<String> {
+        let preset = PresetDTO {
+            temperature: 0.0,
+            prompt,
+            ..Default::default()
+        };
+
+        let request = vec![MessageDTO {
+            content,
+            role: RoleType::User,
+            ..Default::default()
+        }];
+
+        let mut stream = self.lm_api.chat_completions(
+            lm_model.clone(),
+            request.clone(),
+            Some(preset.clone()),
+            vec![],
+            cancel.clone(),
+        )?;
+
+        let mut answer = String::new();
+        while let Some(completion) = stream.next().await {
+            match completion {
+                ComplitationStatus::Message(message) => answer.push_str(&message.content),
+                ComplitationStatus::End(info) => {
+                    let generation =
+                        Self::generation(lm_model, Some(&preset), &request, &answer, "", Some(&info));
+                    self.record_usage(conversation_id, &generation);
+                    break;
+                }
+                ComplitationStatus::Error(e) => return Err(e),
+                ComplitationStatus::Cancelled => break,
+                ComplitationStatus::Start | ComplitationStatus::ToolCalls(_) => {}
+            }
+        }
+
+        Ok(answer)
     }
 }
+
+/// Drops the `-`, `*` or `1.` a model may put before the lines of a list.
+fn strip_list_marker(line: &str) -> &str {
+    let line = line.trim();
+
+    if let Some(rest) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return rest.trim();
+    }
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        let rest = &line[digits..];
+
+        if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return rest.trim();
+        }
+    }
+
+    line
+}
+
+/// The model of the chat, or the default model of its provider.
+pub fn resolve_chat_model(
+    providers_db: &ProvidersDatabase,
+    conversation: &ConversationNodeDTO,
+) -> AppResult<LmModel> {
+    let provider = conversation
+        .provider
+        .and_then(|provider_id| providers_db.get_provider(provider_id))
+        .ok_or_else(|| AppError::validation("Select a provider for this chat"))?;
+
+    let model = conversation.model.clone().unwrap_or_default();
+    let model = if model.is_empty() {
+        provider.default_model.clone()
+    } else {
+        model
+    };
+
+    if model.is_empty() {
+        return Err(AppError::validation("Select a model for this chat"));
+    }
+
+    Ok(LmModel {
+        model_name: model,
+        provider: Some(provider),
+    })
+}
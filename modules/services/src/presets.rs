@@ -1,5 +1,8 @@
 use database::{DatabaseConnection, databases::PresetsDatabase};
-use types::dto::PresetDTO;
+use types::{
+    dto::PresetDTO,
+    error::{AppError, AppResult},
+};
 use utils::event_system::{Event, EventSystem};
 
 #[derive(Debug, Clone)]
@@ -9,7 +12,8 @@ pub struct PresetsService {
 }
 
 impl PresetsService {
-    pub fn new(connection: DatabaseConnection, event_system: EventSystem) -> Self {
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection, event_system: EventSystem) -> Self {
         Self {
             presets_db: PresetsDatabase::new(connection),
             event_system,
@@ -17,46 +21,47 @@ impl PresetsService {
     }
 
     /// # Errors
-    pub fn get_presets(&self) -> Result<Vec<PresetDTO>, String> {
-        self.presets_db.get_all_presets()
+    pub fn get_presets(&self) -> AppResult<Vec<PresetDTO>> {
+        self.presets_db.get_all_presets().map_err(AppError::database)
     }
 
     /// # Errors
-    pub fn add_preset(&self, dto: &PresetDTO) -> Result<PresetDTO, String> {
+    pub fn add_preset(&self, dto: &PresetDTO) -> AppResult<PresetDTO> {
         let result = self.presets_db
-            .add_preset(&dto.name, &dto.prompt, dto.temperature, dto.max_tokens);
+            .add_preset(&dto.name, &dto.prompt, dto.temperature, dto.max_tokens)
+            .map_err(AppError::database);
         
         self.event_system.clone().dispatch(Event::UpdatePresets(
-            self.presets_db.get_all_presets()?,
+            self.get_presets()?,
         ));
 
         result
     }
 
     /// # Errors
-    pub fn delete_preset(&self, id: i64) -> Result<(), String> {
-        let result = self.presets_db.delete_preset(id);
+    pub fn delete_preset(&self, id: i64) -> AppResult<()> {
+        let result = self.presets_db.delete_preset(id).map_err(AppError::database);
 
         self.event_system.clone().dispatch(Event::UpdatePresets(
-            self.presets_db.get_all_presets()?,
+            self.get_presets()?,
         ));
 
         result
     }
 
     /// # Errors
-    pub fn update_preset(&self, dto: &PresetDTO) -> Result<PresetDTO, String> {
-        let result = self.presets_db.update_preset(dto);
+    pub fn update_preset(&self, dto: &PresetDTO) -> AppResult<PresetDTO> {
+        let result = self.presets_db.update_preset(dto).map_err(AppError::database);
         
         self.event_system.clone().dispatch(Event::UpdatePresets(
-            self.presets_db.get_all_presets()?,
+            self.get_presets()?,
         ));
 
         result
     }
 
     /// # Errors
-    pub fn get_preset(&self, id: i64) -> Result<PresetDTO, String> {
-        self.presets_db.get_preset(id)
+    pub fn get_preset(&self, id: i64) -> AppResult<PresetDTO> {
+        self.presets_db.get_preset(id).map_err(AppError::database)
     }
 }
@@ -0,0 +1,291 @@
+use serde_json::json;
+use types::dto::ToolDefinitionDTO;
+
+use super::Tool;
+
+/// Longer expressions are refused, the model writes them and may loop.
+const MAX_EXPRESSION_LENGTH: usize = 1000;
+/// Nesting of parentheses, signs and powers, every level is a recursive call.
+const MAX_DEPTH: usize = 64;
+
+/// Evaluates arithmetic expressions: `+ - * / % ^`, parentheses,
+/// `pi`, `e` and the functions `sqrt abs ln log exp sin cos tan round floor ceil`.
+#[derive(Debug, Clone, Copy)]
+pub struct Calculator;
+
+impl Tool for Calculator {
+    fn definition(&self) -> ToolDefinitionDTO {
+        ToolDefinitionDTO {
+            name: "calculator".to_string(),
+            description: "Evaluates an arithmetic expression, e.g. `2 * (3 + 4) ^ 2 / sqrt(16)`."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "expression": {
+                        "type": "string",
+                        "description": "Expression to evaluate",
+                    }
+                },
+                "required": ["expression"],
+            }),
+        }
+    }
+
+    fn call(&self, arguments: &serde_json::Value) -> Result<String, String> {
+        let expression = arguments
+            .get("expression")
+            .and_then(serde_json::Value::as_str)
+            .ok_or("missing `expression` argument")?;
+
+        if expression.chars().count() > MAX_EXPRESSION_LENGTH {
+            return Err(format!(
+                "expression is longer than {MAX_EXPRESSION_LENGTH} characters"
+            ));
+        }
+
+        let mut parser = Parser {
+            chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+            position: 0,
+            depth: 0,
+        };
+
+        let value = parser.expression()?;
+
+        if parser.position < parser.chars.len() {
+            return Err(format!(
+                "unexpected `{}` at position {}",
+                parser.chars[parser.position], parser.position
+            ));
+        }
+
+        if !value.is_finite() {
+            return Err("result is not a finite number".to_string());
+        }
+
+        Ok(value.to_string())
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            return true;
+        }
+
+        false
+    }
+
+    // expression = term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // term = unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                value %= self.unary()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // unary = ('-' | '+') unary | power
+    // Every nested expression goes through here, so its depth is checked here
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("expression is nested deeper than {MAX_DEPTH} levels"));
+        }
+
+        self.depth += 1;
+        let value = self.signed();
+        self.depth -= 1;
+
+        value
+    }
+
+    fn signed(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            return Ok(-self.unary()?);
+        }
+
+        if self.eat('+') {
+            return self.unary();
+        }
+
+        self.power()
+    }
+
+    // power = atom ('^' unary)?, so `-2^2` is `-(2^2)` and `2^-1` works
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.atom()?;
+
+        if self.eat('^') {
+            return Ok(base.powf(self.unary()?));
+        }
+
+        Ok(base)
+    }
+
+    // atom = number | '(' expression ')' | name | name '(' expression ')'
+    fn atom(&mut self) -> Result<f64, String> {
+        if self.eat('(') {
+            let value = self.expression()?;
+            if !self.eat(')') {
+                return Err("missing `)`".to_string());
+            }
+            return Ok(value);
+        }
+
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.name(),
+            Some(c) => Err(format!("unexpected `{c}` at position {}", self.position)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || c == '.')
+        {
+            self.position += 1;
+        }
+
+        let literal: String = self.chars[start..self.position].iter().collect();
+        literal
+            .parse()
+            .map_err(|_| format!("invalid number `{literal}`"))
+    }
+
+    fn name(&mut self) -> Result<f64, String> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.position += 1;
+        }
+
+        let name: String = self.chars[start..self.position].iter().collect();
+
+        match name.as_str() {
+            "pi" => return Ok(std::f64::consts::PI),
+            "e" => return Ok(std::f64::consts::E),
+            _ => {}
+        }
+
+        if !self.eat('(') {
+            return Err(format!("unknown constant `{name}`"));
+        }
+
+        let argument = self.expression()?;
+
+        if !self.eat(')') {
+            return Err("missing `)`".to_string());
+        }
+
+        Ok(match name.as_str() {
+            "sqrt" => argument.sqrt(),
+            "abs" => argument.abs(),
+            "ln" => argument.ln(),
+            "log" => argument.log10(),
+            "exp" => argument.exp(),
+            "sin" => argument.sin(),
+            "cos" => argument.cos(),
+            "tan" => argument.tan(),
+            "round" => argument.round(),
+            "floor" => argument.floor(),
+            "ceil" => argument.ceil(),
+            _ => return Err(format!("unknown function `{name}`")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Calculator, MAX_DEPTH, MAX_EXPRESSION_LENGTH};
+    use crate::tools::Tool;
+
+    fn evaluate(expression: &str) -> Result<String, String> {
+        Calculator.call(&json!({ "expression": expression }))
+    }
+
+    #[test]
+    fn follows_operator_precedence() {
+        assert_eq!(evaluate("2 + 3 * 4"), Ok("14".to_string()));
+        assert_eq!(evaluate("(2 + 3) * 4"), Ok("20".to_string()));
+        assert_eq!(evaluate("2 * 3 ^ 2"), Ok("18".to_string()));
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), Ok("512".to_string()));
+        assert_eq!(evaluate("10 - 4 - 3"), Ok("3".to_string()));
+        assert_eq!(evaluate("7 % 4 * 2"), Ok("6".to_string()));
+    }
+
+    #[test]
+    fn applies_unary_minus() {
+        assert_eq!(evaluate("-2 ^ 2"), Ok("-4".to_string()));
+        assert_eq!(evaluate("(-2) ^ 2"), Ok("4".to_string()));
+        assert_eq!(evaluate("2 ^ -1"), Ok("0.5".to_string()));
+        assert_eq!(evaluate("3 - -2"), Ok("5".to_string()));
+        assert_eq!(evaluate("--+-1"), Ok("-1".to_string()));
+    }
+
+    #[test]
+    fn refuses_division_by_zero() {
+        assert_eq!(evaluate("1 / 0"), Err("division by zero".to_string()));
+        assert_eq!(evaluate("1 / (2 - 2)"), Err("division by zero".to_string()));
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert_eq!(evaluate(&nested(MAX_DEPTH - 1)), Ok("1".to_string()));
+        assert!(evaluate(&nested(MAX_DEPTH)).is_err());
+        assert!(evaluate(&"-".repeat(MAX_DEPTH * 2)).is_err());
+        assert!(evaluate(&("2^".repeat(MAX_DEPTH) + "2")).is_err());
+    }
+
+    #[test]
+    fn limits_expression_length() {
+        let longest = "1+".repeat(MAX_EXPRESSION_LENGTH / 2 - 1) + "1";
+        let too_long = "1+".repeat(MAX_EXPRESSION_LENGTH / 2) + "1";
+
+        assert!(evaluate(&longest).is_ok());
+        assert!(evaluate(&too_long).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+use serde_json::json;
+use types::dto::ToolDefinitionDTO;
+
+use super::Tool;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CurrentTime;
+
+impl Tool for CurrentTime {
+    fn definition(&self) -> ToolDefinitionDTO {
+        ToolDefinitionDTO {
+            name: "current_time".to_string(),
+            description: "Returns the current local date and time in RFC 3339 format.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {},
+            }),
+        }
+    }
+
+    fn call(&self, _arguments: &serde_json::Value) -> Result<String, String> {
+        Ok(chrono::Local::now().to_rfc3339())
+    }
+}
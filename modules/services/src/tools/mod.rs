@@ -0,0 +1,84 @@
+mod calculator;
+mod current_time;
+mod read_file;
+
+use std::{collections::BTreeMap, fmt::Debug, path::PathBuf, sync::Arc};
+
+use types::dto::{ToolCallDTO, ToolDefinitionDTO};
+
+pub use calculator::Calculator;
+pub use current_time::CurrentTime;
+pub use read_file::ReadFile;
+
+/// Local function the model can call during a completion.
+pub trait Tool: Send + Sync {
+    fn definition(&self) -> ToolDefinitionDTO;
+
+    /// # Errors
+    /// Returns a message for the model describing what went wrong.
+    fn call(&self, arguments: &serde_json::Value) -> Result<String, String>;
+}
+
+#[derive(Clone)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Arc<dyn Tool>>,
+}
+
+impl Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field("tools", &self.tools.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl ToolRegistry {
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            tools: BTreeMap::new(),
+        }
+    }
+
+    /// Registry with the built-in tools, `files_root` is the only directory
+    /// the file reader has access to.
+    #[must_use]
+    pub fn with_builtins(files_root: PathBuf) -> Self {
+        let mut registry = Self::empty();
+
+        registry.register(Calculator);
+        registry.register(CurrentTime);
+        registry.register(ReadFile::new(files_root));
+
+        registry
+    }
+
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        self.tools.insert(tool.definition().name, Arc::new(tool));
+    }
+
+    #[must_use]
+    pub fn definitions(&self) -> Vec<ToolDefinitionDTO> {
+        self.tools.values().map(|tool| tool.definition()).collect()
+    }
+
+    /// Runs the call, failures are returned as text so the model can react to them.
+    #[must_use]
+    pub fn call(&self, tool_call: &ToolCallDTO) -> String {
+        let Some(tool) = self.tools.get(&tool_call.name) else {
+            return format!("Error: unknown tool `{}`", tool_call.name);
+        };
+
+        let arguments = if tool_call.arguments.trim().is_empty() {
+            serde_json::Value::Object(serde_json::Map::new())
+        } else {
+            match serde_json::from_str(&tool_call.arguments) {
+                Ok(arguments) => arguments,
+                Err(e) => return format!("Error: invalid arguments: {e}"),
+            }
+        };
+
+        tool.call(&arguments)
+            .unwrap_or_else(|e| format!("Error: {e}"))
+    }
+}
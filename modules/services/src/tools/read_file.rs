@@ -0,0 +1,97 @@
+use std::{fs, path::PathBuf};
+
+use serde_json::json;
+use types::dto::ToolDefinitionDTO;
+
+use super::Tool;
+
+const MAX_FILE_SIZE: u64 = 256 * 1024;
+
+/// Read-only access to the files inside a single directory.
+#[derive(Debug, Clone)]
+pub struct ReadFile {
+    root: PathBuf,
+}
+
+impl ReadFile {
+    #[must_use]
+    pub const fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let root = self
+            .root
+            .canonicalize()
+            .map_err(|e| format!("files directory is not available: {e}"))?;
+
+        let resolved = root
+            .join(path.trim_start_matches('/'))
+            .canonicalize()
+            .map_err(|_| format!("`{path}` not found"))?;
+
+        // Canonical paths have `..` and symlinks resolved, so this also stops escapes through them
+        if !resolved.starts_with(&root) {
+            return Err(format!("`{path}` is outside of the files directory"));
+        }
+
+        Ok(resolved)
+    }
+}
+
+impl Tool for ReadFile {
+    fn definition(&self) -> ToolDefinitionDTO {
+        ToolDefinitionDTO {
+            name: "read_file".to_string(),
+            description: "Reads a text file from the user's files directory. \
+                Pass a directory path (or `.`) to list its entries."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path relative to the files directory",
+                    }
+                },
+                "required": ["path"],
+            }),
+        }
+    }
+
+    fn call(&self, arguments: &serde_json::Value) -> Result<String, String> {
+        let path = arguments
+            .get("path")
+            .and_then(serde_json::Value::as_str)
+            .ok_or("missing `path` argument")?;
+
+        let resolved = self.resolve(path)?;
+
+        if resolved.is_dir() {
+            let mut entries = fs::read_dir(&resolved)
+                .map_err(|e| e.to_string())?
+                .filter_map(Result::ok)
+                .map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if entry.path().is_dir() {
+                        format!("{name}/")
+                    } else {
+                        name
+                    }
+                })
+                .collect::<Vec<_>>();
+            entries.sort();
+
+            return Ok(entries.join("\n"));
+        }
+
+        let size = fs::metadata(&resolved).map_err(|e| e.to_string())?.len();
+        if size > MAX_FILE_SIZE {
+            return Err(format!(
+                "`{path}` is too large ({size} bytes, limit is {MAX_FILE_SIZE})"
+            ));
+        }
+
+        fs::read_to_string(&resolved).map_err(|_| format!("`{path}` is not a text file"))
+    }
+}
@@ -0,0 +1,50 @@
+use database::{DatabaseConnection, databases::UsageDatabase};
+use types::{
+    dto::{ModelPriceDTO, ProviderID, UsageGrouping, UsageRowDTO},
+    error::{AppError, AppResult},
+};
+
+#[derive(Debug, Clone)]
+pub struct UsageService {
+    usage_db: UsageDatabase,
+}
+
+impl UsageService {
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            usage_db: UsageDatabase::new(connection),
+        }
+    }
+
+    /// `days` of `None` takes the whole history.
+    ///
+    /// # Errors
+    pub fn get_usage(
+        &self,
+        grouping: UsageGrouping,
+        days: Option<u32>,
+    ) -> AppResult<Vec<UsageRowDTO>> {
+        self.usage_db
+            .get_usage(grouping, days)
+            .map_err(AppError::database)
+    }
+
+    /// # Errors
+    pub fn get_model_prices(&self, provider_id: ProviderID) -> AppResult<Vec<ModelPriceDTO>> {
+        self.usage_db
+            .get_model_prices(provider_id)
+            .map_err(AppError::database)
+    }
+
+    /// # Errors
+    pub fn set_model_prices(
+        &self,
+        provider_id: ProviderID,
+        prices: &[ModelPriceDTO],
+    ) -> AppResult<()> {
+        self.usage_db
+            .set_model_prices(provider_id, prices)
+            .map_err(AppError::database)
+    }
+}
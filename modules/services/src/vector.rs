@@ -1,49 +1,138 @@
-use std::{fmt::Debug, fs, ops::Range, path::PathBuf, pin::Pin};
+use std::{collections::{HashMap, HashSet}, fmt::{Debug, Write}, ops::Range, path::{Path, PathBuf}, pin::Pin, sync::{Arc, Mutex}, time::Duration};
 
-use api::open_ai_api::OpenAiApi;
-use database::{databases::{ConversationDatabase, ProvidersDatabase, VectorDatabase}, DatabaseConnection};
+use api::{CancellationToken, ComplitationStatus, RerankResult, lm_backend::LmApi};
+use database::{databases::{ConversationDatabase, KnowledgeBasesDatabase, ProvidersDatabase, VectorDatabase}, DatabaseConnection};
 use iced::{futures::{channel::mpsc, SinkExt, Stream, StreamExt}, Subscription};
-use text_splitter::{ChunkConfig, MarkdownSplitter, TextSplitter};
+use ignore::{WalkBuilder, overrides::{Override, OverrideBuilder}};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher, event::ModifyKind};
+use text_splitter::{ChunkConfig, CodeSplitter, MarkdownSplitter, TextSplitter};
 use tiktoken_rs::{o200k_base, CoreBPE};
-use types::{common::ProgressStatus, dto::{ChunkRagDTO, ChunkRagId, ConversationNodeID, LmModel, MessageUsedRagChunk, RagFileDTO, RagFileID}};
+use types::{common::{NotificationAction, ProgressStatus}, dto::{ChunkContextDTO, ChunkRagDTO, ChunkRagId, ConversationNodeDTO, ConversationNodeID, FileChunk, IndexedFile, KnowledgeBaseDTO, KnowledgeBaseID, LmModel, MessageDTO, MessageUsedRagChunk, PresetDTO, ProviderID, RagFileDTO, RagFileID, RagSource, RoleType, WatchedPathDTO}, error::{AppError, AppResult}};
 use utils::event_system::{Event, EventSystem};
 
+use crate::{documents::{self, CodeLanguage, DocumentFormat, LineCounter}, messaging::resolve_chat_model};
+
 const EMBEDDING_CHUNKS_BATCH: usize = 1000;
 
+/// Each search returns this many candidates for every chunk the chat uses,
+/// a chunk ranked low by one search can still win through the other.
+const FUSION_CANDIDATES_PER_CHUNK: usize = 4;
+
+/// Damps the difference between the first places of a ranking, the usual
+/// value for reciprocal rank fusion.
+const RECIPROCAL_RANK_K: f32 = 60.0;
+
+/// Text of the file shown on each side of an inspected chunk, in bytes.
+const CHUNK_CONTEXT_BYTES: usize = 1500;
+
+/// Saving a file sends several events, the changes are indexed once they
+/// stop for this long.
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Asks the chat model for scores when there is no rerank endpoint.
+const CHAT_RERANK_PROMPT: &str = "Rate how relevant each numbered passage is to the query, \
+    from 0 (unrelated) to 10 (answers it). \
+    Reply only with one `number: score` line for every passage.";
+
+struct LoadingFile {
+    path: PathBuf,
+    /// Shown in citations, the path in the directory for files loaded with it.
+    name: String,
+    /// Files of a directory that cannot be read as text are skipped.
+    in_directory: bool,
+}
+
+/// Watches the watched paths of every source, one watch for each path.
+#[derive(Debug)]
+struct PathsWatcher {
+    watcher: RecommendedWatcher,
+    paths: HashSet<PathBuf>,
+}
+
 #[derive(Debug, Clone)]
 pub struct VectorService {
     vector_db: VectorDatabase,
     conversation_db: ConversationDatabase,
+    knowledge_db: KnowledgeBasesDatabase,
     providers_db: ProvidersDatabase,
-    lm_api: OpenAiApi,
+    lm_api: LmApi,
     event_system: EventSystem,
+    /// Started by [`VectorService::subscribe_watched_paths`].
+    paths_watcher: Arc<Mutex<Option<PathsWatcher>>>,
 }
 
 impl VectorService {
     pub fn new(
         _max_chunk_size: usize,
         connection: DatabaseConnection,
-        lm_api: OpenAiApi,
+        lm_api: LmApi,
         event_system: EventSystem,
     ) -> Self {
         Self {
             vector_db: VectorDatabase::new(connection.clone()),
             conversation_db: ConversationDatabase::new(connection.clone()),
+            knowledge_db: KnowledgeBasesDatabase::new(connection.clone()),
             providers_db: ProvidersDatabase::new(connection),
             lm_api,
             event_system,
+            paths_watcher: Arc::new(Mutex::new(None)),
         }
     }
 
-    #[must_use] pub fn get_chunk(
+    /// # Errors
+    pub fn get_chunk(
         &self,
-        conversation_id: ConversationNodeID,
+        source: RagSource,
         chunk_id: ChunkRagId,
         dimenstion: usize,
         embedding_model: &str
-    ) -> Result<Option<ChunkRagDTO>, String> {
+    ) -> AppResult<Option<ChunkRagDTO>> {
         self.vector_db
-            .get_chunk_by_id(conversation_id, chunk_id, dimenstion, embedding_model)
+            .get_chunk_by_id(source, chunk_id, dimenstion, embedding_model)
+            .map_err(AppError::database)
+    }
+
+    /// The chunk with the text around it in its file, cut at whole lines.
+    ///
+    /// # Errors
+    pub fn get_chunk_context(&self, source: RagSource, chunk: &ChunkRagDTO) -> AppResult<ChunkContextDTO> {
+        let content = self
+            .vector_db
+            .get_file_content(source, chunk.file_id)
+            .map_err(AppError::database)?;
+
+        let spans = content.as_deref().zip(chunk.bytes).and_then(|(content, (start, end))| {
+            Some((content.get(..start)?, content.get(start..end)?, content.get(end..)?))
+        });
+
+        let Some((before, text, after)) = spans else {
+            return Ok(ChunkContextDTO {
+                chunk: chunk.chunk.clone(),
+                ..Default::default()
+            });
+        };
+
+        let mut before_start = before.len().saturating_sub(CHUNK_CONTEXT_BYTES);
+        while !before.is_char_boundary(before_start) {
+            before_start += 1;
+        }
+        if before_start > 0 {
+            before_start += before[before_start..].find('\n').map_or(0, |newline| newline + 1);
+        }
+
+        let mut after_end = after.len().min(CHUNK_CONTEXT_BYTES);
+        while !after.is_char_boundary(after_end) {
+            after_end -= 1;
+        }
+        if after_end < after.len() {
+            after_end = after[..after_end].rfind('\n').unwrap_or(after_end);
+        }
+
+        Ok(ChunkContextDTO {
+            before: before[before_start..].to_string(),
+            chunk: text.to_string(),
+            after: after[..after_end].to_string(),
+        })
     }
 
     pub fn cancel_loading_files(&self) {
@@ -52,76 +141,464 @@ impl VectorService {
             .dispatch(Event::LoadingFilesStatus(ProgressStatus::Finished));
     }
 
-    /// # Panics
+    /// Whether [`Self::search`] has anything to look in, files of the conversation
+    /// or of an attached knowledge base that have an embedding model.
+    ///
+    /// # Errors
+    pub fn has_sources(&self, conversation_id: ConversationNodeID) -> AppResult<bool> {
+        let conversation = self.conversation_db.get_conversation(conversation_id)
+            .map_err(AppError::database)?;
+
+        let knowledge_bases = self.knowledge_db.get_attached(conversation_id)
+            .map_err(AppError::database)?;
+
+        let mut sources = vec![(
+            RagSource::Conversation(conversation_id),
+            conversation.embedding_provider,
+            conversation.embedding_model,
+        )];
+        sources.extend(knowledge_bases.into_iter().map(|knowledge_base| (
+            RagSource::KnowledgeBase(knowledge_base.id),
+            knowledge_base.embedding_provider,
+            knowledge_base.embedding_model,
+        )));
+
+        for (source, provider_id, model_name) in sources {
+            let embedded = provider_id.is_some() && model_name.is_some_and(|model| !model.is_empty());
+
+            if embedded && !self.get_files(source)?.is_empty() {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Searches files of the conversation and of the knowledge bases attached
+    /// to it, the queries are embedded once for each embedding model they use.
+    /// Every query is ranked on its own and the rankings are fused, so a chunk
+    /// found by several queries is used once. Reranking scores the chunks
+    /// against the first query.
+    ///
     /// # Errors
     pub async fn search(
         &self,
-        embedding_lm_model: LmModel,
-        query: String,
+        queries: Vec<String>,
         conversation_id: ConversationNodeID,
-    ) -> Vec<MessageUsedRagChunk> {
+    ) -> AppResult<Vec<MessageUsedRagChunk>> {
         let conversation = self.conversation_db.get_conversation(conversation_id)
-            .expect("Failed to get conversation");
+            .map_err(AppError::database)?;
 
-        let query_embedding = self
-            .lm_api.clone()
-            .embeddings(embedding_lm_model.clone(), vec![query])
-            .await
-            .expect("Failed to get embeddings");
+        let knowledge_bases = self.knowledge_db.get_attached(conversation_id)
+            .map_err(AppError::database)?;
 
-        let embedding_lm_model = embedding_lm_model.model_name.clone();
+        let mut sources = vec![(
+            RagSource::Conversation(conversation_id),
+            conversation.embedding_provider,
+            conversation.embedding_model.clone(),
+        )];
+        sources.extend(knowledge_bases.into_iter().map(|knowledge_base| (
+            RagSource::KnowledgeBase(knowledge_base.id),
+            knowledge_base.embedding_provider,
+            knowledge_base.embedding_model,
+        )));
 
-        let mut founded = vec![];
         let count = conversation.rag_chunks_count;
+        let reranked = conversation.rag_rerank_candidates > 0;
+        let fused_count = if reranked {
+            conversation.rag_rerank_candidates.max(count)
+        } else {
+            count
+        };
+        let candidates = fused_count * FUSION_CANDIDATES_PER_CHUNK;
+        let mut query_embeddings: HashMap<(ProviderID, String), Vec<Vec<f32>>> = HashMap::new();
+        let mut vector_matches = vec![vec![]; queries.len()];
+        let mut keyword_matches = vec![vec![]; queries.len()];
 
-        for embedding in &query_embedding.data {
-            founded.extend(self.vector_db
-                .search(
-                    conversation_id,
-                    &embedding.embedding,
-                    count, 
-                    &embedding_lm_model
-                ).expect("Failed to search vector database")
-            );
-        }
+        for (source, provider_id, model_name) in sources {
+            let Some(model_name) = model_name.filter(|model| !model.is_empty()) else {
+                continue;
+            };
+            let Some(provider_id) = provider_id else {
+                continue;
+            };
 
-        founded.sort_by(|(distance_a, _a), (distance_b, _b)| {
-            distance_a.partial_cmp(distance_b).unwrap()
-        });
+            let key = (provider_id, model_name.clone());
 
-        founded
-            .iter()
-            .filter(|(distance, _chunk_id)| {
-                distance < &1.05f32
-            })
-            .map(|(_distance, chunk_id)| *chunk_id)
-            .map(|chunk_id| {
-                MessageUsedRagChunk {
+            if !query_embeddings.contains_key(&key) {
+                let embedding_lm_model = LmModel {
+                    model_name: model_name.clone(),
+                    provider: self.providers_db.get_provider(provider_id),
+                };
+
+                if embedding_lm_model.provider.is_none() {
+                    continue;
+                }
+
+                let mut query_embedding = self
+                    .lm_api.clone()
+                    .embeddings(embedding_lm_model, queries.clone())
+                    .await?;
+
+                query_embedding.data.sort_by_key(|data| data.index);
+
+                query_embeddings.insert(
+                    key.clone(),
+                    query_embedding.data.into_iter().map(|data| data.embedding).collect(),
+                );
+            }
+
+            let knowledge_base_id = match source {
+                RagSource::KnowledgeBase(id) => Some(id),
+                RagSource::Conversation(_) => None,
+            };
+
+            for (index, (query, embedding)) in queries.iter().zip(&query_embeddings[&key]).enumerate() {
+                if embedding.is_empty() {
+                    continue;
+                }
+
+                let used_chunk = |chunk_id| MessageUsedRagChunk {
                     chunk_id,
-                    dimension: query_embedding.data.first().unwrap().embedding.len(),
-                    embedding_model: embedding_lm_model.to_string(),
+                    dimension: embedding.len(),
+                    embedding_model: model_name.clone(),
+                    knowledge_base_id,
+                    distance: None,
+                };
+
+                for (distance, chunk_id) in self.vector_db
+                    .search(source, embedding, candidates, &model_name)
+                    .map_err(AppError::database)?
+                {
+                    if distance < conversation.rag_distance_cutoff {
+                        vector_matches[index].push((distance, used_chunk(chunk_id)));
+                    }
+                }
+
+                if conversation.rag_keyword_weight > 0.0 {
+                    for (rank, chunk_id) in self.vector_db
+                        .keyword_search(source, query, candidates, embedding.len(), &model_name)
+                        .map_err(AppError::database)?
+                    {
+                        keyword_matches[index].push((rank, used_chunk(chunk_id)));
+                    }
                 }
+            }
+        }
+
+        let mut rankings = vec![];
+        let mut distances = vec![];
+        for (mut vector_matches, mut keyword_matches) in vector_matches.into_iter().zip(keyword_matches) {
+            vector_matches.sort_by(|(distance_a, _a), (distance_b, _b)| distance_a.total_cmp(distance_b));
+            keyword_matches.sort_by(|(rank_a, _a), (rank_b, _b)| rank_a.total_cmp(rank_b));
+
+            distances.extend(vector_matches.iter().cloned());
+
+            rankings.push((conversation.rag_vector_weight, vector_matches.into_iter().map(|(_distance, chunk)| chunk).collect()));
+            rankings.push((conversation.rag_keyword_weight, keyword_matches.into_iter().map(|(_rank, chunk)| chunk).collect()));
+        }
+
+        let fused = Self::fuse_rankings(rankings, fused_count);
+
+        let found = match queries.first() {
+            Some(query) if reranked && !fused.is_empty() => self.rerank(&conversation, query, fused, count).await?,
+            _ => fused,
+        };
+
+        // Compared before the distance is set, a chunk is found once for every query
+        Ok(found
+            .into_iter()
+            .map(|mut chunk| {
+                chunk.distance = distances
+                    .iter()
+                    .filter(|(_distance, matched)| *matched == chunk)
+                    .map(|(distance, _matched)| *distance)
+                    .reduce(f32::min);
+
+                chunk
+            })
+            .collect())
+    }
+
+    /// Scores the candidates with the rerank model of the chat, or with the chat
+    /// model when none is selected or its endpoint fails, and keeps the `count`
+    /// best. The fused order is kept if neither can score them.
+    async fn rerank(
+        &self,
+        conversation: &ConversationNodeDTO,
+        query: &str,
+        candidates: Vec<MessageUsedRagChunk>,
+        count: usize,
+    ) -> AppResult<Vec<MessageUsedRagChunk>> {
+        let mut documents = vec![];
+        for chunk in &candidates {
+            let text = self
+                .get_chunk(chunk.source(conversation.id), chunk.chunk_id, chunk.dimension, &chunk.embedding_model)?
+                .map(|chunk| chunk.chunk)
+                .unwrap_or_default();
+
+            documents.push(text);
+        }
+
+        let rerank_lm_model = conversation
+            .rerank_model
+            .clone()
+            .filter(|model| !model.is_empty())
+            .zip(conversation.rerank_provider.and_then(|p_id| self.providers_db.get_provider(p_id)))
+            .map(|(model_name, provider)| LmModel {
+                model_name,
+                provider: Some(provider),
+            });
+
+        let mut results = None;
+
+        if let Some(rerank_lm_model) = rerank_lm_model {
+            match self.lm_api.rerank(rerank_lm_model, query.to_string(), documents.clone()).await {
+                Ok(scores) => results = Some(scores),
+                Err(e) => log::warn!("Rerank endpoint failed, scoring with the chat model: {e}"),
+            }
+        }
+
+        let results = match results {
+            Some(results) => results,
+            None => self
+                .score_with_chat_model(conversation, query, &documents)
+                .await
+                .unwrap_or_else(|e| {
+                    log::warn!("Failed to rerank chunks, keeping the fused order: {e}");
+                    vec![]
+                }),
+        };
+
+        let mut scored: Vec<(f32, MessageUsedRagChunk)> = candidates
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let score = results
+                    .iter()
+                    .filter(|result| result.index == index)
+                    .map(|result| result.relevance_score)
+                    .fold(f32::NEG_INFINITY, f32::max);
+
+                (score, chunk)
+            })
+            .collect();
+
+        // Stable, so chunks left without a score stay in the fused order.
+        scored.sort_by(|(score_a, _a), (score_b, _b)| score_b.total_cmp(score_a));
+
+        Ok(scored
+            .into_iter()
+            .take(count)
+            .map(|(_score, chunk)| chunk)
+            .collect())
+    }
+
+    /// Fallback for servers without a `/rerank` endpoint, the chat model
+    /// rates every passage in one request.
+    async fn score_with_chat_model(
+        &self,
+        conversation: &ConversationNodeDTO,
+        query: &str,
+        documents: &[String],
+    ) -> AppResult<Vec<RerankResult>> {
+        let lm_model = resolve_chat_model(&self.providers_db, conversation)?;
+
+        let mut passages = String::new();
+        for (index, document) in documents.iter().enumerate() {
+            let _ = write!(passages, "[{index}]\n{document}\n\n");
+        }
+
+        let request = vec![MessageDTO {
+            content: format!("Query: {query}\n\nPassages:\n\n{passages}"),
+            role: RoleType::User,
+            ..Default::default()
+        }];
+
+        let preset = PresetDTO {
+            temperature: 0.0,
+            prompt: CHAT_RERANK_PROMPT.to_string(),
+            ..Default::default()
+        };
+
+        let mut stream = self.lm_api.chat_completions(
+            lm_model,
+            request,
+            Some(preset),
+            vec![],
+            CancellationToken::new(),
+        )?;
+
+        let mut answer = String::new();
+        while let Some(completion) = stream.next().await {
+            match completion {
+                ComplitationStatus::Message(message) => answer.push_str(&message.content),
+                ComplitationStatus::Error(e) => return Err(e),
+                ComplitationStatus::End(_) | ComplitationStatus::Cancelled => break,
+                ComplitationStatus::Start | ComplitationStatus::ToolCalls(_) => {}
+            }
+        }
+
+        Ok(Self::parse_chat_scores(&answer, documents.len()))
+    }
+
+    /// Reads the `number: score` lines of the answer, anything else is skipped.
+    fn parse_chat_scores(answer: &str, documents: usize) -> Vec<RerankResult> {
+        answer
+            .lines()
+            .filter_map(|line| {
+                let (index, score) = line.split_once(':')?;
+                let index = index
+                    .trim()
+                    .trim_matches(|c: char| c == '[' || c == ']' || c == '*')
+                    .parse::<usize>()
+                    .ok()?;
+                let relevance_score = score
+                    .trim()
+                    .trim_matches('*')
+                    .split(|c: char| c.is_whitespace() || c == '/')
+                    .next()?
+                    .parse::<f32>()
+                    .ok()?;
+
+                (index < documents).then_some(RerankResult { index, relevance_score })
             })
             .collect()
     }
 
-    /// # Panics
+    /// Reciprocal rank fusion: a chunk scores `weight / (k + place)` in every
+    /// ranking it is in, the `count` chunks with the highest sums are used.
+    /// Only places matter, so distances and BM25 scores need no common scale.
+    fn fuse_rankings(
+        rankings: Vec<(f32, Vec<MessageUsedRagChunk>)>,
+        count: usize,
+    ) -> Vec<MessageUsedRagChunk> {
+        let mut scores: Vec<(f32, MessageUsedRagChunk)> = vec![];
+
+        for (weight, ranking) in rankings {
+            if weight <= 0.0 {
+                continue;
+            }
+
+            for (index, chunk) in ranking.into_iter().enumerate() {
+                #[allow(clippy::cast_precision_loss)]
+                let score = weight / (RECIPROCAL_RANK_K + index as f32 + 1.0);
+
+                match scores.iter_mut().find(|(_score, scored)| *scored == chunk) {
+                    Some((total, _scored)) => *total += score,
+                    None => scores.push((score, chunk)),
+                }
+            }
+        }
+
+        scores.sort_by(|(score_a, _a), (score_b, _b)| score_b.total_cmp(score_a));
+
+        scores
+            .into_iter()
+            .take(count)
+            .map(|(_score, chunk)| chunk)
+            .collect()
+    }
+
+    /// Model and chunk size the files of `source` are embedded with, the model
+    /// is `None` until one is selected.
+    fn embedding_settings(&self, source: RagSource) -> AppResult<(Option<LmModel>, usize)> {
+        let (provider_id, model_name, chunk_size) = match source {
+            RagSource::Conversation(conversation_id) => {
+                let chat = self
+                    .conversation_db
+                    .get_conversation(conversation_id)
+                    .map_err(AppError::database)?;
+
+                (chat.embedding_provider, chat.embedding_model, chat.rag_chunk_size)
+            }
+            RagSource::KnowledgeBase(knowledge_base_id) => {
+                let knowledge_base = self.get_knowledge_base(knowledge_base_id)?;
+
+                (knowledge_base.embedding_provider, knowledge_base.embedding_model, knowledge_base.chunk_size)
+            }
+        };
+
+        let model = model_name
+            .filter(|model| !model.is_empty())
+            .map(|model_name| LmModel {
+                model_name,
+                provider: provider_id.and_then(|p_id| self.providers_db.get_provider(p_id)),
+            });
+
+        Ok((model, chunk_size))
+    }
+
+    /// Directories are loaded with the files in them, skipping the ones that are
+    /// not text or a supported document. Otherwise loading stops at the first
+    /// failure, which is reported as [`ProgressStatus::Failed`] instead of
+    /// [`ProgressStatus::Finished`]. A file loaded again replaces the one at
+    /// its path, only its new chunks are embedded.
+    ///
     /// # Errors
-    #[must_use] pub fn load_files(
+    pub fn load_files(
         &self,
-        conversation_id: ConversationNodeID,
+        source: RagSource,
         loading_files: Vec<PathBuf>,
-    ) -> Pin<Box<dyn Stream<Item = ProgressStatus> + Send>> {
-        let providers_db = self.providers_db.clone();
-        let chat = self
-            .conversation_db
-            .get_conversation(conversation_id)
-            .expect("Failed to get conversation");
-
-        let embedding_lm_model = LmModel {
-            model_name: chat.embedding_model.expect("Failed to get model"),
-            provider: chat.embedding_provider
-                .and_then(move |p_id| providers_db.get_provider(p_id)),
+    ) -> AppResult<Pin<Box<dyn Stream<Item = ProgressStatus> + Send>>> {
+        self.index_files(source, vec![], move || Self::collect_files(loading_files, None))
+    }
+
+    /// Indexes the files of the watched paths that changed since they were
+    /// indexed and deletes the ones that are not in them anymore.
+    ///
+    /// # Errors
+    pub fn sync_watched_paths(
+        &self,
+        source: RagSource,
+        watched_paths: &[WatchedPathDTO],
+    ) -> AppResult<Pin<Box<dyn Stream<Item = ProgressStatus> + Send>>> {
+        let roots = watched_paths
+            .iter()
+            .map(|watched_path| {
+                let root = PathBuf::from(&watched_path.path);
+                let filter = Self::path_filter(&root, &watched_path.include, &watched_path.exclude)?;
+
+                Ok((root, filter))
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+
+        let watched_roots = roots.iter().map(|(root, _)| root.clone()).collect();
+
+        self.index_files(source, watched_roots, move || {
+            roots
+                .into_iter()
+                // A removed path has all of its files deleted
+                .filter(|(root, _)| root.exists())
+                .flat_map(|(root, filter)| Self::collect_files(vec![root], Some(&filter)))
+                .collect()
+        })
+    }
+
+    /// Reads, chunks and embeds the files `collect` lists. With the roots of
+    /// watched paths, files that did not change are not read again and the
+    /// files under them that are not listed are deleted.
+    fn index_files(
+        &self,
+        source: RagSource,
+        watched_roots: Vec<PathBuf>,
+        collect: impl FnOnce() -> Vec<LoadingFile> + Send + 'static,
+    ) -> AppResult<Pin<Box<dyn Stream<Item = ProgressStatus> + Send>>> {
+        let (embedding_lm_model, chunk_size) = self.embedding_settings(source)?;
+
+        let Some(embedding_lm_model) = embedding_lm_model else {
+            let error = AppError::validation("Select an embedding model to load files");
+
+            let action = match source {
+                RagSource::Conversation(conversation_id) => {
+                    Some(NotificationAction::OpenModelPicker(conversation_id))
+                }
+                RagSource::KnowledgeBase(_) => None,
+            };
+
+            self.event_system.clone().notify_error("Failed to load files", &error, action);
+
+            return Err(error);
         };
 
         let self_cp = self.clone();
@@ -130,37 +607,91 @@ impl VectorService {
             let mut output = output;
             output.emit(ProgressStatus::Started).await;
 
-            for file_path in loading_files {
-                let file_content = fs::read_to_string(&file_path.clone()).expect("Failed to read file");
+            let loading_files = match tokio::task::spawn_blocking(collect).await {
+                Ok(loading_files) => loading_files,
+                Err(e) => {
+                    output.emit(ProgressStatus::Failed(AppError::file(format!("Failed to list files: {e}")))).await;
+                    return;
+                }
+            };
+
+            let model_name = embedding_lm_model.model_name.clone();
+
+            let indexed_hashes = if watched_roots.is_empty() {
+                HashMap::new()
+            } else {
+                match self_cp.vector_db.get_file_hashes(source, &model_name) {
+                    Ok(indexed_hashes) => indexed_hashes,
+                    Err(e) => {
+                        output.emit(ProgressStatus::Failed(AppError::database(e))).await;
+                        return;
+                    }
+                }
+            };
+
+            let mut listed_paths = HashSet::new();
+
+            for LoadingFile { path: file_path, name: file_name, in_directory } in loading_files {
+                let path = file_path.to_string_lossy().to_string();
+                listed_paths.insert(path.clone());
 
                 output.emit(ProgressStatus::Progress {
-                    name: format!("{}", file_path.to_string_lossy()),
-                    range: 0..file_content.len(),
+                    name: format!("Reading {path} ..."),
+                    range: 0..1,
                     current: 0,
                 }).await;
 
-                let file_hash = seahash::hash(file_content.as_bytes()).to_string();
+                let indexed_hash = indexed_hashes.get(&path).cloned();
+                let path_for_reading = file_path.clone();
+                let document = match tokio::task::spawn_blocking(move || {
+                    if indexed_hash.is_some() && indexed_hash == documents::read_hash(&path_for_reading).ok() {
+                        return Ok(None);
+                    }
 
-                let file_name = file_path.file_name().map(|f| f.to_os_string());
-                let file_name_for_chunking = file_name.clone();
+                    documents::read_document(&path_for_reading).map(Some)
+                }).await {
+                    Ok(Ok(Some(document))) => document,
+                    Ok(Ok(None)) => continue,
+                    // Folders have images and build outputs next to the sources
+                    Ok(Err(AppError::Validation(message))) if in_directory => {
+                        log::info!("Skipping {message}");
+                        continue;
+                    }
+                    Ok(Err(e)) => {
+                        output.emit(ProgressStatus::Failed(e)).await;
+                        return;
+                    }
+                    Err(e) => {
+                        output.emit(ProgressStatus::Failed(AppError::file(format!(
+                            "Failed to read {}: {e}",
+                            file_path.display()
+                        )))).await;
+                        return;
+                    }
+                };
+
+                let text_len = document.text_len();
 
-                let mut current_len = 0;
-                let mut buffer_chunks: Vec<String> = Vec::new();
-                let mut batches_chunks: Vec<(usize, Vec<String>)> = Vec::new();
+                let indexed_file = IndexedFile {
+                    path: path.clone(),
+                    name: file_name,
+                    hash: document.hash.clone(),
+                    content: document.text(),
+                };
 
                 output.emit(ProgressStatus::Progress {
-                    name: format!("Chunking {} ...", file_path.to_string_lossy()),
+                    name: format!("Chunking {path} ..."),
                     range: 0..1,
                     current: 0
                 }).await;
 
                 let (mut sender, mut reciever) = mpsc::channel::<Option<(Range<usize>, usize)>>(0);
 
-                let path = file_path.to_string_lossy().to_string().clone();
+                let chunking_path = path.clone();
                 let chunking_sender_thread = async move {
                     while let Some(Some((range, current))) = reciever.next().await {
                         output.emit(ProgressStatus::Progress {
-                            name: format!("Chunking {path} ..."),
+                            name: format!("Chunking {chunking_path} ..."),
                             range,
                             current,
                         }).await;
@@ -168,204 +699,788 @@ impl VectorService {
 
                     output
                 };
-                
-                let file_content_for_chunking = file_content.clone();
+
                 let chunking_parsing_thread = tokio::spawn(async move {
-                    for chunk in Self::create_splitter(
-                        &file_name_for_chunking.clone().unwrap_or_default().to_string_lossy(),
-                        &file_content_for_chunking,
-                        &MarkdownSplitter::new(chat.rag_chunk_size),
-                        &text_splitter::TextSplitter::new(
-                            ChunkConfig::new(chat.rag_chunk_size)
-                                .with_overlap(16)
-                                .expect("Failed to create chunk config")
-                                .with_trim(true)
-                                .with_sizer(o200k_base().expect("Failed to get tokenizer"))
-                        )
-                    ) {
-                        current_len += chunk.len();
-    
-                        buffer_chunks.push(chunk.to_string());
-    
-                        if buffer_chunks.len() >= EMBEDDING_CHUNKS_BATCH {
-                            batches_chunks.push((current_len, buffer_chunks.clone()));
-                            buffer_chunks.clear();
-                        }
-    
-                        let file_content_for_chunking = file_content_for_chunking.clone();
+                    let markdown_splitter = MarkdownSplitter::new(chunk_size);
+                    let text_splitter = TextSplitter::new(
+                        ChunkConfig::new(chunk_size)
+                            .with_overlap(16)
+                            .map_err(|e| AppError::validation(format!("Invalid chunk size {chunk_size}: {e}")))?
+                            .with_trim(true)
+                            .with_sizer(Self::tokenizer()?)
+                    );
+
+                    let mut current_len = 0;
+                    let mut chunks: Vec<FileChunk> = Vec::new();
+
+                    let mut push_chunk = |chunk: FileChunk| {
+                        // Headers repeated in table chunks count more than the file has
+                        current_len = (current_len + chunk.text.len()).min(text_len);
+
+                        chunks.push(chunk);
+
                         let mut sender_cp = sender.clone();
-                        let _ = sender_cp.try_send(Some((0..file_content_for_chunking.len(), current_len)));
-                    }
+                        let _ = sender_cp.try_send(Some((0..text_len, current_len)));
+                    };
+
+                    if let DocumentFormat::Table { header } = &document.format {
+                        let tokenizer = Self::tokenizer()?;
+
+                        for chunk in documents::group_rows(header, &document.sections, chunk_size, |text| {
+                            tokenizer.encode_ordinary(text).len()
+                        }) {
+                            push_chunk(chunk);
+                        }
+                    } else {
+                        let code_splitter = match &document.format {
+                            DocumentFormat::Code(language) => Self::create_code_splitter(*language, chunk_size),
+                            _ => None,
+                        };
+
+                        // Chunks never span two pages, so each one knows its page
+                        for (section, section_offset) in document.sections.iter().zip(document.section_offsets()) {
+                            let mut line_counter = section.line.map(|line| LineCounter::new(&section.text, line));
 
-                    if !buffer_chunks.is_empty() {
-                        batches_chunks.push((current_len, buffer_chunks.clone()));
-                        buffer_chunks.clear();
+                            for (offset, chunk) in Self::create_splitter(
+                                &document.format,
+                                &section.text,
+                                &markdown_splitter,
+                                &text_splitter,
+                                code_splitter.as_ref(),
+                            ) {
+                                push_chunk(FileChunk {
+                                    text: chunk.to_string(),
+                                    page: section.page,
+                                    lines: line_counter.as_mut().map(|counter| counter.lines(offset, chunk)),
+                                    bytes: Some((section_offset + offset, section_offset + offset + chunk.len())),
+                                });
+                            }
+                        }
                     }
 
-                    sender.send(None).await.expect("Failed to send to channel");
+                    sender
+                        .send(None)
+                        .await
+                        .map_err(|e| AppError::file(format!("Failed to report chunking progress: {e}")))?;
 
-                    batches_chunks
+                    Ok::<_, AppError>(chunks)
                 });
 
                 let threads_result = tokio::join!(chunking_sender_thread, chunking_parsing_thread);
                 output = threads_result.0;
-                batches_chunks = threads_result.1.expect("Failed to join thread");
+                let chunks = match threads_result.1 {
+                    Ok(Ok(chunks)) => chunks,
+                    Ok(Err(e)) => {
+                        output.emit(ProgressStatus::Failed(e)).await;
+                        return;
+                    }
+                    Err(e) => {
+                        output.emit(ProgressStatus::Failed(AppError::file(format!(
+                            "Failed to chunk {path}: {e}"
+                        )))).await;
+                        return;
+                    }
+                };
 
-                for (len, batch) in batches_chunks {
-                    let embeddings = self_cp.lm_api.embeddings(embedding_lm_model.clone(), batch.clone()).await;
-                    let embeddings = embeddings.expect("Failed to get embeddings");
+                // Chunks the previous version of the file had keep their vectors
+                let (stored_dimensions, stored_hashes): (Option<usize>, HashSet<u64>) = match self_cp.vector_db.get_file_chunks(source, &indexed_file, &model_name) {
+                    Ok(Some((dimensions, stored_chunks))) => (
+                        Some(dimensions),
+                        stored_chunks.iter().map(|chunk| seahash::hash(chunk.as_bytes())).collect(),
+                    ),
+                    Ok(None) => (None, HashSet::new()),
+                    Err(e) => {
+                        output.emit(ProgressStatus::Failed(AppError::database(e))).await;
+                        return;
+                    }
+                };
 
-                    let vectors = embeddings.data.iter().map(|e| e.embedding.clone());
-                    let vectors = vectors.collect::<Vec<Vec<f32>>>();
+                let mut new_hashes = HashSet::new();
+                let new_texts: Vec<String> = chunks
+                    .iter()
+                    .filter(|chunk| {
+                        let hash = seahash::hash(chunk.text.as_bytes());
+                        !stored_hashes.contains(&hash) && new_hashes.insert(hash)
+                    })
+                    .map(|chunk| chunk.text.clone())
+                    .collect();
 
-                    let file_name = file_name
-                        .clone()
-                        .expect("Failed to get file name")
-                        .to_string_lossy()
-                        .to_string()
-                        .clone();
-
-                    let model_name = embedding_lm_model.model_name.clone();
-
-                    self_cp.vector_db.insert_records(
-                        conversation_id,
-                        &file_hash,
-                        &file_name,
-                        &batch,
-                        &vectors,
-                        &model_name,
-                    );
+                let mut vectors = HashMap::new();
+
+                for (batch_index, batch) in new_texts.chunks(EMBEDDING_CHUNKS_BATCH).enumerate() {
+                    let embeddings = match self_cp.lm_api.embeddings(embedding_lm_model.clone(), batch.to_vec()).await {
+                        Ok(embeddings) => embeddings,
+                        Err(e) => {
+                            output.emit(ProgressStatus::Failed(e)).await;
+                            return;
+                        }
+                    };
+
+                    vectors.extend(batch.iter().cloned().zip(embeddings.data.into_iter().map(|e| e.embedding)));
 
                     output.emit(ProgressStatus::Progress {
-                        name: format!("Loading {} ...", file_path.to_string_lossy()),
-                        range: 0..file_content.len(),
-                        current: len,
+                        name: format!("Loading {path} ..."),
+                        range: 0..new_texts.len(),
+                        current: batch_index * EMBEDDING_CHUNKS_BATCH + batch.len(),
                     }).await;
                 }
+
+                let Some(dimensions) = vectors.values().next().map(Vec::len).or(stored_dimensions) else {
+                    continue;
+                };
+
+                if let Err(e) = self_cp.vector_db.replace_file(
+                    source,
+                    &indexed_file,
+                    &chunks,
+                    &vectors,
+                    dimensions,
+                    &model_name,
+                ) {
+                    output.emit(ProgressStatus::Failed(AppError::database(e))).await;
+                    return;
+                }
+            }
+
+            if let Err(e) = self_cp.delete_unlisted_files(source, &watched_roots, &listed_paths) {
+                output.emit(ProgressStatus::Failed(e)).await;
+                return;
             }
 
-            let files = self_cp
-                .vector_db
-                .get_files(conversation_id)
-                .expect("Failed to get files");
+            let files = match self_cp.vector_db.get_files(source) {
+                Ok(files) => files,
+                Err(e) => {
+                    output.emit(ProgressStatus::Failed(AppError::database(e))).await;
+                    return;
+                }
+            };
 
             output.emit(ProgressStatus::Finished).await;
 
             self_cp.event_system
                 .clone()
                 .dispatch(utils::event_system::Event::RagFilesUpdated {
-                    conversation_id,
+                    source,
                     files,
                 });
         });
 
         let mut event_system = self.event_system.clone();
         let stream = stream.map(move |status| {
+            if let ProgressStatus::Failed(ref e) = status {
+                event_system.notify_error("Failed to load files", e, None);
+            }
+
             event_system.dispatch(Event::LoadingFilesStatus(status.clone()));
             status
         });
 
-        (Box::pin(stream)) as _
+        Ok((Box::pin(stream)) as _)
     }
 
+    /// Deletes the files indexed from under `roots` that are not in `listed_paths`.
+    fn delete_unlisted_files(
+        &self,
+        source: RagSource,
+        roots: &[PathBuf],
+        listed_paths: &HashSet<String>,
+    ) -> AppResult<()> {
+        if roots.is_empty() {
+            return Ok(());
+        }
+
+        for file in self.get_files(source)? {
+            let Some(file_path) = file.file_path else {
+                continue;
+            };
+
+            let is_under_roots = roots.iter().any(|root| Path::new(&file_path).starts_with(root));
+
+            if is_under_roots && !listed_paths.contains(&file_path) {
+                self.vector_db
+                    .delete_rag_file(source, file.id)
+                    .map_err(AppError::database)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Chunks with their byte offsets in `text`.
     fn create_splitter<'a>(
-        filename: &'a str,
+        format: &DocumentFormat,
         text: &'a str,
         markdown_splitter: &'a MarkdownSplitter<text_splitter::Characters>,
         text_splitter: &'a TextSplitter<CoreBPE>,
-    ) -> Box<dyn Iterator<Item = &'a str> + 'a> {
-        let file_extension = filename
-            .split('.')
-            .last()
-            .unwrap_or("txt");
-
-        if file_extension == "md" {
-            Box::new(markdown_splitter.chunks(text))
-        } else {
-            Box::new(text_splitter.chunks(text))
+        code_splitter: Option<&'a CodeSplitter<CoreBPE>>,
+    ) -> Box<dyn Iterator<Item = (usize, &'a str)> + 'a> {
+        match (format, code_splitter) {
+            (DocumentFormat::Code(_), Some(code_splitter)) => Box::new(code_splitter.chunk_indices(text)),
+            (DocumentFormat::Markdown, _) => Box::new(markdown_splitter.chunk_indices(text)),
+            _ => Box::new(text_splitter.chunk_indices(text)),
         }
     }
 
-    /// # Panics
+    /// `None` when the grammar was built for another version of the parser,
+    /// the code is split as text then.
+    fn create_code_splitter(language: CodeLanguage, chunk_size: usize) -> Option<CodeSplitter<CoreBPE>> {
+        let grammar = match language {
+            CodeLanguage::Rust => tree_sitter_rust::LANGUAGE,
+            CodeLanguage::Python => tree_sitter_python::LANGUAGE,
+            CodeLanguage::JavaScript => tree_sitter_javascript::LANGUAGE,
+            CodeLanguage::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+            CodeLanguage::Tsx => tree_sitter_typescript::LANGUAGE_TSX,
+            CodeLanguage::Go => tree_sitter_go::LANGUAGE,
+            CodeLanguage::Java => tree_sitter_java::LANGUAGE,
+            CodeLanguage::C => tree_sitter_c::LANGUAGE,
+            CodeLanguage::Cpp => tree_sitter_cpp::LANGUAGE,
+        };
+
+        let tokenizer = Self::tokenizer()
+            .inspect_err(|e| log::warn!("Failed to create {language:?} splitter: {e}"))
+            .ok()?;
+
+        CodeSplitter::new(
+            grammar,
+            ChunkConfig::new(chunk_size)
+                .with_trim(true)
+                .with_sizer(tokenizer),
+        )
+        .inspect_err(|e| log::warn!("Failed to create {language:?} splitter: {e}"))
+        .ok()
+    }
+
+    /// Chunks are measured in the tokens of the `OpenAI` models, close enough for the others.
+    fn tokenizer() -> AppResult<CoreBPE> {
+        o200k_base().map_err(|e| AppError::parse(format!("Failed to get tokenizer: {e}")))
+    }
+
+    /// Files of the directories among `paths` are listed with their path in the
+    /// directory as the name, leaving out hidden files, what `.gitignore`
+    /// excludes and what `filter` does not include.
+    fn collect_files(paths: Vec<PathBuf>, filter: Option<&Override>) -> Vec<LoadingFile> {
+        let mut files = Vec::new();
+
+        for path in paths {
+            if !path.is_dir() {
+                files.push(LoadingFile {
+                    name: path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                    path,
+                    in_directory: false,
+                });
+
+                continue;
+            }
+
+            let mut walk = WalkBuilder::new(&path);
+
+            // The ignore files are read even when the directory is not a repository
+            walk.require_git(false);
+
+            if let Some(filter) = filter {
+                walk.overrides(filter.clone());
+            }
+
+            for entry in walk.build() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        log::warn!("Failed to read {}: {e}", path.display());
+                        continue;
+                    }
+                };
+
+                if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+                    continue;
+                }
+
+                let name = entry
+                    .path()
+                    .strip_prefix(&path)
+                    .unwrap_or_else(|_| entry.path())
+                    .to_string_lossy()
+                    .replace('\\', "/");
+
+                files.push(LoadingFile {
+                    path: entry.into_path(),
+                    name,
+                    in_directory: true,
+                });
+            }
+        }
+
+        files
+    }
+
+    /// Globs as `.gitignore` has them, relative to `root`. Only the files matching
+    /// an included glob are indexed when there is one.
+    fn path_filter(root: &Path, include: &[String], exclude: &[String]) -> AppResult<Override> {
+        let mut builder = OverrideBuilder::new(root);
+
+        let globs = include
+            .iter()
+            .cloned()
+            .chain(exclude.iter().map(|glob| format!("!{glob}")));
+
+        for glob in globs {
+            builder
+                .add(&glob)
+                .map_err(|e| AppError::validation(format!("Invalid pattern {glob}: {e}")))?;
+        }
+
+        builder
+            .build()
+            .map_err(|e| AppError::validation(format!("Invalid patterns: {e}")))
+    }
+
     /// # Errors
-    pub fn delete_all_files(&self, conversation_id: ConversationNodeID) -> Result<(), String> {
+    pub fn delete_all_files(&self, source: RagSource) -> AppResult<()> {
         self.vector_db
-            .delete_all_files_in_conversation(conversation_id)
-            .expect("Failed to delete all files");
+            .delete_all_files(source)
+            .map_err(AppError::database)?;
 
-        let files = self
-            .vector_db
-            .get_files(conversation_id)
-            .expect("Failed to get files");
+        self.update_watches()?;
+
+        let files = self.get_files(source)?;
 
         self.event_system
             .clone()
             .dispatch(utils::event_system::Event::RagFilesUpdated {
-                conversation_id,
+                source,
                 files,
             });
 
         Ok(())
     }
 
-    /// # Panics
     /// # Errors
     pub fn delete_rag_file(
         &self,
-        conversation_id: ConversationNodeID,
+        source: RagSource,
         rag_file_id: RagFileID,
-    ) -> Result<(), String> {
+    ) -> AppResult<()> {
         self.vector_db
-            .delete_rag_file(conversation_id, rag_file_id)
-            .expect("Failed to delete rag file");
+            .delete_rag_file(source, rag_file_id)
+            .map_err(AppError::database)
+            .inspect_err(|e| {
+                self.event_system
+                    .clone()
+                    .notify_error("Failed to delete the file", e, None);
+            })?;
 
-        let files = self
-            .vector_db
-            .get_files(conversation_id)
-            .expect("Failed to get files");
+        let files = self.get_files(source)?;
 
         self.event_system
             .clone()
             .dispatch(utils::event_system::Event::RagFilesUpdated {
-                conversation_id,
+                source,
                 files,
             });
 
         Ok(())
     }
 
-    /// # Panics
     /// # Errors
-    #[must_use]
-    pub fn get_files(&self, conversation_id: ConversationNodeID) -> Vec<RagFileDTO> {
+    pub fn get_files(&self, source: RagSource) -> AppResult<Vec<RagFileDTO>> {
         self.vector_db
-            .get_files(conversation_id)
-            .expect("Failed to get files")
+            .get_files(source)
+            .map_err(AppError::database)
     }
 
-    #[must_use] pub fn get_file(
+    /// # Errors
+    pub fn get_file(
         &self,
-        conversation_id: ConversationNodeID,
+        source: RagSource,
         rag_file_id: RagFileID,
-    ) -> Option<RagFileDTO> {
+    ) -> AppResult<Option<RagFileDTO>> {
         self.vector_db
-            .get_file(conversation_id, rag_file_id)
+            .get_file(source, rag_file_id)
+            .map_err(AppError::database)
     }
 
     pub fn subscribe_files_update<T>(
         &self,
-        conversation_id: ConversationNodeID,
-        converter: impl Fn((ConversationNodeID, Vec<RagFileDTO>)) -> T + 'static,
+        source: RagSource,
+        converter: impl Fn((RagSource, Vec<RagFileDTO>)) -> T + 'static,
     ) -> Subscription<T>
     where
         T: Debug + Send + 'static,
     {
         self.event_system.subscribe(
             &utils::event_system::Event::RagFilesUpdated {
-                conversation_id,
+                source,
                 files: vec![],
             },
-            move |(conversation_id, files)| converter((conversation_id, files)),
+            move |(source, files)| converter((source, files)),
         )
     }
+
+    /// Registers a file or folder to be indexed again when its files change,
+    /// the stream of [`VectorService::sync_watched_paths`] indexes it first.
+    ///
+    /// # Errors
+    pub fn add_watched_path(
+        &self,
+        source: RagSource,
+        path: &Path,
+        include: &[String],
+        exclude: &[String],
+    ) -> AppResult<WatchedPathDTO> {
+        let path = path
+            .canonicalize()
+            .map_err(|e| AppError::file(format!("{}: {e}", path.display())))?;
+
+        Self::path_filter(&path, include, exclude)?;
+
+        let watched_path = self
+            .vector_db
+            .add_watched_path(source, &path.to_string_lossy(), include, exclude)
+            .map_err(AppError::database)?;
+
+        self.update_watches()?;
+
+        Ok(watched_path)
+    }
+
+    /// # Errors
+    pub fn get_watched_paths(&self, source: RagSource) -> AppResult<Vec<WatchedPathDTO>> {
+        self.vector_db
+            .get_watched_paths(source)
+            .map_err(AppError::database)
+    }
+
+    /// Stops watching the path and deletes the files indexed from it.
+    ///
+    /// # Errors
+    pub fn delete_watched_path(&self, watched_path: &WatchedPathDTO) -> AppResult<()> {
+        let source = watched_path.source;
+
+        self.vector_db
+            .delete_watched_path(source, watched_path.id)
+            .map_err(AppError::database)?;
+
+        self.update_watches()?;
+
+        self.delete_unlisted_files(source, &[PathBuf::from(&watched_path.path)], &HashSet::new())?;
+
+        let files = self.get_files(source)?;
+
+        self.event_system
+            .clone()
+            .dispatch(utils::event_system::Event::RagFilesUpdated {
+                source,
+                files,
+            });
+
+        Ok(())
+    }
+
+    /// Indexes the watched paths again when the app starts, their files may
+    /// have changed while it was closed, and then whenever their files change.
+    /// # Panics
+    pub fn subscribe_watched_paths<T>(
+        &self,
+        converter: impl Fn(ProgressStatus) -> T + Send + Sync + 'static,
+    ) -> Subscription<T>
+    where
+        T: Debug + Send + 'static,
+    {
+        let self_cp = self.clone();
+
+        let stream = async_fn_stream::fn_stream(async move |output| {
+            let (sender, mut receiver) = mpsc::unbounded::<PathBuf>();
+
+            let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if Self::is_content_change(event.kind) => {
+                    for path in event.paths {
+                        let _ = sender.unbounded_send(path);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to watch files: {e}"),
+            });
+
+            match watcher {
+                Ok(watcher) => {
+                    *self_cp.paths_watcher.lock().expect("Failed to lock watcher") = Some(PathsWatcher {
+                        watcher,
+                        paths: HashSet::new(),
+                    });
+                }
+                Err(e) => log::error!("Failed to watch files: {e}"),
+            }
+
+            // Every watched path is indexed on startup
+            let mut changed_paths: Option<Vec<PathBuf>> = None;
+
+            loop {
+                let watched_paths = self_cp.update_watches().unwrap_or_else(|e| {
+                    log::error!("Failed to get watched paths: {e}");
+                    vec![]
+                });
+
+                let mut changed_by_source: HashMap<RagSource, Vec<WatchedPathDTO>> = HashMap::new();
+
+                for watched_path in watched_paths {
+                    let is_changed = changed_paths.as_ref().is_none_or(|changed| {
+                        changed.iter().any(|path| path.starts_with(&watched_path.path))
+                    });
+
+                    if is_changed {
+                        changed_by_source.entry(watched_path.source).or_default().push(watched_path);
+                    }
+                }
+
+                for (source, watched_paths) in changed_by_source {
+                    let Ok(mut progress) = self_cp.sync_watched_paths(source, &watched_paths) else {
+                        continue;
+                    };
+
+                    while let Some(status) = progress.next().await {
+                        output.emit(converter(status)).await;
+                    }
+                }
+
+                let Some(path) = receiver.next().await else {
+                    return;
+                };
+
+                let mut changed = vec![path];
+                while let Ok(Some(path)) = tokio::time::timeout(WATCH_DEBOUNCE, receiver.next()).await {
+                    changed.push(path);
+                }
+
+                changed_paths = Some(changed);
+            }
+        });
+
+        Subscription::run_with_id("watched_paths", stream)
+    }
+
+    /// Written, created, removed or renamed, a change of metadata only does
+    /// not change what is indexed.
+    const fn is_content_change(kind: EventKind) -> bool {
+        matches!(
+            kind,
+            EventKind::Create(_)
+                | EventKind::Remove(_)
+                | EventKind::Modify(ModifyKind::Any | ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Other)
+        )
+    }
+
+    /// Watches the watched paths of every source, unwatching the ones that are
+    /// not anymore, and returns them. Nothing is watched before the watcher is
+    /// started.
+    fn update_watches(&self) -> AppResult<Vec<WatchedPathDTO>> {
+        let watched_paths = self
+            .vector_db
+            .get_all_watched_paths()
+            .map_err(AppError::database)?;
+
+        let mut paths_watcher = self.paths_watcher.lock().expect("Failed to lock watcher");
+
+        let Some(PathsWatcher { watcher, paths }) = paths_watcher.as_mut() else {
+            return Ok(watched_paths);
+        };
+
+        let current: HashSet<PathBuf> = watched_paths
+            .iter()
+            .map(|watched_path| PathBuf::from(&watched_path.path))
+            .collect();
+
+        for path in paths.difference(&current) {
+            if let Err(e) = watcher.unwatch(path) {
+                log::warn!("Failed to stop watching {}: {e}", path.display());
+            }
+        }
+
+        // A path that cannot be watched yet is tried again on the next update
+        let watching = current
+            .into_iter()
+            .filter(|path| {
+                paths.contains(path)
+                    || watcher
+                        .watch(path, RecursiveMode::Recursive)
+                        .inspect_err(|e| log::warn!("Failed to watch {}: {e}", path.display()))
+                        .is_ok()
+            })
+            .collect();
+
+        *paths = watching;
+
+        Ok(watched_paths)
+    }
+
+    /// # Errors
+    pub fn get_knowledge_bases(&self) -> AppResult<Vec<KnowledgeBaseDTO>> {
+        self.knowledge_db.get_all().map_err(AppError::database)
+    }
+
+    /// # Errors
+    pub fn get_knowledge_base(&self, id: KnowledgeBaseID) -> AppResult<KnowledgeBaseDTO> {
+        self.knowledge_db
+            .get(id)
+            .map_err(AppError::database)?
+            .ok_or_else(|| AppError::not_found("Knowledge base"))
+    }
+
+    /// # Errors
+    pub fn create_knowledge_base(&self, name: &str) -> AppResult<KnowledgeBaseDTO> {
+        self.knowledge_db.create(name).map_err(AppError::database)
+    }
+
+    /// # Errors
+    pub fn update_knowledge_base(&self, knowledge_base: &KnowledgeBaseDTO) -> AppResult<()> {
+        self.knowledge_db
+            .update(knowledge_base)
+            .map_err(AppError::database)
+    }
+
+    /// Deletes the knowledge base with its files, chats it was attached to
+    /// keep their own files.
+    ///
+    /// # Errors
+    pub fn delete_knowledge_base(&self, id: KnowledgeBaseID) -> AppResult<()> {
+        self.delete_all_files(RagSource::KnowledgeBase(id))?;
+
+        self.knowledge_db.delete(id).map_err(AppError::database)
+    }
+
+    /// # Errors
+    pub fn attach_knowledge_base(
+        &self,
+        id: KnowledgeBaseID,
+        node_id: ConversationNodeID,
+    ) -> AppResult<()> {
+        self.knowledge_db
+            .attach(id, node_id)
+            .map_err(AppError::database)
+    }
+
+    /// # Errors
+    pub fn detach_knowledge_base(
+        &self,
+        id: KnowledgeBaseID,
+        node_id: ConversationNodeID,
+    ) -> AppResult<()> {
+        self.knowledge_db
+            .detach(id, node_id)
+            .map_err(AppError::database)
+    }
+
+    /// # Errors
+    pub fn detach_node(&self, node_id: ConversationNodeID) -> AppResult<()> {
+        self.knowledge_db
+            .detach_node(node_id)
+            .map_err(AppError::database)
+    }
+
+    /// Chats and folders the knowledge base is attached to.
+    ///
+    /// # Errors
+    pub fn get_attached_nodes(&self, id: KnowledgeBaseID) -> AppResult<Vec<ConversationNodeID>> {
+        self.knowledge_db
+            .get_nodes(id)
+            .map_err(AppError::database)
+    }
+
+    /// Knowledge bases attached to the node itself.
+    ///
+    /// # Errors
+    pub fn get_linked_knowledge_bases(
+        &self,
+        node_id: ConversationNodeID,
+    ) -> AppResult<Vec<KnowledgeBaseID>> {
+        self.knowledge_db
+            .get_linked(node_id)
+            .map_err(AppError::database)
+    }
+
+    /// Knowledge bases the chat searches, its own and those of its folders.
+    ///
+    /// # Errors
+    pub fn get_attached_knowledge_bases(
+        &self,
+        conversation_id: ConversationNodeID,
+    ) -> AppResult<Vec<KnowledgeBaseDTO>> {
+        self.knowledge_db
+            .get_attached(conversation_id)
+            .map_err(AppError::database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::dto::{ChunkRagId, MessageUsedRagChunk};
+
+    use super::VectorService;
+
+    fn chunk(chunk_id: ChunkRagId) -> MessageUsedRagChunk {
+        MessageUsedRagChunk {
+            chunk_id,
+            dimension: 4,
+            embedding_model: "model".to_string(),
+            knowledge_base_id: None,
+            distance: None,
+        }
+    }
+
+    fn fused(rankings: &[(f32, &[ChunkRagId])], count: usize) -> Vec<ChunkRagId> {
+        let rankings = rankings
+            .iter()
+            .map(|(weight, ranking)| (*weight, ranking.iter().copied().map(chunk).collect()))
+            .collect();
+
+        VectorService::fuse_rankings(rankings, count)
+            .into_iter()
+            .map(|chunk| chunk.chunk_id)
+            .collect()
+    }
+
+    #[test]
+    fn chunks_found_by_both_searches_rank_first() {
+        assert_eq!(fused(&[(1.0, &[1, 2]), (1.0, &[2, 3])], 3), vec![2, 1, 3]);
+        assert_eq!(fused(&[(1.0, &[1, 2]), (1.0, &[2, 3])], 2), vec![2, 1]);
+    }
+
+    #[test]
+    fn weights_scale_the_rankings() {
+        assert_eq!(fused(&[(1.0, &[1]), (2.0, &[2])], 2), vec![2, 1]);
+        assert_eq!(fused(&[(2.0, &[1]), (1.0, &[2])], 2), vec![1, 2]);
+    }
+
+    #[test]
+    fn rankings_without_weight_are_left_out() {
+        assert_eq!(fused(&[(0.0, &[1, 2]), (1.0, &[3, 2])], 3), vec![3, 2]);
+    }
+
+    fn scores(answer: &str, documents: usize) -> Vec<(usize, f32)> {
+        VectorService::parse_chat_scores(answer, documents)
+            .into_iter()
+            .map(|result| (result.index, result.relevance_score))
+            .collect()
+    }
+
+    #[test]
+    fn parses_chat_scores_in_the_usual_shapes() {
+        let answer = "0: 7\n[1]: 3/10\n**2**: **9.5**\n3: 4 - mentions the topic";
+
+        assert_eq!(scores(answer, 4), vec![(0, 7.0), (1, 3.0), (2, 9.5), (3, 4.0)]);
+    }
+
+    #[test]
+    fn skips_lines_that_are_not_scores() {
+        let answer = "Here are the scores:\n\n0: 8\npassage one: 5\n1: high\n2: 6";
+
+        assert_eq!(scores(answer, 3), vec![(0, 8.0), (2, 6.0)]);
+    }
+
+    #[test]
+    fn skips_scores_of_unknown_passages() {
+        assert_eq!(scores("0: 8\n5: 9\n1: 2", 2), vec![(0, 8.0), (1, 2.0)]);
+    }
 }
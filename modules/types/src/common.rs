@@ -1,5 +1,10 @@
 use std::ops::Range;
 
+use crate::{
+    dto::ConversationNodeID,
+    error::{AppError, ErrorSeverity},
+};
+
 
 #[derive(Debug, Clone)]
 pub enum ProgressStatus
@@ -9,4 +14,41 @@ where
     Started,
     Progress { name: String, range: Range<usize>, current: usize },
     Finished,
+    Failed(AppError),
+}
+
+/// What of a conversation goes into the next request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContextUsage {
+    /// History messages that fit.
+    pub messages: usize,
+    pub tokens: usize,
+    /// Context window of the model without the tokens reserved for the answer,
+    /// `None` when the size of the model is not known.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl From<ErrorSeverity> for NotificationLevel {
+    fn from(severity: ErrorSeverity) -> Self {
+        match severity {
+            ErrorSeverity::Info => Self::Info,
+            ErrorSeverity::Warning => Self::Warning,
+            ErrorSeverity::Error => Self::Error,
+        }
+    }
+}
+
+/// Where the user can fix what a notification is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationAction {
+    /// Settings of the chat, where its models are picked.
+    OpenModelPicker(ConversationNodeID),
+    OpenProviders,
 }
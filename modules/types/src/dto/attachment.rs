@@ -0,0 +1,45 @@
+use std::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+
+use super::MessageID;
+
+pub type AttachmentID = i64;
+
+pub const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Binary file sent along with a message, only images for now.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentDTO {
+    pub id: AttachmentID,
+    pub message_id: MessageID,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl AttachmentDTO {
+    #[must_use]
+    pub fn image_mime_type(extension: &str) -> Option<&'static str> {
+        match extension.to_lowercase().as_str() {
+            "png" => Some("image/png"),
+            "jpg" | "jpeg" => Some("image/jpeg"),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn is_image(&self) -> bool {
+        self.mime_type.starts_with("image/")
+    }
+}
+
+impl Debug for AttachmentDTO {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttachmentDTO")
+            .field("id", &self.id)
+            .field("message_id", &self.message_id)
+            .field("mime_type", &self.mime_type)
+            .field("data", &format_args!("{} bytes", self.data.len()))
+            .finish()
+    }
+}
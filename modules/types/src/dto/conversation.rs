@@ -1,15 +1,29 @@
+use serde::{Deserialize, Serialize};
+
 use super::{PresetId, ProviderID};
 
 pub type ConversationNodeID = i64;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Vector matches further away than this are not relevant enough to be used.
+pub const DEFAULT_RAG_DISTANCE_CUTOFF: f32 = 1.05;
+
+const fn default_rag_weight() -> f32 {
+    1.0
+}
+
+const fn default_rag_distance_cutoff() -> f32 {
+    DEFAULT_RAG_DISTANCE_CUTOFF
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConversationType {
     #[allow(dead_code)]
     Chat,
     Folder,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ConversationNodeDTO {
     pub id: ConversationNodeID,
     #[allow(dead_code)]
@@ -25,12 +39,44 @@ pub struct ConversationNodeDTO {
     pub embedding_provider: Option<ProviderID>,
     pub rag_chunk_size: usize,
     pub rag_chunks_count: usize,
+    /// Weight of the vector search when its ranking is fused with the keyword search.
+    #[serde(default = "default_rag_weight")]
+    pub rag_vector_weight: f32,
+    /// Weight of the keyword search, 0 searches by vectors only.
+    #[serde(default = "default_rag_weight")]
+    pub rag_keyword_weight: f32,
+    /// Vector matches further away are dropped.
+    #[serde(default = "default_rag_distance_cutoff")]
+    pub rag_distance_cutoff: f32,
+    /// Candidates passed to the rerank model, 0 keeps the fused ranking.
+    #[serde(default)]
+    pub rag_rerank_candidates: usize,
+    #[serde(default)]
+    pub rerank_model: Option<String>,
+    #[serde(default)]
+    pub rerank_provider: Option<ProviderID>,
+    /// The last message is rewritten into a standalone query before retrieval.
+    #[serde(default)]
+    pub rag_query_rewrite: bool,
+    /// Extra queries for parts of the question, searched along with the rewritten one.
+    #[serde(default)]
+    pub rag_sub_queries: usize,
+    /// A hypothetical answer is searched too, it reads more like the files than the question.
+    #[serde(default)]
+    pub rag_hyde: bool,
+    #[serde(default)]
+    pub rewrite_model: Option<String>,
+    #[serde(default)]
+    pub rewrite_provider: Option<ProviderID>,
     pub summary_enabled: bool,
     pub summary_model: Option<String>,
     pub summary_provider: Option<ProviderID>,
     pub provider: Option<ProviderID>,
     pub model: Option<String>,
     pub prompt: String,
+    pub tools_enabled: bool,
+    /// Tokens the model takes in, 0 uses the known size of the model.
+    pub context_size: usize,
 }
 
 impl ConversationNodeDTO {
@@ -51,6 +97,17 @@ impl ConversationNodeDTO {
             embedding_provider: None,
             rag_chunk_size: 512,
             rag_chunks_count: 2,
+            rag_vector_weight: 1.0,
+            rag_keyword_weight: 1.0,
+            rag_distance_cutoff: DEFAULT_RAG_DISTANCE_CUTOFF,
+            rag_rerank_candidates: 0,
+            rerank_model: None,
+            rerank_provider: None,
+            rag_query_rewrite: false,
+            rag_sub_queries: 0,
+            rag_hyde: false,
+            rewrite_model: None,
+            rewrite_provider: None,
             summary_enabled: false,
             summary_model: None,
             summary_provider: None,
@@ -58,6 +115,8 @@ impl ConversationNodeDTO {
             provider: None,
             model: None,
             prompt: String::new(),
+            tools_enabled: false,
+            context_size: 0,
         }
     }
 }
@@ -0,0 +1,54 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ConversationNodeDTO, MessageDTO, MessageID};
+
+/// Version of [`ConversationExportDTO`], raised on incompatible changes.
+pub const CONVERSATION_EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [Self; 3] = [Self::Markdown, Self::Json, Self::Html];
+
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Markdown => write!(f, "Markdown"),
+            Self::Json => write!(f, "JSON"),
+            Self::Html => write!(f, "HTML"),
+        }
+    }
+}
+
+/// A chat as it is written to JSON, with the settings and every branch of its messages.
+///
+/// This is the lossless exchange format of the client: a folder is exported
+/// as a directory of such files and imported back the same way. Ids only link
+/// the messages within the document, new ones are given on import. Attachments
+/// keep their bytes, `siblings` are left out as they follow from `parent_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationExportDTO {
+    pub version: u32,
+    pub conversation: ConversationNodeDTO,
+    /// Last message of the shown branch.
+    pub active_message_id: Option<MessageID>,
+    /// Oldest first, the branches are linked by `parent_id`.
+    pub messages: Vec<MessageDTO>,
+}
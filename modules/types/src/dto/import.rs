@@ -0,0 +1,26 @@
+use super::ConversationExportDTO;
+
+/// Folder or chat read from an import, written to the database as it is.
+#[derive(Debug, Clone)]
+pub enum ImportNodeDTO {
+    Folder {
+        name: String,
+        children: Vec<Self>,
+    },
+    Chat {
+        /// Identifies the chat in its origin, importing it again is reported as a duplicate.
+        source: String,
+        chat: Box<ConversationExportDTO>,
+    },
+}
+
+/// What an import did, skipped items are described for the user.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReportDTO {
+    pub folders: usize,
+    pub chats: usize,
+    pub messages: usize,
+    /// Names of the chats imported before.
+    pub duplicates: Vec<String>,
+    pub skipped: Vec<String>,
+}
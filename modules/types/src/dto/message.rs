@@ -1,14 +1,19 @@
 use serde::{Deserialize, Serialize};
 
-use super::{ChunkRagId, ConversationNodeID};
+use super::{
+    AttachmentDTO, ChunkRagId, ConversationNodeID, KnowledgeBaseID, PresetDTO, ProviderID, RagSource,
+    ToolCallDTO,
+};
 
 pub type MessageID = i64;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum RoleType {
     User,
     Assistant,
     System,
+    Tool,
 }
 
 impl RoleType {
@@ -18,6 +23,7 @@ impl RoleType {
             Self::User => "user".to_string(),
             Self::Assistant => "assistant".to_string(),
             Self::System => "system".to_string(),
+            Self::Tool => "tool".to_string(),
         }
     }
     
@@ -28,6 +34,7 @@ impl RoleType {
             "user" => Self::User,
             "system" => Self::System,
             "assistant" => Self::Assistant,
+            "tool" => Self::Tool,
             _ => panic!("Invalid role type"),
         }
     }
@@ -41,19 +48,53 @@ impl TryFrom<String> for RoleType {
             "user" => Ok(Self::User),
             "system" => Ok(Self::System),
             "assistant" => Ok(Self::Assistant),
+            "tool" => Ok(Self::Tool),
             _ => Err(format!("Invalid role type: {role}")),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MessageUsedRagChunk {
     pub chunk_id: ChunkRagId,
     pub dimension: usize,
     pub embedding_model: String,
+    /// `None` for files of the conversation itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub knowledge_base_id: Option<KnowledgeBaseID>,
+    /// Closest vector distance to the queries, `None` for chunks found by keywords only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<f32>,
 }
 
-#[derive(Debug, Clone)]
+impl MessageUsedRagChunk {
+    #[must_use]
+    pub const fn source(&self, conversation_id: ConversationNodeID) -> RagSource {
+        match self.knowledge_base_id {
+            Some(knowledge_base_id) => RagSource::KnowledgeBase(knowledge_base_id),
+            None => RagSource::Conversation(conversation_id),
+        }
+    }
+}
+
+/// How an answer was generated, kept as it was at that time.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationDTO {
+    pub provider_id: Option<ProviderID>,
+    pub model: String,
+    pub preset: Option<PresetDTO>,
+    /// Counted with a tokenizer when the provider does not report usage.
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// Milliseconds from the request to the first received token.
+    pub time_to_first_token: Option<u64>,
+    /// Milliseconds from the request to the end of the answer.
+    pub duration: Option<u64>,
+    /// Why the model stopped, as the provider names it.
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageDTO {
     pub id: MessageID,
     pub conversation_id: ConversationNodeID,
@@ -63,6 +104,24 @@ pub struct MessageDTO {
     pub role: RoleType,
     pub summary: Option<String>,
     pub chunks: Vec<MessageUsedRagChunk>,
+    /// What was searched for `chunks` when the message was rewritten before retrieval.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rag_queries: Vec<String>,
+    /// Generation was stopped by the user before the model finished.
+    pub interrupted: bool,
+    /// Tools the assistant asked to run.
+    pub tool_calls: Vec<ToolCallDTO>,
+    /// Call answered by a `Tool` message.
+    pub tool_call_id: Option<String>,
+    /// Images sent with the message, stored in their own table.
+    pub attachments: Vec<AttachmentDTO>,
+    /// Message this one follows, `None` for the first message of a conversation.
+    pub parent_id: Option<MessageID>,
+    /// Alternatives of this message sharing its parent, itself included, oldest first.
+    #[serde(skip)]
+    pub siblings: Vec<MessageID>,
+    /// `None` for messages that were not generated.
+    pub generation: Option<GenerationDTO>,
 }
 
 impl Default for MessageDTO {
@@ -76,6 +135,84 @@ impl Default for MessageDTO {
             role: RoleType::User,
             summary: None,
             chunks: Vec::new(),
+            rag_queries: Vec::new(),
+            interrupted: false,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            attachments: Vec::new(),
+            parent_id: None,
+            siblings: Vec::new(),
+            generation: None,
+        }
+    }
+}
+
+impl MessageDTO {
+    /// Position among the alternatives, `(1, 1)` when there are none.
+    #[must_use]
+    pub fn branch_position(&self) -> (usize, usize) {
+        let index = self
+            .siblings
+            .iter()
+            .position(|id| *id == self.id)
+            .unwrap_or_default();
+
+        (index + 1, self.siblings.len().max(1))
+    }
+}
+
+/// Marks the start of a matched term in [`MessageSearchResultDTO::snippet`].
+pub const SEARCH_MATCH_START: char = '\u{2}';
+/// Marks the end of a matched term in [`MessageSearchResultDTO::snippet`].
+pub const SEARCH_MATCH_END: char = '\u{3}';
+
+/// Narrows a message search, `None` matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageSearchFilters {
+    /// Only chats inside this folder, at any depth.
+    pub folder_id: Option<ConversationNodeID>,
+    pub role: Option<RoleType>,
+    /// Inclusive `YYYY-MM-DD` bounds of the message date.
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    /// Part of the model name selected in the chat of the message.
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageSearchResultDTO {
+    pub message_id: MessageID,
+    pub conversation_id: ConversationNodeID,
+    pub conversation_name: String,
+    pub role: RoleType,
+    pub timestamp: String,
+    /// Text around the match, matched terms are wrapped in
+    /// [`SEARCH_MATCH_START`] and [`SEARCH_MATCH_END`].
+    pub snippet: String,
+}
+
+impl MessageSearchResultDTO {
+    /// Snippet split into parts, `true` for the matched ones.
+    #[must_use]
+    pub fn snippet_parts(&self) -> Vec<(&str, bool)> {
+        let mut parts = vec![];
+
+        for (index, part) in self.snippet.split(SEARCH_MATCH_START).enumerate() {
+            // Nothing before the first start marker is matched
+            let (matched, rest) = match part.split_once(SEARCH_MATCH_END) {
+                Some((matched, rest)) if index > 0 => (matched, rest),
+                _ => ("", part),
+            };
+
+            if !matched.is_empty() {
+                parts.push((matched, true));
+            }
+
+            if !rest.is_empty() {
+                parts.push((rest, false));
+            }
         }
+
+        parts
     }
 }
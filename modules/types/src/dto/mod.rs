@@ -1,11 +1,21 @@
+mod attachment;
 mod conversation;
+mod export;
+mod import;
 mod message;
 mod preset;
 mod provider;
 mod rag;
+mod tool;
+mod usage;
 
+pub use attachment::*;
 pub use conversation::*;
+pub use export::*;
+pub use import::*;
 pub use message::*;
 pub use preset::*;
 pub use provider::*;
 pub use rag::*;
+pub use tool::*;
+pub use usage::*;
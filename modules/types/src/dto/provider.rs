@@ -2,6 +2,30 @@ use std::fmt::Display;
 
 pub type ProviderID = i64;
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProviderKind {
+    #[default]
+    OpenAi,
+    Anthropic,
+}
+
+impl ProviderKind {
+    pub const ALL: [Self; 2] = [Self::OpenAi, Self::Anthropic];
+}
+
+/// Used when a provider does not define its own reasoning tags.
+pub const DEFAULT_REASONING_TAGS: &str =
+    "<reasoning> </reasoning>, <think> </think>, <thinking> </thinking>, <reason> </reason>";
+
+impl Display for ProviderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenAi => write!(f, "OpenAI Compatible"),
+            Self::Anthropic => write!(f, "Anthropic"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct LmModel {
     pub model_name: String,
@@ -17,6 +41,9 @@ pub struct ProviderDTO {
     pub url: String,
     pub api_key: String,
     pub default_model: String,
+    pub kind: ProviderKind,
+    /// Comma separated pairs of open and close tags, e.g. `<think> </think>, [THINK] [/THINK]`.
+    pub reasoning_tags: String,
 }
 
 impl ProviderDTO {
@@ -25,6 +52,28 @@ impl ProviderDTO {
             && self.url == dto.url
             && self.api_key == dto.api_key
             && self.default_model == dto.default_model
+            && self.kind == dto.kind
+            && self.reasoning_tags == dto.reasoning_tags
+    }
+
+    /// Open and close tags wrapping reasoning inside the content of this provider.
+    #[must_use]
+    pub fn reasoning_tag_pairs(&self) -> Vec<(String, String)> {
+        let tags = if self.reasoning_tags.trim().is_empty() {
+            DEFAULT_REASONING_TAGS
+        } else {
+            &self.reasoning_tags
+        };
+
+        tags.split(',')
+            .filter_map(|pair| {
+                let mut parts = pair.split_whitespace();
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(open), Some(close), None) => Some((open.to_string(), close.to_string())),
+                    _ => None,
+                }
+            })
+            .collect()
     }
 }
 
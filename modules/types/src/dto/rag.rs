@@ -1,11 +1,16 @@
+use super::{ConversationNodeID, ProviderID};
+
 pub type RagFileID = i64;
 
 #[derive(Debug, Clone)]
 pub struct RagFileDTO {
     pub file_name: String,
+    /// `None` for files loaded before their path was kept.
+    pub file_path: Option<String>,
     pub id: RagFileID,
     pub dimension: usize,
     pub embedding_model: String,
+    pub chunks: usize,
 }
 
 pub type ChunkRagId = i64;
@@ -15,4 +20,99 @@ pub struct ChunkRagDTO {
     pub id: ChunkRagId,
     pub file_id: RagFileID,
     pub chunk: String,
+    /// Page of the document the chunk starts on, counted from 1.
+    pub page: Option<usize>,
+    /// First and last line of a source file the chunk spans, counted from 1.
+    pub lines: Option<(usize, usize)>,
+    /// Start and end of the chunk in the text extracted from its file, in bytes.
+    pub bytes: Option<(usize, usize)>,
+}
+
+impl ChunkRagDTO {
+    /// Where the chunk was taken from, like `spec.pdf p. 42` or `src/lib.rs:120-168`.
+    #[must_use]
+    pub fn location(&self, file_name: &str) -> String {
+        match (self.lines, self.page) {
+            (Some((start, end)), _) if start == end => format!("{file_name}:{start}"),
+            (Some((start, end)), _) => format!("{file_name}:{start}-{end}"),
+            (None, Some(page)) => format!("{file_name} p. {page}"),
+            (None, None) => file_name.to_string(),
+        }
+    }
+}
+
+/// Chunk of a file that is not embedded yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChunk {
+    pub text: String,
+    pub page: Option<usize>,
+    pub lines: Option<(usize, usize)>,
+    pub bytes: Option<(usize, usize)>,
+}
+
+/// File whose chunks are stored in place of the ones stored for its path.
+#[derive(Debug, Clone)]
+pub struct IndexedFile {
+    pub path: String,
+    pub name: String,
+    pub hash: String,
+    /// The text the byte ranges of the chunks point into.
+    pub content: String,
+}
+
+/// Chunk with the text around it in its file, empty when the text of the
+/// file was not kept.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkContextDTO {
+    pub before: String,
+    pub chunk: String,
+    pub after: String,
+}
+
+pub type KnowledgeBaseID = i64;
+
+/// Whose files and vectors they are, each keeps them in tables of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RagSource {
+    /// Files loaded into one chat.
+    Conversation(ConversationNodeID),
+    /// Files shared by the chats and folders it is attached to.
+    KnowledgeBase(KnowledgeBaseID),
+}
+
+pub type WatchedPathID = i64;
+
+/// File or folder indexed again on startup and whenever it changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedPathDTO {
+    pub id: WatchedPathID,
+    pub source: RagSource,
+    pub path: String,
+    /// Globs of the files of a folder that are indexed, all of them when empty.
+    pub include: Vec<String>,
+    /// Globs of the files of a folder that are left out.
+    pub exclude: Vec<String>,
+}
+
+impl WatchedPathDTO {
+    /// Globs typed separated by commas.
+    #[must_use]
+    pub fn split_globs(text: &str) -> Vec<String> {
+        text.split(',')
+            .map(str::trim)
+            .filter(|glob| !glob.is_empty())
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+/// Named set of files embedded once and searched from every chat it is
+/// attached to, directly or through a folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnowledgeBaseDTO {
+    pub id: KnowledgeBaseID,
+    pub name: String,
+    pub embedding_provider: Option<ProviderID>,
+    pub embedding_model: Option<String>,
+    pub chunk_size: usize,
 }
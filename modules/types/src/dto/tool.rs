@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Function the model is allowed to call, `parameters` is a JSON Schema object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolDefinitionDTO {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+/// Call requested by the model, `arguments` is the raw JSON produced by it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCallDTO {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+}
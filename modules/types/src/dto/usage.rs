@@ -0,0 +1,49 @@
+use std::fmt::Display;
+
+use super::ProviderID;
+
+/// Price of a model in USD per million tokens.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelPriceDTO {
+    pub provider_id: ProviderID,
+    pub model: String,
+    pub prompt_price: f64,
+    pub completion_price: f64,
+}
+
+/// How the usage dashboard groups completions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UsageGrouping {
+    #[default]
+    Provider,
+    Model,
+    Conversation,
+    Day,
+}
+
+impl UsageGrouping {
+    pub const ALL: [Self; 4] = [Self::Provider, Self::Model, Self::Conversation, Self::Day];
+}
+
+impl Display for UsageGrouping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Provider => write!(f, "Provider"),
+            Self::Model => write!(f, "Model"),
+            Self::Conversation => write!(f, "Conversation"),
+            Self::Day => write!(f, "Day"),
+        }
+    }
+}
+
+/// Completions of one group, the cost is estimated with the current prices.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageRowDTO {
+    pub label: String,
+    pub requests: usize,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub cost: f64,
+    /// Some of the completions were made with a model without a price.
+    pub unpriced: bool,
+}
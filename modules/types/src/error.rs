@@ -0,0 +1,117 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+pub type AppResult<T> = Result<T, AppError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorSeverity {
+    /// Expected outcome of a user action, nothing is broken.
+    Info,
+    /// The action failed, but the user can fix it.
+    Warning,
+    Error,
+}
+
+/// Error shared by every layer, from the api and database up to the GUI.
+///
+/// Variants keep only text so the error can be cloned into GUI messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AppError {
+    /// The request did not reach the server or the connection dropped.
+    Network(String),
+    /// The server answered with a non-success status.
+    Http(u16, String),
+    Database(String),
+    /// Reading or writing a file on the disk failed.
+    File(String),
+    /// Data that does not have the expected format.
+    Parse(String),
+    /// Missing or wrong input, e.g. no model selected.
+    Validation(String),
+    NotFound(String),
+    Cancelled,
+}
+
+impl AppError {
+    pub fn network(error: impl Display) -> Self {
+        Self::Network(error.to_string())
+    }
+
+    pub fn database(error: impl Display) -> Self {
+        Self::Database(error.to_string())
+    }
+
+    pub fn file(error: impl Display) -> Self {
+        Self::File(error.to_string())
+    }
+
+    pub fn parse(error: impl Display) -> Self {
+        Self::Parse(error.to_string())
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::Validation(message.into())
+    }
+
+    pub fn not_found(what: impl Into<String>) -> Self {
+        Self::NotFound(what.into())
+    }
+
+    #[must_use]
+    pub const fn severity(&self) -> ErrorSeverity {
+        match self {
+            Self::Cancelled => ErrorSeverity::Info,
+            Self::Validation(_) | Self::NotFound(_) => ErrorSeverity::Warning,
+            Self::Network(_)
+            | Self::Http(_, _)
+            | Self::Database(_)
+            | Self::File(_)
+            | Self::Parse(_) => {
+                ErrorSeverity::Error
+            }
+        }
+    }
+
+    /// Short explanation for the GUI, details stay in [`Display`] for the logs.
+    #[must_use]
+    pub fn user_message(&self) -> String {
+        match self {
+            Self::Network(_) => "Could not connect to the provider, check the URL and your connection".to_string(),
+            Self::Http(401 | 403, _) => "The provider rejected the API key".to_string(),
+            Self::Http(404, _) => "The provider does not know this endpoint or model".to_string(),
+            Self::Http(429, _) => "Rate limit exceeded, try again later".to_string(),
+            Self::Http(status, _) if *status >= 500 => format!("The provider failed with status {status}"),
+            Self::Http(status, body) => format!("The provider answered with status {status}: {body}"),
+            Self::Database(_) => "Failed to access the local database".to_string(),
+            Self::File(error) => format!("Failed to access the file: {error}"),
+            Self::Parse(_) => "Received data in an unexpected format".to_string(),
+            Self::Validation(message) => message.clone(),
+            Self::NotFound(what) => format!("{what} not found"),
+            Self::Cancelled => "Cancelled".to_string(),
+        }
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Network(error) => write!(f, "Network error: {error}"),
+            Self::Http(status, body) => write!(f, "HTTP error {status}: {body}"),
+            Self::Database(error) => write!(f, "Database error: {error}"),
+            Self::File(error) => write!(f, "File error: {error}"),
+            Self::Parse(error) => write!(f, "Parse error: {error}"),
+            Self::Validation(message) => write!(f, "Validation error: {message}"),
+            Self::NotFound(what) => write!(f, "{what} not found"),
+            Self::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<serde_json::Error> for AppError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::parse(error)
+    }
+}
@@ -0,0 +1,21 @@
+/// Image currently held by the system clipboard, encoded as PNG.
+#[must_use]
+pub fn read_image_png() -> Option<Vec<u8>> {
+    let image = arboard::Clipboard::new().ok()?.get_image().ok()?;
+
+    let mut png_data = Vec::new();
+
+    let mut encoder = png::Encoder::new(
+        &mut png_data,
+        u32::try_from(image.width).ok()?,
+        u32::try_from(image.height).ok()?,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().ok()?;
+    writer.write_image_data(&image.bytes).ok()?;
+    writer.finish().ok()?;
+
+    Some(png_data)
+}
@@ -5,7 +5,7 @@ use std::{
 };
 
 use iced::{Subscription, futures::stream};
-use types::{common::ProgressStatus, dto::{ConversationNodeDTO, ConversationNodeID, MessageDTO, MessageID, PresetDTO, ProviderDTO, RagFileDTO}};
+use types::{common::{NotificationAction, NotificationLevel, ProgressStatus}, dto::{ConversationNodeDTO, ConversationNodeID, MessageDTO, MessageID, PresetDTO, ProviderDTO, RagFileDTO, RagSource}, error::AppError};
 use uuid::Uuid;
 
 #[derive(Debug, Clone)]
@@ -14,13 +14,19 @@ pub enum Event {
     ConversationReceiveMessage(MessageDTO),
     MessageDelete(MessageID),
     RagFilesUpdated {
-        conversation_id: ConversationNodeID,
+        source: RagSource,
         files: Vec<RagFileDTO>,
     },
     ConversationDelete(ConversationNodeID),
     ProvidersUpdate(Vec<ProviderDTO>),
     LoadingFilesStatus(ProgressStatus),
     UpdatePresets(Vec<PresetDTO>),
+    Notify {
+        level: NotificationLevel,
+        title: String,
+        body: String,
+        action: Option<NotificationAction>,
+    },
 }
 
 impl PartialEq for Event {
@@ -29,14 +35,10 @@ impl PartialEq for Event {
             (Self::ConversationUpdate(a), Self::ConversationUpdate(b)) => a.id == b.id,
 
             (Self::MessageDelete(a), Self::MessageDelete(b)) |
-            (Self::ConversationDelete(a), Self::ConversationDelete(b)) |
+            (Self::ConversationDelete(a), Self::ConversationDelete(b)) => a == b,
             (
-                Self::RagFilesUpdated {
-                    conversation_id: a, ..
-                },
-                Self::RagFilesUpdated {
-                    conversation_id: b, ..
-                },
+                Self::RagFilesUpdated { source: a, .. },
+                Self::RagFilesUpdated { source: b, .. },
             ) => a == b,
 
             (Self::ConversationReceiveMessage(a), Self::ConversationReceiveMessage(b)) => {
@@ -44,7 +46,8 @@ impl PartialEq for Event {
             }
             (Self::LoadingFilesStatus(_a), Self::LoadingFilesStatus(_b)) => true,
             (Self::ProvidersUpdate(_a), Self::ProvidersUpdate(_b)) => true,
-            (Self::UpdatePresets(_a), Self::UpdatePresets(_b)) => true,
+            (Self::UpdatePresets(_), Self::UpdatePresets(_)) |
+            (Self::Notify { .. }, Self::Notify { .. }) => true,
             _ => false,
         }
     }
@@ -64,9 +67,16 @@ impl Event {
             Self::ConversationDelete(data) => Box::new(*data),
 
             Self::RagFilesUpdated {
-                conversation_id,
+                source,
                 files,
-            } => Box::new((*conversation_id, files.clone())),
+            } => Box::new((*source, files.clone())),
+
+            Self::Notify {
+                level,
+                title,
+                body,
+                action,
+            } => Box::new((*level, title.clone(), body.clone(), *action)),
         }
     }
 }
@@ -111,6 +121,35 @@ impl EventSystem {
         queue.push(event);
     }
 
+    pub fn notify(
+        &mut self,
+        level: NotificationLevel,
+        title: impl Into<String>,
+        body: impl Into<String>,
+        action: Option<NotificationAction>,
+    ) {
+        self.dispatch(Event::Notify {
+            level,
+            title: title.into(),
+            body: body.into(),
+            action,
+        });
+    }
+
+    /// Reports a failed operation, cancellations are up to the user and not reported.
+    pub fn notify_error(
+        &mut self,
+        title: impl Into<String>,
+        error: &AppError,
+        action: Option<NotificationAction>,
+    ) {
+        if *error == AppError::Cancelled {
+            return;
+        }
+
+        self.notify(error.severity().into(), title, error.user_message(), action);
+    }
+
     /// # Panics
     pub fn subscribe<TData, TMessage>(
         &self,
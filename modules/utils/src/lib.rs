@@ -2,6 +2,7 @@ mod common;
 pub mod focus_manager;
 pub use common::*;
 
+pub mod clipboard;
 pub mod notify;
 
 pub mod event_system;
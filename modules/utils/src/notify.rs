@@ -34,3 +34,12 @@ pub fn file_selection(title: impl Into<String>) -> Vec<PathBuf> {
         .show()
         .expect("Failed to show file selection dialog")
 }
+
+/// # Panics
+pub fn directory_selection(title: impl Into<String>) -> Option<PathBuf> {
+    native_dialog::DialogBuilder::file()
+        .set_title(title.into())
+        .open_single_dir()
+        .show()
+        .expect("Failed to show directory selection dialog")
+}
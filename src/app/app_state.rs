@@ -1,4 +1,5 @@
 use framework::{
+    types::common::ProgressStatus,
     utils::{
         focus_manager, take_component
     }, Context
@@ -6,14 +7,19 @@ use framework::{
 
 use super::{
     conversations::{self, Conversations},
+    knowledge::{self, Knowledge},
+    notifications::{self, Notifications},
     presets::{self, Presets},
     settings::{self, Settings},
+    usage::{self, Usage},
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum View {
     Conversations,
     Presets,
+    Knowledge,
+    Usage,
     Settings,
 }
 
@@ -21,9 +27,13 @@ pub enum View {
 pub enum Message {
     Conversations(conversations::Message),
     Presets(presets::Message),
+    Knowledge(knowledge::Message),
     Settings(settings::Message),
+    Usage(usage::Message),
+    Notifications(notifications::Message),
     StartChangeView(View),
     CompleteChangeView(Option<View>),
+    WatchedPathsProgress(ProgressStatus),
 
     FocusManager(focus_manager::Message),
 }
@@ -32,7 +42,10 @@ pub struct App {
     // Components
     pub(super) conversations: Conversations,
     pub(super) presets: Presets,
+    pub(super) knowledge: Knowledge,
     pub(super) settings: Settings,
+    pub(super) usage: Usage,
+    pub(super) notifications: Notifications,
 
     // State
     pub(super) current_view: View,
@@ -42,11 +55,9 @@ pub struct App {
 }
 
 impl App {
-    pub fn new() -> (Self, iced::Task<Message>) {
+    pub fn new(context: Context) -> (Self, iced::Task<Message>) {
         let mut tasks = vec![];
 
-        let context = framework::Context::new();
-
         (
             Self {
                 context,
@@ -57,7 +68,14 @@ impl App {
                     Conversations::new(),
                 ),
                 presets: take_component(&mut tasks, Message::Presets, Presets::new()),
+                knowledge: take_component(&mut tasks, Message::Knowledge, Knowledge::new()),
                 settings: take_component(&mut tasks, Message::Settings, Settings::new()),
+                usage: take_component(&mut tasks, Message::Usage, Usage::new()),
+                notifications: take_component(
+                    &mut tasks,
+                    Message::Notifications,
+                    Notifications::new(),
+                ),
             },
             iced::Task::batch(tasks),
         )
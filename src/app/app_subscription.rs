@@ -11,6 +11,8 @@ impl App {
             super::app_state::View::Presets => {
                 subs.push(self.presets.selected_subscription(&self.context).map(super::Message::Presets));
             }
+            super::app_state::View::Knowledge |
+            super::app_state::View::Usage => {}
             super::app_state::View::Settings => {
                 subs.push(self.settings.selected_subscription(&self.context).map(super::Message::Settings));
             }
@@ -33,6 +35,24 @@ impl App {
                 .map(super::Message::Presets),
         );
 
+        subs.push(
+            self.knowledge
+                .subscription(&self.context)
+                .map(super::Message::Knowledge),
+        );
+
+        subs.push(
+            self.context
+                .vector_service
+                .subscribe_watched_paths(super::Message::WatchedPathsProgress),
+        );
+
+        subs.push(
+            self.notifications
+                .subscription(&self.context)
+                .map(super::Message::Notifications),
+        );
+
         subs.push(
             self.context
                 .focus_manager
@@ -1,7 +1,7 @@
-use framework::utils::notify;
+use framework::{types::common::{NotificationAction, ProgressStatus}, utils::notify};
 use iced::Task;
 
-use super::App;
+use super::{App, app_state::View, conversations, knowledge, notifications, usage};
 
 impl App {
     pub fn update(&mut self, message: super::Message) -> iced::Task<super::Message> {
@@ -35,11 +35,32 @@ impl App {
                     .update(&mut self.context, message)
                     .map(super::Message::Settings),
             ),
+            super::Message::Usage(message) => tasks.push(
+                self.usage
+                    .update(&mut self.context, message)
+                    .map(super::Message::Usage),
+            ),
+            super::Message::Knowledge(message) => tasks.push(
+                self.knowledge
+                    .update(&mut self.context, message)
+                    .map(super::Message::Knowledge),
+            ),
             super::Message::Presets(message) => tasks.push(
                 self.presets
                     .update(&self.context, message)
                     .map(super::Message::Presets),
             ),
+            super::Message::Notifications(message) => {
+                if let notifications::Message::RunAction(_, action) = message {
+                    tasks.push(Self::run_notification_action(action));
+                }
+
+                tasks.push(
+                    self.notifications
+                        .update(message)
+                        .map(super::Message::Notifications),
+                );
+            }
 
             // Updates
             super::Message::StartChangeView(view) => {
@@ -62,11 +83,41 @@ impl App {
                 if let Some(view) = view {
                     self.presets.try_reset_temp();
                     self.settings.try_reset_temp();
+                    // Completions made since the dashboard was last opened
+                    if view == View::Usage {
+                        tasks.push(Task::done(super::Message::Usage(usage::Message::Refresh)));
+                    }
+                    // Chats and folders to attach to may have changed
+                    if view == View::Knowledge {
+                        tasks.push(Task::done(super::Message::Knowledge(knowledge::Message::Refresh)));
+                    }
+
                     self.current_view = view;
                 }
             }
+            // The chats show the progress, it is dispatched as `LoadingFilesStatus`
+            super::Message::WatchedPathsProgress(status) => {
+                if let ProgressStatus::Failed(e) = status {
+                    log::error!("Failed to index watched paths: {e}");
+                }
+            }
         }
 
         Task::batch(tasks)
     }
+
+    fn run_notification_action(action: NotificationAction) -> Task<super::Message> {
+        match action {
+            NotificationAction::OpenModelPicker(conversation_id) => {
+                Task::done(super::Message::StartChangeView(View::Conversations)).chain(
+                    Task::done(super::Message::Conversations(
+                        conversations::Message::OpenSettings(conversation_id),
+                    )),
+                )
+            }
+            NotificationAction::OpenProviders => {
+                Task::done(super::Message::StartChangeView(View::Settings))
+            }
+        }
+    }
 }
@@ -1,7 +1,7 @@
 use framework::Context;
 use iced::{
     Background, Length, Theme,
-    widget::{self, Container, Row, container},
+    widget::{self, Container, Row, Stack, container, vertical_space},
 };
 
 use crate::widgets::{
@@ -9,7 +9,7 @@ use crate::widgets::{
     icon_button::IconButton,
 };
 
-use super::{App, app_state};
+use super::{App, app_state, notifications};
 
 impl App {
     pub fn view(&self) -> iced::Element<'_, super::Message> {
@@ -22,7 +22,12 @@ impl App {
                 .height(iced::Length::Fill),
         );
 
-        Container::new(main_row).into()
+        Stack::new()
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .push(Container::new(main_row))
+            .push(self.notifications.view().map(super::Message::Notifications))
+            .into()
     }
 
     fn selection_panel(&self) -> iced::Element<'_, super::Message> {
@@ -40,10 +45,22 @@ impl App {
             self.selection_panel_button(IconType::Solid(IconName::Box), app_state::View::Presets),
         );
 
+        selection_panel = selection_panel.push(
+            self.selection_panel_button(IconType::Solid(IconName::Book), app_state::View::Knowledge),
+        );
+
+        selection_panel = selection_panel.push(self.selection_panel_button(
+            IconType::Solid(IconName::ChartSimple),
+            app_state::View::Usage,
+        ));
+
         selection_panel = selection_panel.push(
             self.selection_panel_button(IconType::Solid(IconName::Gear), app_state::View::Settings),
         );
 
+        selection_panel = selection_panel.push(vertical_space());
+        selection_panel = selection_panel.push(self.notifications_button());
+
         Container::new(selection_panel)
             .style(|theme: &Theme| {
                 let palette = theme.extended_palette();
@@ -90,6 +107,22 @@ impl App {
             .into()
     }
 
+    fn notifications_button<'a>(&self) -> iced::Element<'a, super::Message> {
+        let icon = if self.notifications.unread() > 0 {
+            IconType::Solid(IconName::Bell)
+        } else {
+            IconType::Regular(IconName::Bell)
+        };
+
+        IconButton::new(
+            icon,
+            super::Message::Notifications(notifications::Message::ToggleHistory),
+        )
+        .padding(8.0)
+        .size(20.0)
+        .into()
+    }
+
     fn get_current_view<'a>(&'a self, ctx: &'a Context) -> iced::Element<'a, super::Message> {
         match self.current_view {
             app_state::View::Conversations => self
@@ -97,6 +130,8 @@ impl App {
                 .view(ctx)
                 .map(super::Message::Conversations),
             app_state::View::Presets => self.presets.view(ctx).map(super::Message::Presets),
+            app_state::View::Knowledge => self.knowledge.view(ctx).map(super::Message::Knowledge),
+            app_state::View::Usage => self.usage.view().map(super::Message::Usage),
             app_state::View::Settings => self.settings.view(ctx).map(super::Message::Settings),
         }
     }
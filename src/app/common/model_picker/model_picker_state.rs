@@ -1,6 +1,6 @@
 use framework::{
     Context,
-    types::dto::{ConversationNodeID, ProviderDTO},
+    types::dto::{ConversationNodeID, KnowledgeBaseID, ProviderDTO},
 };
 use iced::Task;
 
@@ -9,6 +9,9 @@ pub enum ModelType {
     Basic(ConversationNodeID),
     Embedding(ConversationNodeID),
     Summary(ConversationNodeID),
+    Rerank(ConversationNodeID),
+    QueryRewrite(ConversationNodeID),
+    KnowledgeBase(KnowledgeBaseID),
 }
 
 #[derive(Debug, Clone)]
@@ -86,6 +89,41 @@ impl ModelPicker {
                     .update_conversation(dto.id, &dto)
                     .expect("Failed to update conversation");
             }
+            ModelType::Rerank(conversation_id) => {
+                let chat = ctx
+                    .conversations_service
+                    .get_conversation(conversation_id)
+                    .expect("Failed to get conversation");
+
+                let mut dto = chat;
+                dto.rerank_model.clone_from(&model);
+                ctx.conversations_service
+                    .update_conversation(dto.id, &dto)
+                    .expect("Failed to update conversation");
+            }
+            ModelType::QueryRewrite(conversation_id) => {
+                let chat = ctx
+                    .conversations_service
+                    .get_conversation(conversation_id)
+                    .expect("Failed to get conversation");
+
+                let mut dto = chat;
+                dto.rewrite_model.clone_from(&model);
+                ctx.conversations_service
+                    .update_conversation(dto.id, &dto)
+                    .expect("Failed to update conversation");
+            }
+            ModelType::KnowledgeBase(knowledge_base_id) => {
+                let mut dto = ctx
+                    .vector_service
+                    .get_knowledge_base(knowledge_base_id)
+                    .expect("Failed to get knowledge base");
+
+                dto.embedding_model.clone_from(&model);
+                ctx.vector_service
+                    .update_knowledge_base(&dto)
+                    .expect("Failed to update knowledge base");
+            }
         }
 
         self.selected_model = model;
@@ -117,13 +155,37 @@ impl ModelPicker {
 
                 chat.and_then(|chat| chat.summary_model)
             }
+            ModelType::Rerank(conversation_id) => {
+                let chat = ctx
+                    .conversations_service
+                    .get_conversation(conversation_id)
+                    .ok();
+
+                chat.and_then(|chat| chat.rerank_model)
+            }
+            ModelType::QueryRewrite(conversation_id) => {
+                let chat = ctx
+                    .conversations_service
+                    .get_conversation(conversation_id)
+                    .ok();
+
+                chat.and_then(|chat| chat.rewrite_model)
+            }
+            ModelType::KnowledgeBase(knowledge_base_id) => ctx
+                .vector_service
+                .get_knowledge_base(knowledge_base_id)
+                .ok()
+                .and_then(|knowledge_base| knowledge_base.embedding_model),
         }
     }
 
     pub const fn is_defined(&self) -> bool {
         match self.model_type {
             ModelType::Summary(_) |
+            ModelType::Rerank(_) |
+            ModelType::QueryRewrite(_) |
             ModelType::Embedding(_) |
+            ModelType::KnowledgeBase(_) |
             ModelType::Basic(_) => self.selected_model.is_some(),
         }
     }
@@ -139,6 +201,24 @@ impl ModelPicker {
                 chat.and_then(|chat| chat.summary_provider)
                     .and_then(|provider_id| ctx.app_settings.get_provider(provider_id))
             }
+            ModelType::Rerank(conversation_id) => {
+                let chat = ctx
+                    .conversations_service
+                    .get_conversation(conversation_id)
+                    .ok();
+
+                chat.and_then(|chat| chat.rerank_provider)
+                    .and_then(|provider_id| ctx.app_settings.get_provider(provider_id))
+            }
+            ModelType::QueryRewrite(conversation_id) => {
+                let chat = ctx
+                    .conversations_service
+                    .get_conversation(conversation_id)
+                    .ok();
+
+                chat.and_then(|chat| chat.rewrite_provider)
+                    .and_then(|provider_id| ctx.app_settings.get_provider(provider_id))
+            }
             ModelType::Basic(conversation_id) => {
                 let chat = ctx
                     .conversations_service
@@ -157,6 +237,12 @@ impl ModelPicker {
                 chat.and_then(|chat| chat.embedding_provider)
                     .and_then(|provider_id| ctx.app_settings.get_provider(provider_id))
             }
+            ModelType::KnowledgeBase(knowledge_base_id) => ctx
+                .vector_service
+                .get_knowledge_base(knowledge_base_id)
+                .ok()
+                .and_then(|knowledge_base| knowledge_base.embedding_provider)
+                .and_then(|provider_id| ctx.app_settings.get_provider(provider_id)),
         }
     }
 
@@ -202,6 +288,41 @@ impl ModelPicker {
                     .update_conversation(dto.id, &dto)
                     .expect("Failed to update conversation");
             }
+            ModelType::Rerank(conversation_id) => {
+                let chat = ctx
+                    .conversations_service
+                    .get_conversation(conversation_id)
+                    .expect("Failed to get conversation");
+
+                let mut dto = chat;
+                dto.rerank_provider = provider.clone().map(|p| p.id);
+                ctx.conversations_service
+                    .update_conversation(dto.id, &dto)
+                    .expect("Failed to update conversation");
+            }
+            ModelType::QueryRewrite(conversation_id) => {
+                let chat = ctx
+                    .conversations_service
+                    .get_conversation(conversation_id)
+                    .expect("Failed to get conversation");
+
+                let mut dto = chat;
+                dto.rewrite_provider = provider.clone().map(|p| p.id);
+                ctx.conversations_service
+                    .update_conversation(dto.id, &dto)
+                    .expect("Failed to update conversation");
+            }
+            ModelType::KnowledgeBase(knowledge_base_id) => {
+                let mut dto = ctx
+                    .vector_service
+                    .get_knowledge_base(knowledge_base_id)
+                    .expect("Failed to get knowledge base");
+
+                dto.embedding_provider = provider.clone().map(|p| p.id);
+                ctx.vector_service
+                    .update_knowledge_base(&dto)
+                    .expect("Failed to update knowledge base");
+            }
         }
 
         self.selected_provider = provider;
@@ -1,11 +1,11 @@
-use std::{collections::HashMap, ops::Range};
+use std::{collections::HashMap, ops::Range, path::PathBuf};
 
 use framework::{
-    services::MessagingEvent, types::{common::ProgressStatus, dto::{ConversationNodeDTO, ConversationNodeID, MessageDTO, MessageID}}
+    CancellationToken, services::MessagingEvent, types::{common::{ContextUsage, ProgressStatus}, dto::{AttachmentDTO, ChunkContextDTO, ConversationNodeDTO, ConversationNodeID, MessageDTO, MessageID}}
 };
 use iced::{
     Task,
-    widget::{scrollable::Viewport, text_editor},
+    widget::{image, scrollable::Viewport, text_editor},
 };
 
 use super::message_viewer::{self, MessageViewer};
@@ -35,8 +35,29 @@ pub enum Message {
     ToggleSettings(bool),
     LoadingFilesStatus(ProgressStatus),
     StopMessageLoading,
+    StartAttachImages,
+    ImagesSelected(Vec<PathBuf>),
+    PasteFromClipboard,
+    ClipboardImageRead(Option<Vec<u8>>),
+    RemovePendingAttachment(usize),
     Summarized(MessageDTO),
     StartSummarizing,
+    /// Highlights the message and scrolls to it, loading older messages if needed.
+    FocusOn(MessageID),
+    ContextUsageLoaded(Option<ContextUsage>),
+    CloseInspector,
+}
+
+/// Chunk opened from its footnote or a citation of the answer.
+#[derive(Debug, Clone)]
+pub struct ChunkInspector {
+    pub(super) number: usize,
+    pub(super) location: String,
+    pub(super) embedding_model: String,
+    pub(super) dimension: usize,
+    /// `None` for chunks found by keywords only.
+    pub(super) distance: Option<f32>,
+    pub(super) context: ChunkContextDTO,
 }
 
 #[allow(clippy::struct_excessive_bools)]
@@ -56,8 +77,18 @@ pub struct Chat {
     pub(super) text_editor_id: String,
     pub(super) loading_progress: Option<(String, Range<f32>, f32)>,
     pub(super) gathering_message_aborter: Option<iced::task::Handle>,
+    pub(super) gathering_message_cancel: Option<CancellationToken>,
     pub(super) is_need_generate: bool,
     pub(super) sorted_messages_ids: Vec<MessageID>,
+    /// Images attached to the message being typed, with their thumbnails.
+    pub(super) pending_attachments: Vec<(AttachmentDTO, image::Handle)>,
+    pub(super) messages_scrollable_id: String,
+    pub(super) focused_message: Option<MessageID>,
+    /// The focused message is not loaded yet, scroll once it is.
+    pub(super) focus_pending: bool,
+    /// What of the conversation the next request takes, `None` until a model is selected.
+    pub(super) context_usage: Option<ContextUsage>,
+    pub(super) inspected_chunk: Option<ChunkInspector>,
 }
 
 impl Chat {
@@ -70,11 +101,18 @@ impl Chat {
             Self {
                 is_need_generate: false,
                 gathering_message_aborter: None,
+                gathering_message_cancel: None,
                 loading_progress: None,
                 text_editor_id: format!("text_editor_{conversation_id}"),
+                messages_scrollable_id: format!("messages_{conversation_id}"),
+                focused_message: None,
+                focus_pending: false,
+                context_usage: None,
+                inspected_chunk: None,
                 conversation_id,
                 chat: None,
                 sorted_messages_ids: vec![],
+                pending_attachments: vec![],
                 messages: HashMap::new(),
                 is_loaded_all_messages: true,
                 last_message_id: 0,
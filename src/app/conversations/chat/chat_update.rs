@@ -1,20 +1,28 @@
+use std::sync::Arc;
+
 use super::{
     Chat,
+    chat_state::ChunkInspector,
     message_viewer::{self, MessageViewer},
 };
 use framework::{
-    ComplitationStatus, Context,
+    CancellationToken, ComplitationStatus, Context,
     services::MessagingEvent,
-    types::{common::ProgressStatus, dto::MessageID},
+    types::{
+        common::{NotificationLevel, ProgressStatus},
+        dto::{AttachmentDTO, IMAGE_EXTENSIONS, MessageID},
+        error::AppResult,
+    },
 };
 use framework::{
     types::dto::{MessageDTO, RoleType},
-    utils::take_component,
+    utils::{clipboard, notify, take_component},
 };
 use iced::{
     Task,
     advanced::widget::{operate, operation},
-    widget::text_editor,
+    futures::Stream,
+    widget::{image, markdown, scrollable, text_editor},
 };
 
 const BATCH_SIZE: usize = 8;
@@ -22,7 +30,20 @@ const INITIAL_BATCH_SIZE: usize = 64;
 
 impl Chat {
     pub fn update(&mut self, ctx: &mut Context, message: super::Message) -> Task<super::Message> {
-        let task = self.basic_update(ctx, message);
+        let context_changed = matches!(
+            message,
+            super::Message::LoadedBatchMessages(_)
+                | super::Message::CommitGatheringMessage(_)
+                | super::Message::DeleteMessage(_)
+                | super::Message::ChatUpdate(_)
+                | super::Message::Summarized(_)
+        );
+
+        let mut task = self.basic_update(ctx, message);
+
+        if context_changed {
+            task = Task::batch([task, self.load_context_usage(ctx)]);
+        }
 
         let mut sorted_messages_ids: Vec<i64> = self.messages.keys().copied().collect();
 
@@ -44,6 +65,12 @@ impl Chat {
     fn basic_update(&mut self, ctx: &mut Context, message: super::Message) -> Task<super::Message> {
         match message {
             super::Message::StopMessageLoading => {
+                // The stream reports `Cancelled` itself and the partial answer is saved there
+                if let Some(cancel) = self.gathering_message_cancel.take() {
+                    cancel.cancel();
+                    return Task::none();
+                }
+
                 if let Some(aborter) = self.gathering_message_aborter.take() {
                     aborter.abort();
                 }
@@ -60,6 +87,9 @@ impl Chat {
                     self.last_message_id = 0;
                     self.is_loaded_all_messages = false;
 
+                    if let Some(cancel) = self.gathering_message_cancel.take() {
+                        cancel.cancel();
+                    }
                     if let Some(aborter) = self.gathering_message_aborter.take() {
                         aborter.abort();
                     }
@@ -76,6 +106,11 @@ impl Chat {
                 Task::none()
             }
             super::Message::UpdateGatheringMessage(message) => {
+                // The answer being generated cites the chunks of the last user message
+                if let message_viewer::Message::LinkClicked(url) = &message {
+                    self.open_citation(ctx, self.sorted_messages_ids.last().copied(), url);
+                }
+
                 if let Some(ref mut gathering_message) = self.gathering_message {
                     return gathering_message
                         .update(message, &mut self.shared_messages_state)
@@ -88,16 +123,47 @@ impl Chat {
                 MessagingEvent::ReceiveMessage(status) => {
                     self.chat_complitation_event(ctx, status)
                 }
-                MessagingEvent::Error(_err) => {
+                MessagingEvent::Generated(generation) => {
+                    if let Some(ref mut gathering_message) = self.gathering_message {
+                        gathering_message.set_generation(generation);
+                    }
+
+                    Task::none()
+                }
+                MessagingEvent::Error(err) => {
+                    log::error!("Failed to generate message: {err}");
+
                     self.gathering_message_process = false;
                     self.gathering_message = None;
                     self.gathering_message_aborter = None;
+                    self.gathering_message_cancel = None;
                     self.text_editor_content = text_editor::Content::new();
 
                     Task::none()
                 }
             },
-            super::Message::SendMessage => self.send_message(ctx),
+            super::Message::SendMessage => {
+                self.focused_message = None;
+                self.send_message(ctx)
+            }
+            super::Message::FocusOn(message_id) => {
+                if self.gathering_message_process {
+                    return Task::none();
+                }
+
+                self.focused_message = Some(message_id);
+                self.focus_pending = true;
+
+                match ctx.conversations_service.activate_message(message_id) {
+                    // Scrolls once the other branch is loaded
+                    Ok(true) => self.reload_messages(ctx),
+                    Ok(false) => self.focus_message(ctx),
+                    Err(e) => {
+                        log::error!("Failed to show message {message_id}: {e}");
+                        Task::none()
+                    }
+                }
+            }
             super::Message::UpdateTextEditor(action) => {
                 if self.gathering_message_process {
                     return Task::none();
@@ -188,7 +254,8 @@ impl Chat {
 
                     Task::none()
                 }
-                ProgressStatus::Finished => {
+                // The settings that started the loading report the failure
+                ProgressStatus::Finished | ProgressStatus::Failed(_) => {
                     self.loading_progress = None;
                     self.loading_file = false;
                     Task::none()
@@ -198,15 +265,171 @@ impl Chat {
                     Task::none()
                 }
             },
+            super::Message::StartAttachImages => Task::perform(
+                async move { notify::file_selection("Select images") },
+                super::Message::ImagesSelected,
+            ),
+            super::Message::ImagesSelected(paths) => {
+                for path in paths {
+                    let mime_type = path
+                        .extension()
+                        .and_then(|extension| extension.to_str())
+                        .and_then(AttachmentDTO::image_mime_type);
+
+                    let Some(mime_type) = mime_type else {
+                        ctx.event_system.notify(
+                            NotificationLevel::Warning,
+                            "Unsupported image",
+                            format!(
+                                "{} is not supported, only {} images can be attached",
+                                path.display(),
+                                IMAGE_EXTENSIONS.join(", ")
+                            ),
+                            None,
+                        );
+                        continue;
+                    };
+
+                    match std::fs::read(&path) {
+                        Ok(data) => self.add_pending_attachment(mime_type, data),
+                        Err(e) => {
+                            log::error!("Failed to read {}: {e}", path.display());
+                            ctx.event_system.notify(
+                                NotificationLevel::Error,
+                                "Failed to attach image",
+                                format!("Failed to read {}: {e}", path.display()),
+                                None,
+                            );
+                        }
+                    }
+                }
+
+                Task::none()
+            }
+            super::Message::PasteFromClipboard => Task::perform(
+                async move { clipboard::read_image_png() },
+                super::Message::ClipboardImageRead,
+            ),
+            super::Message::ClipboardImageRead(image) => {
+                if let Some(data) = image {
+                    self.add_pending_attachment("image/png", data);
+                    return Task::none();
+                }
+
+                // Not an image, fall back to the usual text paste
+                iced::clipboard::read().map(|text| {
+                    super::Message::UpdateTextEditor(text_editor::Action::Edit(
+                        text_editor::Edit::Paste(Arc::new(text.unwrap_or_default())),
+                    ))
+                })
+            }
+            super::Message::RemovePendingAttachment(index) => {
+                if index < self.pending_attachments.len() {
+                    self.pending_attachments.remove(index);
+                }
+                Task::none()
+            }
             super::Message::StartSummarizing => {
                 self.gathering_message_process = true;
                 Task::none()
             }
             super::Message::Summarized(_updated_message) => {
                 self.gathering_message_process = false;
+                self.gathering_message_cancel = None;
+                Task::none()
+            }
+            super::Message::ContextUsageLoaded(context_usage) => {
+                self.context_usage = context_usage;
                 Task::none()
             }
+            super::Message::CloseInspector => {
+                self.inspected_chunk = None;
+                Task::none()
+            }
+        }
+    }
+
+    /// Opens the chunk a `cite:n` link of an answer points to, `from` is the
+    /// message the search starts at for the user message holding the chunks.
+    fn open_citation(&mut self, ctx: &Context, from: Option<MessageID>, url: &markdown::Url) {
+        if url.scheme() != "cite" {
+            return;
         }
+
+        let Ok(number) = url.path().parse() else {
+            return;
+        };
+
+        // Tool calls and results can come between the answer and the question
+        let mut message_id = from;
+        while let Some(id) = message_id {
+            let Some(message) = self.messages.get(&id) else {
+                return;
+            };
+
+            let dto = message.get_dto();
+            if dto.role == RoleType::User {
+                self.inspect_chunk(ctx, id, number);
+                return;
+            }
+
+            message_id = dto.parent_id;
+        }
+    }
+
+    /// Shows the used chunk with the given number, counted from 1, of the message.
+    fn inspect_chunk(&mut self, ctx: &Context, message_id: MessageID, number: usize) {
+        let Some(message) = self.messages.get(&message_id) else {
+            return;
+        };
+
+        let dto = message.get_dto();
+        let Some(chunk) = number.checked_sub(1).and_then(|index| dto.chunks.get(index)) else {
+            return;
+        };
+
+        let source = chunk.source(dto.conversation_id);
+
+        let inspected = ctx
+            .vector_service
+            .get_chunk(source, chunk.chunk_id, chunk.dimension, &chunk.embedding_model)
+            .and_then(|chunk_dto| {
+                let Some(chunk_dto) = chunk_dto else {
+                    return Ok(None);
+                };
+
+                let location = ctx
+                    .vector_service
+                    .get_file(source, chunk_dto.file_id)?
+                    .map(|file| chunk_dto.location(&file.file_name))
+                    .unwrap_or_default();
+
+                Ok(Some(ChunkInspector {
+                    number,
+                    location,
+                    embedding_model: chunk.embedding_model.clone(),
+                    dimension: chunk.dimension,
+                    distance: chunk.distance,
+                    context: ctx.vector_service.get_chunk_context(source, &chunk_dto)?,
+                }))
+            });
+
+        match inspected {
+            Ok(Some(inspected)) => self.inspected_chunk = Some(inspected),
+            // The file of the chunk was deleted since
+            Ok(None) => self.inspected_chunk = None,
+            Err(e) => log::error!("Failed to inspect chunk {number} of message {message_id}: {e}"),
+        }
+    }
+
+    fn load_context_usage(&self, ctx: &Context) -> Task<super::Message> {
+        let messaging_service = ctx.messaging_service.clone();
+        let conversation_id = self.conversation_id;
+
+        Task::perform(
+            async move { messaging_service.context_usage(conversation_id).ok() },
+            super::Message::ContextUsageLoaded,
+        )
     }
 
     fn send_message(&mut self, ctx: &Context) -> Task<super::Message> {
@@ -214,31 +437,74 @@ impl Chat {
             return Task::none();
         }
 
-        if self.text_editor_content.text().trim().is_empty() && !self.is_need_generate {
+        if self.text_editor_content.text().trim().is_empty()
+            && self.pending_attachments.is_empty()
+            && !self.is_need_generate
+        {
             return Task::none();
         }
 
         let messaging_service = ctx.messaging_service.clone();
         let conversation_id = self.conversation_id;
         let message = self.text_editor_content.text();
+        let cancel = CancellationToken::new();
 
         let stream = if self.is_need_generate {
-            messaging_service
-                .generate_message(conversation_id)
-                .expect("Failed to generate message")
+            messaging_service.generate_message(conversation_id, cancel.clone())
         } else {
-            messaging_service
-                .send_message(conversation_id, message)
-                .expect("Failed to send message")
+            let attachments = std::mem::take(&mut self.pending_attachments)
+                .into_iter()
+                .map(|(attachment, _)| attachment)
+                .collect();
+
+            messaging_service.send_message(conversation_id, message, attachments, cancel.clone())
+        };
+
+        self.run_generation(stream, cancel)
+    }
+
+    fn run_generation(
+        &mut self,
+        stream: AppResult<impl Stream<Item = MessagingEvent> + Send + 'static>,
+        cancel: CancellationToken,
+    ) -> Task<super::Message> {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::error!("Failed to send message: {e}");
+                return Task::none();
+            }
         };
 
         let (task, abort) = Task::run(stream, super::Message::MessagingServiceEvent).abortable();
 
         self.gathering_message_aborter = Some(abort);
+        self.gathering_message_cancel = Some(cancel);
 
         task
     }
 
+    /// Shows the branch the conversation points to now.
+    fn reload_messages(&mut self, ctx: &Context) -> Task<super::Message> {
+        self.messages.clear();
+        self.last_message_id = 0;
+
+        self.start_loading(ctx)
+    }
+
+    fn add_pending_attachment(&mut self, mime_type: &str, data: Vec<u8>) {
+        let handle = image::Handle::from_bytes(data.clone());
+
+        self.pending_attachments.push((
+            AttachmentDTO {
+                mime_type: mime_type.to_string(),
+                data,
+                ..Default::default()
+            },
+            handle,
+        ));
+    }
+
     fn update_message(
         &mut self,
         ctx: &mut Context,
@@ -357,9 +623,69 @@ impl Chat {
             tasks.push(Task::done(super::Message::EndLoadingMessages));
         }
 
+        tasks.push(self.focus_message(ctx));
+
         Task::batch(tasks)
     }
 
+    fn focus_message(&mut self, ctx: &Context) -> Task<super::Message> {
+        let Some(message_id) = self.focused_message.filter(|_| self.focus_pending) else {
+            return Task::none();
+        };
+
+        if self.messages.contains_key(&message_id) {
+            self.focus_pending = false;
+            return self.scroll_to_message(message_id);
+        }
+
+        // The first batch is still loading
+        if self.last_message_id == 0 {
+            return Task::none();
+        }
+
+        // Older messages are loaded already, the message is not in this chat
+        if message_id > self.last_message_id {
+            self.focus_pending = false;
+            return Task::none();
+        }
+
+        let service = ctx.conversations_service.clone();
+        let conversation_id = self.conversation_id;
+        let last_message_id = self.last_message_id;
+
+        Task::perform(
+            async move {
+                service
+                    .get_last_messages(conversation_id, last_message_id, INITIAL_BATCH_SIZE)
+                    .expect("Failed to load messages")
+            },
+            super::Message::LoadedBatchMessages,
+        )
+    }
+
+    fn scroll_to_message(&self, message_id: MessageID) -> Task<super::Message> {
+        let mut ids: Vec<MessageID> = self.messages.keys().copied().collect();
+        ids.sort_unstable();
+
+        let Some(index) = ids.iter().position(|id| *id == message_id) else {
+            return Task::none();
+        };
+
+        // The list is anchored to the bottom, so the offset counts from the newest
+        // message. Messages differ in height, the offset only lands near the message.
+        #[allow(clippy::cast_precision_loss)]
+        let y = if ids.len() > 1 {
+            (ids.len() - 1 - index) as f32 / (ids.len() - 1) as f32
+        } else {
+            0.0
+        };
+
+        scrollable::snap_to(
+            scrollable::Id::new(self.messages_scrollable_id.clone()),
+            scrollable::RelativeOffset { x: 0.0, y },
+        )
+    }
+
     fn chat_complitation_event(
         &mut self,
         ctx: &mut Context,
@@ -372,14 +698,9 @@ impl Chat {
                     MessageViewer::new(
                         ctx.conversations_service.clone(),
                         MessageDTO {
-                            content: String::new(),
                             conversation_id: self.conversation_id,
-                            id: 0,
-                            reasoning: None,
                             role: RoleType::Assistant,
-                            timestamp: String::new(),
-                            summary: None,
-                            chunks: vec![],
+                            ..Default::default()
                         },
                     )
                     .0,
@@ -402,7 +723,7 @@ impl Chat {
 
                 Task::none()
             }
-            ComplitationStatus::End => {
+            ComplitationStatus::End(_) => {
                 let conversation = ctx
                     .conversations_service
                     .get_conversation(self.conversation_id);
@@ -417,12 +738,16 @@ impl Chat {
                     if conversation.is_chat() && is_summary {
                         let messaging_service = ctx.messaging_service.clone();
                         let conversation_id = self.conversation_id;
-                        return self.end_task(ctx)
+                        let cancel = self.gathering_message_cancel.clone().unwrap_or_default();
+                        let task = self.end_task(ctx);
+                        // Stop cancels the summary as it did the answer
+                        self.gathering_message_cancel = Some(cancel.clone());
+                        return task
                             .chain(Task::done(super::Message::StartSummarizing))
                             .chain(Task::perform(
                             async move {
                                 let result = messaging_service
-                                    .summarize(conversation_id)
+                                    .summarize(conversation_id, cancel)
                                     .await;
 
                                 if result.is_err() {
@@ -440,13 +765,45 @@ impl Chat {
 
                 Task::none()
             }
-            ComplitationStatus::Error(_err) => {
-                self.gathering_message_process = false;
+            ComplitationStatus::ToolCalls(_) => {
+                // The service saves this turn with its tool results and goes on
+                // with a new completion, they come back as received messages
+                self.gathering_message = None;
                 Task::none()
             }
+            ComplitationStatus::Error(err) => {
+                log::error!("Completion failed: {err}");
+
+                self.interrupt_gathering_message(ctx)
+            }
+            ComplitationStatus::Cancelled => self.interrupt_gathering_message(ctx),
         }
     }
 
+    /// Keeps what was generated before a cancellation or failure, marked as
+    /// interrupted, and drops an answer that has nothing yet.
+    fn interrupt_gathering_message(&mut self, ctx: &mut Context) -> Task<super::Message> {
+        self.gathering_message_cancel = None;
+
+        let is_empty = self.gathering_message.as_ref().is_none_or(|message| {
+            let dto = message.get_dto();
+            dto.content.trim().is_empty() && dto.reasoning.is_none()
+        });
+
+        if is_empty {
+            self.gathering_message = None;
+            self.gathering_message_process = false;
+            self.gathering_message_aborter = None;
+            return Task::none();
+        }
+
+        if let Some(ref mut gathering_message) = self.gathering_message {
+            gathering_message.mark_interrupted();
+        }
+
+        self.end_task(ctx)
+    }
+
     fn end_task(&mut self, ctx: &mut Context) -> iced::Task<super::Message> {
         let gathering_message = self.gathering_message.clone();
 
@@ -466,6 +823,7 @@ impl Chat {
         let gathering_dto = gathering_message.get_dto();
 
         self.gathering_message_aborter = None;
+        self.gathering_message_cancel = None;
 
         Task::perform(
             async move {
@@ -494,13 +852,67 @@ impl Chat {
         match message {
             message_viewer::Message::DeleteComplete => {
                 if ctx.conversations_service.get_message(message_id).is_err() {
-                    self.messages
-                        .remove(&message_id)
-                        .expect("Failed to remove message");
+                    let removed = self.messages.remove(&message_id);
+
+                    // Another alternative takes the place of the deleted message
+                    if removed.is_some_and(|message| message.get_dto().siblings.len() > 1) {
+                        return self.reload_messages(ctx);
+                    }
+                }
+
+                Task::none()
+            }
+            message_viewer::Message::Regenerate => {
+                if self.gathering_message_process {
+                    return Task::none();
                 }
 
+                let cancel = CancellationToken::new();
+                let stream = ctx
+                    .messaging_service
+                    .regenerate_message(message_id, cancel.clone());
+
+                Task::batch([self.reload_messages(ctx), self.run_generation(stream, cancel)])
+            }
+            message_viewer::Message::Resend(content) => {
+                if self.gathering_message_process {
+                    return Task::none();
+                }
+
+                let cancel = CancellationToken::new();
+                let stream =
+                    ctx.messaging_service
+                        .resend_message(message_id, content.clone(), cancel.clone());
+
+                Task::batch([self.reload_messages(ctx), self.run_generation(stream, cancel)])
+            }
+            message_viewer::Message::InspectChunk(number) => {
+                self.inspect_chunk(ctx, message_id, *number);
                 Task::none()
             }
+            message_viewer::Message::LinkClicked(url) => {
+                let parent_id = self
+                    .messages
+                    .get(&message_id)
+                    .and_then(|message| message.get_dto().parent_id);
+
+                self.open_citation(ctx, parent_id, url);
+                Task::none()
+            }
+            message_viewer::Message::SwitchBranch(sibling_id) => {
+                if self.gathering_message_process {
+                    return Task::none();
+                }
+
+                match ctx.conversations_service.activate_message(*sibling_id) {
+                    Ok(true) => self.reload_messages(ctx),
+                    Ok(false) => Task::none(),
+                    Err(e) => {
+                        log::error!("Failed to switch to message {sibling_id}: {e}");
+                        Task::none()
+                    }
+                }
+            }
             _ => Task::none(),
         }
     }
@@ -17,7 +17,13 @@ impl Chat {
             .push(self.view_messages(ctx))
             .push(self.view_texteditor(ctx));
 
-        Container::new(main_column)
+        let mut content = Row::new().push(main_column);
+
+        if let Some(inspected) = &self.inspected_chunk {
+            content = content.push(Self::view_inspector(inspected));
+        }
+
+        Container::new(content)
             .style(|theme: &Theme| container::Style {
                 shadow: styles::fake_oneside_border(theme, &styles::Side::Left),
                 ..Default::default()
@@ -0,0 +1,81 @@
+use iced::{
+    Element, Length, Theme,
+    widget::{Column, Container, Row, Scrollable, Text, container, text},
+};
+
+use crate::{theme::styles, widgets::{icon::{IconName, IconType}, icon_button::IconButton}};
+
+use super::{Chat, chat_state::ChunkInspector};
+
+const INSPECTOR_WIDTH: f32 = 420.0;
+
+impl Chat {
+    /// The chunk with the text around it in its file, the chunk itself highlighted.
+    pub(super) fn view_inspector(inspected: &ChunkInspector) -> Element<'_, super::Message> {
+        let retrieval = inspected.distance.map_or_else(
+            || "Keyword match".to_string(),
+            |distance| format!("Distance {distance:.3}"),
+        );
+
+        let mut context = Column::new().spacing(4);
+
+        if !inspected.context.before.is_empty() {
+            context = context.push(Text::new(&inspected.context.before).style(text::secondary));
+        }
+
+        context = context.push(
+            Container::new(Text::new(&inspected.context.chunk))
+                .padding(6)
+                .width(Length::Fill)
+                .style(|theme: &Theme| container::Style {
+                    background: Some(iced::Background::Color(
+                        theme.extended_palette().primary.base.color.scale_alpha(0.3),
+                    )),
+                    ..Default::default()
+                }),
+        );
+
+        if !inspected.context.after.is_empty() {
+            context = context.push(Text::new(&inspected.context.after).style(text::secondary));
+        }
+
+        let main_column = Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .align_y(iced::alignment::Vertical::Center)
+                    .spacing(10)
+                    .push(
+                        Text::new(format!("[{}] {}", inspected.number, inspected.location))
+                            .width(Length::Fill),
+                    )
+                    .push(IconButton::new(
+                        IconType::Solid(IconName::XMark),
+                        super::Message::CloseInspector,
+                    )),
+            )
+            .push(
+                Text::new(format!(
+                    "{}: {} / {retrieval}",
+                    inspected.embedding_model, inspected.dimension
+                ))
+                .style(text::secondary)
+                .size(12),
+            )
+            .push(
+                Scrollable::new(context)
+                    .style(styles::scrollable_style)
+                    .height(Length::Fill),
+            );
+
+        Container::new(main_column)
+            .padding(10)
+            .width(INSPECTOR_WIDTH)
+            .height(Length::Fill)
+            .style(|theme: &Theme| container::Style {
+                shadow: styles::fake_oneside_border(theme, &styles::Side::Left),
+                ..Default::default()
+            })
+            .into()
+    }
+}
@@ -1,5 +1,8 @@
 use framework::Context;
-use iced::{Element, Length, widget::Scrollable};
+use iced::{
+    Border, Element, Length, Theme,
+    widget::{Container, Scrollable, container, scrollable},
+};
 
 use crate::theme::styles::scrollable_style;
 
@@ -15,11 +18,28 @@ impl Chat {
         let sorted_messages = self.get_sorted_messages();
 
         for message in sorted_messages {
+            let is_focused = self.focused_message == Some(message.get_id());
+
             let message: Element<'_, super::Message> = message
                 .view(&self.shared_messages_state, ctx)
                 .map(|m| super::Message::UpdateMessage(message.get_id(), m));
 
-            main_column = main_column.push(message);
+            if is_focused {
+                main_column = main_column.push(
+                    Container::new(message)
+                        .padding(5)
+                        .style(|theme: &Theme| container::Style {
+                            border: Border {
+                                color: theme.extended_palette().primary.base.color,
+                                width: 1.0,
+                                radius: 6.0.into(),
+                            },
+                            ..Default::default()
+                        }),
+                );
+            } else {
+                main_column = main_column.push(message);
+            }
         }
 
         if let Some(ref gathering_message) = self.gathering_message {
@@ -32,6 +52,7 @@ impl Chat {
 
         iced::widget::Container::new(
             Scrollable::new(main_column)
+                .id(scrollable::Id::new(self.messages_scrollable_id.clone()))
                 .style(scrollable_style)
                 .anchor_bottom()
                 .on_scroll(super::Message::OnScrollMessageList)
@@ -2,13 +2,15 @@ use std::ops::RangeInclusive;
 
 use framework::Context;
 use iced::{
-    keyboard::key::{Code, Physical}, widget::{horizontal_space, text_editor::{Binding, KeyPress}, Column, Container, ProgressBar, Row, Text, TextEditor}, Element, Length
+    keyboard::key::{Code, Physical}, widget::{horizontal_space, text, text_editor::{Binding, KeyPress}, Column, Container, Image, ProgressBar, Row, Text, TextEditor}, ContentFit, Element, Length
 };
 
-use crate::widgets::button::Button;
+use crate::widgets::{button::Button, icon::{IconName, IconType}, icon_button::IconButton};
 
 use super::Chat;
 
+const PENDING_THUMBNAIL_SIZE: f32 = 64.0;
+
 impl Chat {
     pub(super) fn view_texteditor(&self, _ctx: &Context) -> Element<'_, super::Message> {
         let mut main_column = iced::widget::Column::new()
@@ -27,6 +29,10 @@ impl Chat {
                 .on_action(super::Message::UpdateTextEditor);
         }
 
+        if !self.pending_attachments.is_empty() {
+            main_column = main_column.push(self.view_pending_attachments());
+        }
+
         main_column = main_column.push(text_editor);
 
         if self.gathering_message_aborter.is_some() {
@@ -69,12 +75,29 @@ impl Chat {
             if is_available {
                 send_button = send_button.on_press(super::Message::SendMessage);
             }
+
+            let attach_button = IconButton::new(
+                IconType::Solid(IconName::PaperClip),
+                super::Message::StartAttachImages,
+            )
+            .disabled(!is_available || self.is_need_generate);
+
+            let context_usage = self.context_usage.map(|usage| {
+                Text::new(format!(
+                    "{} messages / {} tokens in context",
+                    usage.messages, usage.tokens
+                ))
+                .size(12)
+                .style(text::secondary)
+            });
     
             main_column = main_column.push(
                 Row::new()
                     .align_y(iced::alignment::Vertical::Center)
                     .padding(10)
                     .spacing(10)
+                    .push(attach_button)
+                    .push_maybe(context_usage)
                     .push(horizontal_space())
                     .push(send_button),
             );
@@ -93,10 +116,36 @@ impl Chat {
         match key_press.clone().physical_key {
             Physical::Code(Code::Enter) if key_press.modifiers.shift() => Some(Binding::Enter),
             Physical::Code(Code::Enter) => Some(Binding::Custom(super::Message::SendMessage)),
+            Physical::Code(Code::KeyV) if key_press.modifiers.command() => {
+                Some(Binding::Custom(super::Message::PasteFromClipboard))
+            }
             _ => Binding::from_key_press(key_press),
         }
     }
 
+    fn view_pending_attachments(&self) -> Element<'_, super::Message> {
+        let mut row = Row::new().spacing(10);
+
+        for (index, (_, handle)) in self.pending_attachments.iter().enumerate() {
+            row = row.push(
+                Column::new()
+                    .align_x(iced::Alignment::Center)
+                    .push(
+                        Image::new(handle.clone())
+                            .width(PENDING_THUMBNAIL_SIZE)
+                            .height(PENDING_THUMBNAIL_SIZE)
+                            .content_fit(ContentFit::Cover),
+                    )
+                    .push(IconButton::new(
+                        IconType::Solid(IconName::XMark),
+                        super::Message::RemovePendingAttachment(index),
+                    )),
+            );
+        }
+
+        row.wrap().into()
+    }
+
     fn view_progress_bar_file_loading(&self) -> Option<Element<super::Message>> {
         if let Some((label, range, value)) = self.loading_progress.clone() {
             return Some(Container::new(
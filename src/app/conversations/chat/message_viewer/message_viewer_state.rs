@@ -1,8 +1,10 @@
+use std::fmt::Write;
+
 use framework::{
     services::ConversationsService,
-    types::dto::{MessageDTO, MessageID, RoleType},
+    types::dto::{GenerationDTO, MessageDTO, MessageID, RoleType},
 };
-use iced::widget::{markdown, text_editor};
+use iced::widget::{image, markdown, text_editor};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -19,6 +21,16 @@ pub enum Message {
 
     Delete,
     DeleteComplete,
+
+    /// Handled by the chat, it runs the generation.
+    Regenerate,
+    /// Handled by the chat, it shows the branch of the given alternative.
+    SwitchBranch(MessageID),
+    SubmitResend,
+    /// Handled by the chat, sends the edited text as an alternative message.
+    Resend(String),
+    /// Handled by the chat, shows the used chunk with the given number in the inspector.
+    InspectChunk(usize),
 }
 
 #[derive(Debug, Default)]
@@ -39,6 +51,8 @@ pub struct MessageViewer {
     pub(super) reasoning: Vec<markdown::Item>,
     pub(super) reasoning_expanded: bool,
 
+    pub(super) images: Vec<image::Handle>,
+
     pub(super) conversations_service: ConversationsService,
 }
 
@@ -55,17 +69,36 @@ impl MessageViewer {
         (
             Self {
                 conversations_service: conversation_service,
-                message_dto,
                 content_string: initial_content.clone(),
-                content: markdown::parse(&initial_content).collect(),
+                content: Self::parse_content(&message_dto.role, &initial_content),
                 reasoning_string: initial_reasoning.clone(),
                 reasoning: markdown::parse(&initial_reasoning).collect(),
                 reasoning_expanded: false,
+                images: Self::image_handles(&message_dto),
+                message_dto,
             },
             iced::Task::batch(tasks),
         )
     }
 
+    /// Answers cite the retrieved chunks with `[n]`, they become `cite:n` links.
+    pub(super) fn parse_content(role: &RoleType, content: &str) -> Vec<markdown::Item> {
+        if *role == RoleType::Assistant {
+            markdown::parse(&link_citations(content)).collect()
+        } else {
+            markdown::parse(content).collect()
+        }
+    }
+
+    pub(super) fn image_handles(message_dto: &MessageDTO) -> Vec<image::Handle> {
+        message_dto
+            .attachments
+            .iter()
+            .filter(|attachment| attachment.is_image())
+            .map(|attachment| image::Handle::from_bytes(attachment.data.clone()))
+            .collect()
+    }
+
     pub const fn get_id(&self) -> MessageID {
         self.message_dto.id
     }
@@ -74,21 +107,24 @@ impl MessageViewer {
         let origin_dto = self.message_dto.clone();
 
         MessageDTO {
-            id: origin_dto.id,
-            conversation_id: origin_dto.conversation_id,
             content: self.content_string.clone(),
             reasoning: if self.reasoning_string.trim().is_empty() {
                 None
             } else {
                 Some(self.reasoning_string.trim().to_string())
             },
-            timestamp: origin_dto.timestamp,
-            role: origin_dto.role,
-            summary: origin_dto.summary,
-            chunks: origin_dto.chunks.clone(),
+            ..origin_dto
         }
     }
 
+    pub const fn mark_interrupted(&mut self) {
+        self.message_dto.interrupted = true;
+    }
+
+    pub fn set_generation(&mut self, generation: GenerationDTO) {
+        self.message_dto.generation = Some(generation);
+    }
+
     pub fn is_user_message(&self) -> bool {
         self.message_dto.role == RoleType::User
     }
@@ -97,3 +133,59 @@ impl MessageViewer {
         state.editing == Some(self.message_dto.id)
     }
 }
+
+/// Code blocks and inline code are left as they are.
+fn link_citations(content: &str) -> String {
+    let mut linked = String::with_capacity(content.len());
+    let mut in_code_block = false;
+
+    for line in content.split_inclusive('\n') {
+        let is_fence = line.trim_start().starts_with("```");
+        if is_fence {
+            in_code_block = !in_code_block;
+        }
+
+        if is_fence || in_code_block {
+            linked.push_str(line);
+            continue;
+        }
+
+        for (index, segment) in line.split('`').enumerate() {
+            if index > 0 {
+                linked.push('`');
+            }
+
+            if index % 2 == 0 {
+                link_segment_citations(segment, &mut linked);
+            } else {
+                linked.push_str(segment);
+            }
+        }
+    }
+
+    linked
+}
+
+fn link_segment_citations(segment: &str, linked: &mut String) {
+    let mut rest = segment;
+
+    while let Some(start) = rest.find('[') {
+        linked.push_str(&rest[..start]);
+
+        let after = &rest[start + 1..];
+        let digits = after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len());
+        let tail = &after[digits..];
+
+        // `[1](...)` and `[1]: ...` are links of the answer itself
+        if digits > 0 && tail.starts_with(']') && !tail[1..].starts_with(['(', ':']) {
+            let number = &after[..digits];
+            let _ = write!(linked, "[\\[{number}\\]](cite:{number})");
+            rest = &tail[1..];
+        } else {
+            linked.push('[');
+            rest = after;
+        }
+    }
+
+    linked.push_str(rest);
+}
@@ -57,12 +57,7 @@ impl MessageViewer {
                 let dto = MessageDTO {
                     content: state.editing_tmp_content.text(),
                     reasoning: Some(state.editing_tmp_reasoning.text()),
-                    conversation_id: self.message_dto.conversation_id,
-                    id: self.message_dto.id,
-                    timestamp: self.message_dto.timestamp.clone(),
-                    role: self.message_dto.role.clone(),
-                    summary: self.message_dto.summary.clone(),
-                    chunks: self.message_dto.chunks.clone(),
+                    ..self.message_dto.clone()
                 };
 
                 let service = self.conversations_service.clone();
@@ -80,12 +75,21 @@ impl MessageViewer {
                     super::Message::UpdateMessageDTO,
                 )
             }
+            super::Message::SubmitResend => {
+                state.editing = None;
+                Task::done(super::Message::Resend(state.editing_tmp_content.text()))
+            }
+            super::Message::Regenerate
+            | super::Message::SwitchBranch(_)
+            | super::Message::InspectChunk(_)
+            | super::Message::Resend(_) => Task::none(),
             super::Message::UpdateMessageDTO(dto) => {
                 log::debug!("Update message DTO: {:?}", dto);
+                self.images = Self::image_handles(&dto);
                 self.message_dto = dto.clone();
                 self.content_string = dto.content;
                 self.reasoning_string = dto.reasoning.unwrap_or_default();
-                self.content = markdown::parse(&self.content_string).collect();
+                self.content = Self::parse_content(&self.message_dto.role, &self.content_string);
                 self.reasoning = markdown::parse(&self.reasoning_string).collect();
                 Task::none()
             }
@@ -99,7 +103,7 @@ impl MessageViewer {
 
     pub fn append_content(&mut self, new_content: &str) {
         self.content_string.push_str(new_content);
-        self.content = markdown::parse(&self.content_string).collect();
+        self.content = Self::parse_content(&self.message_dto.role, &self.content_string);
     }
 
     pub fn append_reasoning(&mut self, new_content: &str) {
@@ -1,22 +1,28 @@
-use framework::{types::dto::MessageID, Context};
+use framework::{types::dto::{GenerationDTO, MessageID, RoleType}, Context};
 use iced::{
     keyboard::{key::Named, Key}, widget::{
-        container, horizontal_space, markdown, text_editor::{self, Binding, KeyPress}, Column, Container, Row, Text, Tooltip
-    }, Element, Padding, Theme
+        button, container, horizontal_space, markdown, Image, text, text_editor::{self, Binding, KeyPress}, Column, Container, Row, Text, Tooltip
+    }, ContentFit, Element, Padding, Theme
 };
 
 use crate::widgets::{
     collapsible,
-    icon::{IconName, IconType},
+    icon::{Icon, IconName, IconType},
     icon_button::IconButton,
 };
 
 use super::{MessageViewer, message_viewer_state::SharedState};
 
+const IMAGE_THUMBNAIL_SIZE: f32 = 160.0;
+
 impl MessageViewer {
     pub fn view<'a>(&'a self, state: &'a SharedState, ctx: &'a Context) -> Element<'a, super::Message> {
         let mut main_column = Column::new();
 
+        if self.message_dto.role == RoleType::Tool {
+            main_column = main_column.push(Text::new("Tool result").style(text::secondary).size(12));
+        }
+
         let reasoning_string = self.reasoning_string.trim();
         if !reasoning_string.is_empty() {
             main_column = main_column
@@ -34,6 +40,10 @@ impl MessageViewer {
                 .spacing(10);
         }
 
+        if !self.images.is_empty() {
+            main_column = main_column.push(self.images_row());
+        }
+
         main_column = main_column
             .push(self.markdown_content::<'a>(
                 state,
@@ -43,6 +53,14 @@ impl MessageViewer {
             ))
             .spacing(10);
 
+        if !self.message_dto.tool_calls.is_empty() {
+            main_column = main_column.push(self.tool_calls());
+        }
+
+        if !self.message_dto.rag_queries.is_empty() {
+            main_column = main_column.push(self.rag_queries());
+        }
+
         main_column = main_column
             .push(self.used_chunks(ctx));
 
@@ -82,13 +100,59 @@ impl MessageViewer {
             .into()
     }
 
+    fn images_row(&self) -> Element<'_, super::Message> {
+        let mut row = Row::new().spacing(10);
+
+        for handle in &self.images {
+            row = row.push(
+                Image::new(handle.clone())
+                    .width(IMAGE_THUMBNAIL_SIZE)
+                    .height(IMAGE_THUMBNAIL_SIZE)
+                    .content_fit(ContentFit::Cover),
+            );
+        }
+
+        row.wrap().into()
+    }
+
+    fn tool_calls(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(4);
+
+        for tool_call in &self.message_dto.tool_calls {
+            column = column.push(
+                Text::new(format!("Called {}({})", tool_call.name, tool_call.arguments))
+                    .style(text::secondary)
+                    .size(12),
+            );
+        }
+
+        column.into()
+    }
+
+    fn rag_queries(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(4);
+
+        for query in &self.message_dto.rag_queries {
+            column = column.push(
+                Text::new(format!("Searched {query}"))
+                    .style(text::secondary)
+                    .size(12),
+            );
+        }
+
+        column.into()
+    }
+
     fn used_chunks(&self, ctx: &Context) -> Element<'_, super::Message> {   
         let mut main_row = Row::new()
             .spacing(10);
 
-        for chunk in &self.message_dto.chunks {
+        // Numbered as the answer cites them
+        for (number, chunk) in (1..).zip(&self.message_dto.chunks) {
+            let source = chunk.source(self.message_dto.conversation_id);
+
             let chunk_dto = ctx.vector_service.get_chunk(
-                self.message_dto.conversation_id,
+                source,
                 chunk.chunk_id,
                 chunk.dimension,
                 &chunk.embedding_model,
@@ -106,37 +170,28 @@ impl MessageViewer {
 
             let chunk_dto = chunk_dto.unwrap();
 
-            let file = ctx.vector_service.get_file(self.message_dto.conversation_id, chunk_dto.file_id)
-                .expect("File not found");
+            let Ok(Some(file)) = ctx.vector_service.get_file(source, chunk_dto.file_id) else {
+                continue;
+            };
 
             main_row = main_row.push(
-                Tooltip::new(
-                    Text::new(file.file_name).style(|theme: &Theme| {
-                        let palette = theme.extended_palette();
-                        iced::widget::text::Style {
-                            color: Some(palette.primary.base.color),
-                        }
-                    }),
-                    Container::new(
-                        Text::new(format!("{}: {}\n{}", file.embedding_model, file.dimension, chunk_dto.chunk)),
-                    )
-                    .max_width(600.0)
-                    .max_height(400.0)
-                    .padding(10)
-                    .style(|theme: &Theme| {
-                        let palette = theme.extended_palette();
-                        container::Style {
-                            background: Some(iced::Background::Color(palette.primary.base.color)),
-                            ..Default::default()
-                        }
-                    }),
-
-                    iced::widget::tooltip::Position::FollowCursor,
+                button::Button::new(
+                    Text::new(format!("[{number}] {}", chunk_dto.location(&file.file_name)))
+                        .size(12)
+                        .style(|theme: &Theme| {
+                            let palette = theme.extended_palette();
+                            iced::widget::text::Style {
+                                color: Some(palette.primary.base.color),
+                            }
+                        }),
                 )
-            )
+                .style(button::text)
+                .padding(0)
+                .on_press(super::Message::InspectChunk(number)),
+            );
         }
-        
-        Container::new(main_row).into()
+
+        Container::new(main_row.wrap()).into()
     }
 
     fn markdown_content<'a>(
@@ -163,21 +218,130 @@ impl MessageViewer {
 
     fn message_controls(&self) -> Element<'_, super::Message> {
         let is_gathering_message = self.get_id() == MessageID::default();
+        let mut row = Row::new()
+            .align_y(iced::alignment::Vertical::Center)
+            .push(self.branch_switcher())
+            .push(if self.message_dto.interrupted {
+                Text::new("Interrupted").style(text::secondary).size(12)
+            } else {
+                Text::new("").size(12)
+            })
+            .push(horizontal_space());
+
+        if let Some(generation) = &self.message_dto.generation {
+            row = row.push(Self::generation_details(generation));
+        }
+
+        // The buttons own what they show, they are shortened to the lifetime of the row
+        if self.message_dto.role == RoleType::Assistant {
+            let regenerate: Element<'_, super::Message> =
+                IconButton::new(IconType::Solid(IconName::ArrowsRotate), super::Message::Regenerate)
+                    .disabled(is_gathering_message)
+                    .into();
+
+            row = row.push(regenerate);
+        }
+
+        let edit: Element<'_, super::Message> =
+            IconButton::new(IconType::Solid(IconName::Pencil), super::Message::StartEdit)
+                .disabled(is_gathering_message)
+                .into();
+        let delete: Element<'_, super::Message> =
+            IconButton::new(IconType::Solid(IconName::Trash), super::Message::Delete)
+                .disabled(is_gathering_message)
+                .into();
+
+        row
+            .push(edit)
+            .push(delete)
+            .into()
+    }
+
+    /// Info icon showing how the answer was generated on hover.
+    fn generation_details(generation: &GenerationDTO) -> Element<'_, super::Message> {
+        let mut lines = vec![format!("Model: {}", generation.model)];
+
+        if let Some(preset) = &generation.preset {
+            lines.push(format!(
+                "Preset: {} (temperature {}, max tokens {})",
+                preset.name, preset.temperature, preset.max_tokens
+            ));
+        }
+
+        lines.push(format!(
+            "Tokens: {} prompt / {} completion",
+            generation.prompt_tokens, generation.completion_tokens
+        ));
+
+        if let Some(time_to_first_token) = generation.time_to_first_token {
+            lines.push(format!("First token: {time_to_first_token} ms"));
+        }
+
+        if let Some(duration) = generation.duration {
+            #[allow(clippy::cast_precision_loss)]
+            let seconds = duration as f64 / 1000.0;
+            lines.push(format!("Duration: {seconds:.1} s"));
+        }
+
+        if let Some(finish_reason) = &generation.finish_reason {
+            lines.push(format!("Finish reason: {finish_reason}"));
+        }
+
+        Tooltip::new(
+            Icon::new(IconType::Solid(IconName::CircleInfo)).alpha(0.6),
+            Container::new(Text::new(lines.join("\n")).size(12))
+                .padding(10)
+                .style(|theme: &Theme| {
+                    let palette = theme.extended_palette();
+                    container::Style {
+                        background: Some(iced::Background::Color(palette.background.strong.color)),
+                        ..Default::default()
+                    }
+                }),
+            iced::widget::tooltip::Position::Top,
+        )
+        .into()
+    }
+
+    /// `< 2/3 >` to switch between the alternatives of the message.
+    fn branch_switcher(&self) -> Element<'_, super::Message> {
+        let siblings = &self.message_dto.siblings;
+        let (position, count) = self.message_dto.branch_position();
+
+        if count < 2 {
+            return Row::new().into();
+        }
+
+        let previous = position
+            .checked_sub(2)
+            .and_then(|index| siblings.get(index))
+            .copied();
+        let next = siblings.get(position).copied();
+
         Row::new()
-            .push(horizontal_space())
+            .align_y(iced::alignment::Vertical::Center)
             .push(
-                IconButton::new(IconType::Solid(IconName::Pencil), super::Message::StartEdit)
-                    .disabled(is_gathering_message),
+                IconButton::new(
+                    IconType::Solid(IconName::ChevronLeft),
+                    super::Message::SwitchBranch(previous.unwrap_or_default()),
+                )
+                .disabled(previous.is_none())
+                .size(12.0),
             )
+            .push(Text::new(format!("{position}/{count}")).style(text::secondary).size(12))
             .push(
-                IconButton::new(IconType::Solid(IconName::Trash), super::Message::Delete)
-                    .disabled(is_gathering_message),
+                IconButton::new(
+                    IconType::Solid(IconName::ChevronRight),
+                    super::Message::SwitchBranch(next.unwrap_or_default()),
+                )
+                .disabled(next.is_none())
+                .size(12.0),
             )
             .into()
     }
 
     fn editing_controls(&self) -> Element<'_, super::Message> {
-        Row::new()
+        let mut row = Row::new()
             .push(horizontal_space())
             .push(IconButton::new(
                 IconType::Solid(IconName::XMark),
@@ -186,8 +350,16 @@ impl MessageViewer {
             .push(IconButton::new(
                 IconType::Solid(IconName::FloppyDisk),
                 super::Message::SubmitEdit,
-            ))
-            .into()
+            ));
+
+        if self.is_user_message() {
+            row = row.push(IconButton::new(
+                IconType::Solid(IconName::PaperPlane),
+                super::Message::SubmitResend,
+            ));
+        }
+
+        row.into()
     }
 
     fn key_bindings(&self, key: KeyPress) -> Option<Binding<super::Message>> {
@@ -2,6 +2,7 @@ mod chat_state;
 mod chat_subscription;
 mod chat_update;
 mod chat_view;
+mod chat_view_inspector;
 mod chat_view_messages;
 mod chat_view_texteditor;
 
@@ -23,6 +23,8 @@ pub enum Message {
 
     HideSettingsPane,
     ShowSettingsPane,
+    /// Selects the conversation and shows its settings with the model picker.
+    OpenSettings(ConversationNodeID),
 }
 
 #[derive(Debug)]
@@ -38,6 +38,19 @@ impl Conversations {
                 self.panes.resize(chat_split, self.settings_pane_ratio);
                 Task::none()
             }
+            super::Message::OpenSettings(conversation_id) => {
+                let mut tasks = vec![self.catch_selected_conversation(ctx, conversation_id)];
+
+                if !self.settings_expanded() {
+                    tasks.push(Task::done(super::Message::ShowSettingsPane));
+                }
+
+                tasks.push(Task::done(super::Message::Settings(
+                    settings::Message::ExpandGroup(settings::settings_state::BASIC_GROUP.to_string()),
+                )));
+
+                Task::batch(tasks)
+            }
             super::Message::DeleteConversation(id) => {
                 self.chats.remove(&id);
                 if self.current_chat_id == Some(id) {
@@ -56,6 +69,16 @@ impl Conversations {
             super::Message::Folders(folders::Message::Selected(selected_id)) => {
                 self.catch_selected_conversation(ctx, selected_id)
             }
+            super::Message::Folders(folders::Message::Search(folders::search::Message::Open(
+                conversation_id,
+                message_id,
+            ))) => Task::batch([
+                self.catch_selected_conversation(ctx, conversation_id),
+                Task::done(super::Message::Chat(
+                    conversation_id,
+                    chat::Message::FocusOn(message_id),
+                )),
+            ]),
             super::Message::Folders(message) => self
                 .folders
                 .update(ctx, message)
@@ -5,12 +5,16 @@ use framework::{
 };
 
 
-use super::tree_node::{SharedState, TreeNode};
+use super::{
+    search::Search,
+    tree_node::{SharedState, TreeNode},
+};
 
 #[derive(Debug, Clone)]
 pub struct Folders {
     // Components
     pub(super) root_folder: TreeNode,
+    pub(super) search: Search,
 
     // State
     pub(super) shared_state: SharedState,
@@ -19,8 +23,10 @@ pub struct Folders {
 #[derive(Debug, Clone)]
 pub enum Message {
     TreeNode(super::tree_node::Message),
+    Search(super::search::Message),
     CreateFolder,
     CreateChat,
+    Import,
     Drag(ConversationNodeID),
     Drop(Option<(ConversationNodeID, usize)>),
     Selected(ConversationNodeID),
@@ -38,6 +44,7 @@ impl Folders {
                     Message::TreeNode,
                     TreeNode::new_root(ConversationNodeID::default()),
                 ),
+                search: take_component(&mut tasks, Message::Search, Search::new()),
                 shared_state: SharedState::new(),
             },
             iced::Task::batch(tasks),
@@ -33,8 +33,13 @@ impl Folders {
 
                 Task::batch(tasks)
             }
+            super::Message::Search(message) => self
+                .search
+                .update(ctx, message)
+                .map(super::Message::Search),
             super::Message::CreateFolder => create_folder_task(self.root_folder.get_id()),
             super::Message::CreateChat => create_chat_task(self.root_folder.get_id()),
+            super::Message::Import => import_task(self.root_folder.get_id()),
             super::Message::Drag(id) => {
                 self.shared_state.dragged = Some(id);
                 Task::none()
@@ -193,3 +198,10 @@ fn create_chat_task(id: ConversationNodeID) -> Task<super::Message> {
         tree_node::NodeAction::StartConversationCreate,
     )))
 }
+
+fn import_task(id: ConversationNodeID) -> Task<super::Message> {
+    Task::done(super::Message::TreeNode(tree_node::Message::NodeAction(
+        id,
+        tree_node::NodeAction::StartImportFiles,
+    )))
+}
@@ -2,7 +2,7 @@ use framework::Context;
 use iced::{
     Element, Padding, Size,
     widget::{
-        Column, MouseArea, Row, Scrollable, container, horizontal_space,
+        Column, Container, MouseArea, Row, Scrollable, container, horizontal_space,
         vertical_space,
     },
 };
@@ -35,8 +35,23 @@ impl Folders {
                 .push(IconButton::new(
                     IconType::Solid(IconName::FolderPlus),
                     super::Message::CreateFolder,
+                ))
+                .push(IconButton::new(
+                    IconType::Solid(IconName::FileImport),
+                    super::Message::Import,
                 )),
         );
+        main_column = main_column.push(
+            Container::new(self.search.view().map(super::Message::Search)).padding(Padding {
+                bottom: 5.0,
+                ..Default::default()
+            }),
+        );
+
+        if self.search.is_active() {
+            return Self::scrollable_panel(main_column);
+        }
+
         main_column = main_column.push(
             self.root_folder
                 .view(&self.shared_state)
@@ -68,8 +83,12 @@ impl Folders {
             main_column = main_column.push(placeholder);
         }
 
-        iced::widget::Container::new(
-            Scrollable::new(main_column)
+        Self::scrollable_panel(main_column)
+    }
+
+    fn scrollable_panel(content: Column<'_, super::Message>) -> Element<'_, super::Message> {
+        Container::new(
+            Scrollable::new(content)
                 .style(styles::scrollable_style)
         )
             .style(|theme| container::Style {
@@ -3,6 +3,7 @@ mod folders_subscription;
 mod folders_update;
 mod folders_view;
 
+pub mod search;
 mod tree_node;
 
 pub use folders_state::Folders;
@@ -0,0 +1,6 @@
+mod search_state;
+mod search_update;
+mod search_view;
+
+pub use search_state::Message;
+pub use search_state::Search;
@@ -0,0 +1,130 @@
+use std::fmt::Display;
+
+use framework::types::dto::{
+    ConversationNodeID, MessageID, MessageSearchFilters, MessageSearchResultDTO, RoleType,
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    QueryChanged(String),
+    Clear,
+    ToggleFilters,
+    FolderSelected(FolderFilter),
+    RoleSelected(RoleFilter),
+    DateFromChanged(String),
+    DateToChanged(String),
+    ModelChanged(String),
+    /// Opens the chat scrolled to the message.
+    Open(ConversationNodeID, MessageID),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderFilter {
+    pub id: Option<ConversationNodeID>,
+    pub name: String,
+}
+
+impl FolderFilter {
+    pub(super) fn any() -> Self {
+        Self {
+            id: None,
+            name: "All folders".to_string(),
+        }
+    }
+}
+
+impl Display for FolderFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoleFilter(pub Option<RoleType>);
+
+impl RoleFilter {
+    pub(super) const ALL: &[Self] = &[
+        Self(None),
+        Self(Some(RoleType::User)),
+        Self(Some(RoleType::Assistant)),
+        Self(Some(RoleType::System)),
+        Self(Some(RoleType::Tool)),
+    ];
+}
+
+impl Display for RoleFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.0 {
+            None => "All roles",
+            Some(RoleType::User) => "User",
+            Some(RoleType::Assistant) => "Assistant",
+            Some(RoleType::System) => "System",
+            Some(RoleType::Tool) => "Tool",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub(super) query: String,
+    pub(super) results: Vec<MessageSearchResultDTO>,
+
+    pub(super) filters_expanded: bool,
+    pub(super) folders: Vec<FolderFilter>,
+    pub(super) folder: FolderFilter,
+    pub(super) role: RoleFilter,
+    pub(super) date_from: String,
+    pub(super) date_to: String,
+    pub(super) model: String,
+}
+
+impl Search {
+    pub fn new() -> (Self, iced::Task<Message>) {
+        (
+            Self {
+                query: String::new(),
+                results: vec![],
+                filters_expanded: false,
+                folders: vec![FolderFilter::any()],
+                folder: FolderFilter::any(),
+                role: RoleFilter::default(),
+                date_from: String::new(),
+                date_to: String::new(),
+                model: String::new(),
+            },
+            iced::Task::none(),
+        )
+    }
+
+    /// Results replace the folder tree while there is a query.
+    pub fn is_active(&self) -> bool {
+        !self.query.trim().is_empty()
+    }
+
+    pub(super) fn filters(&self) -> MessageSearchFilters {
+        let model = self.model.trim();
+
+        MessageSearchFilters {
+            folder_id: self.folder.id,
+            role: self.role.0.clone(),
+            date_from: parse_date(&self.date_from),
+            date_to: parse_date(&self.date_to),
+            model: (!model.is_empty()).then(|| model.to_string()),
+        }
+    }
+}
+
+/// `YYYY-MM-DD`, anything else does not filter.
+pub(super) fn parse_date(value: &str) -> Option<String> {
+    let value = value.trim();
+
+    let is_date = value.len() == 10
+        && value.char_indices().all(|(index, char)| match index {
+            4 | 7 => char == '-',
+            _ => char.is_ascii_digit(),
+        });
+
+    is_date.then(|| value.to_string())
+}
@@ -0,0 +1,92 @@
+use framework::Context;
+use iced::Task;
+
+use super::{
+    Search,
+    search_state::FolderFilter,
+};
+
+impl Search {
+    pub fn update(&mut self, ctx: &mut Context, message: super::Message) -> Task<super::Message> {
+        match message {
+            super::Message::QueryChanged(query) => {
+                self.query = query;
+                self.search(ctx);
+            }
+            super::Message::Clear => {
+                self.query.clear();
+                self.results.clear();
+            }
+            super::Message::ToggleFilters => {
+                self.filters_expanded = !self.filters_expanded;
+
+                if self.filters_expanded {
+                    self.load_folders(ctx);
+                }
+            }
+            super::Message::FolderSelected(folder) => {
+                self.folder = folder;
+                self.search(ctx);
+            }
+            super::Message::RoleSelected(role) => {
+                self.role = role;
+                self.search(ctx);
+            }
+            super::Message::DateFromChanged(date) => {
+                self.date_from = date;
+                self.search(ctx);
+            }
+            super::Message::DateToChanged(date) => {
+                self.date_to = date;
+                self.search(ctx);
+            }
+            super::Message::ModelChanged(model) => {
+                self.model = model;
+                self.search(ctx);
+            }
+            // Handled by the conversations, they own the chats
+            super::Message::Open(_, _) => {}
+        }
+
+        Task::none()
+    }
+
+    fn search(&mut self, ctx: &mut Context) {
+        if !self.is_active() {
+            self.results.clear();
+            return;
+        }
+
+        match ctx
+            .conversations_service
+            .search_messages(&self.query, &self.filters())
+        {
+            Ok(results) => self.results = results,
+            Err(e) => {
+                log::error!("Failed to search messages: {e}");
+                ctx.event_system.notify_error("Search failed", &e, None);
+            }
+        }
+    }
+
+    fn load_folders(&mut self, ctx: &mut Context) {
+        let folders = match ctx.conversations_service.get_folders() {
+            Ok(folders) => folders,
+            Err(e) => {
+                log::error!("Failed to load folders: {e}");
+                return;
+            }
+        };
+
+        self.folders = std::iter::once(FolderFilter::any())
+            .chain(folders.into_iter().map(|folder| FolderFilter {
+                id: Some(folder.id),
+                name: folder.name,
+            }))
+            .collect();
+
+        if !self.folders.contains(&self.folder) {
+            self.folder = FolderFilter::any();
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use iced::{
+    Element, Font, Length,
+    font::Weight,
+    widget::{
+        Column, PickList, Row, Text, TextInput, button, horizontal_space, rich_text, text,
+        text::Span,
+    },
+};
+
+use crate::widgets::{
+    icon::{IconName, IconType},
+    icon_button::IconButton,
+    input::Input,
+};
+
+use super::{
+    Search,
+    search_state::{RoleFilter, parse_date},
+};
+
+impl Search {
+    pub fn view(&self) -> Element<'_, super::Message> {
+        let mut search_row = Row::new()
+            .spacing(4)
+            .align_y(iced::alignment::Vertical::Center)
+            .push(
+                TextInput::new("Search messages", &self.query)
+                    .on_input(super::Message::QueryChanged)
+                    .padding(6)
+                    .width(Length::Fill),
+            );
+
+        if !self.query.is_empty() {
+            search_row = search_row.push(IconButton::new(
+                IconType::Solid(IconName::XMark),
+                super::Message::Clear,
+            ));
+        }
+
+        search_row = search_row.push(IconButton::new(
+            IconType::Solid(IconName::Filter),
+            super::Message::ToggleFilters,
+        ));
+
+        let mut column = Column::new().spacing(6).push(search_row);
+
+        if self.filters_expanded {
+            column = column.push(self.filters_view());
+        }
+
+        if self.is_active() {
+            column = column.push(self.results_view());
+        }
+
+        column.into()
+    }
+
+    fn filters_view(&self) -> Element<'_, super::Message> {
+        Column::new()
+            .spacing(6)
+            .push(
+                PickList::new(
+                    self.folders.as_slice(),
+                    Some(self.folder.clone()),
+                    super::Message::FolderSelected,
+                )
+                .width(Length::Fill),
+            )
+            .push(
+                PickList::new(RoleFilter::ALL, Some(self.role.clone()), super::Message::RoleSelected)
+                    .width(Length::Fill),
+            )
+            .push(
+                Row::new()
+                    .spacing(4)
+                    .push(date_input(&self.date_from, "From", super::Message::DateFromChanged))
+                    .push(date_input(&self.date_to, "To", super::Message::DateToChanged)),
+            )
+            .push(
+                Input::new(&self.model)
+                    .placeholder("Model")
+                    .on_change(super::Message::ModelChanged)
+                    .view(),
+            )
+            .into()
+    }
+
+    fn results_view(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(2);
+
+        if self.results.is_empty() {
+            return column
+                .push(Text::new("No messages found").size(13).style(text::secondary))
+                .into();
+        }
+
+        for result in &self.results {
+            let spans: Vec<Span<'_, super::Message, Font>> = result
+                .snippet_parts()
+                .into_iter()
+                .map(|(part, matched)| {
+                    if matched {
+                        Span::new(part).font(Font {
+                            weight: Weight::Bold,
+                            ..Font::default()
+                        })
+                    } else {
+                        Span::new(part)
+                    }
+                })
+                .collect();
+
+            let header = Row::new()
+                .spacing(4)
+                .push(Text::new(&result.conversation_name).size(13))
+                .push(horizontal_space())
+                .push(Text::new(&result.timestamp).size(11).style(text::secondary));
+
+            column = column.push(
+                button(
+                    Column::new()
+                        .spacing(2)
+                        .push(header)
+                        .push(rich_text(spans).size(12)),
+                )
+                .style(button::text)
+                .width(Length::Fill)
+                .on_press(super::Message::Open(result.conversation_id, result.message_id)),
+            );
+        }
+
+        column.into()
+    }
+}
+
+fn date_input<'a>(
+    value: &'a str,
+    placeholder: &str,
+    on_change: fn(String) -> super::Message,
+) -> Element<'a, super::Message> {
+    let error = (!value.trim().is_empty() && parse_date(value).is_none()).then_some("YYYY-MM-DD");
+
+    Input::new(value)
+        .placeholder(placeholder)
+        .error(error)
+        .on_change(on_change)
+        .view()
+}
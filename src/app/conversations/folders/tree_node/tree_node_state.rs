@@ -1,6 +1,11 @@
-use std::collections::HashMap;
-
-use framework::types::dto::{ConversationNodeDTO, ConversationNodeID, ConversationType};
+use std::{collections::HashMap, path::PathBuf};
+
+use framework::types::{
+    dto::{
+        ConversationNodeDTO, ConversationNodeID, ConversationType, ExportFormat, ImportReportDTO,
+    },
+    error::AppResult,
+};
 use iced::Point;
 
 #[derive(Clone, Debug)]
@@ -42,6 +47,15 @@ pub enum NodeAction {
     RenameError(String),
 
     StartDelete,
+
+    StartExport(ExportFormat),
+    /// `None` when no directory was selected.
+    Exported(Option<AppResult<PathBuf>>),
+
+    StartImportFiles,
+    StartImportFolder,
+    /// `None` when nothing was selected.
+    Imported(Option<AppResult<ImportReportDTO>>),
 }
 
 #[derive(Debug, Clone)]
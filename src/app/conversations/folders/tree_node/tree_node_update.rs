@@ -3,10 +3,14 @@ use super::{
 };
 use framework::{
     Context,
-    types::dto::{ConversationNodeDTO, ConversationNodeID},
+    types::{
+        common::NotificationLevel,
+        dto::{ConversationNodeDTO, ConversationNodeID, ExportFormat, ImportReportDTO},
+    },
     utils::notify,
 };
 use iced::Task;
+use std::fmt::Write;
 
 impl TreeNode {
     pub fn update(
@@ -92,6 +96,47 @@ impl TreeNode {
     ) -> Task<super::Message> {
         match action.clone() {
             super::NodeAction::StartDelete => self.start_delete(ctx),
+            super::NodeAction::StartExport(format) => self.start_export(ctx, format),
+            super::NodeAction::Exported(result) => {
+                match result {
+                    Some(Ok(path)) => ctx.event_system.clone().notify(
+                        NotificationLevel::Info,
+                        "Export finished",
+                        format!("Saved to {}", path.display()),
+                        None,
+                    ),
+                    Some(Err(e)) => {
+                        log::error!("Failed to export {}: {e}", self.id);
+                        ctx.event_system
+                            .clone()
+                            .notify_error("Failed to export", &e, None);
+                    }
+                    None => {}
+                }
+
+                Task::none()
+            }
+            super::NodeAction::StartImportFiles => self.start_import(ctx, false),
+            super::NodeAction::StartImportFolder => self.start_import(ctx, true),
+            super::NodeAction::Imported(result) => match result {
+                Some(Ok(report)) => {
+                    notify_import_report(ctx, &report);
+
+                    Task::done(super::Message::NodeAction(
+                        self.id,
+                        super::NodeAction::StartActualizeChildren,
+                    ))
+                }
+                Some(Err(e)) => {
+                    log::error!("Failed to import into {}: {e}", self.id);
+                    ctx.event_system
+                        .clone()
+                        .notify_error("Failed to import", &e, None);
+
+                    Task::none()
+                }
+                None => Task::none(),
+            },
             super::NodeAction::StartRename => {
                 state.renaming_process = Some(self.id);
                 state.rename_temp_value = Some(self.name.clone());
@@ -223,6 +268,43 @@ impl TreeNode {
         )
     }
 
+    fn start_export(&self, ctx: &Context, format: ExportFormat) -> Task<super::Message> {
+        let service = ctx.conversations_service.clone();
+        let id = self.id;
+
+        Task::perform(
+            async move {
+                notify::directory_selection("Export to")
+                    .map(|directory| service.export(id, format, &directory))
+            },
+            move |result| super::Message::NodeAction(id, super::NodeAction::Exported(result)),
+        )
+    }
+
+    fn start_import(&self, ctx: &Context, is_folder: bool) -> Task<super::Message> {
+        let service = ctx.conversations_service.clone();
+        let id = self.id;
+
+        Task::perform(
+            async move {
+                let paths = if is_folder {
+                    notify::directory_selection("Import folder")
+                        .into_iter()
+                        .collect()
+                } else {
+                    notify::file_selection("Import files")
+                };
+
+                if paths.is_empty() {
+                    return None;
+                }
+
+                Some(service.import(id, &paths))
+            },
+            move |result| super::Message::NodeAction(id, super::NodeAction::Imported(result)),
+        )
+    }
+
     fn rename_start_save(
         &self,
         ctx: &Context,
@@ -362,3 +444,43 @@ impl TreeNode {
         Task::batch(tasks)
     }
 }
+
+/// Skipped items listed in the notification, the rest are only counted.
+const NOTIFIED_SKIPPED: usize = 5;
+
+fn notify_import_report(ctx: &Context, report: &ImportReportDTO) {
+    let mut body = format!(
+        "{} chats with {} messages, {} folders",
+        report.chats, report.messages, report.folders
+    );
+
+    if !report.duplicates.is_empty() {
+        let _ = write!(body, "\nAlready imported: {}", report.duplicates.join(", "));
+    }
+
+    for skipped in report.skipped.iter().take(NOTIFIED_SKIPPED) {
+        let _ = write!(body, "\nSkipped {skipped}");
+    }
+
+    if report.skipped.len() > NOTIFIED_SKIPPED {
+        let _ = write!(
+            body,
+            "\n...and {} more",
+            report.skipped.len() - NOTIFIED_SKIPPED
+        );
+    }
+
+    for skipped in &report.skipped {
+        log::warn!("Import skipped {skipped}");
+    }
+
+    let level = if report.skipped.is_empty() {
+        NotificationLevel::Info
+    } else {
+        NotificationLevel::Warning
+    };
+
+    ctx.event_system
+        .clone()
+        .notify(level, "Import finished", body, None);
+}
@@ -3,6 +3,8 @@ use iced::{
     widget::{Container, MouseArea, Row, TextInput, container::Style, horizontal_space},
 };
 
+use framework::types::dto::ExportFormat;
+
 use crate::widgets::{
     context_menu::Action,
     context_menu::ContextMenu,
@@ -134,7 +136,7 @@ impl TreeNode {
             .into(),
         );
 
-        let ctx_menu = match self.content {
+        let mut ctx_menu = match self.content {
             Content::Folder(_) => ctx_menu
                 .action(self.create_action(
                     "New Folder",
@@ -146,6 +148,16 @@ impl TreeNode {
                     super::NodeAction::StartConversationCreate,
                     IconType::Solid(IconName::Comments),
                 ))
+                .action(self.create_action(
+                    "Import Files",
+                    super::NodeAction::StartImportFiles,
+                    IconType::Solid(IconName::FileImport),
+                ))
+                .action(self.create_action(
+                    "Import Folder",
+                    super::NodeAction::StartImportFolder,
+                    IconType::Solid(IconName::FileImport),
+                ))
                 .action(self.create_action(
                     "Rename",
                     super::NodeAction::StartRename,
@@ -168,9 +180,17 @@ impl TreeNode {
                     super::NodeAction::StartDelete,
                     IconType::Solid(IconName::Trash),
                 )),
-            Content::Loading => ctx_menu,
+            Content::Loading => return ctx_menu.into(),
         };
 
+        for format in ExportFormat::ALL {
+            ctx_menu = ctx_menu.action(self.create_action(
+                export_title(format),
+                super::NodeAction::StartExport(format),
+                IconType::Solid(IconName::FileExport),
+            ));
+        }
+
         ctx_menu.into()
     }
 
@@ -299,3 +319,11 @@ impl TreeNode {
         element.into()
     }
 }
+
+const fn export_title(format: ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::Markdown => "Export as Markdown",
+        ExportFormat::Json => "Export as JSON",
+        ExportFormat::Html => "Export as HTML",
+    }
+}
@@ -9,6 +9,8 @@ pub enum Message {
     ModelPicker(model_picker::Message),
 
     ChangeMaxMessages(i32),
+    ChangeContextSize(String),
+    ToggleTools(bool),
     InputName(String),
     SubmitName,
     RenameComplete(String),
@@ -17,6 +17,10 @@ impl Basic {
             super::Message::ChangeMaxMessages(max_messages) => {
                 self.change_max_messages(ctx, max_messages)
             }
+            super::Message::ChangeContextSize(context_size) => {
+                self.change_context_size(ctx, &context_size)
+            }
+            super::Message::ToggleTools(enabled) => self.toggle_tools(ctx, enabled),
             super::Message::InputName(name) => {
                 self.temp_name = name;
                 Task::none()
@@ -143,4 +147,45 @@ impl Basic {
 
         Task::none()
     }
+
+    fn change_context_size(&mut self, ctx: &Context, context_size: &str) -> Task<super::Message> {
+        let context_size = if context_size.is_empty() {
+            0
+        } else {
+            let Ok(context_size) = context_size.parse::<usize>() else {
+                return Task::none();
+            };
+            context_size
+        };
+
+        let mut conversations_service = ctx.conversations_service.clone();
+        let conversation_id = self.conversation.id;
+        let mut temp_conversation = conversations_service
+            .get_conversation(conversation_id)
+            .expect("Failed to get conversation");
+        temp_conversation.context_size = context_size;
+        conversations_service
+            .update_conversation(conversation_id, &temp_conversation)
+            .expect("Failed to update context size");
+
+        self.conversation.context_size = context_size;
+
+        Task::none()
+    }
+
+    fn toggle_tools(&mut self, ctx: &Context, enabled: bool) -> Task<super::Message> {
+        let mut conversations_service = ctx.conversations_service.clone();
+        let conversation_id = self.conversation.id;
+        let mut temp_conversation = conversations_service
+            .get_conversation(conversation_id)
+            .expect("Failed to get conversation");
+        temp_conversation.tools_enabled = enabled;
+        conversations_service
+            .update_conversation(conversation_id, &temp_conversation)
+            .expect("Failed to update tools");
+
+        self.conversation.tools_enabled = enabled;
+
+        Task::none()
+    }
 }
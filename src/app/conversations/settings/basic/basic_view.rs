@@ -1,7 +1,7 @@
 use std::ops::RangeInclusive;
 
 use framework::{types::dto::PresetDTO, Context};
-use iced::{widget::{text_editor, Column, Container, PickList, Row, Slider, Text, TextEditor, TextInput}, Element};
+use iced::{widget::{Checkbox, Column, Container, PickList, Row, Slider, Text, TextEditor, TextInput}, Element};
 
 use super::Basic;
 
@@ -23,7 +23,12 @@ impl Basic {
                     super::Message::ChangeMaxMessages,
                 ),
             ))
+            .push(self.field_setting("Context Size", self.context_size_input()))
             .push(self.model_picker.view(ctx).map(super::Message::ModelPicker))
+            .push(
+                Checkbox::new("Tools", self.conversation.tools_enabled)
+                    .on_toggle(super::Message::ToggleTools),
+            )
             .push(
                 self.field_setting("Conversation Prompt", self.prompt_input())
             );
@@ -50,6 +55,19 @@ impl Basic {
         input.into()
     }
 
+    /// Empty while the known size of the model is used.
+    fn context_size_input(&self) -> Element<'_, super::Message> {
+        let value = if self.conversation.context_size == 0 {
+            String::new()
+        } else {
+            self.conversation.context_size.to_string()
+        };
+
+        TextInput::new("Model default", &value)
+            .on_input(super::Message::ChangeContextSize)
+            .into()
+    }
+
     fn presets_selector(&self) -> Element<super::Message> {
         let mut presets = self.presets.clone();
 
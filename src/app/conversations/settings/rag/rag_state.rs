@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use framework::{types::{common::ProgressStatus, dto::{ConversationNodeDTO, RagFileDTO, RagFileID}}, utils::take_component};
+use framework::{types::{common::ProgressStatus, dto::{ConversationNodeDTO, KnowledgeBaseDTO, KnowledgeBaseID, RagFileDTO, RagFileID, WatchedPathDTO}}, utils::take_component};
 
 use crate::app::common::model_picker;
 
@@ -8,27 +8,56 @@ use crate::app::common::model_picker;
 #[derive(Debug, Clone)]
 pub enum Message {
     ModelPicker(model_picker::Message),
+    RerankModelPicker(model_picker::Message),
+    RewriteModelPicker(model_picker::Message),
 
     CancelLoadingFiles,
     FilesLoaded(Vec<PathBuf>),
     UpdateProgressFilesLoading(ProgressStatus),
     StartLoadingFiles,
+    StartLoadingDirectory,
     RagFilesListLoaded(Vec<RagFileDTO>),
     StartLoadingRagFilesLists,
     StartDeletingRagFile(RagFileID),    
+    StartWatchingFiles,
+    StartWatchingDirectory,
+    PathsSelectedToWatch(Vec<PathBuf>),
+    ChangeWatchInclude(String),
+    ChangeWatchExclude(String),
+    StartLoadingWatchedPaths,
+    StartDeletingWatchedPath(WatchedPathDTO),
     ChangeChunkSize(i32),
     ChangeChunksCount(i32),
+    ChangeVectorWeight(f32),
+    ChangeKeywordWeight(f32),
+    ChangeDistanceCutoff(f32),
+    ChangeRerankCandidates(i32),
+    ToggleQueryRewrite(bool),
+    ChangeSubQueries(i32),
+    ToggleHyde(bool),
+    StartLoadingKnowledgeBases,
+    ToggleKnowledgeBase(KnowledgeBaseID, bool),
 }
 
 #[derive(Debug, Clone)]
 pub struct Rag {
     // Components
     pub(super) model_picker: model_picker::ModelPicker,
+    pub(super) rerank_model_picker: model_picker::ModelPicker,
+    pub(super) rewrite_model_picker: model_picker::ModelPicker,
 
     // State
     pub(super) rag_files: Vec<RagFileDTO>,
+    pub(super) watched_paths: Vec<WatchedPathDTO>,
+    /// Globs separated by commas for the next watched folder.
+    pub(super) watch_include: String,
+    pub(super) watch_exclude: String,
     pub(super) loading_files_aborter: Option<iced::task::Handle>,
     pub(super) conversation: ConversationNodeDTO,
+    pub(super) knowledge_bases: Vec<KnowledgeBaseDTO>,
+    /// Attached to the chat itself, the others come from its folders.
+    pub(super) linked_knowledge_bases: Vec<KnowledgeBaseID>,
+    pub(super) attached_knowledge_bases: Vec<KnowledgeBaseID>,
 }
 
 impl Rag {
@@ -38,6 +67,8 @@ impl Rag {
         let mut tasks = vec![]; 
 
         tasks.push(iced::Task::done(super::Message::StartLoadingRagFilesLists));
+        tasks.push(iced::Task::done(super::Message::StartLoadingKnowledgeBases));
+        tasks.push(iced::Task::done(super::Message::StartLoadingWatchedPaths));
 
         (
             Self {
@@ -47,8 +78,24 @@ impl Rag {
                     Message::ModelPicker,
                     model_picker::ModelPicker::new(model_picker::ModelType::Embedding(conversation.clone().id))
                 ),
+                rerank_model_picker: take_component(
+                    &mut tasks,
+                    Message::RerankModelPicker,
+                    model_picker::ModelPicker::new(model_picker::ModelType::Rerank(conversation.id))
+                ),
+                rewrite_model_picker: take_component(
+                    &mut tasks,
+                    Message::RewriteModelPicker,
+                    model_picker::ModelPicker::new(model_picker::ModelType::QueryRewrite(conversation.id))
+                ),
                 loading_files_aborter: None,
                 rag_files: vec![],
+                watched_paths: vec![],
+                watch_include: String::new(),
+                watch_exclude: String::new(),
+                knowledge_bases: vec![],
+                linked_knowledge_bases: vec![],
+                attached_knowledge_bases: vec![],
             },
             iced::Task::batch(tasks)
         )
@@ -56,6 +103,7 @@ impl Rag {
 
     pub fn clear_view(&mut self) {
         self.rag_files.clear();
+        self.watched_paths.clear();
         self.loading_files_aborter = None;
     }
 }
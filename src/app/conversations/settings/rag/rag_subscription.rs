@@ -1,5 +1,5 @@
 use iced::Subscription;
-use framework::Context;
+use framework::{Context, types::dto::RagSource};
 
 use super::Rag;
 
@@ -9,7 +9,7 @@ impl Rag {
 
         subs.push(
             ctx.vector_service
-                .subscribe_files_update(self.conversation.id, |_| {
+                .subscribe_files_update(RagSource::Conversation(self.conversation.id), |_| {
                     super::Message::StartLoadingRagFilesLists
                 }),
         );
@@ -21,6 +21,18 @@ impl Rag {
                 .map(super::Message::ModelPicker),
         );
 
+        subs.push(
+            self.rerank_model_picker
+                .subscription(ctx)
+                .map(super::Message::RerankModelPicker),
+        );
+
+        subs.push(
+            self.rewrite_model_picker
+                .subscription(ctx)
+                .map(super::Message::RewriteModelPicker),
+        );
+
         Subscription::batch(subs)
     }
 }
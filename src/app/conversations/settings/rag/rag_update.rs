@@ -1,5 +1,7 @@
+use std::path::PathBuf;
+
 use super::Rag;
-use framework::{Context, types::common::ProgressStatus, utils::notify};
+use framework::{Context, types::{common::ProgressStatus, dto::{ConversationNodeDTO, KnowledgeBaseID, RagSource, WatchedPathDTO}}, utils::notify};
 use iced::Task;
 
 impl Rag {
@@ -9,6 +11,14 @@ impl Rag {
                 .model_picker
                 .update(ctx, message)
                 .map(super::Message::ModelPicker),
+            super::Message::RerankModelPicker(message) => self
+                .rerank_model_picker
+                .update(ctx, message)
+                .map(super::Message::RerankModelPicker),
+            super::Message::RewriteModelPicker(message) => self
+                .rewrite_model_picker
+                .update(ctx, message)
+                .map(super::Message::RewriteModelPicker),
             super::Message::CancelLoadingFiles => {
                 if let Some(aborter) = self.loading_files_aborter.take() {
                     aborter.abort();
@@ -19,10 +29,15 @@ impl Rag {
                 Task::none()
             }
             super::Message::FilesLoaded(files) => {
-                let task = Task::run(
-                    ctx.vector_service.load_files(self.conversation.id, files),
-                    super::Message::UpdateProgressFilesLoading,
-                );
+                let stream = match ctx.vector_service.load_files(RagSource::Conversation(self.conversation.id), files) {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::error!("Failed to load files: {e}");
+                        return Task::none();
+                    }
+                };
+
+                let task = Task::run(stream, super::Message::UpdateProgressFilesLoading);
 
                 let (task, task_abort) = task.abortable();
                 self.loading_files_aborter = Some(task_abort);
@@ -34,6 +49,12 @@ impl Rag {
                     self.loading_files_aborter = None;
                     Task::none()
                 }
+                ProgressStatus::Failed(e) => {
+                    log::error!("Failed to load files: {e}");
+
+                    self.loading_files_aborter = None;
+                    Task::none()
+                }
                 ProgressStatus::Started
                 | ProgressStatus::Progress {
                     name: _,
@@ -51,21 +72,37 @@ impl Rag {
 
                 Task::none()
             }
+            super::Message::StartLoadingDirectory => {
+                if self.model_picker.is_defined() {
+                    return Task::perform(
+                        async move {
+                            notify::directory_selection("Select folder")
+                                .into_iter()
+                                .collect()
+                        },
+                        super::Message::FilesLoaded,
+                    );
+                }
+
+                Task::none()
+            }
             super::Message::RagFilesListLoaded(rag_files) => {
                 self.rag_files = rag_files;
                 Task::none()
             }
             super::Message::StartDeletingRagFile(rag_file_id) => {
                 let vector_service = ctx.vector_service.clone();
-                let conversation_id = self.conversation.id;
+                let source = RagSource::Conversation(self.conversation.id);
 
                 Task::perform(
                     async move {
                         if notify::confirmation("Are you sure you want to delete this file?").await
                         {
-                            vector_service
-                                .delete_rag_file(conversation_id, rag_file_id)
-                                .expect("Failed to delete rag file");
+                            let result = vector_service.delete_rag_file(source, rag_file_id);
+
+                            if let Err(e) = result {
+                                log::error!("Failed to delete rag file: {e}");
+                            }
                         }
                     },
                     |()| super::Message::StartLoadingRagFilesLists,
@@ -75,18 +112,198 @@ impl Rag {
             super::Message::StartLoadingRagFilesLists => {
                 let vector_service = ctx.vector_service.clone();
 
-                let conversation_id = self.conversation.id;
+                let source = RagSource::Conversation(self.conversation.id);
 
                 Task::perform(
-                    async move { vector_service.get_files(conversation_id) },
+                    async move {
+                        vector_service.get_files(source).unwrap_or_else(|e| {
+                            log::error!("Failed to get files: {e}");
+                            vec![]
+                        })
+                    },
                     super::Message::RagFilesListLoaded,
                 )
             }
+            super::Message::StartWatchingFiles => {
+                if self.model_picker.is_defined() {
+                    return Task::perform(
+                        async move { notify::file_selection("Select files to watch") },
+                        super::Message::PathsSelectedToWatch,
+                    );
+                }
+
+                Task::none()
+            }
+            super::Message::StartWatchingDirectory => {
+                if self.model_picker.is_defined() {
+                    return Task::perform(
+                        async move {
+                            notify::directory_selection("Select folder to watch")
+                                .into_iter()
+                                .collect()
+                        },
+                        super::Message::PathsSelectedToWatch,
+                    );
+                }
+
+                Task::none()
+            }
+            super::Message::PathsSelectedToWatch(paths) => self.watch_paths(ctx, paths),
+            super::Message::ChangeWatchInclude(include) => {
+                self.watch_include = include;
+                Task::none()
+            }
+            super::Message::ChangeWatchExclude(exclude) => {
+                self.watch_exclude = exclude;
+                Task::none()
+            }
+            super::Message::StartLoadingWatchedPaths => {
+                self.load_watched_paths(ctx);
+                Task::none()
+            }
+            super::Message::StartDeletingWatchedPath(watched_path) => {
+                let vector_service = ctx.vector_service.clone();
+
+                Task::perform(
+                    async move {
+                        let confirmation = format!("Stop watching {} and delete its files?", watched_path.path);
+
+                        let deleted = if notify::confirmation(confirmation).await {
+                            vector_service.delete_watched_path(&watched_path)
+                        } else {
+                            Ok(())
+                        };
+
+                        if let Err(e) = deleted {
+                            log::error!("Failed to stop watching: {e}");
+                        }
+                    },
+                    |()| super::Message::StartLoadingWatchedPaths,
+                )
+            }
             super::Message::ChangeChunkSize(chunk_size) => self.change_chunk_size(ctx, chunk_size),
             super::Message::ChangeChunksCount(chunks_count) => {
                 self.change_chunks_count(ctx, chunks_count)
             }
+            super::Message::ChangeVectorWeight(weight) => self.change_search_setting(ctx, |conversation| {
+                conversation.rag_vector_weight = weight;
+            }),
+            super::Message::ChangeKeywordWeight(weight) => self.change_search_setting(ctx, |conversation| {
+                conversation.rag_keyword_weight = weight;
+            }),
+            super::Message::ChangeDistanceCutoff(cutoff) => self.change_search_setting(ctx, |conversation| {
+                conversation.rag_distance_cutoff = cutoff;
+            }),
+            super::Message::ChangeRerankCandidates(candidates) => self.change_search_setting(ctx, |conversation| {
+                conversation.rag_rerank_candidates = usize::try_from(candidates).unwrap_or_default();
+            }),
+            super::Message::ToggleQueryRewrite(enabled) => self.change_search_setting(ctx, |conversation| {
+                conversation.rag_query_rewrite = enabled;
+            }),
+            super::Message::ChangeSubQueries(sub_queries) => self.change_search_setting(ctx, |conversation| {
+                conversation.rag_sub_queries = usize::try_from(sub_queries).unwrap_or_default();
+            }),
+            super::Message::ToggleHyde(enabled) => self.change_search_setting(ctx, |conversation| {
+                conversation.rag_hyde = enabled;
+            }),
+            super::Message::StartLoadingKnowledgeBases => {
+                self.load_knowledge_bases(ctx);
+                Task::none()
+            }
+            super::Message::ToggleKnowledgeBase(knowledge_base_id, attached) => {
+                self.toggle_knowledge_base(ctx, knowledge_base_id, attached);
+                Task::none()
+            }
+        }
+    }
+
+    /// Registers the paths with the globs typed for them and indexes them.
+    fn watch_paths(&mut self, ctx: &Context, paths: Vec<PathBuf>) -> Task<super::Message> {
+        let source = RagSource::Conversation(self.conversation.id);
+        let include = WatchedPathDTO::split_globs(&self.watch_include);
+        let exclude = WatchedPathDTO::split_globs(&self.watch_exclude);
+
+        let mut added = vec![];
+
+        for path in paths {
+            match ctx.vector_service.add_watched_path(source, &path, &include, &exclude) {
+                Ok(watched_path) => added.push(watched_path),
+                Err(e) => ctx.event_system.clone().notify_error("Failed to watch the path", &e, None),
+            }
+        }
+
+        self.load_watched_paths(ctx);
+
+        if added.is_empty() {
+            return Task::none();
         }
+
+        let stream = match ctx.vector_service.sync_watched_paths(source, &added) {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::error!("Failed to load files: {e}");
+                return Task::none();
+            }
+        };
+
+        let (task, task_abort) = Task::run(stream, super::Message::UpdateProgressFilesLoading).abortable();
+        self.loading_files_aborter = Some(task_abort);
+
+        task
+    }
+
+    fn load_watched_paths(&mut self, ctx: &Context) {
+        self.watched_paths = ctx
+            .vector_service
+            .get_watched_paths(RagSource::Conversation(self.conversation.id))
+            .unwrap_or_else(|e| {
+                log::error!("Failed to get watched paths: {e}");
+                vec![]
+            });
+    }
+
+    fn load_knowledge_bases(&mut self, ctx: &Context) {
+        let conversation_id = self.conversation.id;
+
+        let loaded = ctx.vector_service.get_knowledge_bases().and_then(|knowledge_bases| {
+            Ok((
+                knowledge_bases,
+                ctx.vector_service.get_linked_knowledge_bases(conversation_id)?,
+                ctx.vector_service.get_attached_knowledge_bases(conversation_id)?,
+            ))
+        });
+
+        match loaded {
+            Ok((knowledge_bases, linked, attached)) => {
+                self.knowledge_bases = knowledge_bases;
+                self.linked_knowledge_bases = linked;
+                self.attached_knowledge_bases = attached.iter().map(|knowledge_base| knowledge_base.id).collect();
+            }
+            Err(e) => log::error!("Failed to get knowledge bases: {e}"),
+        }
+    }
+
+    fn toggle_knowledge_base(
+        &mut self,
+        ctx: &Context,
+        knowledge_base_id: KnowledgeBaseID,
+        attached: bool,
+    ) {
+        let conversation_id = self.conversation.id;
+
+        let result = if attached {
+            ctx.vector_service.attach_knowledge_base(knowledge_base_id, conversation_id)
+        } else {
+            ctx.vector_service.detach_knowledge_base(knowledge_base_id, conversation_id)
+        };
+
+        if let Err(e) = result {
+            ctx.event_system
+                .clone()
+                .notify_error("Failed to change knowledge bases", &e, None);
+        }
+
+        self.load_knowledge_bases(ctx);
     }
 
     fn change_chunk_size(&mut self, ctx: &Context, chunk_size: i32) -> Task<super::Message> {
@@ -126,4 +343,26 @@ impl Rag {
 
         Task::none()
     }
+
+    /// Fusion weights and the distance cutoff are saved while they are dragged.
+    fn change_search_setting(
+        &mut self,
+        ctx: &Context,
+        change: impl Fn(&mut ConversationNodeDTO),
+    ) -> Task<super::Message> {
+        let mut conversations_service = ctx.conversations_service.clone();
+        let conversation_id = self.conversation.id;
+        let mut temp_conversation = ctx
+            .conversations_service
+            .get_conversation(conversation_id)
+            .expect("Failed to get conversation");
+        change(&mut temp_conversation);
+        conversations_service
+            .update_conversation(conversation_id, &temp_conversation)
+            .expect("Failed to update search settings");
+
+        change(&mut self.conversation);
+
+        Task::none()
+    }
 }
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, ops::RangeInclusive};
 
-use iced::{widget::{button, Column, Container, Row, Slider, Text}, Element};
+use iced::{widget::{button, Checkbox, Column, Container, Row, Slider, Text, TextInput}, Element};
 use framework::{types::dto::RagFileDTO, Context};
 
 use crate::widgets::{button::Button, icon::{IconName, IconType}, icon_button::IconButton};
@@ -25,12 +25,173 @@ impl Rag {
                     super::Message::ChangeChunksCount
                 )),
             )
+            .push(
+                self.field_setting("Vector Search Weight", Self::float_slider_input(
+                    RangeInclusive::new(0.0, 2.0),
+                    self.conversation.rag_vector_weight,
+                    super::Message::ChangeVectorWeight
+                )),
+            )
+            .push(
+                self.field_setting("Keyword Search Weight", Self::float_slider_input(
+                    RangeInclusive::new(0.0, 2.0),
+                    self.conversation.rag_keyword_weight,
+                    super::Message::ChangeKeywordWeight
+                )),
+            )
+            .push(
+                self.field_setting("Max Vector Distance", Self::float_slider_input(
+                    RangeInclusive::new(0.1, 2.0),
+                    self.conversation.rag_distance_cutoff,
+                    super::Message::ChangeDistanceCutoff
+                )),
+            )
             .push(self.model_picker.view(ctx).map(super::Message::ModelPicker))
-            .push(self.rag_files());
+            .push(
+                self.field_setting("Rerank Candidates (0 = off)", self.slider_input(
+                    RangeInclusive::new(0, 64),
+                    self.conversation.rag_rerank_candidates,
+                    super::Message::ChangeRerankCandidates
+                )),
+            )
+            .push(
+                self.field_setting(
+                    "Rerank Model (the chat model when empty)",
+                    self.rerank_model_picker.view(ctx).map(super::Message::RerankModelPicker),
+                ),
+            )
+            .push(self.query_rewriting(ctx))
+            .push(self.rag_files())
+            .push(self.watched_paths())
+            .push(self.knowledge_bases());
 
         main_column.into()
     }
 
+    fn query_rewriting(&self, ctx: &Context) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(10).push(
+            Checkbox::new("Rewrite the message into a standalone query", self.conversation.rag_query_rewrite)
+                .on_toggle(super::Message::ToggleQueryRewrite),
+        );
+
+        if self.conversation.rag_query_rewrite {
+            column = column
+                .push(self.field_setting("Sub-Queries", self.slider_input(
+                    RangeInclusive::new(0, 5),
+                    self.conversation.rag_sub_queries,
+                    super::Message::ChangeSubQueries
+                )))
+                .push(
+                    Checkbox::new("Also search a hypothetical answer (HyDE)", self.conversation.rag_hyde)
+                        .on_toggle(super::Message::ToggleHyde),
+                )
+                .push(self.field_setting(
+                    "Rewrite Model (the chat model when empty)",
+                    self.rewrite_model_picker.view(ctx).map(super::Message::RewriteModelPicker),
+                ));
+        }
+
+        self.field_setting("Query Rewriting", column.into())
+    }
+
+    /// Files and folders indexed again when they change, with the globs for
+    /// the next one.
+    fn watched_paths(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(5).width(iced::Length::Fill);
+
+        for watched_path in &self.watched_paths {
+            let mut globs = watched_path.include.clone();
+            globs.extend(watched_path.exclude.iter().map(|glob| format!("!{glob}")));
+
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(iced::Alignment::Center)
+                    .push(
+                        Column::new()
+                            .width(iced::Length::Fill)
+                            .push(Text::new(watched_path.path.clone()))
+                            .push(Text::new(globs.join(", ")).size(12).style(iced::widget::text::secondary)),
+                    )
+                    .push(IconButton::new(
+                        IconType::Solid(IconName::Trash),
+                        super::Message::StartDeletingWatchedPath(watched_path.clone()),
+                    )),
+            );
+        }
+
+        column = column
+            .push(
+                TextInput::new("Include, e.g. *.md, docs/**", &self.watch_include)
+                    .on_input(super::Message::ChangeWatchInclude),
+            )
+            .push(
+                TextInput::new("Exclude, e.g. drafts/**", &self.watch_exclude)
+                    .on_input(super::Message::ChangeWatchExclude),
+            );
+
+        if self.loading_files_aborter.is_none() {
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        Button::new(
+                            Text::new("Watch files")
+                                .width(iced::Length::Fill)
+                                .align_x(iced::Alignment::Center)
+                                .align_y(iced::Alignment::Center)
+                            )
+                            .on_press(super::Message::StartWatchingFiles)
+                            .view()
+                            .width(iced::Length::Fill)
+                            .padding(5)
+                    )
+                    .push(
+                        Button::new(
+                            Text::new("Watch folder")
+                                .width(iced::Length::Fill)
+                                .align_x(iced::Alignment::Center)
+                                .align_y(iced::Alignment::Center)
+                            )
+                            .on_press(super::Message::StartWatchingDirectory)
+                            .view()
+                            .width(iced::Length::Fill)
+                            .padding(5)
+                    ),
+            );
+        }
+
+        self.field_setting("Watched (indexed again when they change)", column.into())
+    }
+
+    fn knowledge_bases(&self) -> Element<'_, super::Message> {
+        if self.knowledge_bases.is_empty() {
+            return Column::new().into();
+        }
+
+        let mut knowledge_bases = Column::new().spacing(5);
+
+        for knowledge_base in &self.knowledge_bases {
+            let is_linked = self.linked_knowledge_bases.contains(&knowledge_base.id);
+            let is_attached = self.attached_knowledge_bases.contains(&knowledge_base.id);
+
+            // Attached to a folder, it is detached from there
+            let checkbox = if is_attached && !is_linked {
+                Checkbox::new(format!("{} (from folder)", knowledge_base.name), true)
+            } else {
+                let knowledge_base_id = knowledge_base.id;
+
+                Checkbox::new(knowledge_base.name.clone(), is_linked).on_toggle(move |attached| {
+                    super::Message::ToggleKnowledgeBase(knowledge_base_id, attached)
+                })
+            };
+
+            knowledge_bases = knowledge_bases.push(checkbox);
+        }
+
+        self.field_setting("Knowledge Bases", knowledge_bases.into())
+    }
+
     fn rag_files(&self) -> Element<super::Message> {
         let mut main_column = Column::new()
             .spacing(10)
@@ -103,16 +264,32 @@ impl Rag {
             );
         } else {
             main_column = main_column.push(
-                Button::new(
-                    Text::new("Load files")
-                        .width(iced::Length::Fill)
-                        .align_x(iced::Alignment::Center)
-                        .align_y(iced::Alignment::Center)
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        Button::new(
+                            Text::new("Load files")
+                                .width(iced::Length::Fill)
+                                .align_x(iced::Alignment::Center)
+                                .align_y(iced::Alignment::Center)
+                            )
+                            .on_press(super::Message::StartLoadingFiles)
+                            .view()
+                            .width(iced::Length::Fill)
+                            .padding(5)
+                    )
+                    .push(
+                        Button::new(
+                            Text::new("Load folder")
+                                .width(iced::Length::Fill)
+                                .align_x(iced::Alignment::Center)
+                                .align_y(iced::Alignment::Center)
+                            )
+                            .on_press(super::Message::StartLoadingDirectory)
+                            .view()
+                            .width(iced::Length::Fill)
+                            .padding(5)
                     )
-                    .on_press(super::Message::StartLoadingFiles)
-                    .view()
-                    .width(iced::Length::Fill)
-                    .padding(5)
             );
         }
 
@@ -162,4 +339,22 @@ impl Rag {
         )
         .into()
     }
+
+    fn float_slider_input<'a>(
+        range: RangeInclusive<f32>,
+        value: f32,
+        message: impl Fn(f32) -> super::Message + 'static,
+    ) -> Element<'a, super::Message> {
+        let slider = Slider::<f32, super::Message>::new(range, value, message).step(0.05);
+
+        Container::new(
+            Row::new()
+                .spacing(10)
+                .width(iced::Length::Fill)
+                .align_y(iced::Alignment::Center)
+                .push(slider)
+                .push(Text::new(format!("{value:.2}"))),
+        )
+        .into()
+    }
 }
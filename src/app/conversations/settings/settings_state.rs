@@ -4,6 +4,9 @@ use framework::{types::dto::ConversationNodeDTO, utils::take_component};
 
 use super::basic::Basic;
 
+/// Group with the model picker of the conversation.
+pub const BASIC_GROUP: &str = "Basic";
+
 #[derive(Debug, Clone)]
 pub enum Message {
     Summary(super::summary::Message),
@@ -12,6 +15,7 @@ pub enum Message {
     
     ClearView,
     ToggleGroup(String),
+    ExpandGroup(String),
     UpdateConversation(ConversationNodeDTO),
 }
 
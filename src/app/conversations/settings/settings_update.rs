@@ -33,6 +33,10 @@ impl Settings {
                 *expanded = !*expanded;
                 Task::none()
             }
+            super::Message::ExpandGroup(group) => {
+                self.groups_expaned.insert(group, true);
+                Task::none()
+            }
             super::Message::ClearView => self.clear_view(),
         }
     }
@@ -32,7 +32,7 @@ impl Settings {
                             .align_y(iced::Alignment::Center),
                     )
                     .push(self.groups(vec![
-                        ("Basic", self.basic.view(ctx).map(super::Message::Basic)),
+                        (super::settings_state::BASIC_GROUP, self.basic.view(ctx).map(super::Message::Basic)),
                         ("RAG", self.rag.view(ctx).map(super::Message::Rag)),
                         (
                             "Summary",
@@ -0,0 +1,152 @@
+use std::{collections::HashMap, fmt::Display, path::PathBuf};
+
+use framework::types::{
+    common::ProgressStatus,
+    dto::{
+        ConversationNodeDTO, ConversationNodeID, KnowledgeBaseDTO, KnowledgeBaseID, RagFileDTO,
+        RagFileID, WatchedPathDTO,
+    },
+};
+use iced::{Task, widget::pane_grid};
+
+use crate::app::common::model_picker;
+
+#[derive(Debug, Clone)]
+pub(super) enum Pane {
+    KnowledgeBasesList,
+    KnowledgeBaseDetails,
+}
+
+/// Chat or folder a knowledge base can be attached to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeTarget {
+    pub id: ConversationNodeID,
+    /// Names of the folders above and of the node itself.
+    pub path: String,
+    pub is_folder: bool,
+}
+
+impl Display for NodeTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_folder {
+            write!(f, "{}/", self.path)
+        } else {
+            write!(f, "{}", self.path)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Refresh,
+    PaneResize(pane_grid::ResizeEvent),
+    AddKnowledgeBase,
+    SelectKnowledgeBase(KnowledgeBaseID),
+    NameEdit(String),
+    ChangeChunkSize(i32),
+    ModelPicker(model_picker::Message),
+
+    StartLoadingFiles,
+    StartLoadingDirectory,
+    FilesSelected(Vec<PathBuf>),
+    UpdateProgressFilesLoading(ProgressStatus),
+    CancelLoadingFiles,
+    FilesUpdated(Vec<RagFileDTO>),
+    StartDeletingFile(RagFileID),
+
+    StartWatchingFiles,
+    StartWatchingDirectory,
+    PathsSelectedToWatch(Vec<PathBuf>),
+    WatchIncludeEdit(String),
+    WatchExcludeEdit(String),
+    WatchedPathsUpdated(Vec<WatchedPathDTO>),
+    StartDeletingWatchedPath(WatchedPathDTO),
+
+    Attach(NodeTarget),
+    Detach(ConversationNodeID),
+
+    DeleteKnowledgeBase,
+    DeleteKnowledgeBaseComplete(Option<KnowledgeBaseID>),
+}
+
+#[derive(Debug)]
+pub struct Knowledge {
+    // Components
+    pub(super) model_picker: Option<model_picker::ModelPicker>,
+
+    // State
+    pub(super) panes: pane_grid::State<Pane>,
+    pub(super) knowledge_bases: Vec<KnowledgeBaseDTO>,
+    pub(super) selected: Option<KnowledgeBaseDTO>,
+    pub(super) files: Vec<RagFileDTO>,
+    pub(super) watched_paths: Vec<WatchedPathDTO>,
+    /// Globs separated by commas for the next watched folder.
+    pub(super) watch_include: String,
+    pub(super) watch_exclude: String,
+    pub(super) nodes: Vec<NodeTarget>,
+    pub(super) attached_nodes: Vec<ConversationNodeID>,
+    pub(super) loading_files_aborter: Option<iced::task::Handle>,
+}
+
+impl Knowledge {
+    pub fn new() -> (Self, Task<Message>) {
+        let (mut panes, knowledge_bases_list) = pane_grid::State::new(Pane::KnowledgeBasesList);
+
+        let (_, left_split) = panes
+            .split(
+                pane_grid::Axis::Vertical,
+                knowledge_bases_list,
+                Pane::KnowledgeBaseDetails,
+            )
+            .expect("Failed to split pane");
+
+        panes.resize(left_split, 0.2);
+
+        (
+            Self {
+                model_picker: None,
+                panes,
+                knowledge_bases: vec![],
+                selected: None,
+                files: vec![],
+                watched_paths: vec![],
+                watch_include: String::new(),
+                watch_exclude: String::new(),
+                nodes: vec![],
+                attached_nodes: vec![],
+                loading_files_aborter: None,
+            },
+            Task::done(Message::Refresh),
+        )
+    }
+
+    /// Chats and folders with the path to them, sorted by it.
+    pub(super) fn node_targets(nodes: &[ConversationNodeDTO]) -> Vec<NodeTarget> {
+        let by_id: HashMap<ConversationNodeID, &ConversationNodeDTO> =
+            nodes.iter().map(|node| (node.id, node)).collect();
+
+        let mut targets: Vec<NodeTarget> = nodes
+            .iter()
+            .map(|node| {
+                let mut names = vec![node.name.as_str()];
+                let mut parent = by_id.get(&node.parent_id);
+
+                while let Some(folder) = parent {
+                    names.push(folder.name.as_str());
+                    parent = by_id.get(&folder.parent_id);
+                }
+
+                names.reverse();
+
+                NodeTarget {
+                    id: node.id,
+                    path: names.join("/"),
+                    is_folder: !node.is_chat(),
+                }
+            })
+            .collect();
+
+        targets.sort_by_key(|target| target.path.to_lowercase());
+        targets
+    }
+}
@@ -0,0 +1,29 @@
+use framework::{Context, types::dto::RagSource};
+use iced::Subscription;
+
+use super::Knowledge;
+
+impl Knowledge {
+    pub fn subscription(&self, ctx: &Context) -> Subscription<super::Message> {
+        let mut subs = vec![];
+
+        if let Some(selected) = self.selected.as_ref() {
+            subs.push(
+                ctx.vector_service
+                    .subscribe_files_update(RagSource::KnowledgeBase(selected.id), |(_, files)| {
+                        super::Message::FilesUpdated(files)
+                    }),
+            );
+        }
+
+        if let Some(model_picker) = self.model_picker.as_ref() {
+            subs.push(
+                model_picker
+                    .subscription(ctx)
+                    .map(super::Message::ModelPicker),
+            );
+        }
+
+        Subscription::batch(subs)
+    }
+}
@@ -0,0 +1,455 @@
+use std::path::PathBuf;
+
+use framework::{
+    Context,
+    types::{
+        common::ProgressStatus,
+        dto::{KnowledgeBaseDTO, KnowledgeBaseID, RagSource, WatchedPathDTO},
+    },
+    utils::{notify, take_component},
+};
+use iced::Task;
+
+use crate::app::common::model_picker;
+
+use super::Knowledge;
+
+impl Knowledge {
+    pub fn update(&mut self, ctx: &mut Context, message: super::Message) -> Task<super::Message> {
+        match message {
+            super::Message::Refresh => {
+                self.refresh(ctx);
+                Task::none()
+            }
+            super::Message::PaneResize(event) => {
+                self.panes.resize(event.split, event.ratio);
+                Task::none()
+            }
+            super::Message::AddKnowledgeBase => {
+                match ctx.vector_service.create_knowledge_base("Knowledge Base") {
+                    Ok(knowledge_base) => {
+                        self.knowledge_bases.push(knowledge_base.clone());
+                        Task::done(super::Message::SelectKnowledgeBase(knowledge_base.id))
+                    }
+                    Err(e) => {
+                        ctx.event_system.clone().notify_error(
+                            "Failed to add a knowledge base",
+                            &e,
+                            None,
+                        );
+                        Task::none()
+                    }
+                }
+            }
+            super::Message::SelectKnowledgeBase(knowledge_base_id) => {
+                self.select(ctx, knowledge_base_id)
+            }
+            super::Message::NameEdit(name) => {
+                self.update_selected(ctx, |knowledge_base| knowledge_base.name = name);
+                Task::none()
+            }
+            super::Message::ChangeChunkSize(chunk_size) => {
+                #[allow(clippy::cast_sign_loss)]
+                let chunk_size = chunk_size as usize;
+                self.update_selected(ctx, |knowledge_base| knowledge_base.chunk_size = chunk_size);
+                Task::none()
+            }
+            super::Message::ModelPicker(message) => {
+                let Some(model_picker) = self.model_picker.as_mut() else {
+                    return Task::none();
+                };
+
+                let task = model_picker
+                    .update(ctx, message)
+                    .map(super::Message::ModelPicker);
+
+                // The picker saves the model, the copy here has to follow
+                let refreshed = self.selected.as_ref().and_then(|selected| {
+                    ctx.vector_service.get_knowledge_base(selected.id).ok()
+                });
+                if refreshed.is_some() {
+                    self.selected = refreshed;
+                }
+
+                task
+            }
+            super::Message::StartLoadingFiles => Task::perform(
+                async move { notify::file_selection("Select files") },
+                super::Message::FilesSelected,
+            ),
+            super::Message::StartLoadingDirectory => Task::perform(
+                async move {
+                    notify::directory_selection("Select folder")
+                        .into_iter()
+                        .collect()
+                },
+                super::Message::FilesSelected,
+            ),
+            super::Message::FilesSelected(files) => {
+                let Some(source) = self.source() else {
+                    return Task::none();
+                };
+
+                if files.is_empty() {
+                    return Task::none();
+                }
+
+                let stream = match ctx.vector_service.load_files(source, files) {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::error!("Failed to load files: {e}");
+                        return Task::none();
+                    }
+                };
+
+                let (task, task_abort) =
+                    Task::run(stream, super::Message::UpdateProgressFilesLoading).abortable();
+                self.loading_files_aborter = Some(task_abort);
+
+                task
+            }
+            super::Message::UpdateProgressFilesLoading(progress) => {
+                if matches!(
+                    progress,
+                    ProgressStatus::Finished | ProgressStatus::Failed(_)
+                ) {
+                    self.loading_files_aborter = None;
+                }
+
+                Task::none()
+            }
+            super::Message::CancelLoadingFiles => {
+                if let Some(aborter) = self.loading_files_aborter.take() {
+                    aborter.abort();
+                    ctx.vector_service.cancel_loading_files();
+                }
+
+                Task::none()
+            }
+            super::Message::FilesUpdated(files) => {
+                self.files = files;
+                Task::none()
+            }
+            super::Message::StartDeletingFile(rag_file_id) => {
+                let Some(source) = self.source() else {
+                    return Task::none();
+                };
+                let vector_service = ctx.vector_service.clone();
+
+                Task::perform(
+                    async move {
+                        let deleted =
+                            if notify::confirmation("Are you sure you want to delete this file?")
+                                .await
+                            {
+                                vector_service.delete_rag_file(source, rag_file_id)
+                            } else {
+                                Ok(())
+                            };
+
+                        if let Err(e) = deleted {
+                            log::error!("Failed to delete rag file: {e}");
+                        }
+
+                        vector_service.get_files(source).unwrap_or_default()
+                    },
+                    super::Message::FilesUpdated,
+                )
+            }
+            super::Message::StartWatchingFiles => Task::perform(
+                async move { notify::file_selection("Select files to watch") },
+                super::Message::PathsSelectedToWatch,
+            ),
+            super::Message::StartWatchingDirectory => Task::perform(
+                async move {
+                    notify::directory_selection("Select folder to watch")
+                        .into_iter()
+                        .collect()
+                },
+                super::Message::PathsSelectedToWatch,
+            ),
+            super::Message::PathsSelectedToWatch(paths) => self.watch_paths(ctx, paths),
+            super::Message::WatchIncludeEdit(include) => {
+                self.watch_include = include;
+                Task::none()
+            }
+            super::Message::WatchExcludeEdit(exclude) => {
+                self.watch_exclude = exclude;
+                Task::none()
+            }
+            super::Message::WatchedPathsUpdated(watched_paths) => {
+                self.watched_paths = watched_paths;
+                Task::none()
+            }
+            super::Message::StartDeletingWatchedPath(watched_path) => {
+                let vector_service = ctx.vector_service.clone();
+
+                Task::perform(
+                    async move {
+                        let confirmation = format!(
+                            "Stop watching {} and delete its files?",
+                            watched_path.path
+                        );
+
+                        let deleted = if notify::confirmation(confirmation).await {
+                            vector_service.delete_watched_path(&watched_path)
+                        } else {
+                            Ok(())
+                        };
+
+                        if let Err(e) = deleted {
+                            log::error!("Failed to stop watching: {e}");
+                        }
+
+                        vector_service
+                            .get_watched_paths(watched_path.source)
+                            .unwrap_or_default()
+                    },
+                    super::Message::WatchedPathsUpdated,
+                )
+            }
+            super::Message::Attach(node) => {
+                let attached = self.selected.as_ref().map_or(Ok(()), |selected| {
+                    ctx.vector_service
+                        .attach_knowledge_base(selected.id, node.id)
+                });
+                if let Err(e) = attached {
+                    ctx.event_system.clone().notify_error(
+                        "Failed to attach the knowledge base",
+                        &e,
+                        None,
+                    );
+                }
+
+                self.load_attached_nodes(ctx);
+                Task::none()
+            }
+            super::Message::Detach(node_id) => {
+                let detached = self.selected.as_ref().map_or(Ok(()), |selected| {
+                    ctx.vector_service
+                        .detach_knowledge_base(selected.id, node_id)
+                });
+                if let Err(e) = detached {
+                    ctx.event_system.clone().notify_error(
+                        "Failed to detach the knowledge base",
+                        &e,
+                        None,
+                    );
+                }
+
+                self.load_attached_nodes(ctx);
+                Task::none()
+            }
+            super::Message::DeleteKnowledgeBase => {
+                let Some(selected) = self.selected.clone() else {
+                    return Task::none();
+                };
+                let vector_service = ctx.vector_service.clone();
+
+                Task::perform(
+                    async move {
+                        let confirmation = format!(
+                            "Delete the knowledge base \"{}\" with all its files?",
+                            selected.name
+                        );
+
+                        if !notify::confirmation(confirmation).await {
+                            return None;
+                        }
+
+                        match vector_service.delete_knowledge_base(selected.id) {
+                            Ok(()) => Some(selected.id),
+                            Err(e) => {
+                                log::error!("Failed to delete knowledge base: {e}");
+                                None
+                            }
+                        }
+                    },
+                    super::Message::DeleteKnowledgeBaseComplete,
+                )
+            }
+            super::Message::DeleteKnowledgeBaseComplete(knowledge_base_id) => {
+                if let Some(knowledge_base_id) = knowledge_base_id {
+                    self.knowledge_bases
+                        .retain(|knowledge_base| knowledge_base.id != knowledge_base_id);
+
+                    if self.source() == Some(RagSource::KnowledgeBase(knowledge_base_id)) {
+                        self.clear_selection();
+                    }
+                }
+
+                Task::none()
+            }
+        }
+    }
+
+    pub(super) fn source(&self) -> Option<RagSource> {
+        self.selected
+            .as_ref()
+            .map(|knowledge_base| RagSource::KnowledgeBase(knowledge_base.id))
+    }
+
+    /// Registers the paths with the globs typed for them and indexes them.
+    fn watch_paths(&mut self, ctx: &Context, paths: Vec<PathBuf>) -> Task<super::Message> {
+        let Some(source) = self.source() else {
+            return Task::none();
+        };
+
+        let include = WatchedPathDTO::split_globs(&self.watch_include);
+        let exclude = WatchedPathDTO::split_globs(&self.watch_exclude);
+
+        let mut added = vec![];
+
+        for path in paths {
+            match ctx.vector_service.add_watched_path(source, &path, &include, &exclude) {
+                Ok(watched_path) => added.push(watched_path),
+                Err(e) => ctx
+                    .event_system
+                    .clone()
+                    .notify_error("Failed to watch the path", &e, None),
+            }
+        }
+
+        self.load_watched_paths(ctx);
+
+        if added.is_empty() {
+            return Task::none();
+        }
+
+        let stream = match ctx.vector_service.sync_watched_paths(source, &added) {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::error!("Failed to load files: {e}");
+                return Task::none();
+            }
+        };
+
+        let (task, task_abort) =
+            Task::run(stream, super::Message::UpdateProgressFilesLoading).abortable();
+        self.loading_files_aborter = Some(task_abort);
+
+        task
+    }
+
+    fn load_watched_paths(&mut self, ctx: &Context) {
+        let Some(source) = self.source() else {
+            return;
+        };
+
+        self.watched_paths = ctx
+            .vector_service
+            .get_watched_paths(source)
+            .unwrap_or_else(|e| {
+                log::error!("Failed to get watched paths: {e}");
+                vec![]
+            });
+    }
+
+    /// Chats and folders may have changed in the other views.
+    fn refresh(&mut self, ctx: &Context) {
+        match ctx.vector_service.get_knowledge_bases() {
+            Ok(knowledge_bases) => self.knowledge_bases = knowledge_bases,
+            Err(e) => log::error!("Failed to get knowledge bases: {e}"),
+        }
+
+        match ctx.conversations_service.get_all_nodes() {
+            Ok(nodes) => self.nodes = Self::node_targets(&nodes),
+            Err(e) => log::error!("Failed to get conversations: {e}"),
+        }
+
+        let is_deleted = self.selected.as_ref().is_some_and(|selected| {
+            !self
+                .knowledge_bases
+                .iter()
+                .any(|knowledge_base| knowledge_base.id == selected.id)
+        });
+
+        if is_deleted {
+            self.clear_selection();
+        } else {
+            self.load_attached_nodes(ctx);
+        }
+    }
+
+    fn select(
+        &mut self,
+        ctx: &mut Context,
+        knowledge_base_id: KnowledgeBaseID,
+    ) -> Task<super::Message> {
+        let knowledge_base = match ctx.vector_service.get_knowledge_base(knowledge_base_id) {
+            Ok(knowledge_base) => knowledge_base,
+            Err(e) => {
+                log::error!("Failed to get knowledge base: {e}");
+                return Task::none();
+            }
+        };
+
+        self.files = ctx
+            .vector_service
+            .get_files(RagSource::KnowledgeBase(knowledge_base_id))
+            .unwrap_or_else(|e| {
+                log::error!("Failed to get files: {e}");
+                vec![]
+            });
+        self.selected = Some(knowledge_base);
+        self.load_attached_nodes(ctx);
+        self.load_watched_paths(ctx);
+
+        let mut tasks = vec![];
+
+        self.model_picker = Some(take_component(
+            &mut tasks,
+            super::Message::ModelPicker,
+            model_picker::ModelPicker::new(model_picker::ModelType::KnowledgeBase(
+                knowledge_base_id,
+            )),
+        ));
+
+        Task::batch(tasks)
+    }
+
+    fn clear_selection(&mut self) {
+        self.selected = None;
+        self.model_picker = None;
+        self.files.clear();
+        self.watched_paths.clear();
+        self.attached_nodes.clear();
+    }
+
+    fn load_attached_nodes(&mut self, ctx: &Context) {
+        let Some(selected) = self.selected.as_ref() else {
+            return;
+        };
+
+        self.attached_nodes = ctx
+            .vector_service
+            .get_attached_nodes(selected.id)
+            .unwrap_or_else(|e| {
+                log::error!("Failed to get attached chats: {e}");
+                vec![]
+            });
+    }
+
+    fn update_selected(&mut self, ctx: &Context, change: impl FnOnce(&mut KnowledgeBaseDTO)) {
+        let Some(selected) = self.selected.as_mut() else {
+            return;
+        };
+
+        change(selected);
+
+        if let Err(e) = ctx.vector_service.update_knowledge_base(selected) {
+            ctx.event_system
+                .clone()
+                .notify_error("Failed to save the knowledge base", &e, None);
+            return;
+        }
+
+        if let Some(knowledge_base) = self
+            .knowledge_bases
+            .iter_mut()
+            .find(|k| k.id == selected.id)
+        {
+            knowledge_base.clone_from(selected);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::ops::RangeInclusive;
+
+use framework::{Context, types::dto::KnowledgeBaseDTO};
+use iced::{
+    Element, Padding, Theme,
+    widget::{
+        Column, Container, PickList, Row, Scrollable, Slider, Text, TextInput, button, container,
+        horizontal_space, pane_grid, text,
+    },
+};
+
+use crate::{
+    theme::styles::{self, scrollable_style},
+    widgets::{
+        button::Button,
+        icon::{IconName, IconType},
+        icon_button::IconButton,
+    },
+};
+
+use super::{Knowledge, knowledge_state::Pane};
+
+impl Knowledge {
+    pub fn view<'a>(&'a self, ctx: &'a Context) -> Element<'a, super::Message> {
+        let main_row = pane_grid::PaneGrid::new(&self.panes, |_, pane, _| match pane {
+            Pane::KnowledgeBasesList => self.knowledge_bases_list().into(),
+            Pane::KnowledgeBaseDetails => Container::new(self.selected.as_ref().map_or_else(
+                || Text::new("No knowledge base selected").into(),
+                |knowledge_base| self.knowledge_base_details(ctx, knowledge_base),
+            ))
+            .align_x(iced::alignment::Horizontal::Center)
+            .align_y(iced::alignment::Vertical::Center)
+            .style(|theme: &Theme| container::Style {
+                shadow: styles::fake_oneside_border(theme, &styles::Side::Left),
+                ..Default::default()
+            })
+            .into(),
+        })
+        .on_resize(5, super::Message::PaneResize);
+
+        Container::new(main_row).into()
+    }
+
+    fn knowledge_bases_list(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(10).width(iced::Length::Fill);
+
+        for knowledge_base in &self.knowledge_bases {
+            let mut btn = Button::new(Text::new(knowledge_base.name.clone()));
+            if self.selected.as_ref().map(|selected| selected.id) != Some(knowledge_base.id) {
+                btn = btn.on_press(super::Message::SelectKnowledgeBase(knowledge_base.id));
+            }
+
+            column = column.push(
+                btn.view()
+                    .style(button::secondary)
+                    .width(iced::Length::Fill),
+            );
+        }
+
+        column = column.push(
+            Container::new(
+                Button::new(
+                    Text::new("Add Knowledge Base")
+                        .align_x(iced::alignment::Horizontal::Center)
+                        .align_y(iced::alignment::Vertical::Center)
+                        .width(iced::Length::Fill),
+                )
+                .on_press(super::Message::AddKnowledgeBase)
+                .view()
+                .width(iced::Length::Fill),
+            )
+            .padding(5),
+        );
+
+        Container::new(
+            Scrollable::new(Container::new(column).padding(5))
+                .style(scrollable_style)
+                .width(iced::Length::Fill)
+                .height(iced::Length::Fill),
+        )
+        .style(|theme: &Theme| container::Style {
+            shadow: styles::fake_oneside_border(theme, &styles::Side::Right),
+            ..Default::default()
+        })
+        .into()
+    }
+
+    fn knowledge_base_details<'a>(
+        &'a self,
+        ctx: &'a Context,
+        knowledge_base: &'a KnowledgeBaseDTO,
+    ) -> Element<'a, super::Message> {
+        let mut main_column = Column::new()
+            .spacing(10)
+            .padding(Padding {
+                top: 10.0,
+                bottom: 10.0,
+                left: 20.0,
+                right: 20.0,
+            })
+            .width(iced::Length::Fill);
+
+        main_column = main_column.push(
+            Text::new(format!("Knowledge Base: {}", knowledge_base.name))
+                .size(30)
+                .width(iced::Length::Fill),
+        );
+
+        main_column = main_column
+            .push(TextInput::new("", &knowledge_base.name).on_input(super::Message::NameEdit));
+
+        main_column = main_column.push(Text::new("Embedding Model").size(20));
+
+        if let Some(model_picker) = self.model_picker.as_ref() {
+            main_column = main_column.push(model_picker.view(ctx).map(super::Message::ModelPicker));
+        }
+
+        main_column = main_column.push(Text::new("Max Chunk Size").size(20));
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        let chunk_size = knowledge_base.chunk_size as i32;
+
+        main_column = main_column.push(
+            Row::new()
+                .spacing(10)
+                .align_y(iced::alignment::Vertical::Center)
+                .push(
+                    Slider::new(
+                        RangeInclusive::new(128, 2048),
+                        chunk_size,
+                        super::Message::ChangeChunkSize,
+                    )
+                    .width(iced::Length::Fill),
+                )
+                .push(Text::new(chunk_size.to_string()).size(20).width(50)),
+        );
+
+        main_column = main_column
+            .push(Text::new("Files").size(20))
+            .push(self.files())
+            .push(Text::new("Watched").size(20))
+            .push(self.watched_paths())
+            .push(Text::new("Attached To").size(20))
+            .push(self.attached_nodes());
+
+        Container::new(
+            Column::new()
+                .spacing(10)
+                .padding(Padding {
+                    top: 10.0,
+                    bottom: 10.0,
+                    left: 20.0,
+                    right: 20.0,
+                })
+                .push(
+                    Container::new(Scrollable::new(main_column).style(scrollable_style))
+                        .height(iced::Length::Fill),
+                )
+                .push(
+                    Row::new()
+                        .align_y(iced::alignment::Vertical::Center)
+                        .push(horizontal_space())
+                        .push(
+                            Button::new("Delete")
+                                .on_press(super::Message::DeleteKnowledgeBase)
+                                .view()
+                                .style(button::danger),
+                        ),
+                ),
+        )
+        .into()
+    }
+
+    fn files(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(5).width(iced::Length::Fill);
+
+        for file in &self.files {
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(iced::Alignment::Center)
+                    .push(Text::new(file.file_name.clone()).width(iced::Length::Fill))
+                    .push(
+                        Text::new(format!(
+                            "{} / {}, {} chunks",
+                            file.embedding_model, file.dimension, file.chunks
+                        ))
+                        .size(12)
+                        .style(text::secondary),
+                    )
+                    .push(IconButton::new(
+                        IconType::Solid(IconName::Trash),
+                        super::Message::StartDeletingFile(file.id),
+                    )),
+            );
+        }
+
+        let load_buttons: Element<super::Message> = if self.loading_files_aborter.is_some() {
+            Button::new(
+                Text::new("Cancel files loading...")
+                    .width(iced::Length::Fill)
+                    .align_x(iced::Alignment::Center),
+            )
+            .on_press(super::Message::CancelLoadingFiles)
+            .view()
+            .style(button::danger)
+            .width(iced::Length::Fill)
+            .padding(5)
+            .into()
+        } else {
+            Row::new()
+                .spacing(10)
+                .push(
+                    Button::new(
+                        Text::new("Load files")
+                            .width(iced::Length::Fill)
+                            .align_x(iced::Alignment::Center),
+                    )
+                    .on_press(super::Message::StartLoadingFiles)
+                    .view()
+                    .width(iced::Length::Fill)
+                    .padding(5),
+                )
+                .push(
+                    Button::new(
+                        Text::new("Load folder")
+                            .width(iced::Length::Fill)
+                            .align_x(iced::Alignment::Center),
+                    )
+                    .on_press(super::Message::StartLoadingDirectory)
+                    .view()
+                    .width(iced::Length::Fill)
+                    .padding(5),
+                )
+                .into()
+        };
+
+        column.push(load_buttons).into()
+    }
+
+    /// Files and folders indexed again when they change, with the globs for
+    /// the next one.
+    fn watched_paths(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(5).width(iced::Length::Fill);
+
+        for watched_path in &self.watched_paths {
+            let mut globs = watched_path.include.clone();
+            globs.extend(watched_path.exclude.iter().map(|glob| format!("!{glob}")));
+
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(iced::Alignment::Center)
+                    .push(Text::new(watched_path.path.clone()).width(iced::Length::Fill))
+                    .push(Text::new(globs.join(", ")).size(12).style(text::secondary))
+                    .push(IconButton::new(
+                        IconType::Solid(IconName::Trash),
+                        super::Message::StartDeletingWatchedPath(watched_path.clone()),
+                    )),
+            );
+        }
+
+        column = column.push(
+            Row::new()
+                .spacing(10)
+                .push(
+                    TextInput::new("Include, e.g. *.md, docs/**", &self.watch_include)
+                        .on_input(super::Message::WatchIncludeEdit),
+                )
+                .push(
+                    TextInput::new("Exclude, e.g. drafts/**", &self.watch_exclude)
+                        .on_input(super::Message::WatchExcludeEdit),
+                ),
+        );
+
+        if self.loading_files_aborter.is_none() {
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        Button::new(
+                            Text::new("Watch files")
+                                .width(iced::Length::Fill)
+                                .align_x(iced::Alignment::Center),
+                        )
+                        .on_press(super::Message::StartWatchingFiles)
+                        .view()
+                        .width(iced::Length::Fill)
+                        .padding(5),
+                    )
+                    .push(
+                        Button::new(
+                            Text::new("Watch folder")
+                                .width(iced::Length::Fill)
+                                .align_x(iced::Alignment::Center),
+                        )
+                        .on_press(super::Message::StartWatchingDirectory)
+                        .view()
+                        .width(iced::Length::Fill)
+                        .padding(5),
+                    ),
+            );
+        }
+
+        column.into()
+    }
+
+    fn attached_nodes(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(5).width(iced::Length::Fill);
+
+        for node in self
+            .nodes
+            .iter()
+            .filter(|node| self.attached_nodes.contains(&node.id))
+        {
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(iced::Alignment::Center)
+                    .push(Text::new(node.to_string()).width(iced::Length::Fill))
+                    .push(IconButton::new(
+                        IconType::Solid(IconName::XMark),
+                        super::Message::Detach(node.id),
+                    )),
+            );
+        }
+
+        let options: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|node| !self.attached_nodes.contains(&node.id))
+            .cloned()
+            .collect();
+
+        column
+            .push(
+                PickList::new(
+                    options,
+                    None::<super::knowledge_state::NodeTarget>,
+                    super::Message::Attach,
+                )
+                .placeholder("Attach to a chat or folder")
+                .width(iced::Length::Fill),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,7 @@
+mod knowledge_state;
+mod knowledge_subscription;
+mod knowledge_update;
+mod knowledge_view;
+
+pub use knowledge_state::Knowledge;
+pub use knowledge_state::Message;
@@ -4,8 +4,11 @@ mod app_update;
 mod app_view;
 
 mod conversations;
+mod knowledge;
+mod notifications;
 mod presets;
 mod settings;
+mod usage;
 
 pub mod common;
 pub use app_state::App;
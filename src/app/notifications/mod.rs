@@ -0,0 +1,7 @@
+mod notifications_state;
+mod notifications_subscription;
+mod notifications_update;
+mod notifications_view;
+
+pub use notifications_state::Message;
+pub use notifications_state::Notifications;
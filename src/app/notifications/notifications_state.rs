@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use framework::types::common::{NotificationAction, NotificationLevel};
+
+/// Older notifications are dropped from the history.
+pub(super) const HISTORY_LIMIT: usize = 100;
+
+pub type NotificationID = usize;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Received(NotificationLevel, String, String, Option<NotificationAction>),
+    Dismiss(NotificationID),
+    /// Handled by the app, which knows how to reach the target of the action.
+    RunAction(NotificationID, NotificationAction),
+    ToggleHistory,
+    ClearHistory,
+    Tick(Instant),
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub(super) id: NotificationID,
+    pub(super) level: NotificationLevel,
+    pub(super) title: String,
+    pub(super) body: String,
+    pub(super) action: Option<NotificationAction>,
+    pub(super) received_at: Instant,
+}
+
+impl Notification {
+    /// Errors stay longer, the user is more likely to need them.
+    pub(super) const fn lifetime(&self) -> Duration {
+        match self.level {
+            NotificationLevel::Info => Duration::from_secs(4),
+            NotificationLevel::Warning => Duration::from_secs(7),
+            NotificationLevel::Error => Duration::from_secs(12),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Notifications {
+    // State
+    pub(super) history: Vec<Notification>,
+    /// Notifications shown as toasts until they expire or are dismissed.
+    pub(super) toasts: Vec<NotificationID>,
+    pub(super) history_opened: bool,
+    pub(super) unread: usize,
+    pub(super) next_id: NotificationID,
+}
+
+impl Notifications {
+    pub fn new() -> (Self, iced::Task<Message>) {
+        (Self::default(), iced::Task::none())
+    }
+
+    pub(super) fn get(&self, id: NotificationID) -> Option<&Notification> {
+        self.history.iter().find(|notification| notification.id == id)
+    }
+}
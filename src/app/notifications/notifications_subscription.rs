@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use framework::{
+    Context,
+    types::common::NotificationLevel,
+    utils::event_system::Event,
+};
+use iced::Subscription;
+
+use super::Notifications;
+
+impl Notifications {
+    pub fn subscription(&self, ctx: &Context) -> Subscription<super::Message> {
+        let mut subs = vec![];
+
+        subs.push(ctx.event_system.subscribe(
+            &Event::Notify {
+                level: NotificationLevel::Info,
+                title: String::new(),
+                body: String::new(),
+                action: None,
+            },
+            |(level, title, body, action)| super::Message::Received(level, title, body, action),
+        ));
+
+        if !self.toasts.is_empty() {
+            subs.push(iced::time::every(Duration::from_millis(500)).map(super::Message::Tick));
+        }
+
+        Subscription::batch(subs)
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::Instant;
+
+use iced::Task;
+
+use super::{Notifications, notifications_state::{HISTORY_LIMIT, Notification}};
+
+impl Notifications {
+    pub fn update(&mut self, message: super::Message) -> Task<super::Message> {
+        match message {
+            super::Message::Received(level, title, body, action) => {
+                let id = self.next_id;
+                self.next_id += 1;
+
+                self.history.push(Notification {
+                    id,
+                    level,
+                    title,
+                    body,
+                    action,
+                    received_at: Instant::now(),
+                });
+
+                if self.history.len() > HISTORY_LIMIT {
+                    let removed = self.history.remove(0);
+                    self.toasts.retain(|toast_id| *toast_id != removed.id);
+                }
+
+                self.toasts.push(id);
+
+                if !self.history_opened {
+                    self.unread += 1;
+                }
+
+                Task::none()
+            }
+            super::Message::Dismiss(id) => {
+                self.toasts.retain(|toast_id| *toast_id != id);
+                Task::none()
+            }
+            super::Message::RunAction(id, _) => {
+                self.toasts.retain(|toast_id| *toast_id != id);
+                self.history_opened = false;
+                Task::none()
+            }
+            super::Message::ToggleHistory => {
+                self.history_opened = !self.history_opened;
+                self.unread = 0;
+                Task::none()
+            }
+            super::Message::ClearHistory => {
+                self.history.clear();
+                self.toasts.clear();
+                self.unread = 0;
+                Task::none()
+            }
+            super::Message::Tick(now) => {
+                let history = &self.history;
+
+                self.toasts.retain(|toast_id| {
+                    history
+                        .iter()
+                        .find(|notification| notification.id == *toast_id)
+                        .is_some_and(|notification| {
+                            now.duration_since(notification.received_at) < notification.lifetime()
+                        })
+                });
+
+                Task::none()
+            }
+        }
+    }
+}
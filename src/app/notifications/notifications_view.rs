@@ -0,0 +1,170 @@
+use framework::types::common::{NotificationAction, NotificationLevel};
+use iced::{
+    Border, Color, Element, Length, Theme,
+    widget::{Column, Container, Row, Scrollable, Text, container, horizontal_space, text},
+};
+
+use crate::{
+    theme::styles::scrollable_style,
+    widgets::{
+        button::Button,
+        icon::{IconName, IconType},
+        icon_button::IconButton,
+    },
+};
+
+use super::{Notifications, notifications_state::Notification};
+
+const PANEL_WIDTH: f32 = 360.0;
+
+impl Notifications {
+    /// Layer drawn over the whole app: toasts in the bottom right corner,
+    /// or the history while it is opened.
+    pub fn view(&self) -> Element<'_, super::Message> {
+        let content = if self.history_opened {
+            self.history_view()
+        } else {
+            self.toasts_view()
+        };
+
+        Row::new()
+            .padding(16)
+            .push(horizontal_space())
+            .push(content)
+            .into()
+    }
+
+    pub const fn unread(&self) -> usize {
+        self.unread
+    }
+
+    fn toasts_view(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new().spacing(8).width(PANEL_WIDTH);
+
+        for notification in self.toasts.iter().filter_map(|id| self.get(*id)) {
+            column = column.push(Self::notification_card(notification, true));
+        }
+
+        Container::new(column)
+            .height(Length::Fill)
+            .align_y(iced::alignment::Vertical::Bottom)
+            .into()
+    }
+
+    fn history_view(&self) -> Element<'_, super::Message> {
+        let header = Row::new()
+            .align_y(iced::alignment::Vertical::Center)
+            .push(Text::new("Notifications").size(16))
+            .push(horizontal_space())
+            .push(Button::new(Text::new("Clear").size(13)).on_press(super::Message::ClearHistory))
+            .push(IconButton::new(
+                IconType::Solid(IconName::XMark),
+                super::Message::ToggleHistory,
+            ));
+
+        let mut list = Column::new().spacing(8);
+
+        if self.history.is_empty() {
+            list = list.push(Text::new("Nothing here yet").style(text::secondary));
+        }
+
+        for notification in self.history.iter().rev() {
+            list = list.push(Self::notification_card(notification, false));
+        }
+
+        Container::new(
+            Column::new()
+                .spacing(10)
+                .push(header)
+                .push(Scrollable::new(list).style(scrollable_style)),
+        )
+        .padding(10)
+        .width(PANEL_WIDTH)
+        .height(Length::Fill)
+        .style(|theme: &Theme| panel_style(theme, theme.extended_palette().background.strong.color))
+        .into()
+    }
+
+    fn notification_card(
+        notification: &Notification,
+        dismissible: bool,
+    ) -> Element<'_, super::Message> {
+        let level = notification.level;
+
+        let mut header = Row::new()
+            .align_y(iced::alignment::Vertical::Center)
+            .push(
+                Text::new(&notification.title)
+                    .size(14)
+                    .style(move |theme: &Theme| text::Style {
+                        color: Some(level_color(theme, level)),
+                    }),
+            )
+            .push(horizontal_space());
+
+        if dismissible {
+            let dismiss: Element<'_, super::Message> = IconButton::new(
+                IconType::Solid(IconName::XMark),
+                super::Message::Dismiss(notification.id),
+            )
+            .size(12.0)
+            .into();
+
+            header = header.push(dismiss);
+        }
+
+        let mut column = Column::new()
+            .spacing(6)
+            .push(header)
+            .push(Text::new(&notification.body).size(13));
+
+        if let Some(action) = notification.action {
+            column = column.push(
+                Button::new(Text::new(action_label(action)).size(13))
+                    .on_press(super::Message::RunAction(notification.id, action)),
+            );
+        }
+
+        Container::new(column)
+            .padding(10)
+            .width(Length::Fill)
+            .style(move |theme: &Theme| panel_style(theme, level_color(theme, level)))
+            .into()
+    }
+}
+
+const fn action_label(action: NotificationAction) -> &'static str {
+    match action {
+        NotificationAction::OpenModelPicker(_) => "Open model picker",
+        NotificationAction::OpenProviders => "Open providers",
+    }
+}
+
+fn level_color(theme: &Theme, level: NotificationLevel) -> Color {
+    let palette = theme.extended_palette();
+
+    match level {
+        NotificationLevel::Info => palette.success.base.color,
+        NotificationLevel::Warning => palette.primary.base.color,
+        NotificationLevel::Error => palette.danger.base.color,
+    }
+}
+
+fn panel_style(theme: &Theme, border_color: Color) -> container::Style {
+    let palette = theme.extended_palette();
+
+    container::Style {
+        background: Some(iced::Background::Color(palette.background.base.color)),
+        border: Border {
+            color: border_color,
+            width: 1.0,
+            radius: 6.0.into(),
+        },
+        shadow: iced::Shadow {
+            color: Color::BLACK.scale_alpha(0.4),
+            offset: iced::Vector::new(0.0, 2.0),
+            blur_radius: 8.0,
+        },
+        ..Default::default()
+    }
+}
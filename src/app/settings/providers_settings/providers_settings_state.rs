@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use framework::types::dto::{ProviderDTO, ProviderID};
+use framework::types::dto::{ModelPriceDTO, ProviderDTO, ProviderID, ProviderKind};
 use iced::{widget::pane_grid, Task};
 
 #[derive(Debug, Clone)]
@@ -19,6 +19,14 @@ pub enum Message {
     UpdateProviderUrl(String),
     UpdateProviderApiKey(String),
     UpdateProviderDefaultModel(String),
+    UpdateProviderKind(ProviderKind),
+    UpdateProviderReasoningTags(String),
+
+    AddModelPrice,
+    RemoveModelPrice(usize),
+    UpdatePriceModel(usize, String),
+    UpdatePromptPrice(usize, String),
+    UpdateCompletionPrice(usize, String),
 
     AddProvider,
     DeleteProvider,
@@ -30,6 +38,46 @@ pub enum Message {
     CreatedProvider(Option<ProviderDTO>),
 }
 
+/// Model price as it is typed, the prices are parsed on save.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct PriceRow {
+    pub(super) model: String,
+    pub(super) prompt_price: String,
+    pub(super) completion_price: String,
+}
+
+impl PriceRow {
+    pub(super) fn is_valid(value: &str) -> bool {
+        value.trim().is_empty() || value.trim().parse::<f64>().is_ok_and(|price| price >= 0.0)
+    }
+
+    /// Rows without a model are skipped, empty prices are free.
+    pub(super) fn to_dto(&self, provider_id: ProviderID) -> Option<ModelPriceDTO> {
+        let model = self.model.trim();
+
+        if model.is_empty() {
+            return None;
+        }
+
+        Some(ModelPriceDTO {
+            provider_id,
+            model: model.to_string(),
+            prompt_price: self.prompt_price.trim().parse().unwrap_or_default(),
+            completion_price: self.completion_price.trim().parse().unwrap_or_default(),
+        })
+    }
+}
+
+impl From<ModelPriceDTO> for PriceRow {
+    fn from(price: ModelPriceDTO) -> Self {
+        Self {
+            model: price.model,
+            prompt_price: price.prompt_price.to_string(),
+            completion_price: price.completion_price.to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ProvidersSettings {
         // State
@@ -37,6 +85,8 @@ pub struct ProvidersSettings {
         pub(super) selected_provider: Option<ProviderID>,
         pub(super) panes: pane_grid::State<Pane>,
         pub(super) temp_provider: ProviderDTO,
+        pub(super) prices: Vec<PriceRow>,
+        pub(super) temp_prices: Vec<PriceRow>,
 }
 
 impl ProvidersSettings {
@@ -60,6 +110,8 @@ impl ProvidersSettings {
                 providers: HashMap::new(),
                 panes,
                 selected_provider: None,
+                prices: vec![],
+                temp_prices: vec![],
             },
             iced::Task::batch(tasks)
         )
@@ -73,7 +125,7 @@ impl ProvidersSettings {
         let selected_provider = self.selected_provider.expect("Selected provider is None");
 
         if let Some(provider) = self.providers.get(&selected_provider) {
-            return *provider != self.temp_provider;
+            return *provider != self.temp_provider || self.prices != self.temp_prices;
         }
 
         false
@@ -93,5 +145,7 @@ impl ProvidersSettings {
         } else {
             self.temp_provider = ProviderDTO::default();
         }
+
+        self.temp_prices.clone_from(&self.prices);
     }
 }
@@ -1,5 +1,9 @@
-use super::ProvidersSettings;
-use framework::{Context, types::dto::ProviderDTO, utils::notify};
+use super::{ProvidersSettings, providers_settings_state::PriceRow};
+use framework::{
+    Context,
+    types::{common::NotificationLevel, dto::ProviderDTO},
+    utils::notify,
+};
 use iced::Task;
 
 impl ProvidersSettings {
@@ -13,10 +17,38 @@ impl ProvidersSettings {
                 let mut dto = self.temp_provider.clone();
                 dto.id = self.selected_provider.expect("No provider selected");
 
+                let is_prices_valid = self.temp_prices.iter().all(|price| {
+                    PriceRow::is_valid(&price.prompt_price)
+                        && PriceRow::is_valid(&price.completion_price)
+                });
+
+                if !is_prices_valid {
+                    ctx.event_system.notify(
+                        NotificationLevel::Warning,
+                        "Invalid model price",
+                        "Prices are in USD per million tokens, like 2.5",
+                        None,
+                    );
+                    return Task::none();
+                }
+
                 ctx.app_settings
                     .update_provider(&dto)
                     .expect("Failed to update provider");
 
+                let prices: Vec<_> = self
+                    .temp_prices
+                    .iter()
+                    .filter_map(|price| price.to_dto(dto.id))
+                    .collect();
+
+                if let Err(e) = ctx.usage_service.set_model_prices(dto.id, &prices) {
+                    log::error!("Failed to save model prices: {e}");
+                    ctx.event_system.notify_error("Failed to save model prices", &e, None);
+                }
+
+                self.load_prices(ctx);
+
                 Task::done(super::Message::LoadedProviders(vec![dto]))
             }
             super::Message::ResizePane(event) => {
@@ -61,6 +93,8 @@ impl ProvidersSettings {
                     self.temp_provider = ProviderDTO::default();
                 }
 
+                self.load_prices(ctx);
+
                 Task::none()
             }
             super::Message::DeleteProvider => {
@@ -93,6 +127,10 @@ impl ProvidersSettings {
                 if let Some(provider_id) = provider_id {
                     self.selected_provider = None;
                     self.providers.remove(&provider_id);
+
+                    if let Err(e) = ctx.usage_service.set_model_prices(provider_id, &[]) {
+                        log::error!("Failed to delete model prices: {e}");
+                    }
                 }
 
                 Task::none()
@@ -147,6 +185,42 @@ impl ProvidersSettings {
                 self.temp_provider.default_model.clone_from(&default_model);
                 Task::none()
             }
+            super::Message::UpdateProviderKind(kind) => {
+                self.temp_provider.kind = kind;
+                Task::none()
+            }
+            super::Message::UpdateProviderReasoningTags(reasoning_tags) => {
+                self.temp_provider.reasoning_tags = reasoning_tags;
+                Task::none()
+            }
+            super::Message::AddModelPrice => {
+                self.temp_prices.push(PriceRow::default());
+                Task::none()
+            }
+            super::Message::RemoveModelPrice(index) => {
+                if index < self.temp_prices.len() {
+                    self.temp_prices.remove(index);
+                }
+                Task::none()
+            }
+            super::Message::UpdatePriceModel(index, model) => {
+                if let Some(price) = self.temp_prices.get_mut(index) {
+                    price.model = model;
+                }
+                Task::none()
+            }
+            super::Message::UpdatePromptPrice(index, prompt_price) => {
+                if let Some(price) = self.temp_prices.get_mut(index) {
+                    price.prompt_price = prompt_price;
+                }
+                Task::none()
+            }
+            super::Message::UpdateCompletionPrice(index, completion_price) => {
+                if let Some(price) = self.temp_prices.get_mut(index) {
+                    price.completion_price = completion_price;
+                }
+                Task::none()
+            }
             super::Message::UpdateProviderName(name) => {
                 self.temp_provider.name.clone_from(&name);
                 Task::none()
@@ -157,4 +231,19 @@ impl ProvidersSettings {
             }
         }
     }
+
+    fn load_prices(&mut self, ctx: &mut Context) {
+        self.prices = match self.selected_provider {
+            Some(provider_id) => match ctx.usage_service.get_model_prices(provider_id) {
+                Ok(prices) => prices.into_iter().map(PriceRow::from).collect(),
+                Err(e) => {
+                    log::error!("Failed to load model prices: {e}");
+                    vec![]
+                }
+            },
+            None => vec![],
+        };
+
+        self.temp_prices.clone_from(&self.prices);
+    }
 }
@@ -1,16 +1,24 @@
-use framework::{types::dto::ProviderDTO, Context};
+use framework::{types::dto::{ProviderDTO, ProviderKind, DEFAULT_REASONING_TAGS}, Context};
 use iced::{
     widget::{
-        button, container, horizontal_space, pane_grid, text, vertical_space, Column, Container, Row, Scrollable, Text
+        button, container, horizontal_space, pane_grid, text, vertical_space, Column, Container, PickList, Row, Scrollable, Text
     }, Element, Theme
 };
 
 use crate::{
     theme::styles,
-    widgets::{button::Button, input::Input},
+    widgets::{
+        button::Button,
+        icon::{IconName, IconType},
+        icon_button::IconButton,
+        input::Input,
+    },
 };
 
-use super::{ProvidersSettings, providers_settings_state::Pane};
+use super::{
+    ProvidersSettings,
+    providers_settings_state::{Pane, PriceRow},
+};
 
 impl ProvidersSettings {
     pub fn view(&self, _ctx: &Context) -> Element<super::Message> {
@@ -89,6 +97,52 @@ impl ProvidersSettings {
             .into()
     }
 
+    fn model_prices(&self) -> Element<'_, super::Message> {
+        let mut column = Column::new()
+            .spacing(5)
+            .push(Text::new("Model Prices, USD per 1M tokens"));
+
+        for (index, price) in self.temp_prices.iter().enumerate() {
+            column = column.push(
+                Row::new()
+                    .spacing(5)
+                    .align_y(iced::Alignment::Center)
+                    .push(
+                        Input::new(&price.model)
+                            .placeholder("Model")
+                            .on_change(move |model| super::Message::UpdatePriceModel(index, model))
+                            .view(),
+                    )
+                    .push(
+                        Input::new(&price.prompt_price)
+                            .placeholder("Prompt")
+                            .width(iced::Length::Fixed(100.0))
+                            .error(price_error(&price.prompt_price))
+                            .on_change(move |value| super::Message::UpdatePromptPrice(index, value))
+                            .view(),
+                    )
+                    .push(
+                        Input::new(&price.completion_price)
+                            .placeholder("Completion")
+                            .width(iced::Length::Fixed(100.0))
+                            .error(price_error(&price.completion_price))
+                            .on_change(move |value| {
+                                super::Message::UpdateCompletionPrice(index, value)
+                            })
+                            .view(),
+                    )
+                    .push(IconButton::new(
+                        IconType::Solid(IconName::Trash),
+                        super::Message::RemoveModelPrice(index),
+                    )),
+            );
+        }
+
+        column
+            .push(Button::new(Text::new("Add Price")).on_press(super::Message::AddModelPrice))
+            .into()
+    }
+
     fn provider_details(&self) -> Element<'_, super::Message> {
         Container::new(
             Column::new()
@@ -99,6 +153,19 @@ impl ProvidersSettings {
                         .on_change(super::Message::UpdateProviderName)
                         .label("Provider Name"),
                 )
+                .push(
+                    Column::new()
+                        .spacing(5)
+                        .push(Text::new("Provider Kind"))
+                        .push(
+                            PickList::new(
+                                ProviderKind::ALL,
+                                Some(self.temp_provider.kind),
+                                super::Message::UpdateProviderKind,
+                            )
+                            .width(iced::Length::Fill),
+                        ),
+                )
                 .push(
                     Input::new(&self.temp_provider.url)
                         .on_change(super::Message::UpdateProviderUrl)
@@ -115,6 +182,13 @@ impl ProvidersSettings {
                         .on_change(super::Message::UpdateProviderDefaultModel)
                         .label("Default Model"),
                 )
+                .push(
+                    Input::new(&self.temp_provider.reasoning_tags)
+                        .on_change(super::Message::UpdateProviderReasoningTags)
+                        .placeholder(DEFAULT_REASONING_TAGS)
+                        .label("Reasoning Tags"),
+                )
+                .push(self.model_prices())
                 .push(vertical_space())
                 .push(
                     Row::new()
@@ -148,3 +222,7 @@ impl ProvidersSettings {
         .into()
     }
 }
+
+fn price_error(value: &str) -> Option<&'static str> {
+    (!PriceRow::is_valid(value)).then_some("Not a price")
+}
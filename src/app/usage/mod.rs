@@ -0,0 +1,6 @@
+mod usage_state;
+mod usage_update;
+mod usage_view;
+
+pub use usage_state::Message;
+pub use usage_state::Usage;
@@ -0,0 +1,78 @@
+use std::fmt::Display;
+
+use framework::types::dto::{UsageGrouping, UsageRowDTO};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Message {
+    Refresh,
+    GroupingSelected(UsageGrouping),
+    PeriodSelected(Period),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Period {
+    Week,
+    #[default]
+    Month,
+    AllTime,
+}
+
+impl Period {
+    pub(super) const ALL: [Self; 3] = [Self::Week, Self::Month, Self::AllTime];
+
+    pub(super) const fn days(self) -> Option<u32> {
+        match self {
+            Self::Week => Some(7),
+            Self::Month => Some(30),
+            Self::AllTime => None,
+        }
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Week => write!(f, "Last 7 days"),
+            Self::Month => write!(f, "Last 30 days"),
+            Self::AllTime => write!(f, "All time"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Usage {
+    pub(super) grouping: UsageGrouping,
+    pub(super) period: Period,
+    pub(super) rows: Vec<UsageRowDTO>,
+}
+
+impl Usage {
+    pub fn new() -> (Self, iced::Task<Message>) {
+        (
+            Self {
+                grouping: UsageGrouping::default(),
+                period: Period::default(),
+                rows: vec![],
+            },
+            iced::Task::none(),
+        )
+    }
+
+    /// Sum of all rows, labeled as the total.
+    pub(super) fn total(&self) -> UsageRowDTO {
+        self.rows.iter().fold(
+            UsageRowDTO {
+                label: "Total".to_string(),
+                ..Default::default()
+            },
+            |mut total, row| {
+                total.requests += row.requests;
+                total.prompt_tokens += row.prompt_tokens;
+                total.completion_tokens += row.completion_tokens;
+                total.cost += row.cost;
+                total.unpriced |= row.unpriced;
+                total
+            },
+        )
+    }
+}
@@ -0,0 +1,36 @@
+use framework::Context;
+use iced::Task;
+
+use super::Usage;
+
+impl Usage {
+    pub fn update(&mut self, ctx: &mut Context, message: super::Message) -> Task<super::Message> {
+        match message {
+            super::Message::Refresh => {}
+            super::Message::GroupingSelected(grouping) => {
+                self.grouping = grouping;
+            }
+            super::Message::PeriodSelected(period) => {
+                self.period = period;
+            }
+        }
+
+        self.load(ctx);
+
+        Task::none()
+    }
+
+    fn load(&mut self, ctx: &mut Context) {
+        match ctx
+            .usage_service
+            .get_usage(self.grouping, self.period.days())
+        {
+            Ok(rows) => self.rows = rows,
+            Err(e) => {
+                log::error!("Failed to load usage: {e}");
+                ctx.event_system
+                    .notify_error("Failed to load usage", &e, None);
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use framework::types::dto::{UsageGrouping, UsageRowDTO};
+use iced::{
+    Element, Font, Length, Padding,
+    font::Weight,
+    widget::{Column, Container, PickList, Row, Scrollable, Text, horizontal_rule, text},
+};
+
+use crate::{
+    theme::styles::scrollable_style,
+    widgets::{
+        icon::{IconName, IconType},
+        icon_button::IconButton,
+    },
+};
+
+use super::{Usage, usage_state::Period};
+
+impl Usage {
+    pub fn view(&self) -> Element<'_, super::Message> {
+        let controls = Row::new()
+            .spacing(10)
+            .align_y(iced::alignment::Vertical::Center)
+            .push(Text::new("Usage").size(30).width(Length::Fill))
+            .push(PickList::new(
+                UsageGrouping::ALL,
+                Some(self.grouping),
+                super::Message::GroupingSelected,
+            ))
+            .push(PickList::new(
+                Period::ALL,
+                Some(self.period),
+                super::Message::PeriodSelected,
+            ))
+            .push(IconButton::new(
+                IconType::Solid(IconName::ArrowsRotate),
+                super::Message::Refresh,
+            ));
+
+        let mut table = Column::new()
+            .spacing(6)
+            .push(Self::row_view(
+                &self.grouping.to_string(),
+                ["Requests", "Prompt", "Completion", "Cost"].map(String::from),
+                true,
+            ))
+            .push(horizontal_rule(1));
+
+        if self.rows.is_empty() {
+            table = table.push(Text::new("No completions in this period").style(text::secondary));
+        }
+
+        for row in &self.rows {
+            table = table.push(Self::usage_row(row, false));
+        }
+
+        if !self.rows.is_empty() {
+            table = table
+                .push(horizontal_rule(1))
+                .push(Self::usage_row(&self.total(), true));
+        }
+
+        let mut column = Column::new().spacing(10).push(controls).push(
+            Scrollable::new(table)
+                .style(scrollable_style)
+                .height(Length::Fill),
+        );
+
+        if self.rows.iter().any(|row| row.unpriced) {
+            column = column.push(
+                Text::new("* some models have no price, set it in the provider settings")
+                    .size(13)
+                    .style(text::secondary),
+            );
+        }
+
+        Container::new(column)
+            .padding(Padding {
+                top: 10.0,
+                bottom: 10.0,
+                left: 20.0,
+                right: 20.0,
+            })
+            .into()
+    }
+
+    fn usage_row<'a>(row: &UsageRowDTO, bold: bool) -> Element<'a, super::Message> {
+        let cost = format!("${:.4}{}", row.cost, if row.unpriced { " *" } else { "" });
+
+        Self::row_view(
+            &row.label,
+            [
+                row.requests.to_string(),
+                row.prompt_tokens.to_string(),
+                row.completion_tokens.to_string(),
+                cost,
+            ],
+            bold,
+        )
+    }
+
+    fn row_view<'a>(label: &str, cells: [String; 4], bold: bool) -> Element<'a, super::Message> {
+        let font = Font {
+            weight: if bold { Weight::Bold } else { Weight::Normal },
+            ..Font::default()
+        };
+
+        let mut row = Row::new().spacing(10).push(
+            Text::new(label.to_string())
+                .font(font)
+                .width(Length::FillPortion(3)),
+        );
+
+        for cell in cells {
+            row = row.push(
+                Text::new(cell)
+                    .font(font)
+                    .width(Length::FillPortion(1))
+                    .align_x(iced::alignment::Horizontal::Right),
+            );
+        }
+
+        row.into()
+    }
+}
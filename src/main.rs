@@ -1,6 +1,9 @@
 use app::App;
-use framework::utils::APP_NAME;
-use iced::Theme;
+use framework::{Context, utils::APP_NAME};
+use iced::{
+    Element, Length, Task, Theme,
+    widget::{container, text},
+};
 use theme::dark_theme::dark_theme;
 
 mod app;
@@ -11,9 +14,33 @@ mod widgets;
 async fn main() {
     env_logger::init();
 
+    let context = match Context::new() {
+        Ok(context) => context,
+        Err(error) => {
+            log::error!("{error}");
+            show_startup_error(error);
+            return;
+        }
+    };
+
     iced::application(APP_NAME, App::update, App::view)
         .theme(|_| Theme::Custom(dark_theme()))
         .subscription(App::subscription)
-        .run_with(App::new)
+        .run_with(move || App::new(context))
+        .expect("Failed to run the application");
+}
+
+/// The app can't work without its database, so it only shows why it didn't start.
+fn show_startup_error(error: String) {
+    fn view(error: &String) -> Element<'_, ()> {
+        container(text(error))
+            .padding(20)
+            .center(Length::Fill)
+            .into()
+    }
+
+    iced::application(APP_NAME, |_: &mut String, (): ()| {}, view)
+        .theme(|_| Theme::Custom(dark_theme()))
+        .run_with(move || (error, Task::none()))
         .expect("Failed to run the application");
 }
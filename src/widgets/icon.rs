@@ -22,6 +22,15 @@ pub enum IconName {
     ChevronRight,
     ChevronLeft,
     PaperClip,
+    Bell,
+    Filter,
+    ArrowsRotate,
+    PaperPlane,
+    CircleInfo,
+    ChartSimple,
+    FileExport,
+    FileImport,
+    Book,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -54,6 +63,15 @@ const fn icon_name_to_str<'a>(icon_name: IconName) -> &'a str {
         IconName::ChevronRight => "chevron-right",
         IconName::PaperClip => "paperclip",
         IconName::ChevronLeft => "chevron-left",
+        IconName::Bell => "bell",
+        IconName::Filter => "filter",
+        IconName::ArrowsRotate => "arrows-rotate",
+        IconName::PaperPlane => "paper-plane",
+        IconName::CircleInfo => "circle-info",
+        IconName::ChartSimple => "chart-simple",
+        IconName::FileExport => "file-export",
+        IconName::FileImport => "file-import",
+        IconName::Book => "book",
     }
 }
 